@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS scratchpads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL UNIQUE,
+    student_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS scratchpad_versions (
+    scratchpad_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    author TEXT NOT NULL CHECK (author IN ('student', 'model')),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (scratchpad_id, version),
+    FOREIGN KEY (scratchpad_id) REFERENCES scratchpads(id) ON DELETE CASCADE
+);
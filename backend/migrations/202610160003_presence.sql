@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS presence_intervals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL,
+    started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (student_id) REFERENCES students(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_presence_intervals_student_last_seen
+    ON presence_intervals(student_id, last_seen_at);
+CREATE INDEX IF NOT EXISTS idx_presence_intervals_last_seen ON presence_intervals(last_seen_at);
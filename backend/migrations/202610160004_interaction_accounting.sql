@@ -0,0 +1,6 @@
+ALTER TABLE ai_interactions ADD COLUMN model TEXT;
+ALTER TABLE ai_interactions ADD COLUMN latency_ms INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN ttft_ms INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN prompt_tokens INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN completion_tokens INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN estimated_cost REAL;
@@ -0,0 +1,4 @@
+ALTER TABLE ai_interactions ADD COLUMN experiment TEXT;
+ALTER TABLE ai_interactions ADD COLUMN variant TEXT CHECK (variant IN ('control', 'treatment'));
+
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_experiment ON ai_interactions(experiment, variant);
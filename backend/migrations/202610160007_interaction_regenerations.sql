@@ -0,0 +1,3 @@
+ALTER TABLE ai_interactions ADD COLUMN regenerated_from INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_regenerated_from ON ai_interactions(regenerated_from);
@@ -0,0 +1 @@
+ALTER TABLE ai_interactions ADD COLUMN cancelled INTEGER NOT NULL DEFAULT 0 CHECK (cancelled IN (0, 1));
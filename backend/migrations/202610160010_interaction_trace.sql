@@ -0,0 +1,4 @@
+ALTER TABLE ai_interactions ADD COLUMN request_id TEXT;
+ALTER TABLE ai_interactions ADD COLUMN traceparent TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_request_id ON ai_interactions(request_id);
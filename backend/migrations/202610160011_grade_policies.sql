@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS grade_policies (
+    grade_level TEXT PRIMARY KEY,
+    max_temperature REAL,
+    max_tokens INTEGER,
+    -- JSON array of model names; NULL allows any model.
+    allowed_models TEXT,
+    mode TEXT NOT NULL DEFAULT 'clamp' CHECK (mode IN ('clamp', 'reject')),
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
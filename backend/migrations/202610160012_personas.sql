@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS personas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    system_prompt TEXT NOT NULL,
+    model TEXT,
+    temperature REAL,
+    max_tokens INTEGER,
+    -- JSON array of grade levels; NULL allows every grade.
+    allowed_grades TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE ai_interactions ADD COLUMN persona_id INTEGER REFERENCES personas(id) ON DELETE SET NULL;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS few_shot_examples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    subject TEXT NOT NULL,
+    -- NULL applies to every grade.
+    grade_level TEXT,
+    question TEXT NOT NULL,
+    answer TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_few_shot_examples_subject ON few_shot_examples(subject, grade_level);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS model_downloads (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    repo_id TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    revision TEXT NOT NULL DEFAULT 'main',
+    dest_path TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'completed', 'failed')),
+    bytes_total INTEGER,
+    bytes_downloaded INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_model_downloads_status ON model_downloads(status);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS inference_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    container TEXT NOT NULL,
+    event TEXT NOT NULL CHECK (event IN ('created', 'restarted', 'restart_failed')),
+    detail TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_inference_events_created_at ON inference_events(created_at);
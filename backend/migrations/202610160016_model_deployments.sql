@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS model_deployments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    image TEXT NOT NULL,
+    model_path TEXT NOT NULL,
+    host_port INTEGER NOT NULL UNIQUE,
+    container_port INTEGER NOT NULL DEFAULT 8000,
+    gpus TEXT,
+    args TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
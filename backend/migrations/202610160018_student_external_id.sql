@@ -0,0 +1,3 @@
+ALTER TABLE students ADD COLUMN external_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_students_external_id ON students(external_id);
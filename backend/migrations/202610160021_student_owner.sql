@@ -0,0 +1,4 @@
+ALTER TABLE students ADD COLUMN owner_user_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_students_owner_user_id ON students(owner_user_id);
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_user_id ON ai_interactions(user_id);
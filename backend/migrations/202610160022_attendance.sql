@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS attendance (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    -- Calendar date, YYYY-MM-DD.
+    date TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('present', 'absent', 'tardy')),
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (student_id, date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_attendance_date ON attendance(date);
@@ -0,0 +1,13 @@
+ALTER TABLE assignments ADD COLUMN subject TEXT;
+ALTER TABLE assignments ADD COLUMN description TEXT;
+ALTER TABLE assignments ADD COLUMN max_points REAL;
+
+CREATE TABLE IF NOT EXISTS grades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Assignments belong to one student, so each has at most one grade.
+    assignment_id INTEGER NOT NULL UNIQUE REFERENCES assignments(id) ON DELETE CASCADE,
+    points REAL NOT NULL,
+    comment TEXT,
+    graded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS student_tags (
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    tagged_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (student_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_student_tags_tag_id ON student_tags(tag_id);
+
+CREATE TABLE IF NOT EXISTS tag_policies (
+    tag_id INTEGER PRIMARY KEY REFERENCES tags(id) ON DELETE CASCADE,
+    max_temperature REAL,
+    max_tokens INTEGER,
+    -- JSON array of model names; NULL allows any model.
+    allowed_models TEXT,
+    mode TEXT NOT NULL DEFAULT 'clamp' CHECK (mode IN ('clamp', 'reject')),
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- JSON array of tag names; NULL allows every student.
+ALTER TABLE personas ADD COLUMN allowed_tags TEXT;
@@ -0,0 +1,5 @@
+ALTER TABLE students ADD COLUMN reading_level TEXT;
+-- BCP 47 language tag, e.g. `es` or `pt-BR`.
+ALTER TABLE students ADD COLUMN preferred_language TEXT;
+ALTER TABLE students ADD COLUMN accommodations TEXT;
+ALTER TABLE students ADD COLUMN has_iep INTEGER NOT NULL DEFAULT 0;
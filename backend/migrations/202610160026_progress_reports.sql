@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS progress_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    -- JSON object: summary, strengths, areas_for_growth, recommendations.
+    report TEXT NOT NULL,
+    interaction_count INTEGER NOT NULL,
+    note_count INTEGER NOT NULL,
+    grade_count INTEGER NOT NULL,
+    -- The generation request itself.
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_progress_reports_student_id ON progress_reports(student_id, created_at);
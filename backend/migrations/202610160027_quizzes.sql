@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS quizzes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    grade_level TEXT,
+    -- Student the quiz was generated for, if any.
+    student_id INTEGER REFERENCES students(id) ON DELETE SET NULL,
+    -- JSON array of { question, choices, answer, explanation }.
+    questions TEXT NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_quizzes_grade_level ON quizzes(grade_level);
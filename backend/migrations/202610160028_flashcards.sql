@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS flashcard_decks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    -- Topic the deck was generated from, or NULL when built from a document.
+    topic TEXT,
+    grade_level TEXT,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS flashcards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    deck_id INTEGER NOT NULL REFERENCES flashcard_decks(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    front TEXT NOT NULL,
+    back TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_flashcards_deck ON flashcards(deck_id, position);
+
+-- Leitner-box state per student per card: a correct answer moves the card up
+-- a box (longer interval), a miss sends it back to box 1.
+CREATE TABLE IF NOT EXISTS flashcard_reviews (
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    card_id INTEGER NOT NULL REFERENCES flashcards(id) ON DELETE CASCADE,
+    box INTEGER NOT NULL DEFAULT 1,
+    due_date TEXT NOT NULL,
+    review_count INTEGER NOT NULL DEFAULT 0,
+    correct_count INTEGER NOT NULL DEFAULT 0,
+    last_correct INTEGER NOT NULL,
+    last_reviewed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (student_id, card_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_flashcard_reviews_due ON flashcard_reviews(student_id, due_date);
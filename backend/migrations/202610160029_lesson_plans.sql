@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS lesson_plans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    grade_level TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_lesson_plans_subject ON lesson_plans(subject, grade_level);
+
+-- Every draft is kept; the highest version is the current plan.
+CREATE TABLE IF NOT EXISTS lesson_plan_versions (
+    plan_id INTEGER NOT NULL REFERENCES lesson_plans(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    -- JSON { objectives, materials, activities, assessment }.
+    content TEXT NOT NULL,
+    author TEXT NOT NULL CHECK (author IN ('teacher', 'model')),
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (plan_id, version)
+);
@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS rubrics (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    subject TEXT,
+    -- JSON array of { name, description, max_points }.
+    criteria TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS rubric_gradings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    assignment_id INTEGER NOT NULL REFERENCES assignments(id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    -- Kept when the rubric is deleted; the result holds the criteria names.
+    rubric_id INTEGER REFERENCES rubrics(id) ON DELETE SET NULL,
+    submission TEXT NOT NULL,
+    -- JSON { criteria: [{ name, score, max_points, comment }], overall_comment }.
+    result TEXT NOT NULL,
+    total_points REAL NOT NULL,
+    max_points REAL NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_rubric_gradings_assignment ON rubric_gradings(assignment_id);
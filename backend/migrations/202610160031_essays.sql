@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS essays (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    assignment_id INTEGER REFERENCES assignments(id) ON DELETE SET NULL,
+    title TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_essays_student ON essays(student_id);
+
+-- Successive drafts of one essay, numbered from 1.
+CREATE TABLE IF NOT EXISTS essay_drafts (
+    essay_id INTEGER NOT NULL REFERENCES essays(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    word_count INTEGER NOT NULL,
+    -- JSON { summary, revision_summary, comments: [{ excerpt, offset, category, comment }] }.
+    feedback TEXT NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (essay_id, version)
+);
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS standards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- e.g. CCSS.MATH.CONTENT.4.NF.A.1
+    code TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT NOT NULL,
+    subject TEXT,
+    grade_level TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS quiz_standards (
+    quiz_id INTEGER NOT NULL REFERENCES quizzes(id) ON DELETE CASCADE,
+    standard_id INTEGER NOT NULL REFERENCES standards(id) ON DELETE CASCADE,
+    PRIMARY KEY (quiz_id, standard_id)
+);
+
+CREATE TABLE IF NOT EXISTS lesson_plan_standards (
+    plan_id INTEGER NOT NULL REFERENCES lesson_plans(id) ON DELETE CASCADE,
+    standard_id INTEGER NOT NULL REFERENCES standards(id) ON DELETE CASCADE,
+    PRIMARY KEY (plan_id, standard_id)
+);
+
+CREATE TABLE IF NOT EXISTS interaction_standards (
+    interaction_id INTEGER NOT NULL REFERENCES ai_interactions(id) ON DELETE CASCADE,
+    standard_id INTEGER NOT NULL REFERENCES standards(id) ON DELETE CASCADE,
+    PRIMARY KEY (interaction_id, standard_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_quiz_standards_standard ON quiz_standards(standard_id);
+CREATE INDEX IF NOT EXISTS idx_lesson_plan_standards_standard ON lesson_plan_standards(standard_id);
+CREATE INDEX IF NOT EXISTS idx_interaction_standards_standard ON interaction_standards(standard_id);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS family_digests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    -- Monday of the covered week; the week runs through the following Sunday.
+    week_start TEXT NOT NULL,
+    -- JSON { headline, summary, topics, highlights, try_at_home }.
+    digest TEXT NOT NULL,
+    interaction_count INTEGER NOT NULL,
+    quiz_count INTEGER NOT NULL,
+    note_count INTEGER NOT NULL,
+    grade_count INTEGER NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (student_id, week_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_family_digests_week ON family_digests(week_start);
@@ -0,0 +1,41 @@
+CREATE TABLE IF NOT EXISTS goals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    -- e.g. reading fluency, math computation, behavior.
+    area TEXT,
+    -- How progress is measured, e.g. "words correct per minute on grade-level probes".
+    measurement TEXT NOT NULL,
+    baseline TEXT,
+    target TEXT NOT NULL,
+    target_date TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'met', 'discontinued')),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_goals_student ON goals(student_id);
+
+CREATE TABLE IF NOT EXISTS goal_evidence (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    goal_id INTEGER NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    observed_on TEXT NOT NULL,
+    -- Optional numeric data point in the goal's measurement unit.
+    value REAL,
+    note TEXT NOT NULL,
+    recorded_by TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_goal_evidence_goal ON goal_evidence(goal_id, observed_on);
+
+CREATE TABLE IF NOT EXISTS goal_progress_notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    goal_id INTEGER NOT NULL REFERENCES goals(id) ON DELETE CASCADE,
+    narrative TEXT NOT NULL,
+    -- Evidence the draft was based on.
+    period_start TEXT,
+    evidence_count INTEGER NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS school_years (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    starts_on TEXT NOT NULL,
+    ends_on TEXT NOT NULL,
+    -- Set at year end; the year's students and classes become read-only.
+    archived_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The cohort a student is currently in; NULL students are never archived.
+ALTER TABLE students ADD COLUMN school_year_id INTEGER REFERENCES school_years(id) ON DELETE SET NULL;
+ALTER TABLE classes ADD COLUMN school_year_id INTEGER REFERENCES school_years(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_students_school_year_id ON students(school_year_id);
+CREATE INDEX IF NOT EXISTS idx_classes_school_year_id ON classes(school_year_id);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS guardians (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    relationship TEXT NOT NULL,
+    email TEXT,
+    phone TEXT,
+    consent_status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (consent_status IN ('pending', 'granted', 'declined', 'withdrawn')),
+    consent_updated_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_guardians_student_id ON guardians(student_id);
+CREATE INDEX IF NOT EXISTS idx_guardians_email ON guardians(email);
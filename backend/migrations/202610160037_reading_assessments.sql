@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS reading_assessments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    sample TEXT NOT NULL,
+    -- JSON readability metrics: word counts and one score per formula.
+    metrics TEXT NOT NULL,
+    formula_grade REAL NOT NULL,
+    model_grade REAL NOT NULL,
+    model_rationale TEXT NOT NULL,
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_reading_assessments_student
+    ON reading_assessments(student_id, created_at);
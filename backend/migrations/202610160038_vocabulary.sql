@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS vocabulary_lists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    source TEXT NOT NULL CHECK (source IN ('history', 'text')),
+    generation_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_vocabulary_lists_student_id ON vocabulary_lists(student_id);
+
+CREATE TABLE IF NOT EXISTS vocabulary_words (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL REFERENCES vocabulary_lists(id) ON DELETE CASCADE,
+    word TEXT NOT NULL COLLATE NOCASE,
+    definition TEXT NOT NULL,
+    -- Where the word came up, quoted from the source.
+    context TEXT,
+    UNIQUE (list_id, word)
+);
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS help_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    assignment_id INTEGER NOT NULL REFERENCES assignments(id) ON DELETE CASCADE,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'closed')),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_help_sessions_assignment_id ON help_sessions(assignment_id);
+CREATE INDEX IF NOT EXISTS idx_help_sessions_student_id ON help_sessions(student_id);
+
+CREATE TABLE IF NOT EXISTS help_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL REFERENCES help_sessions(id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('student', 'tutor')),
+    content TEXT NOT NULL,
+    -- A tutor reply the model marked as giving the answer away; the student
+    -- saw `content` (a nudge) instead. Kept for teacher review.
+    withheld TEXT,
+    interaction_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_help_messages_session_id ON help_messages(session_id, id);
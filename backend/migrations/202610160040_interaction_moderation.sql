@@ -0,0 +1,2 @@
+-- The response filter rewrote part of the model's output.
+ALTER TABLE ai_interactions ADD COLUMN filtered INTEGER NOT NULL DEFAULT 0 CHECK (filtered IN (0, 1));
@@ -0,0 +1,2 @@
+-- Set when the retention job scrubbed the row instead of deleting it.
+ALTER TABLE ai_interactions ADD COLUMN anonymized_at TEXT;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS interaction_feedback (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    interaction_id INTEGER NOT NULL REFERENCES ai_interactions(id) ON DELETE CASCADE,
+    rating TEXT NOT NULL CHECK (rating IN ('up', 'down')),
+    comment TEXT,
+    reviewer TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_interaction_feedback_interaction_id ON interaction_feedback(interaction_id);
@@ -0,0 +1,12 @@
+-- Reviewer labels on stored interactions, e.g. `hallucination`. Free-form,
+-- unlike student tags, so no `tags` row is needed first.
+CREATE TABLE IF NOT EXISTS interaction_tags (
+    interaction_id INTEGER NOT NULL REFERENCES ai_interactions(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL COLLATE NOCASE,
+    note TEXT,
+    tagged_by TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (interaction_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_interaction_tags_tag ON interaction_tags(tag);
@@ -0,0 +1,59 @@
+-- Full-text index over what was said: message contents from the prompt and
+-- the reply text from the completion, keyed by ai_interactions.id. Rows whose
+-- text isn't the usual JSON (e.g. a relayed stream) are indexed verbatim.
+CREATE VIRTUAL TABLE IF NOT EXISTS interaction_search USING fts5(
+    prompt_text,
+    response_text,
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER IF NOT EXISTS interaction_search_insert AFTER INSERT ON ai_interactions
+BEGIN
+    INSERT INTO interaction_search(rowid, prompt_text, response_text)
+    VALUES (
+        new.id,
+        CASE WHEN json_valid(new.prompt) AND json_type(new.prompt) = 'array'
+             THEN (SELECT group_concat(json_extract(value, '$.content'), char(10))
+                   FROM json_each(new.prompt))
+             ELSE new.prompt END,
+        CASE WHEN json_valid(new.response)
+             THEN COALESCE(json_extract(new.response, '$.choices[0].message.content'),
+                           json_extract(new.response, '$.message.content'), '')
+             ELSE new.response END
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS interaction_search_update AFTER UPDATE OF prompt, response ON ai_interactions
+BEGIN
+    DELETE FROM interaction_search WHERE rowid = old.id;
+    INSERT INTO interaction_search(rowid, prompt_text, response_text)
+    VALUES (
+        new.id,
+        CASE WHEN json_valid(new.prompt) AND json_type(new.prompt) = 'array'
+             THEN (SELECT group_concat(json_extract(value, '$.content'), char(10))
+                   FROM json_each(new.prompt))
+             ELSE new.prompt END,
+        CASE WHEN json_valid(new.response)
+             THEN COALESCE(json_extract(new.response, '$.choices[0].message.content'),
+                           json_extract(new.response, '$.message.content'), '')
+             ELSE new.response END
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS interaction_search_delete AFTER DELETE ON ai_interactions
+BEGIN
+    DELETE FROM interaction_search WHERE rowid = old.id;
+END;
+
+INSERT INTO interaction_search(rowid, prompt_text, response_text)
+SELECT
+    id,
+    CASE WHEN json_valid(prompt) AND json_type(prompt) = 'array'
+         THEN (SELECT group_concat(json_extract(value, '$.content'), char(10))
+               FROM json_each(prompt))
+         ELSE prompt END,
+    CASE WHEN json_valid(response)
+         THEN COALESCE(json_extract(response, '$.choices[0].message.content'),
+                       json_extract(response, '$.message.content'), '')
+         ELSE response END
+FROM ai_interactions;
@@ -0,0 +1,5 @@
+-- Where a request's time went. `latency_ms` (from the first upstream call to
+-- the last byte) and `ttft_ms` already exist.
+ALTER TABLE ai_interactions ADD COLUMN total_ms INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN queue_ms INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN upstream_ms INTEGER;
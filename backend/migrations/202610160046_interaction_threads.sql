@@ -0,0 +1,16 @@
+-- Multi-turn threading. `conversation_id` is the id of the conversation's
+-- first interaction; `parent_interaction_id` is the turn this one answers.
+ALTER TABLE ai_interactions ADD COLUMN conversation_id INTEGER;
+ALTER TABLE ai_interactions ADD COLUMN parent_interaction_id INTEGER REFERENCES ai_interactions(id) ON DELETE SET NULL;
+
+UPDATE ai_interactions SET conversation_id = id WHERE conversation_id IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_conversation_id ON ai_interactions(conversation_id);
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_parent_interaction_id ON ai_interactions(parent_interaction_id);
+
+-- A turn without a conversation starts its own.
+CREATE TRIGGER IF NOT EXISTS ai_interactions_conversation AFTER INSERT ON ai_interactions
+WHEN new.conversation_id IS NULL
+BEGIN
+    UPDATE ai_interactions SET conversation_id = new.id WHERE id = new.id;
+END;
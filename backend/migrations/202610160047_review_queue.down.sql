@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS review_items_enqueue;
+DROP TABLE IF EXISTS review_items;
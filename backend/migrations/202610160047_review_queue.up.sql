@@ -0,0 +1,29 @@
+-- Interactions moderation flagged, waiting for a person to look at them.
+CREATE TABLE IF NOT EXISTS review_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    interaction_id INTEGER NOT NULL UNIQUE REFERENCES ai_interactions(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL CHECK (reason IN ('response_filter', 'content_filter')),
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'dismissed')),
+    reviewer TEXT,
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    decided_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_items_status ON review_items(status, created_at);
+
+-- Queues every flagged interaction as it's stored: the response filter
+-- rewrote it, or the inference server stopped a choice with `content_filter`.
+-- Streamed responses are stored as plain text, so only the first applies.
+CREATE TRIGGER IF NOT EXISTS review_items_enqueue AFTER INSERT ON ai_interactions
+WHEN new.filtered = 1
+  OR (json_valid(new.response) AND EXISTS(
+        SELECT 1 FROM json_each(new.response, '$.choices')
+        WHERE json_extract(value, '$.finish_reason') = 'content_filter'))
+BEGIN
+    INSERT OR IGNORE INTO review_items(interaction_id, reason)
+    VALUES (new.id, CASE WHEN new.filtered = 1 THEN 'response_filter' ELSE 'content_filter' END);
+END;
+
+INSERT OR IGNORE INTO review_items(interaction_id, reason, created_at)
+SELECT id, 'response_filter', created_at FROM ai_interactions WHERE filtered = 1;
@@ -0,0 +1,10 @@
+-- Arrival times of streamed chunks, recorded when LLM_STREAM_TELEMETRY=true.
+CREATE TABLE IF NOT EXISTS stream_chunks (
+    interaction_id INTEGER NOT NULL REFERENCES ai_interactions(id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    -- Since the upstream request went out, like ttft_ms.
+    offset_ms INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    content_chars INTEGER NOT NULL,
+    PRIMARY KEY (interaction_id, seq)
+);
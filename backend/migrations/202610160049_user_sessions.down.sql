@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS sessions;
+
+ALTER TABLE users DROP COLUMN last_login_at;
+ALTER TABLE users DROP COLUMN password_hash;
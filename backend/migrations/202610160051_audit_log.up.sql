@@ -0,0 +1,36 @@
+-- Every write and admin request, written by the audit middleware. Rows are
+-- never changed or removed.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- No foreign key: deleting the account must not touch its history.
+    actor_user_id INTEGER,
+    actor_email TEXT,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    -- The matched route, e.g. /students/:id.
+    route TEXT,
+    -- Table and key of the row the request targeted, when known.
+    entity TEXT,
+    entity_id TEXT,
+    status INTEGER NOT NULL,
+    request_id TEXT,
+    before_json TEXT,
+    after_json TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor_user_id ON audit_log(actor_user_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity, entity_id);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update
+BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete
+BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,18 @@
+-- Everything returns to a single school. Fails on the unique index if two
+-- schools use the same SIS id, rather than dropping either student's id.
+DROP INDEX IF EXISTS idx_students_external_id;
+DROP INDEX IF EXISTS idx_ai_interactions_school_id;
+DROP INDEX IF EXISTS idx_classes_school_id;
+DROP INDEX IF EXISTS idx_students_school_id;
+DROP INDEX IF EXISTS idx_users_school_id;
+
+ALTER TABLE audit_log DROP COLUMN school_id;
+ALTER TABLE export_jobs DROP COLUMN school_id;
+ALTER TABLE ai_interactions DROP COLUMN school_id;
+ALTER TABLE classes DROP COLUMN school_id;
+ALTER TABLE students DROP COLUMN school_id;
+ALTER TABLE users DROP COLUMN school_id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_students_external_id ON students(external_id);
+
+DROP TABLE IF EXISTS schools;
@@ -0,0 +1,29 @@
+-- Schools (tenants) sharing one deployment. Every existing row belongs to
+-- the default school, id 1.
+CREATE TABLE IF NOT EXISTS schools (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    slug TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO schools(id, name, slug) VALUES(1, 'Default school', 'default');
+
+-- No REFERENCES clause: SQLite can't add a foreign key column with a
+-- non-null default. Schools are never deleted, so nothing dangles.
+ALTER TABLE users ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE students ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE classes ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE ai_interactions ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+-- Research exports and audit entries are visible only to their own school.
+ALTER TABLE export_jobs ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE audit_log ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_users_school_id ON users(school_id);
+CREATE INDEX IF NOT EXISTS idx_students_school_id ON students(school_id);
+CREATE INDEX IF NOT EXISTS idx_classes_school_id ON classes(school_id);
+CREATE INDEX IF NOT EXISTS idx_ai_interactions_school_id ON ai_interactions(school_id, created_at);
+
+-- Two schools may use the same SIS ids.
+DROP INDEX IF EXISTS idx_students_external_id;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_students_external_id ON students(school_id, external_id);
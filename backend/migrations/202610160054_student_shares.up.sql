@@ -0,0 +1,10 @@
+-- Co-teachers a student is shared with, on top of its owner.
+CREATE TABLE IF NOT EXISTS student_shares (
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    shared_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (student_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_student_shares_user_id ON student_shares(user_id);
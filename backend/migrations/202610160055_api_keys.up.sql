@@ -0,0 +1,18 @@
+-- Long-lived keys for scripts and integrations. A key acts as its user,
+-- limited by its scope.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- `full`, `read_only` (GET requests only), or `chat` (the /llm routes only).
+    scope TEXT NOT NULL CHECK (scope IN ('full', 'read_only', 'chat')),
+    -- The first characters of the key, so people can tell their keys apart.
+    key_prefix TEXT NOT NULL,
+    -- SHA-256 of the key; the key itself is only returned when it's created.
+    key_hash TEXT NOT NULL UNIQUE,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id);
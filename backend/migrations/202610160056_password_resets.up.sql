@@ -0,0 +1,15 @@
+-- Single-use password reset tokens, emailed to the user or handed to them
+-- by an admin. Requesting a new one replaces any still unused.
+CREATE TABLE IF NOT EXISTS password_resets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- SHA-256 of the token; the token itself is only sent to the user.
+    token_hash TEXT NOT NULL UNIQUE,
+    -- The admin who issued it; NULL when the user asked by email.
+    issued_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TEXT NOT NULL,
+    used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_resets_user_id ON password_resets(user_id);
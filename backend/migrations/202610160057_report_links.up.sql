@@ -0,0 +1,17 @@
+-- Read-only links to a student's progress report for families without an
+-- account. The link itself is a signed token; rows let teachers see and
+-- revoke the links they've handed out.
+CREATE TABLE IF NOT EXISTS report_links (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    student_id INTEGER NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    -- A specific report; NULL always shows the newest.
+    report_id INTEGER REFERENCES progress_reports(id) ON DELETE CASCADE,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TEXT NOT NULL,
+    revoked_at TEXT,
+    view_count INTEGER NOT NULL DEFAULT 0,
+    last_viewed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_report_links_student_id ON report_links(student_id);
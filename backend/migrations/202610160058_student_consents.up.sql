@@ -0,0 +1,12 @@
+-- Parental consent for a student's use of the AI tutor. Students without a
+-- row are treated as `pending`.
+CREATE TABLE IF NOT EXISTS student_consents (
+    student_id INTEGER PRIMARY KEY REFERENCES students(id) ON DELETE CASCADE,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'granted', 'declined', 'withdrawn')),
+    -- When the family signed or responded, YYYY-MM-DD.
+    consent_date TEXT,
+    -- Where the signed form lives, e.g. a file name or SIS document id.
+    document_ref TEXT,
+    recorded_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,37 @@
+-- Back to one list of school years with globally unique names. Fails if
+-- two schools named a year the same, rather than merging or renaming them.
+-- The rebuild saves and restores student and class links as the up does.
+CREATE TEMP TABLE school_year_links AS
+    SELECT 'students' AS source, id, school_year_id FROM students WHERE school_year_id IS NOT NULL
+    UNION ALL
+    SELECT 'classes', id, school_year_id FROM classes WHERE school_year_id IS NOT NULL;
+
+CREATE TABLE school_years_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    starts_on TEXT NOT NULL,
+    ends_on TEXT NOT NULL,
+    -- Set at year end; the year's students and classes become read-only.
+    archived_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO school_years_old(id, name, starts_on, ends_on, archived_at, created_at)
+SELECT id, name, starts_on, ends_on, archived_at, created_at FROM school_years;
+
+DROP TABLE school_years;
+ALTER TABLE school_years_old RENAME TO school_years;
+
+UPDATE students SET school_year_id = (
+    SELECT l.school_year_id FROM school_year_links l
+    WHERE l.source = 'students' AND l.id = students.id
+)
+WHERE id IN (SELECT id FROM school_year_links WHERE source = 'students');
+
+UPDATE classes SET school_year_id = (
+    SELECT l.school_year_id FROM school_year_links l
+    WHERE l.source = 'classes' AND l.id = classes.id
+)
+WHERE id IN (SELECT id FROM school_year_links WHERE source = 'classes');
+
+DROP TABLE school_year_links;
@@ -0,0 +1,40 @@
+-- School years belong to a school, and names only have to be unique within
+-- one. SQLite can't drop the old column-level UNIQUE, so the table is
+-- rebuilt. Dropping it fires ON DELETE SET NULL on students and classes,
+-- so their links are saved first and put back afterwards.
+CREATE TEMP TABLE school_year_links AS
+    SELECT 'students' AS source, id, school_year_id FROM students WHERE school_year_id IS NOT NULL
+    UNION ALL
+    SELECT 'classes', id, school_year_id FROM classes WHERE school_year_id IS NOT NULL;
+
+CREATE TABLE school_years_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    school_id INTEGER NOT NULL DEFAULT 1 REFERENCES schools(id),
+    name TEXT NOT NULL COLLATE NOCASE,
+    starts_on TEXT NOT NULL,
+    ends_on TEXT NOT NULL,
+    -- Set at year end; the year's students and classes become read-only.
+    archived_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (school_id, name)
+);
+
+INSERT INTO school_years_new(id, name, starts_on, ends_on, archived_at, created_at)
+SELECT id, name, starts_on, ends_on, archived_at, created_at FROM school_years;
+
+DROP TABLE school_years;
+ALTER TABLE school_years_new RENAME TO school_years;
+
+UPDATE students SET school_year_id = (
+    SELECT l.school_year_id FROM school_year_links l
+    WHERE l.source = 'students' AND l.id = students.id
+)
+WHERE id IN (SELECT id FROM school_year_links WHERE source = 'students');
+
+UPDATE classes SET school_year_id = (
+    SELECT l.school_year_id FROM school_year_links l
+    WHERE l.source = 'classes' AND l.id = classes.id
+)
+WHERE id IN (SELECT id FROM school_year_links WHERE source = 'classes');
+
+DROP TABLE school_year_links;
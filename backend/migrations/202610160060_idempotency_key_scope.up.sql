@@ -0,0 +1,20 @@
+-- Stored responses are per caller, so one user's key can't replay another's
+-- response. Rows only live for IDEMPOTENCY_TTL_SECS, so they are dropped
+-- rather than guessed at.
+DROP TABLE IF EXISTS idempotency_keys;
+
+CREATE TABLE idempotency_keys (
+    -- `user:<id>` for a logged-in caller, `school:<id>` otherwise.
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    path TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    -- NULL until the first request finishes.
+    status INTEGER,
+    content_type TEXT,
+    body BLOB,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (scope, key, path)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
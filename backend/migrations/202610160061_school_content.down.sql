@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS idx_rubrics_school_id;
+DROP INDEX IF EXISTS idx_flashcard_decks_school_id;
+DROP INDEX IF EXISTS idx_lesson_plans_school_id;
+DROP INDEX IF EXISTS idx_quizzes_school_id;
+
+ALTER TABLE scratchpads DROP COLUMN school_id;
+ALTER TABLE rubrics DROP COLUMN school_id;
+ALTER TABLE flashcard_decks DROP COLUMN school_id;
+ALTER TABLE lesson_plans DROP COLUMN school_id;
+ALTER TABLE quizzes DROP COLUMN school_id;
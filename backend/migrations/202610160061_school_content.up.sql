@@ -0,0 +1,12 @@
+-- Quizzes, lesson plans, flashcard decks, rubrics, and scratchpads belong to
+-- a school like students do; existing ones go to the default school.
+ALTER TABLE quizzes ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE lesson_plans ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE flashcard_decks ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE rubrics ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE scratchpads ADD COLUMN school_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_quizzes_school_id ON quizzes(school_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_lesson_plans_school_id ON lesson_plans(school_id);
+CREATE INDEX IF NOT EXISTS idx_flashcard_decks_school_id ON flashcard_decks(school_id);
+CREATE INDEX IF NOT EXISTS idx_rubrics_school_id ON rubrics(school_id);
@@ -0,0 +1,114 @@
+//! Long-lived API keys for scripts and integrations, sent as
+//! `Authorization: Bearer sk-...`. A key acts as its user; its scope can
+//! narrow that to read-only requests or to the chat proxy. Only the key's
+//! SHA-256 is stored.
+
+use axum::http::Method;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, Credential, CurrentUser},
+    db,
+    error::AppError,
+};
+
+/// Marks a bearer token as an API key rather than a session token or JWT.
+pub const KEY_PREFIX: &str = "sk-";
+
+pub const SCOPE_FULL: &str = "full";
+pub const SCOPE_READ_ONLY: &str = "read_only";
+pub const SCOPE_CHAT: &str = "chat";
+pub const SCOPES: &[&str] = &[SCOPE_FULL, SCOPE_READ_ONLY, SCOPE_CHAT];
+
+/// A new key; returned to the caller once and never again.
+pub fn new_key() -> String {
+    format!("{KEY_PREFIX}{}", auth::new_token())
+}
+
+/// Enough of the key to recognise it in a list without being usable.
+pub fn display_prefix(key: &str) -> String {
+    key.chars().take(KEY_PREFIX.len() + 8).collect()
+}
+
+/// The key's user, or `None` for an unknown or revoked key.
+pub async fn authenticate(state: &AppState, key: &str) -> Result<Option<CurrentUser>, AppError> {
+    let row = sqlx::query_as::<_, (i64, String, String, String, i64, i64, String)>(
+        r#"
+        SELECT u.id, u.role, u.name, u.email, u.school_id, k.id, k.scope
+        FROM api_keys k
+        JOIN users u ON u.id = k.user_id
+        WHERE k.key_hash = ?
+        "#,
+    )
+    .bind(auth::token_hash(key))
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((id, role, name, email, school_id, key_id, scope)) = row else {
+        return Ok(None);
+    };
+
+    db::retry_busy(|| {
+        sqlx::query("UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(key_id)
+            .execute(&state.pool)
+    })
+    .await?;
+
+    Ok(Some(CurrentUser {
+        id,
+        role,
+        name,
+        email,
+        school_id,
+        credential: Credential::ApiKey { id: key_id, scope },
+    }))
+}
+
+/// Rejects a request the key's scope doesn't cover.
+pub fn check_scope(scope: &str, method: &Method, path: &str) -> Result<(), AppError> {
+    let allowed = match scope {
+        SCOPE_READ_ONLY => matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS),
+        SCOPE_CHAT => path.starts_with("/llm/"),
+        _ => true,
+    };
+    if !allowed {
+        return Err(AppError::Forbidden(format!(
+            "this API key's scope ({scope}) does not allow {method} {path}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scope_allows_everything() {
+        assert!(check_scope(SCOPE_FULL, &Method::DELETE, "/students/1").is_ok());
+        assert!(check_scope(SCOPE_FULL, &Method::POST, "/admin/api-keys").is_ok());
+    }
+
+    #[test]
+    fn read_only_scope_allows_safe_methods() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            assert!(check_scope(SCOPE_READ_ONLY, &method, "/students").is_ok());
+        }
+        let err = check_scope(SCOPE_READ_ONLY, &Method::POST, "/llm/chat").unwrap_err();
+        assert!(matches!(err, AppError::Forbidden(_)));
+    }
+
+    #[test]
+    fn chat_scope_allows_only_llm_routes() {
+        assert!(check_scope(SCOPE_CHAT, &Method::POST, "/llm/chat").is_ok());
+        assert!(check_scope(SCOPE_CHAT, &Method::GET, "/students").is_err());
+        assert!(check_scope(SCOPE_CHAT, &Method::GET, "/llm").is_err());
+    }
+
+    #[test]
+    fn key_prefix_is_the_display_prefix() {
+        let key = new_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(display_prefix(&key).len(), KEY_PREFIX.len() + 8);
+    }
+}
@@ -1,11 +1,30 @@
+use std::{path::PathBuf, sync::Arc};
+
 use reqwest::Client;
 use sqlx::SqlitePool;
 
-use crate::config::Config;
+use crate::{
+    config::Config, docker::InferenceContainer, experiment::Experiment, idle::IdleState,
+    inference_stats::StatsHistory, model_swap::ModelSwap, rate_limit::RateLimiter,
+    response_filter::ResponseFilter, scheduler::Scheduler, upstream::Upstreams,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: SqlitePool,
     pub llm_client: Client,
+    /// Like `llm_client` but with no overall timeout, for model downloads.
+    pub download_client: Client,
+    pub upstreams: Arc<Upstreams>,
+    pub scheduler: Arc<Scheduler>,
+    pub experiment: Option<Arc<Experiment>>,
+    pub response_filter: Arc<ResponseFilter>,
+    pub inference: Option<Arc<InferenceContainer>>,
+    pub model_swap: Arc<ModelSwap>,
+    pub inference_stats: Arc<StatsHistory>,
+    pub idle: Arc<IdleState>,
+    pub rate_limiter: Arc<RateLimiter>,
     pub config: Config,
+    /// The `--config` file, re-read on reload.
+    pub config_path: Option<PathBuf>,
 }
@@ -0,0 +1,130 @@
+//! One-shot prompts to the local model for teacher-facing helpers such as
+//! feedback drafts. They go through the chat proxy, so they are scheduled,
+//! filtered, and recorded in `ai_interactions` like any other chat.
+
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    routes::{
+        consents,
+        llm::{self, ChatOutcome, LlmProxyRequest},
+    },
+    trace::TraceHeaders,
+    validation,
+};
+
+#[derive(Debug, Default)]
+pub struct Prompt {
+    pub system: String,
+    pub user: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+    /// Teacher the interaction is recorded against.
+    pub user_id: Option<i64>,
+    /// Student whose work or records the prompt carries, refused without
+    /// their AI consent. It isn't passed on to the chat proxy: the draft is
+    /// for the teacher, so the student's profile and grade policies don't
+    /// apply.
+    pub student_id: Option<i64>,
+    /// Asks for a JSON object via `response_format`.
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub struct Completion {
+    pub interaction_id: i64,
+    pub text: String,
+}
+
+pub async fn complete(
+    state: &AppState,
+    prompt: Prompt,
+    trace: TraceHeaders,
+) -> Result<Completion, AppError> {
+    if let Some(student_id) = prompt.student_id {
+        consents::ensure_granted(state, student_id).await?;
+    }
+
+    let mut payload = json!({
+        "messages": [
+            { "role": "system", "content": prompt.system },
+            { "role": "user", "content": prompt.user },
+        ],
+    });
+    if let Some(model) = prompt.model {
+        payload["model"] = json!(model);
+    }
+    if let Some(temperature) = prompt.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = prompt.max_tokens {
+        payload["max_tokens"] = json!(max_tokens);
+    }
+    if prompt.json {
+        payload["response_format"] = json!({ "type": "json_object" });
+    }
+
+    let body = LlmProxyRequest {
+        user_id: prompt.user_id,
+        student_id: None,
+        session_id: None,
+        class_name: None,
+        persona_id: None,
+        few_shot: None,
+        conversation_id: None,
+        parent_interaction_id: None,
+        payload,
+    };
+    validation::validate(&body)?;
+
+    let completion = match llm::chat(state.clone(), body, trace, None).await? {
+        ChatOutcome::Complete(completion) => completion,
+        ChatOutcome::Stream(_) => {
+            return Err(AppError::Internal(
+                "assistant prompt unexpectedly streamed".to_string(),
+            ))
+        }
+    };
+
+    let text = completion.upstream["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .ok_or_else(|| AppError::Upstream("model returned no text".to_string()))?
+        .to_string();
+
+    Ok(Completion {
+        interaction_id: completion.interaction_id,
+        text,
+    })
+}
+
+/// Like [`complete`], but parses the reply as `T`. Models sometimes wrap
+/// JSON in prose or a code fence, so the outermost object is used.
+pub async fn complete_json<T: DeserializeOwned>(
+    state: &AppState,
+    mut prompt: Prompt,
+    trace: TraceHeaders,
+) -> Result<(T, Completion), AppError> {
+    prompt.json = true;
+    let completion = complete(state, prompt, trace).await?;
+
+    let parsed = match (completion.text.find('{'), completion.text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<T>(&completion.text[start..=end]).map_err(|err| err.to_string())
+        }
+        _ => Err("no JSON object in reply".to_string()),
+    }
+    .map_err(|err| {
+        AppError::Upstream(format!(
+            "model reply did not match the expected format ({err}); interaction {}",
+            completion.interaction_id
+        ))
+    })?;
+
+    Ok((parsed, completion))
+}
@@ -0,0 +1,305 @@
+//! Append-only record of every write and admin action: who made it, the
+//! route, the outcome, and for known resources the row before and after.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState, auth::CurrentUser, db, error::AppError, routes::schools::DEFAULT_SCHOOL_ID,
+};
+
+/// Writes that aren't worth auditing: constant background chatter, and
+/// chats and logins, which `ai_interactions` and `sessions` already keep.
+const SKIP_PATHS: &[&str] = &[
+    "/presence/heartbeat",
+    "/llm/chat",
+    "/llm/embeddings",
+    "/auth/login",
+    "/auth/logout",
+    "/auth/token",
+    "/auth/token/refresh",
+    "/auth/token/revoke",
+];
+
+/// Never copied into a snapshot or a created record.
+const REDACTED_COLUMNS: &[&str] = &[
+    "password_hash",
+    "token_hash",
+    "key_hash",
+    "secret",
+    "reset_token",
+    "reset_url",
+    "share_token",
+    "share_url",
+];
+
+/// A route whose path parameter names one row, so updates and deletes can
+/// be snapshotted.
+struct Target {
+    route: &'static str,
+    table: &'static str,
+    column: &'static str,
+    param: &'static str,
+}
+
+const fn target(
+    route: &'static str,
+    table: &'static str,
+    column: &'static str,
+    param: &'static str,
+) -> Target {
+    Target {
+        route,
+        table,
+        column,
+        param,
+    }
+}
+
+const TARGETS: &[Target] = &[
+    target("/students/:id", "students", "id", "id"),
+    target(
+        "/students/:id/notes/:note_id",
+        "student_notes",
+        "id",
+        "note_id",
+    ),
+    target(
+        "/students/:id/consent",
+        "student_consents",
+        "student_id",
+        "id",
+    ),
+    target("/guardians/:id", "guardians", "id", "id"),
+    target("/school-years/:id", "school_years", "id", "id"),
+    target("/classes/:id", "classes", "id", "id"),
+    target("/goals/:id", "goals", "id", "id"),
+    target("/vocabulary-lists/:id", "vocabulary_lists", "id", "id"),
+    target("/assignments/:id", "assignments", "id", "id"),
+    target("/assignments/:id/grade", "grades", "assignment_id", "id"),
+    target("/essays/:id", "essays", "id", "id"),
+    target("/rubrics/:id", "rubrics", "id", "id"),
+    target("/quizzes/:id", "quizzes", "id", "id"),
+    target("/flashcard-decks/:id", "flashcard_decks", "id", "id"),
+    target("/lesson-plans/:id", "lesson_plans", "id", "id"),
+    target("/tags/:name", "tags", "name", "name"),
+    target("/standards/:code", "standards", "code", "code"),
+    target("/users/:id", "users", "id", "id"),
+    target("/admin/api-keys/:id", "api_keys", "id", "id"),
+    target("/admin/schools/:id", "schools", "id", "id"),
+    target("/personas/:id", "personas", "id", "id"),
+    target("/few-shot-examples/:id", "few_shot_examples", "id", "id"),
+    target(
+        "/admin/deployments/:name",
+        "model_deployments",
+        "name",
+        "name",
+    ),
+    target("/admin/reservations/:id", "reservations", "id", "id"),
+    target(
+        "/admin/grade-policies/:grade_level",
+        "grade_policies",
+        "grade_level",
+        "grade_level",
+    ),
+];
+
+/// Set on a response whose body can't be recorded (a file download) to
+/// record this as the entry's "after" instead.
+#[derive(Clone, Debug)]
+pub struct AuditAfter(pub Value);
+
+struct Entry {
+    actor: Option<CurrentUser>,
+    method: String,
+    path: String,
+    route: Option<String>,
+    entity: Option<&'static str>,
+    entity_id: Option<String>,
+    status: u16,
+    request_id: Option<String>,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+pub async fn middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    if matches!(method, Method::GET | Method::HEAD | Method::OPTIONS)
+        || SKIP_PATHS.contains(&path.as_str())
+    {
+        return next.run(req).await;
+    }
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let actor = req.extensions().get::<CurrentUser>().cloned();
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let target = route
+        .as_deref()
+        .and_then(|route| TARGETS.iter().find(|t| t.route == route))
+        .and_then(|t| path_param(t, &path).map(|key| (t, key)));
+
+    let before = match &target {
+        Some((t, key)) if method != Method::POST => snapshot(&state.pool, t, key).await,
+        _ => None,
+    };
+
+    let response = next.run(req).await;
+    let status = response.status();
+
+    let (response, after) = if !status.is_success() {
+        (response, None)
+    } else if let Some(AuditAfter(after)) = response.extensions().get::<AuditAfter>().cloned() {
+        (response, Some(redact(after)))
+    } else if let Some((t, key)) = target.as_ref().filter(|_| method != Method::DELETE) {
+        (response, snapshot(&state.pool, t, key).await)
+    } else if method == Method::POST && is_json(&response) && !path.starts_with("/auth/") {
+        // Creates return the new record, which is the best "after" there is.
+        let (parts, body) = response.into_parts();
+        let bytes = match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                return AppError::Internal(format!("failed to buffer response: {err}"))
+                    .into_response()
+            }
+        };
+        let after = serde_json::from_slice(&bytes).ok().map(redact);
+        (Response::from_parts(parts, Body::from(bytes)), after)
+    } else {
+        (response, None)
+    };
+
+    let entry = Entry {
+        actor,
+        method: method.to_string(),
+        path,
+        route,
+        entity: target.as_ref().map(|(t, _)| t.table),
+        entity_id: target.map(|(_, key)| key),
+        status: status.as_u16(),
+        request_id,
+        before,
+        after,
+    };
+    if let Err(err) = db::retry_busy(|| record(&state.pool, &entry)).await {
+        warn!(error = %err, "failed to write audit log entry");
+    }
+
+    response
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"))
+}
+
+fn redact(mut value: Value) -> Value {
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|field, _| !REDACTED_COLUMNS.contains(&field.as_str()));
+    }
+    value
+}
+
+/// The path segment that lines up with `:param` in the target's route.
+fn path_param(target: &Target, path: &str) -> Option<String> {
+    let position = target
+        .route
+        .split('/')
+        .position(|segment| segment.strip_prefix(':') == Some(target.param))?;
+    path.split('/')
+        .nth(position)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// The row as a JSON object, or `None` when it doesn't exist (or can't be
+/// read, which is logged rather than failing the request).
+async fn snapshot(pool: &SqlitePool, target: &Target, key: &str) -> Option<Value> {
+    match try_snapshot(pool, target, key).await {
+        Ok(row) => row,
+        Err(err) => {
+            warn!(error = %err, table = target.table, key, "failed to snapshot row for audit log");
+            None
+        }
+    }
+}
+
+async fn try_snapshot(
+    pool: &SqlitePool,
+    target: &Target,
+    key: &str,
+) -> Result<Option<Value>, sqlx::Error> {
+    let columns: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+            .bind(target.table)
+            .fetch_all(pool)
+            .await?;
+    let pairs = columns
+        .iter()
+        .filter(|c| !REDACTED_COLUMNS.contains(&c.as_str()))
+        .map(|c| format!("'{c}', \"{c}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let row: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT json_object({pairs}) FROM {} WHERE {} = ?",
+        target.table, target.column
+    ))
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+async fn record(pool: &SqlitePool, entry: &Entry) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log(
+            actor_user_id, actor_email, method, path, route, entity, entity_id,
+            status, request_id, before_json, after_json, school_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(entry.actor.as_ref().map(|a| a.id))
+    .bind(entry.actor.as_ref().map(|a| a.email.as_str()))
+    .bind(&entry.method)
+    .bind(&entry.path)
+    .bind(&entry.route)
+    .bind(entry.entity)
+    .bind(&entry.entity_id)
+    .bind(i64::from(entry.status))
+    .bind(&entry.request_id)
+    .bind(entry.before.as_ref().map(Value::to_string))
+    .bind(entry.after.as_ref().map(Value::to_string))
+    .bind(
+        entry
+            .actor
+            .as_ref()
+            .map_or(DEFAULT_SCHOOL_ID, |a| a.school_id),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,355 @@
+//! Password login and server-side sessions. A session token is accepted from
+//! the `session` cookie (the dashboard) or an `Authorization: Bearer` header
+//! (scripts and other clients); only its SHA-256 is stored. Bearer tokens
+//! that look like JWTs are handed to `jwt` instead, and API keys to
+//! `api_keys`.
+
+use std::{fmt::Write, future::Future};
+
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
+    Argon2,
+};
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api_keys, app_state::AppState, db, error::AppError, jwt, rate_limit::LIMIT_HEADER,
+    routes::schools::DEFAULT_SCHOOL_ID,
+};
+
+pub const SESSION_COOKIE: &str = "session";
+
+/// Reachable without a session once login is required.
+const PUBLIC_PATHS: &[&str] = &[
+    "/healthz",
+    "/auth/login",
+    "/auth/register",
+    "/auth/password-reset",
+    "/auth/password-reset/confirm",
+    "/auth/token",
+    "/auth/token/refresh",
+    "/auth/token/revoke",
+    "/auth/oidc/login",
+    "/auth/oidc/callback",
+    "/shared/progress-report",
+];
+
+tokio::task_local! {
+    static CALLER: CurrentUser;
+    static SCHOOL: i64;
+    static ANONYMOUS: bool;
+}
+
+/// The user behind the request's session or access token.
+#[derive(Clone, Debug, Serialize)]
+pub struct CurrentUser {
+    pub id: i64,
+    pub role: String,
+    pub name: String,
+    pub email: String,
+    pub school_id: i64,
+    #[serde(skip)]
+    pub credential: Credential,
+}
+
+/// How the request authenticated, so logout knows what to end.
+#[derive(Clone, Debug)]
+pub enum Credential {
+    Session(i64),
+    AccessToken {
+        jti: String,
+        family_id: String,
+        /// Unix seconds.
+        expires_at: u64,
+    },
+    ApiKey {
+        id: i64,
+        /// One of `api_keys::SCOPES`.
+        scope: String,
+    },
+}
+
+impl CurrentUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    /// Fails with 403 unless the user is an admin, for operator routes.
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        if !self.is_admin() {
+            return Err(AppError::Forbidden("admin role required".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The request-rate window this caller counts against: each API key has
+    /// its own, apart from its owner's sessions and tokens.
+    fn rate_limit_key(&self) -> String {
+        match &self.credential {
+            Credential::ApiKey { id, .. } => format!("api_key:{id}"),
+            _ => format!("user:{}", self.id),
+        }
+    }
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &AppState) -> Result<Self, AppError> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("login required".to_string()))
+    }
+}
+
+/// The authenticated caller of the request being handled, so code far from
+/// the handler (the chat proxy) can attribute and limit usage.
+pub fn caller() -> Option<CurrentUser> {
+    CALLER.try_with(CurrentUser::clone).ok()
+}
+
+/// Whether this is a request without a login, as opposed to a logged-in
+/// request or a background job, which have no request at all.
+pub fn is_anonymous() -> bool {
+    ANONYMOUS.try_with(|anonymous| *anonymous).unwrap_or(false)
+}
+
+/// The school every query is scoped to: the caller's, the one a background
+/// job set with [`in_school`], or the default school. Spawned tasks don't
+/// inherit it, so capture it before spawning.
+pub fn school_id() -> i64 {
+    SCHOOL.try_with(|id| *id).unwrap_or(DEFAULT_SCHOOL_ID)
+}
+
+/// Runs `fut` scoped to `school_id`, for background jobs that work through
+/// every school's records.
+pub async fn in_school<F: Future>(school_id: i64, fut: F) -> F::Output {
+    SCHOOL.scope(school_id, fut).await
+}
+
+/// Resolves the caller for `CurrentUser` and `caller()`, holds API keys to
+/// their scope, counts the request against the caller's per-minute limit,
+/// and rejects anonymous requests outside `PUBLIC_PATHS` once login is
+/// required: with `AUTH_REQUIRED=true`, with single sign-on configured, or
+/// as soon as any account can log in.
+pub async fn middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(user) = resolve(&state, req.headers()).await? else {
+        if !PUBLIC_PATHS.contains(&req.uri().path()) && login_required(&state).await? {
+            return Err(AppError::Unauthorized("login required".to_string()));
+        }
+        return Ok(ANONYMOUS.scope(true, next.run(req)).await);
+    };
+    if let Credential::ApiKey { scope, .. } = &user.credential {
+        api_keys::check_scope(scope, req.method(), req.uri().path())?;
+    }
+
+    let quota = state.rate_limiter.check(
+        &user.rate_limit_key(),
+        state.rate_limiter.requests_per_minute(),
+    )?;
+    req.extensions_mut().insert(user.clone());
+
+    let school_id = user.school_id;
+    let mut response = CALLER
+        .scope(user, SCHOOL.scope(school_id, next.run(req)))
+        .await;
+    // A token-limit rejection already carries its own quota.
+    if let Some(quota) = quota.filter(|_| !response.headers().contains_key(LIMIT_HEADER)) {
+        quota.apply(response.headers_mut());
+    }
+    Ok(response)
+}
+
+/// Before the first account gets a password or a single sign-on identity
+/// nobody could log in, so the API stays open for setup. With OIDC
+/// configured anyone can get an account that way, so it is never open.
+async fn login_required(state: &AppState) -> Result<bool, AppError> {
+    if state.config.auth_required || state.config.oidc_issuer.is_some() {
+        return Ok(true);
+    }
+    let has_accounts: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM users WHERE password_hash IS NOT NULL)
+            OR EXISTS(SELECT 1 FROM user_identities)
+        "#,
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(has_accounts)
+}
+
+async fn resolve(state: &AppState, headers: &HeaderMap) -> Result<Option<CurrentUser>, AppError> {
+    let Some(token) = session_token(headers) else {
+        return Ok(None);
+    };
+
+    // Session tokens are plain hex, so a dot means a JWT.
+    if token.contains('.') {
+        return jwt::authenticate(state, &token).await;
+    }
+    if token.starts_with(api_keys::KEY_PREFIX) {
+        return api_keys::authenticate(state, &token).await;
+    }
+
+    let row = sqlx::query_as::<_, (i64, String, String, String, i64, i64)>(
+        r#"
+        SELECT u.id, u.role, u.name, u.email, u.school_id, s.id
+        FROM sessions s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.token_hash = ? AND s.expires_at > CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(token_hash(&token))
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((id, role, name, email, school_id, session_id)) = row else {
+        return Ok(None);
+    };
+
+    db::retry_busy(|| {
+        sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(session_id)
+            .execute(&state.pool)
+    })
+    .await?;
+
+    Ok(Some(CurrentUser {
+        id,
+        role,
+        name,
+        email,
+        school_id,
+        credential: Credential::Session(session_id),
+    }))
+}
+
+/// Creates a session for `user_id`, returning its token and expiry.
+pub async fn start_session(
+    state: &AppState,
+    user_id: i64,
+    headers: &HeaderMap,
+) -> Result<(String, String), AppError> {
+    sqlx::query("DELETE FROM sessions WHERE expires_at <= CURRENT_TIMESTAMP")
+        .execute(&state.pool)
+        .await?;
+
+    let token = new_token();
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok());
+    let expires_at: String = sqlx::query_scalar(
+        r#"
+        INSERT INTO sessions(user_id, token_hash, user_agent, expires_at)
+        VALUES(?, ?, ?, datetime('now', ?))
+        RETURNING expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(token_hash(&token))
+    .bind(user_agent)
+    .bind(format!("+{} hours", state.config.session_ttl_hours))
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((token, expires_at))
+}
+
+/// The bearer token if present, otherwise the session cookie.
+fn session_token(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    if let Some(token) = bearer.filter(|t| !t.is_empty()) {
+        return Some(token.to_string());
+    }
+
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, value)| *name == SESSION_COOKIE && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+/// 32 random bytes, hex-encoded.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+pub fn token_hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Hashing is deliberately slow, so it runs off the async workers.
+pub async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| AppError::Internal(format!("password hashing failed: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::Internal(format!("password hashing task failed: {err}")))?
+}
+
+pub async fn verify_password(hash: String, password: String) -> Result<bool, AppError> {
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&hash)
+            .map(|parsed| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &parsed)
+                    .is_ok()
+            })
+            .map_err(|err| AppError::Internal(format!("stored password hash is invalid: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::Internal(format!("password check task failed: {err}")))?
+}
+
+/// `Set-Cookie` value for a new session, or one that clears it when `token`
+/// is `None`.
+pub fn session_cookie(state: &AppState, token: Option<&str>) -> String {
+    let (value, max_age) = match token {
+        Some(token) => (token, state.config.session_ttl_hours * 3600),
+        None => ("", 0),
+    };
+    let secure = if state.config.session_cookie_secure {
+        "; Secure"
+    } else {
+        ""
+    };
+
+    format!("{SESSION_COOKIE}={value}; Path=/; HttpOnly; SameSite=Lax; Max-Age={max_age}{secure}")
+}
@@ -0,0 +1,220 @@
+//! Online snapshots of the SQLite database. `VACUUM INTO` copies a
+//! consistent view of the live database (WAL included) into a new file
+//! without blocking writers, so backups are safe while the server runs.
+//! Every `BACKUP_INTERVAL_SECS` a background job takes one and rotates
+//! `BACKUP_DIR`, keeping the newest snapshot of each of the last
+//! `BACKUP_KEEP_DAILY` days and `BACKUP_KEEP_WEEKLY` weeks.
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Serialize;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool},
+    ConnectOptions,
+};
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, config::Config, db};
+
+#[derive(Debug, Serialize)]
+pub struct Backup {
+    pub path: String,
+    pub bytes: u64,
+    pub created_at: String,
+}
+
+/// Writes `backup-<UTC timestamp>.db` into `dir`, creating it if needed.
+pub async fn snapshot(pool: &SqlitePool, dir: &Path) -> Result<Backup, sqlx::Error> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    let (stamp, created_at): (String, String) = sqlx::query_as(
+        "SELECT strftime('%Y%m%dT%H%M%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')",
+    )
+    .fetch_one(pool)
+    .await?;
+    // `VACUUM INTO` refuses to overwrite, so two snapshots in the same
+    // second get a suffix.
+    let mut path = dir.join(format!("backup-{stamp}.db"));
+    let mut n = 1;
+    while tokio::fs::try_exists(&path).await? {
+        n += 1;
+        path = dir.join(format!("backup-{stamp}-{n}.db"));
+    }
+    let path = path.to_string_lossy().into_owned();
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(&path)
+        .execute(pool)
+        .await?;
+    let bytes = tokio::fs::metadata(&path).await?.len();
+
+    info!(path, bytes, "database backup written");
+    Ok(Backup {
+        path,
+        bytes,
+        created_at,
+    })
+}
+
+pub fn spawn(state: AppState) {
+    let interval_secs = state.config.backup_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let dir = PathBuf::from(&state.config.backup_dir);
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            if let Err(err) = snapshot(&state.pool, &dir).await {
+                warn!(error = %err, "scheduled database backup failed");
+                continue;
+            }
+            match rotate(
+                &dir,
+                state.config.backup_keep_daily,
+                state.config.backup_keep_weekly,
+            )
+            .await
+            {
+                Ok(removed) if !removed.is_empty() => {
+                    info!(removed = removed.len(), "rotated database backups");
+                }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "database backup rotation failed"),
+            }
+        }
+    });
+}
+
+/// Deletes the snapshots in `dir` that neither rule keeps: the newest of
+/// each of the last `keep_daily` days and of each of the last `keep_weekly`
+/// weeks (Monday to Sunday, UTC) that have one. With both at zero nothing
+/// is deleted. Files not named like a snapshot are never touched.
+pub async fn rotate(dir: &Path, keep_daily: u32, keep_weekly: u32) -> io::Result<Vec<PathBuf>> {
+    if keep_daily == 0 && keep_weekly == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(day) = snapshot_day(&name) {
+            snapshots.push((name, day, entry.path()));
+        }
+    }
+    // Newest first; the timestamp sorts lexically.
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = Vec::new();
+    for (_, day, path) in snapshots {
+        // 1970-01-01 was a Thursday; shift so weeks start on Monday.
+        let week = (day + 3).div_euclid(7);
+        let mut keep = false;
+        if days.len() < keep_daily as usize && days.insert(day) {
+            keep = true;
+        }
+        if weeks.len() < keep_weekly as usize && weeks.insert(week) {
+            keep = true;
+        }
+        if !keep {
+            tokio::fs::remove_file(&path).await?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Days since 1970-01-01 from a `backup-YYYYMMDDTHHMMSSZ[-n].db` name.
+fn snapshot_day(name: &str) -> Option<i64> {
+    let stamp = name.strip_prefix("backup-")?.strip_suffix(".db")?;
+    let date = stamp.get(..8)?;
+    if stamp.get(8..9) != Some("T") || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year: i64 = date[..4].parse().ok()?;
+    let month: i64 = date[4..6].parse().ok()?;
+    let day: i64 = date[6..].parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Howard Hinnant's days_from_civil.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146097 + doe - 719468)
+}
+
+/// Replaces the `DATABASE_URL` file with `file`. The server must be
+/// stopped. The current database is snapshotted into `BACKUP_DIR` first,
+/// and its WAL and shared-memory files are removed so SQLite can't replay
+/// them over the restored copy. Returns that snapshot, if one was taken.
+pub async fn restore(
+    cfg: &Config,
+    file: &Path,
+) -> Result<Option<Backup>, Box<dyn std::error::Error>> {
+    let target = db::sqlite_file(&cfg.database_url)
+        .ok_or("DATABASE_URL does not name a database file")?
+        .to_path_buf();
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(file)
+        .read_only(true)
+        .connect()
+        .await?;
+    let check: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(&mut conn)
+        .await?;
+    if check != "ok" {
+        return Err(format!("{} failed its integrity check: {check}", file.display()).into());
+    }
+    let has_migrations: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut conn)
+    .await?;
+    if !has_migrations {
+        return Err(format!("{} is not a backup of this database", file.display()).into());
+    }
+    drop(conn);
+
+    let previous = if tokio::fs::try_exists(&target).await? {
+        let pool = db::connect(cfg).await?;
+        let backup = snapshot(&pool, Path::new(&cfg.backup_dir)).await?;
+        pool.close().await;
+        Some(backup)
+    } else {
+        None
+    };
+
+    // Copy next to the target, then rename, so a failed copy never leaves
+    // a half-written database behind.
+    let mut staging = target.clone().into_os_string();
+    staging.push(".restore");
+    tokio::fs::copy(file, &staging).await?;
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = target.clone().into_os_string();
+        sidecar.push(suffix);
+        if let Err(err) = tokio::fs::remove_file(&sidecar).await {
+            if err.kind() != io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+    }
+    tokio::fs::rename(&staging, &target).await?;
+
+    info!(from = %file.display(), to = %target.display(), "database restored");
+    Ok(previous)
+}
@@ -0,0 +1,463 @@
+//! Command line. With no subcommand the binary serves the API; the others are
+//! the ops tasks that would otherwise need hand-written SQL. Flags override
+//! the matching env vars, which override the config file.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+    auth, backup,
+    config::{self, Config, Origin},
+    config_file::ConfigFile,
+    db::{self, MigrationState},
+    interactions::{self, InteractionFilter},
+    routes::{
+        auth::check_new_password,
+        students::{Student, STUDENT_COLUMNS},
+    },
+    validation::Validator,
+};
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Parser)]
+#[command(name = "homeschool-backend", version, about)]
+pub struct Cli {
+    /// TOML or YAML settings file. Falls back to `CONFIG_PATH`.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Overrides `DATABASE_URL`.
+    #[arg(long, global = true, value_name = "URL")]
+    pub database_url: Option<String>,
+    /// Print the resolved settings as JSON, secrets redacted, and exit.
+    #[arg(long, global = true)]
+    pub print_config: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run migrations and serve the API (the default).
+    Serve(ServeArgs),
+    /// Show, apply, or revert schema migrations (`run` by default).
+    Migrate(MigrateArgs),
+    /// Create an admin account, or reset its password if the email exists.
+    Seed(SeedArgs),
+    /// Write students or interactions to a file or stdout.
+    Export(ExportArgs),
+    /// Load the settings, report any problems, and exit.
+    CheckConfig,
+    /// Snapshot the database with `VACUUM INTO`; safe while the server runs.
+    Backup(BackupArgs),
+    /// Replace the database with a snapshot. Stop the server first.
+    Restore(RestoreArgs),
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Serve(ServeArgs::default())
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Overrides `APP_HOST`.
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Overrides `APP_PORT`.
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Overrides `AUTH_REQUIRED`.
+    #[arg(long, value_name = "BOOL")]
+    pub auth_required: Option<bool>,
+}
+
+impl ServeArgs {
+    pub fn apply(&self, cfg: &mut Config) {
+        if let Some(host) = &self.host {
+            cfg.app_host = host.clone();
+            cfg.sources
+                .insert("APP_HOST".to_string(), Origin::CommandLine);
+        }
+        if let Some(port) = self.port {
+            cfg.app_port = port;
+            cfg.sources
+                .insert("APP_PORT".to_string(), Origin::CommandLine);
+        }
+        if let Some(auth_required) = self.auth_required {
+            cfg.auth_required = auth_required;
+            cfg.sources
+                .insert("AUTH_REQUIRED".to_string(), Origin::CommandLine);
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    #[command(subcommand)]
+    pub action: Option<MigrateAction>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateAction {
+    /// List every migration and whether it has been applied.
+    Status,
+    /// Apply pending migrations.
+    Run,
+    /// Undo the latest migration, or every one newer than `--target`.
+    Revert {
+        #[arg(long, value_name = "VERSION")]
+        target: Option<i64>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    #[arg(long)]
+    pub admin_email: String,
+    #[arg(long, default_value = "Admin")]
+    pub admin_name: String,
+    /// Falls back to `SEED_ADMIN_PASSWORD`; prefer the env var, so the
+    /// password stays out of shell history.
+    #[arg(long)]
+    pub admin_password: Option<String>,
+    #[arg(long, default_value_t = 1)]
+    pub school_id: i64,
+}
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    /// Directory for the snapshot; overrides `BACKUP_DIR`.
+    #[arg(long, short, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Snapshot written by `backup` or `POST /admin/backup`.
+    #[arg(value_name = "FILE")]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[arg(value_enum)]
+    pub what: ExportKind,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// Defaults to stdout.
+    #[arg(long, short, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    #[arg(long, default_value_t = 1)]
+    pub school_id: i64,
+    /// Interactions created at or after this time.
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub since: Option<String>,
+    /// Interactions created before this time.
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub until: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportKind {
+    Students,
+    Interactions,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl Cli {
+    /// Parses the command line. `CONFIG_PATH` is read here rather than by
+    /// clap so that it honours `ENV_PREFIX` like every other setting.
+    pub fn parse_with_env() -> Self {
+        let mut cli = Self::parse();
+        if cli.config.is_none() {
+            cli.config = config::env_var("CONFIG_PATH").map(PathBuf::from);
+        }
+        cli
+    }
+
+    /// Reads the config file (if any) and the environment, then applies the
+    /// global flags.
+    pub fn load_config(&self) -> Result<(Option<ConfigFile>, Config), Box<dyn Error>> {
+        let file = self.config.as_deref().map(ConfigFile::load).transpose()?;
+        let mut cfg = Config::load(file.as_ref())?;
+        if let Some(database_url) = &self.database_url {
+            cfg.database_url = database_url.clone();
+            cfg.sources
+                .insert("DATABASE_URL".to_string(), Origin::CommandLine);
+        }
+        Ok((file, cfg))
+    }
+}
+
+pub async fn migrate(cfg: &Config, args: MigrateArgs) -> Result<(), Box<dyn Error>> {
+    let pool = db::connect(cfg).await?;
+    match args.action.unwrap_or(MigrateAction::Run) {
+        MigrateAction::Status => {
+            let migrations = db::migration_status(&pool).await?;
+            println!(
+                "{:<14} {:<9} {:<20} DESCRIPTION",
+                "VERSION", "STATE", "INSTALLED"
+            );
+            for m in &migrations {
+                let state = match m.state {
+                    MigrationState::Applied => "applied",
+                    MigrationState::Pending => "pending",
+                    MigrationState::Failed => "FAILED",
+                    MigrationState::Modified => "MODIFIED",
+                };
+                println!(
+                    "{:<14} {:<9} {:<20} {}{}",
+                    m.version,
+                    state,
+                    m.installed_on.as_deref().unwrap_or("-"),
+                    m.description,
+                    if m.reversible { " (reversible)" } else { "" }
+                );
+            }
+            let count =
+                |state: MigrationState| migrations.iter().filter(|m| m.state == state).count();
+            println!(
+                "{} applied, {} pending, {} failed, {} modified ({})",
+                count(MigrationState::Applied),
+                count(MigrationState::Pending),
+                count(MigrationState::Failed),
+                count(MigrationState::Modified),
+                cfg.database_url
+            );
+        }
+        MigrateAction::Run => {
+            let applied = db::migrate(&pool).await?;
+            println!("applied {applied} migration(s) to {}", cfg.database_url);
+        }
+        MigrateAction::Revert { target } => {
+            let reverted = db::revert(&pool, target).await?;
+            if reverted.is_empty() {
+                println!("nothing to revert in {}", cfg.database_url);
+            }
+            for version in reverted {
+                println!("reverted {version}");
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn seed(cfg: &Config, args: SeedArgs) -> Result<(), Box<dyn Error>> {
+    let email = args.admin_email.trim().to_lowercase();
+    let admin_password = args
+        .admin_password
+        .or_else(|| config::env_var("SEED_ADMIN_PASSWORD"))
+        .unwrap_or_default();
+    let mut v = Validator::default();
+    v.required("admin_email", &email);
+    v.required("admin_name", &args.admin_name);
+    check_new_password(&mut v, "admin_password", &admin_password);
+    v.finish()?;
+
+    let pool = db::connect(cfg).await?;
+    db::migrate(&pool).await?;
+
+    let school_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM schools WHERE id = ?)")
+            .bind(args.school_id)
+            .fetch_one(&pool)
+            .await?;
+    if !school_exists {
+        return Err(format!("school {} does not exist", args.school_id).into());
+    }
+
+    let password_hash = auth::hash_password(admin_password).await?;
+    // An existing account keeps its school; it is only promoted and given
+    // the new password.
+    let (id, school_id): (i64, i64) = sqlx::query_as(
+        r#"
+        INSERT INTO users(role, name, email, password_hash, school_id) VALUES('admin', ?, ?, ?, ?)
+        ON CONFLICT(email) DO UPDATE SET
+            role = 'admin',
+            name = excluded.name,
+            password_hash = excluded.password_hash
+        RETURNING id, school_id
+        "#,
+    )
+    .bind(args.admin_name.trim())
+    .bind(&email)
+    .bind(&password_hash)
+    .bind(args.school_id)
+    .fetch_one(&pool)
+    .await?;
+
+    println!("admin {email} is user {id} in school {school_id}");
+    Ok(())
+}
+
+pub async fn backup(cfg: &Config, args: BackupArgs) -> Result<(), Box<dyn Error>> {
+    let pool = db::connect(cfg).await?;
+    let dir = args
+        .output_dir
+        .unwrap_or_else(|| PathBuf::from(&cfg.backup_dir));
+    let backup = backup::snapshot(&pool, &dir).await?;
+    println!("wrote {} ({} bytes)", backup.path, backup.bytes);
+    Ok(())
+}
+
+pub async fn restore(cfg: &Config, args: RestoreArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(previous) = backup::restore(cfg, &args.file).await? {
+        println!("saved the previous database as {}", previous.path);
+    }
+    println!("restored {} to {}", args.file.display(), cfg.database_url);
+    Ok(())
+}
+
+pub async fn export(cfg: &Config, args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let pool = db::connect(cfg).await?;
+    db::migrate(&pool).await?;
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut rows = RowWriter::new(args.format, out)?;
+
+    match args.what {
+        ExportKind::Students => {
+            let students = sqlx::query_as::<_, Student>(&format!(
+                "SELECT {STUDENT_COLUMNS} FROM students \
+                 WHERE school_id = ? AND deleted_at IS NULL ORDER BY id ASC"
+            ))
+            .bind(args.school_id)
+            .fetch_all(&pool)
+            .await?;
+            for student in &students {
+                rows.write(student)?;
+            }
+        }
+        ExportKind::Interactions => {
+            let filter = InteractionFilter {
+                since: args.since,
+                until: args.until,
+                ..Default::default()
+            };
+            let mut offset = 0;
+            loop {
+                let page = auth::in_school(
+                    args.school_id,
+                    interactions::list(&pool, &filter, EXPORT_PAGE_SIZE, offset),
+                )
+                .await?;
+                for interaction in &page.items {
+                    rows.write(interaction)?;
+                }
+                offset += EXPORT_PAGE_SIZE;
+                if offset >= page.total {
+                    break;
+                }
+            }
+        }
+    }
+
+    let count = rows.finish()?;
+    eprintln!("exported {count} row(s)");
+    Ok(())
+}
+
+pub fn print_config(cfg: &Config, config_file: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&cfg.effective(config_file))?
+    );
+    Ok(())
+}
+
+/// Prints the settings that matter for a deploy, never secrets.
+pub fn check_config(cfg: &Config, file: Option<&ConfigFile>) -> Result<(), Box<dyn Error>> {
+    let set = |value: &Option<String>| if value.is_some() { "set" } else { "not set" };
+
+    if let Some(file) = file {
+        println!("config file:      {}", file.path.display());
+    }
+    println!("database:         {}", cfg.database_url);
+    println!(
+        "listen:           {}://{}:{}",
+        if cfg.tls_cert_path.is_some() {
+            "https"
+        } else {
+            "http"
+        },
+        cfg.app_host,
+        cfg.app_port
+    );
+    println!("llm backends:     {}", cfg.llm_base_urls.join(", "));
+    println!("auth required:    {}", cfg.auth_required);
+    println!("jwt secret:       {}", set(&cfg.jwt_secret));
+    println!(
+        "smtp host:        {}",
+        cfg.smtp_host.as_deref().unwrap_or("not set")
+    );
+    println!("report links:     {}", set(&cfg.report_link_secret));
+    println!("config OK");
+    Ok(())
+}
+
+enum RowWriter {
+    // Boxed: the CSV writer carries its own buffer.
+    Csv(Box<csv::Writer<Box<dyn Write>>>, usize),
+    Json(Box<dyn Write>, usize),
+}
+
+impl RowWriter {
+    fn new(format: ExportFormat, mut out: Box<dyn Write>) -> io::Result<Self> {
+        Ok(match format {
+            ExportFormat::Csv => RowWriter::Csv(Box::new(csv::Writer::from_writer(out)), 0),
+            ExportFormat::Json => {
+                out.write_all(b"[")?;
+                RowWriter::Json(out, 0)
+            }
+        })
+    }
+
+    fn write<T: Serialize>(&mut self, row: &T) -> Result<(), Box<dyn Error>> {
+        match self {
+            RowWriter::Csv(writer, count) => {
+                writer.serialize(row)?;
+                *count += 1;
+            }
+            RowWriter::Json(out, count) => {
+                if *count > 0 {
+                    out.write_all(b",")?;
+                }
+                out.write_all(b"\n")?;
+                serde_json::to_writer(&mut *out, row)?;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<usize, Box<dyn Error>> {
+        match self {
+            RowWriter::Csv(mut writer, count) => {
+                writer.flush()?;
+                Ok(count)
+            }
+            RowWriter::Json(mut out, count) => {
+                out.write_all(b"\n]\n")?;
+                out.flush()?;
+                Ok(count)
+            }
+        }
+    }
+}
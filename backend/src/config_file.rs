@@ -0,0 +1,99 @@
+//! Settings from a TOML or YAML file, for deployments that would rather not
+//! keep dozens of env vars. Keys are the env var names in any case, and
+//! tables nest with `_`, so `[llm] base_url` is `LLM_BASE_URL`. Lists are
+//! joined with commas. Env vars still win over the file.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    values: HashMap<String, String>,
+}
+
+impl ConfigFile {
+    /// Reads the file, picking the format from its extension (`.toml`,
+    /// `.yaml`, or `.yml`).
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read config file {}: {err}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let root: Value = match extension.as_deref() {
+            Some("toml") => toml::from_str(&text)
+                .map_err(|err| format!("invalid TOML in {}: {err}", path.display()))?,
+            Some("yaml" | "yml") => serde_yaml::from_str(&text)
+                .map_err(|err| format!("invalid YAML in {}: {err}", path.display()))?,
+            _ => {
+                return Err(format!(
+                    "config file {} must end in .toml, .yaml, or .yml",
+                    path.display()
+                )
+                .into())
+            }
+        };
+
+        let mut values = HashMap::new();
+        flatten("", &root, &mut values)
+            .map_err(|err| format!("invalid config file {}: {err}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            values,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut HashMap<String, String>) -> Result<(), String> {
+    let scalar = |value: &Value| match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = key.to_ascii_uppercase().replace(['-', '.'], "_");
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}_{key}")
+                };
+                flatten(&key, value, out)?;
+            }
+        }
+        _ if prefix.is_empty() => return Err("expected a table of settings".to_string()),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| {
+                    scalar(item).ok_or_else(|| format!("{prefix} must be a list of values"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            out.insert(prefix.to_string(), items.join(","));
+        }
+        Value::Null => {}
+        other => {
+            out.insert(prefix.to_string(), scalar(other).unwrap_or_default());
+        }
+    }
+    Ok(())
+}
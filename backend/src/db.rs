@@ -1,60 +1,406 @@
-use std::{path::Path, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    future::Future,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use reqwest::Client;
+use serde::Serialize;
 use sqlx::{
-    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
-    Executor,
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+use tracing::debug;
+
+use crate::{
+    app_state::AppState, config::Config, docker::InferenceContainer, experiment::Experiment,
+    http_client, inference_stats::StatsHistory, rate_limit::RateLimiter,
+    response_filter::ResponseFilter, scheduler::Scheduler, upstream::Upstreams,
 };
 
-use crate::{app_state::AppState, config::Config};
+pub async fn build_state(
+    cfg: Config,
+    config_path: Option<PathBuf>,
+) -> Result<AppState, Box<dyn std::error::Error>> {
+    let pool = connect(&cfg).await?;
+    if cfg.database_auto_migrate {
+        migrate(&pool).await?;
+    } else {
+        let pending = migration_status(&pool)
+            .await?
+            .iter()
+            .filter(|m| m.state != MigrationState::Applied)
+            .count();
+        if pending > 0 {
+            return Err(format!(
+                "{pending} migration(s) not applied and DATABASE_AUTO_MIGRATE is off; \
+                 run `migrate status` and `migrate run`"
+            )
+            .into());
+        }
+    }
+
+    let llm_client = http_client::llm_client(&cfg)?;
+    let download_client = http_client::download_client(&cfg)?;
+
+    let upstreams = Arc::new(Upstreams::from_config(&cfg));
+    let experiment = Experiment::from_config(&cfg).map(Arc::new);
+    let response_filter = Arc::new(ResponseFilter::load(
+        cfg.llm_response_filter_file.as_deref(),
+    )?);
+    let inference = InferenceContainer::from_config(&cfg)?.map(Arc::new);
+    let scheduler = Arc::new(Scheduler::new(
+        cfg.llm_max_concurrency,
+        cfg.llm_unreserved_slots,
+    ));
+
+    Ok(AppState {
+        pool,
+        llm_client,
+        download_client,
+        upstreams,
+        scheduler,
+        experiment,
+        response_filter,
+        inference,
+        model_swap: Arc::default(),
+        idle: Arc::default(),
+        rate_limiter: Arc::new(RateLimiter::from_config(&cfg)),
+        inference_stats: Arc::new(StatsHistory::new(cfg.docker_stats_history_minutes * 60)),
+        config: cfg,
+        config_path,
+    })
+}
 
-pub async fn build_state(cfg: Config) -> Result<AppState, Box<dyn std::error::Error>> {
+/// Opens the SQLite pool, creating the file (and its directory) if needed.
+/// Doesn't run migrations.
+pub async fn connect(cfg: &Config) -> Result<SqlitePool, Box<dyn std::error::Error>> {
     ensure_sqlite_parent_dir(&cfg.database_url)?;
 
+    // Set on every pooled connection as it opens, not just the first.
+    // Without a busy timeout a write that meets another fails at once with
+    // SQLITE_BUSY instead of waiting its turn.
     let opts = SqliteConnectOptions::from_str(&cfg.database_url)?
         .create_if_missing(true)
-        .journal_mode(SqliteJournalMode::Wal)
-        .synchronous(SqliteSynchronous::Normal)
+        .journal_mode(cfg.sqlite_journal_mode)
+        .synchronous(cfg.sqlite_synchronous)
+        .busy_timeout(Duration::from_millis(cfg.sqlite_busy_timeout_ms))
+        .pragma("cache_size", Cow::Owned(cfg.sqlite_cache_size.to_string()))
         .foreign_keys(true);
 
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
+        .max_connections(cfg.sqlite_max_connections)
         .connect_with(opts)
         .await?;
 
-    // Startup pragmas keep defaults explicit if settings are changed by external tooling.
-    pool.execute("PRAGMA journal_mode=WAL;").await?;
-    pool.execute("PRAGMA synchronous=NORMAL;").await?;
-    pool.execute("PRAGMA foreign_keys=ON;").await?;
+    Ok(pool)
+}
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+/// Extra attempts [`retry_busy`] makes after the first.
+const BUSY_RETRIES: u32 = 3;
+const BUSY_BACKOFF_MS: u64 = 50;
 
-    let llm_client = Client::builder()
-        .timeout(std::time::Duration::from_secs(90))
-        .build()?;
+/// Whether SQLite gave up waiting for another connection's lock:
+/// `SQLITE_BUSY` or `SQLITE_LOCKED`, including their extended codes. The
+/// busy timeout covers most contention, but a deferred transaction that
+/// reads and then tries to write fails with `SQLITE_BUSY_SNAPSHOT` at
+/// once, and a long enough write can outlast the timeout.
+pub fn is_busy(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db) = err else {
+        return false;
+    };
+    let code = db.code().and_then(|code| code.parse::<i32>().ok());
+    matches!(code.map(|code| code & 0xff), Some(5 | 6))
+}
 
-    Ok(AppState {
-        pool,
-        llm_client,
-        config: cfg,
-    })
+/// Runs `op` again, with a short growing backoff, while it fails with a
+/// lock error. `op` must be safe to repeat: a single statement, or a whole
+/// transaction that is begun inside it.
+pub async fn retry_busy<T, F, Fut>(mut op: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(err) if attempt < BUSY_RETRIES && is_busy(&err) => {
+                attempt += 1;
+                // Jitter, so writers that collided don't collide again.
+                let jitter = u64::from(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.subsec_nanos()),
+                ) % BUSY_BACKOFF_MS;
+                let delay = BUSY_BACKOFF_MS * (1 << (attempt - 1)) + jitter;
+                debug!(attempt, delay_ms = delay, "database busy, retrying");
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+            result => return result,
+        }
+    }
 }
 
-fn ensure_sqlite_parent_dir(database_url: &str) -> Result<(), Box<dyn std::error::Error>> {
-    const PREFIX: &str = "sqlite://";
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Started but didn't finish; sqlx refuses to run anything until it's
+    /// fixed by hand.
+    Failed,
+    /// The file changed after it was applied.
+    Modified,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+    pub installed_on: Option<String>,
+    /// Whether a `.down.sql` exists, so `migrate revert` can undo it.
+    pub reversible: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct AppliedMigration {
+    version: i64,
+    installed_on: String,
+    success: bool,
+    checksum: Vec<u8>,
+}
+
+/// Applies any migrations the database hasn't seen, returning how many.
+pub async fn migrate(pool: &SqlitePool) -> Result<usize, Box<dyn std::error::Error>> {
+    let applied_before = successful(&applied_migrations(pool).await?).count();
+    MIGRATOR.run(pool).await?;
+    Ok(successful(&applied_migrations(pool).await?).count() - applied_before)
+}
 
-    if let Some(rest) = database_url.strip_prefix(PREFIX) {
-        let file_part = rest.split('?').next().unwrap_or(rest);
+/// Every migration this build knows about, oldest first, and whether the
+/// database has it.
+pub async fn migration_status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    let applied = applied_migrations(pool).await?;
+    let reversible: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .collect();
 
-        if file_part != ":memory:" && !file_part.is_empty() {
-            let path = Path::new(file_part);
-            if let Some(parent) = path.parent() {
-                if !parent.as_os_str().is_empty() {
-                    std::fs::create_dir_all(parent)?;
-                }
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| {
+            let row = applied.get(&m.version);
+            let state = match row {
+                None => MigrationState::Pending,
+                Some(row) if !row.success => MigrationState::Failed,
+                Some(row) if row.checksum != *m.checksum => MigrationState::Modified,
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                state,
+                installed_on: row.map(|row| row.installed_on.clone()),
+                reversible: reversible.contains(&m.version),
             }
+        })
+        .collect())
+}
+
+/// Undoes applied migrations newer than `target`, newest first; without a
+/// target, just the latest. Fails before changing anything if one of them
+/// has no `.down.sql`. Returns the reverted versions.
+pub async fn revert(
+    pool: &SqlitePool,
+    target: Option<i64>,
+) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    let applied: Vec<i64> = successful(&applied_migrations(pool).await?).rev().collect();
+    if applied.is_empty() {
+        return Ok(Vec::new());
+    }
+    let target = target.unwrap_or_else(|| applied.get(1).copied().unwrap_or(0));
+
+    let to_revert: Vec<i64> = applied.into_iter().filter(|v| *v > target).collect();
+    for version in &to_revert {
+        let has_down = MIGRATOR
+            .iter()
+            .any(|m| m.version == *version && m.migration_type.is_down_migration());
+        if !has_down {
+            return Err(format!(
+                "migration {version} has no .down.sql and can't be reverted; \
+                 restore a backup taken before it instead"
+            )
+            .into());
+        }
+    }
+    MIGRATOR.undo(pool, target).await?;
+    Ok(to_revert)
+}
+
+/// Versions that finished applying, oldest first; a failed row is not
+/// applied.
+fn successful(
+    applied: &BTreeMap<i64, AppliedMigration>,
+) -> impl DoubleEndedIterator<Item = i64> + '_ {
+    applied
+        .values()
+        .filter(|row| row.success)
+        .map(|row| row.version)
+}
+
+async fn applied_migrations(
+    pool: &SqlitePool,
+) -> Result<BTreeMap<i64, AppliedMigration>, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(BTreeMap::new());
+    }
+    let rows = sqlx::query_as::<_, AppliedMigration>(
+        "SELECT version, installed_on, success, checksum FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|row| (row.version, row)).collect())
+}
+
+/// The database file a `sqlite://` URL points at; `None` for in-memory
+/// databases and other URL forms.
+pub fn sqlite_file(database_url: &str) -> Option<&Path> {
+    const PREFIX: &str = "sqlite://";
+
+    let rest = database_url.strip_prefix(PREFIX)?;
+    let file_part = rest.split('?').next().unwrap_or(rest);
+    (file_part != ":memory:" && !file_part.is_empty()).then(|| Path::new(file_part))
+}
+
+fn ensure_sqlite_parent_dir(database_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = sqlite_file(database_url).and_then(Path::parent) {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        error::Error as StdError,
+        fmt,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Code(&'static str);
+
+    impl fmt::Display for Code {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "sqlite error {}", self.0)
+        }
+    }
+
+    impl StdError for Code {}
+
+    impl DatabaseError for Code {
+        fn message(&self) -> &str {
+            "database error"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn db_error(code: &'static str) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(Code(code)))
+    }
+
+    #[test]
+    fn busy_and_locked_codes_are_busy() {
+        // SQLITE_BUSY, SQLITE_LOCKED, SQLITE_BUSY_SNAPSHOT, SQLITE_LOCKED_SHAREDCACHE
+        for code in ["5", "6", "517", "262"] {
+            assert!(is_busy(&db_error(code)), "{code}");
+        }
+    }
+
+    #[test]
+    fn other_errors_are_not_busy() {
+        // SQLITE_CONSTRAINT_UNIQUE
+        assert!(!is_busy(&db_error("2067")));
+        assert!(!is_busy(&db_error("not a code")));
+        assert!(!is_busy(&sqlx::Error::RowNotFound));
+    }
+
+    #[tokio::test]
+    async fn retries_while_busy() {
+        let calls = AtomicU32::new(0);
+        let result = retry_busy(|| async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(db_error("5")),
+                _ => Ok(7),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_retry_limit() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = retry_busy(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(db_error("517"))
+        })
+        .await;
+        assert!(result.is_err_and(|err| is_busy(&err)));
+        assert_eq!(calls.load(Ordering::SeqCst), BUSY_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_errors() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = retry_busy(|| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(sqlx::Error::RowNotFound)
+        })
+        .await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
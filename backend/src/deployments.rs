@@ -0,0 +1,89 @@
+//! Named model deployments: extra inference containers the backend creates
+//! and routes to, e.g. an embeddings model next to the chat model.
+
+use std::path::Path;
+
+use serde::Serialize;
+use sqlx::{types::Json as SqlJson, SqlitePool};
+use tracing::info;
+
+use crate::{
+    app_state::AppState,
+    docker::{ContainerSpec, InferenceContainer},
+    error::AppError,
+    model_swap::{self, CONTAINER_MODEL_PATH},
+};
+
+/// Prefix for deployment container names, so they are easy to spot in
+/// `docker ps` and don't collide with `compose.yml` services.
+const CONTAINER_PREFIX: &str = "llm-";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Deployment {
+    pub id: i64,
+    pub name: String,
+    pub image: String,
+    pub model_path: String,
+    pub host_port: i64,
+    pub container_port: i64,
+    pub gpus: Option<String>,
+    pub args: SqlJson<Vec<String>>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+pub const DEPLOYMENT_COLUMNS: &str = "id, name, image, model_path, host_port, container_port, \
+                                      gpus, args, created_at, updated_at";
+
+impl Deployment {
+    pub fn container_name(&self) -> String {
+        format!("{CONTAINER_PREFIX}{}", self.name)
+    }
+
+    pub fn container(&self, state: &AppState) -> Result<InferenceContainer, AppError> {
+        InferenceContainer::connect(self.container_name(), state.config.docker_stop_timeout_secs)
+            .map_err(|err| AppError::Docker(err.to_string()))
+    }
+
+    pub fn base_url(&self, host: &str) -> String {
+        format!("http://{host}:{}", self.host_port)
+    }
+
+    /// The model is mounted read-only at `/model` and `--model` is pointed
+    /// at it, as for the main inference container.
+    pub fn spec(&self) -> ContainerSpec {
+        let (mount_source, model_arg) = model_swap::model_mount(Path::new(&self.model_path));
+        let mut args = self.args.0.clone();
+        model_swap::set_model_arg(&mut args, &model_arg);
+
+        ContainerSpec {
+            image: self.image.clone(),
+            args,
+            ports: vec![format!("{}:{}", self.host_port, self.container_port)],
+            volumes: vec![format!("{mount_source}:{CONTAINER_MODEL_PATH}:ro")],
+            gpus: self.gpus.clone(),
+            restart_unless_stopped: true,
+        }
+    }
+}
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<Deployment>, sqlx::Error> {
+    sqlx::query_as::<_, Deployment>(&format!(
+        "SELECT {DEPLOYMENT_COLUMNS} FROM model_deployments ORDER BY name ASC"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Registers every stored deployment as a backend. Docker's restart policy
+/// keeps the containers themselves running across reboots.
+pub async fn register_all(state: &AppState) -> Result<(), sqlx::Error> {
+    for deployment in list(&state.pool).await? {
+        info!(name = %deployment.name, "registering model deployment");
+        state.upstreams.set_deployment(
+            &deployment.name,
+            deployment.base_url(&state.config.docker_deployment_host),
+        );
+    }
+    Ok(())
+}
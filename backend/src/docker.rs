@@ -0,0 +1,414 @@
+//! Control of the inference server's Docker container through the Docker
+//! Engine API (the local socket, or `DOCKER_HOST`).
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bollard::{
+    container::{
+        Config as CreateConfig, CreateContainerOptions, InspectContainerOptions, LogOutput,
+        LogsOptions, RemoveContainerOptions, RestartContainerOptions, StartContainerOptions,
+        StatsOptions, StopContainerOptions,
+    },
+    errors::Error as DockerError,
+    models::{DeviceRequest, HostConfig, PortBinding, RestartPolicy, RestartPolicyNameEnum},
+    Docker,
+};
+use serde::Serialize;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{config::Config, error::AppError};
+
+#[derive(Debug, Serialize)]
+pub struct ContainerStatus {
+    pub name: String,
+    pub image: Option<String>,
+    /// Docker's state: `created`, `running`, `exited`, ...
+    pub state: Option<String>,
+    pub running: bool,
+    /// Container healthcheck result, when the image defines one.
+    pub health: Option<String>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub exit_code: Option<i64>,
+}
+
+/// CPU and memory use reported by `docker stats`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ContainerUsage {
+    /// Percent of one core, so a busy 8-core container reads up to 800.
+    pub cpu_percent: Option<f64>,
+    pub memory_used_bytes: Option<u64>,
+    pub memory_limit_bytes: Option<u64>,
+}
+
+/// How to create the container when it doesn't exist yet, mirroring the
+/// `llm` service in `compose.yml`.
+pub struct ContainerSpec {
+    pub image: String,
+    pub args: Vec<String>,
+    pub ports: Vec<String>,
+    pub volumes: Vec<String>,
+    pub gpus: Option<String>,
+    /// Let Docker bring the container back after crashes and reboots.
+    /// Off for the supervised container, whose restarts the supervisor owns.
+    pub restart_unless_stopped: bool,
+}
+
+pub struct InferenceContainer {
+    docker: Docker,
+    pub name: String,
+    stop_timeout_secs: i64,
+    /// Set by an explicit stop so the supervisor leaves the container down.
+    held_stopped: AtomicBool,
+}
+
+impl InferenceContainer {
+    /// `None` when `DOCKER_LLM_CONTAINER` is unset, i.e. the container is
+    /// managed outside this backend.
+    pub fn from_config(cfg: &Config) -> Result<Option<Self>, DockerError> {
+        let Some(name) = cfg.docker_llm_container.clone() else {
+            return Ok(None);
+        };
+
+        Self::connect(name, cfg.docker_stop_timeout_secs).map(Some)
+    }
+
+    pub fn connect(name: String, stop_timeout_secs: i64) -> Result<Self, DockerError> {
+        Ok(Self {
+            docker: Docker::connect_with_local_defaults()?,
+            name,
+            stop_timeout_secs,
+            held_stopped: AtomicBool::new(false),
+        })
+    }
+
+    /// Whether the last admin action was a stop.
+    pub fn held_stopped(&self) -> bool {
+        self.held_stopped.load(Ordering::Relaxed)
+    }
+
+    pub async fn status(&self) -> Result<ContainerStatus, AppError> {
+        let info = self
+            .docker
+            .inspect_container(&self.name, None::<InspectContainerOptions>)
+            .await
+            .map_err(|err| self.error(err))?;
+        let state = info.state.unwrap_or_default();
+
+        Ok(ContainerStatus {
+            name: self.name.clone(),
+            image: info.config.and_then(|config| config.image),
+            state: state.status.map(|s| s.to_string()),
+            running: state.running.unwrap_or(false),
+            health: state
+                .health
+                .and_then(|health| health.status)
+                .map(|s| s.to_string()),
+            started_at: state.started_at,
+            finished_at: state.finished_at,
+            exit_code: state.exit_code,
+        })
+    }
+
+    /// Creates the container from `spec`.
+    pub async fn create(&self, spec: &ContainerSpec) -> Result<(), AppError> {
+        let mut exposed_ports = HashMap::new();
+        let mut port_bindings = HashMap::new();
+        for mapping in &spec.ports {
+            let (host, container) = mapping.split_once(':').unwrap_or((mapping, mapping));
+            let key = if container.contains('/') {
+                container.to_string()
+            } else {
+                format!("{container}/tcp")
+            };
+            exposed_ports.insert(key.clone(), HashMap::new());
+            port_bindings.insert(
+                key,
+                Some(vec![PortBinding {
+                    host_ip: None,
+                    host_port: Some(host.to_string()),
+                }]),
+            );
+        }
+
+        let device_requests = spec.gpus.as_deref().map(|gpus| {
+            let (count, device_ids) = match gpus.trim() {
+                "all" => (Some(-1), None),
+                ids => (
+                    None,
+                    Some(ids.split(',').map(|id| id.trim().to_string()).collect()),
+                ),
+            };
+            vec![DeviceRequest {
+                driver: Some("nvidia".to_string()),
+                count,
+                device_ids,
+                capabilities: Some(vec![vec!["gpu".to_string()]]),
+                options: None,
+            }]
+        });
+
+        let config = CreateConfig {
+            image: Some(spec.image.clone()),
+            cmd: (!spec.args.is_empty()).then(|| spec.args.clone()),
+            exposed_ports: Some(exposed_ports),
+            host_config: Some(HostConfig {
+                binds: Some(spec.volumes.clone()),
+                port_bindings: Some(port_bindings),
+                device_requests,
+                ipc_mode: Some("host".to_string()),
+                restart_policy: Some(RestartPolicy {
+                    name: Some(if spec.restart_unless_stopped {
+                        RestartPolicyNameEnum::UNLESS_STOPPED
+                    } else {
+                        RestartPolicyNameEnum::NO
+                    }),
+                    maximum_retry_count: None,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let options = CreateContainerOptions {
+            name: self.name.clone(),
+            platform: None,
+        };
+
+        self.docker
+            .create_container(Some(options), config)
+            .await
+            .map_err(|err| match err {
+                DockerError::DockerResponseServerError {
+                    status_code: 404, ..
+                } => AppError::Docker(format!("image '{}' not found locally", spec.image)),
+                other => AppError::Docker(other.to_string()),
+            })?;
+        Ok(())
+    }
+
+    /// One stats sample. Docker measures CPU over about a second, so this
+    /// call takes that long.
+    pub async fn usage(&self) -> Result<ContainerUsage, AppError> {
+        let options = StatsOptions {
+            stream: false,
+            one_shot: false,
+        };
+        let stats = self
+            .docker
+            .stats(&self.name, Some(options))
+            .next()
+            .await
+            .ok_or_else(|| AppError::Docker("docker returned no stats".to_string()))?
+            .map_err(|err| self.error(err))?;
+
+        let cpu_delta = stats
+            .cpu_stats
+            .cpu_usage
+            .total_usage
+            .saturating_sub(stats.precpu_stats.cpu_usage.total_usage);
+        let system_delta = stats
+            .cpu_stats
+            .system_cpu_usage
+            .zip(stats.precpu_stats.system_cpu_usage)
+            .map(|(now, before)| now.saturating_sub(before))
+            .filter(|delta| *delta > 0);
+        let cpus = stats.cpu_stats.online_cpus.unwrap_or(1) as f64;
+
+        Ok(ContainerUsage {
+            cpu_percent: system_delta.map(|system| cpu_delta as f64 / system as f64 * cpus * 100.0),
+            memory_used_bytes: stats.memory_stats.usage,
+            memory_limit_bytes: stats.memory_stats.limit,
+        })
+    }
+
+    /// stdout and stderr lines, starting with the last `tail` and then
+    /// following new output if `follow` is set.
+    pub fn logs(
+        &self,
+        tail: u32,
+        follow: bool,
+    ) -> impl Stream<Item = Result<LogOutput, AppError>> + Send + 'static {
+        let options = LogsOptions::<String> {
+            follow,
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+        let name = self.name.clone();
+        self.docker
+            .clone()
+            .logs(&self.name, Some(options))
+            .map(move |line| {
+                line.map_err(|err| match err {
+                    DockerError::DockerResponseServerError {
+                        status_code: 404, ..
+                    } => AppError::NotFound(format!("container '{name}'")),
+                    other => AppError::Docker(other.to_string()),
+                })
+            })
+    }
+
+    /// Host path bind-mounted at `container_path`, e.g. the model directory
+    /// mounted at `/model` in `compose.yml`.
+    pub async fn mount_source(&self, container_path: &str) -> Result<Option<String>, AppError> {
+        let info = self
+            .docker
+            .inspect_container(&self.name, None::<InspectContainerOptions>)
+            .await
+            .map_err(|err| self.error(err))?;
+
+        Ok(info
+            .mounts
+            .unwrap_or_default()
+            .into_iter()
+            .find(|mount| mount.destination.as_deref() == Some(container_path))
+            .and_then(|mount| mount.source))
+    }
+
+    /// Replaces the container with a copy whose `container_path` bind mount
+    /// points at `source`, keeping the image, ports, and devices. `edit_cmd`
+    /// adjusts the server arguments.
+    pub async fn recreate(
+        &self,
+        container_path: &str,
+        source: &str,
+        edit_cmd: impl FnOnce(&mut Vec<String>),
+    ) -> Result<ContainerStatus, AppError> {
+        let info = self
+            .docker
+            .inspect_container(&self.name, None::<InspectContainerOptions>)
+            .await
+            .map_err(|err| self.error(err))?;
+        let mut config: CreateConfig<String> = info
+            .config
+            .map(Into::into)
+            .ok_or_else(|| AppError::Docker(format!("container '{}' has no config", self.name)))?;
+
+        let mut host_config = info.host_config.unwrap_or_default();
+        let mut binds: Vec<String> = host_config
+            .binds
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|bind| bind.split(':').nth(1) != Some(container_path))
+            .collect();
+        binds.push(format!("{source}:{container_path}:ro"));
+        host_config.binds = Some(binds);
+        config.host_config = Some(host_config);
+
+        let mut cmd = config.cmd.take().unwrap_or_default();
+        edit_cmd(&mut cmd);
+        config.cmd = (!cmd.is_empty()).then_some(cmd);
+
+        self.stop().await?;
+        self.docker
+            .remove_container(
+                &self.name,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(|err| self.error(err))?;
+        self.docker
+            .create_container(
+                Some(CreateContainerOptions {
+                    name: self.name.clone(),
+                    platform: None,
+                }),
+                config,
+            )
+            .await
+            .map_err(|err| self.error(err))?;
+        self.start().await
+    }
+
+    pub async fn start(&self) -> Result<ContainerStatus, AppError> {
+        self.held_stopped.store(false, Ordering::Relaxed);
+        match self
+            .docker
+            .start_container(&self.name, None::<StartContainerOptions<String>>)
+            .await
+        {
+            // 304: already running.
+            Ok(())
+            | Err(DockerError::DockerResponseServerError {
+                status_code: 304, ..
+            }) => {}
+            Err(err) => return Err(self.error(err)),
+        }
+        self.status().await
+    }
+
+    pub async fn stop(&self) -> Result<ContainerStatus, AppError> {
+        self.held_stopped.store(true, Ordering::Relaxed);
+        let options = StopContainerOptions {
+            t: self.stop_timeout_secs,
+        };
+        match self.docker.stop_container(&self.name, Some(options)).await {
+            // 304: already stopped.
+            Ok(())
+            | Err(DockerError::DockerResponseServerError {
+                status_code: 304, ..
+            }) => {}
+            Err(err) => return Err(self.error(err)),
+        }
+        self.status().await
+    }
+
+    /// Creates the container from `spec` if it doesn't exist, then starts it.
+    pub async fn ensure_running(&self, spec: &ContainerSpec) -> Result<ContainerStatus, AppError> {
+        match self.status().await {
+            Ok(_) => {}
+            Err(AppError::NotFound(_)) => self.create(spec).await?,
+            Err(err) => return Err(err),
+        }
+        self.start().await
+    }
+
+    /// Stops and deletes the container. A missing container is not an error.
+    pub async fn remove(&self) -> Result<(), AppError> {
+        let options = RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        };
+        match self
+            .docker
+            .remove_container(&self.name, Some(options))
+            .await
+        {
+            Ok(())
+            | Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Ok(()),
+            Err(err) => Err(self.error(err)),
+        }
+    }
+
+    pub async fn restart(&self) -> Result<ContainerStatus, AppError> {
+        self.held_stopped.store(false, Ordering::Relaxed);
+        let options = RestartContainerOptions {
+            t: self.stop_timeout_secs as isize,
+        };
+        self.docker
+            .restart_container(&self.name, Some(options))
+            .await
+            .map_err(|err| self.error(err))?;
+        self.status().await
+    }
+
+    fn error(&self, err: DockerError) -> AppError {
+        match err {
+            DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            } => AppError::NotFound(format!("container '{}'", self.name)),
+            other => AppError::Docker(other.to_string()),
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::Serialize;
 
+use crate::{db, rate_limit::Quota, validation::FieldErrors};
+
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("database error")]
@@ -13,27 +15,99 @@ pub enum AppError {
     HttpClient(#[from] reqwest::Error),
     #[error("upstream llm error: {0}")]
     Upstream(String),
+    #[error("upstream llm overloaded: {message}")]
+    Overloaded {
+        retry_after: Option<u64>,
+        message: String,
+    },
+    #[error("rate limited: {message}")]
+    RateLimited { quota: Quota, message: String },
     #[error("bad request: {0}")]
     BadRequest(String),
+    #[error("validation failed")]
+    Validation(FieldErrors),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("docker error: {0}")]
+    Docker(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl AppError {
+    /// Maps a unique-constraint violation to `409`; other errors stay `Db`.
+    pub fn unique_conflict(err: sqlx::Error, message: impl FnOnce() -> String) -> Self {
+        match err {
+            sqlx::Error::Database(db) if db.is_unique_violation() => AppError::Conflict(message()),
+            other => AppError::Db(other),
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct ErrorBody {
     error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<FieldErrors>,
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        // Still locked after the busy timeout and any retries: the request
+        // itself was fine, so ask the client to try again.
+        let busy = matches!(&self, AppError::Db(err) if db::is_busy(err));
         let status = match self {
+            _ if busy => StatusCode::SERVICE_UNAVAILABLE,
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
-            AppError::Db(_) | AppError::HttpClient(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Upstream(_) | AppError::Docker(_) => StatusCode::BAD_GATEWAY,
+            AppError::Overloaded { .. } | AppError::RateLimited { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            AppError::Db(_) | AppError::HttpClient(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
         };
 
-        let body = Json(ErrorBody {
-            error: self.to_string(),
-        });
+        let error = if busy {
+            "database busy".to_string()
+        } else {
+            self.to_string()
+        };
+        let retry_after = match &self {
+            _ if busy => Some(1),
+            AppError::Overloaded { retry_after, .. } => *retry_after,
+            AppError::RateLimited { quota, .. } => Some(quota.reset_secs),
+            _ => None,
+        };
+        let quota = match &self {
+            AppError::RateLimited { quota, .. } => Some(*quota),
+            _ => None,
+        };
+        let fields = match self {
+            AppError::Validation(fields) => Some(fields),
+            _ => None,
+        };
 
-        (status, body).into_response()
+        let mut response = (status, Json(ErrorBody { error, fields })).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        if let Some(quota) = quota {
+            quota.apply(response.headers_mut());
+        }
+        response
     }
 }
@@ -0,0 +1,79 @@
+//! A/B comparison between the default model and an alternate one.
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use serde::Serialize;
+
+use crate::config::Config;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    Control,
+    Treatment,
+}
+
+impl Variant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Control => "control",
+            Variant::Treatment => "treatment",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Experiment {
+    pub name: String,
+    /// Replaces `payload.model` for treatment requests.
+    pub model: Option<String>,
+    percent: AtomicU8,
+    counter: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExperimentStatus {
+    pub name: String,
+    pub model: Option<String>,
+    pub percent: u8,
+}
+
+impl Experiment {
+    pub fn from_config(cfg: &Config) -> Option<Self> {
+        Some(Self {
+            name: cfg.llm_experiment_name.clone()?,
+            model: cfg.llm_experiment_model.clone(),
+            percent: AtomicU8::new(cfg.llm_experiment_percent),
+            counter: AtomicU64::new(0),
+        })
+    }
+
+    /// Students always land in the same bucket so a teacher comparing
+    /// their sessions sees one model consistently; anonymous requests
+    /// alternate by request count.
+    pub fn assign(&self, student_id: Option<i64>) -> Variant {
+        let percent = u64::from(self.percent.load(Ordering::Relaxed));
+        let bucket = match student_id {
+            Some(id) => ((id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) % 100,
+            None => self.counter.fetch_add(1, Ordering::Relaxed) % 100,
+        };
+
+        if bucket < percent {
+            Variant::Treatment
+        } else {
+            Variant::Control
+        }
+    }
+
+    pub fn set_percent(&self, percent: u8) {
+        self.percent.store(percent, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> ExperimentStatus {
+        ExperimentStatus {
+            name: self.name.clone(),
+            model: self.model.clone(),
+            percent: self.percent.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Weekly, parent-friendly summaries of each student's activity. Every
+//! `FAMILY_DIGEST_INTERVAL_SECS` the job looks for students with activity in
+//! the last full week (Monday to Sunday) but no digest for it yet, and has
+//! the local model write one. Students with a quiet week are skipped.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlJson;
+use tracing::{info, warn};
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    auth,
+    error::AppError,
+    routes::{
+        progress_reports::{excerpt, question},
+        students,
+    },
+    trace::TraceHeaders,
+};
+
+/// Per-source caps so the prompt fits a small model's context.
+const MAX_INTERACTIONS: i64 = 30;
+const MAX_ITEMS: i64 = 20;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DigestContent {
+    pub headline: String,
+    pub summary: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub highlights: Vec<String>,
+    /// Suggestions for practicing at home.
+    #[serde(default)]
+    pub try_at_home: Vec<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FamilyDigest {
+    pub id: i64,
+    pub student_id: i64,
+    pub student_name: String,
+    /// The owning parent's email, for sending the digest on.
+    pub parent_email: Option<String>,
+    pub week_start: String,
+    pub week_end: String,
+    pub digest: SqlJson<DigestContent>,
+    pub interaction_count: i64,
+    pub quiz_count: i64,
+    pub note_count: i64,
+    pub grade_count: i64,
+    /// The `ai_interactions` row for the generation request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+pub const DIGEST_SELECT: &str = r#"
+    SELECT d.id, d.student_id, s.name AS student_name, u.email AS parent_email,
+           d.week_start, date(d.week_start, '+6 days') AS week_end, d.digest,
+           d.interaction_count, d.quiz_count, d.note_count, d.grade_count,
+           d.generation_id, d.created_at
+    FROM family_digests d
+    JOIN students s ON s.id = d.student_id
+    LEFT JOIN users u ON u.id = s.owner_user_id
+"#;
+
+/// Starts the job unless `FAMILY_DIGEST_INTERVAL_SECS` is `0`.
+pub fn spawn(state: AppState) {
+    let interval_secs = state.config.family_digest_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            if let Err(err) = run_once(&state).await {
+                warn!(error = %err, "family digest run failed");
+            }
+        }
+    });
+}
+
+async fn run_once(state: &AppState) -> Result<(), AppError> {
+    let week_start: String = sqlx::query_scalar("SELECT date('now', 'weekday 0', '-13 days')")
+        .fetch_one(&state.pool)
+        .await?;
+    let pending = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT s.id, s.school_id
+        FROM students s
+        WHERE s.deleted_at IS NULL
+          AND NOT EXISTS(SELECT 1 FROM family_digests d
+                         WHERE d.student_id = s.id AND d.week_start = ?)
+        ORDER BY s.id ASC
+        "#,
+    )
+    .bind(&week_start)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut written = 0;
+    for (student_id, school_id) in pending {
+        let model = state.config.family_digest_model.clone();
+        let digest = generate(
+            state,
+            student_id,
+            &week_start,
+            model,
+            None,
+            TraceHeaders::default(),
+        );
+        // So the digest's chat is recorded against the student's school.
+        match auth::in_school(school_id, digest).await {
+            Ok(Some(_)) => written += 1,
+            Ok(None) => {}
+            Err(err) => {
+                warn!(student_id, error = %err, "family digest failed");
+            }
+        }
+    }
+    if written > 0 {
+        info!(week_start, written, "family digests written");
+    }
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct QuizExcerpt {
+    title: String,
+    topic: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct NoteExcerpt {
+    body: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GradeExcerpt {
+    title: String,
+    points: f64,
+    max_points: Option<f64>,
+}
+
+/// Writes (or rewrites) the digest for the week starting on `week_start`, a
+/// Monday. Returns `None` without calling the model when the student had no
+/// activity that week.
+pub async fn generate(
+    state: &AppState,
+    student_id: i64,
+    week_start: &str,
+    model: Option<String>,
+    user_id: Option<i64>,
+    trace: TraceHeaders,
+) -> Result<Option<FamilyDigest>, AppError> {
+    let week_end: String = sqlx::query_scalar("SELECT date(?, '+7 days')")
+        .bind(week_start)
+        .fetch_one(&state.pool)
+        .await?;
+
+    let prompts = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT prompt
+        FROM ai_interactions
+        WHERE student_id = ? AND created_at >= ? AND created_at < ?
+        ORDER BY created_at ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(week_start)
+    .bind(&week_end)
+    .bind(MAX_INTERACTIONS)
+    .fetch_all(&state.pool)
+    .await?;
+    let quizzes = sqlx::query_as::<_, QuizExcerpt>(
+        r#"
+        SELECT title, topic
+        FROM quizzes
+        WHERE student_id = ? AND created_at >= ? AND created_at < ?
+        ORDER BY created_at ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(week_start)
+    .bind(&week_end)
+    .bind(MAX_ITEMS)
+    .fetch_all(&state.pool)
+    .await?;
+    let notes = sqlx::query_as::<_, NoteExcerpt>(
+        r#"
+        SELECT body
+        FROM student_notes
+        WHERE student_id = ? AND created_at >= ? AND created_at < ?
+        ORDER BY created_at ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(week_start)
+    .bind(&week_end)
+    .bind(MAX_ITEMS)
+    .fetch_all(&state.pool)
+    .await?;
+    let grades = sqlx::query_as::<_, GradeExcerpt>(
+        r#"
+        SELECT a.title, g.points, a.max_points
+        FROM grades g
+        JOIN assignments a ON a.id = g.assignment_id
+        WHERE a.student_id = ? AND g.graded_at >= ? AND g.graded_at < ?
+        ORDER BY g.graded_at ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(week_start)
+    .bind(&week_end)
+    .bind(MAX_ITEMS)
+    .fetch_all(&state.pool)
+    .await?;
+
+    if prompts.is_empty() && quizzes.is_empty() && notes.is_empty() && grades.is_empty() {
+        return Ok(None);
+    }
+
+    let profile = students::profile(&state.pool, student_id).await?;
+    let mut sections = vec![format!(
+        "Week of {week_start}. Grade level: {}.",
+        profile.grade_level.as_deref().unwrap_or("unknown")
+    )];
+    if !prompts.is_empty() {
+        let lines = prompts
+            .iter()
+            .map(|p| format!("- {}", excerpt(&question(p))));
+        sections.push(format!(
+            "Questions the student asked the AI tutor:\n{}",
+            lines.collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !quizzes.is_empty() {
+        let lines = quizzes
+            .iter()
+            .map(|q| format!("- {} ({})", q.title, q.topic));
+        sections.push(format!(
+            "Quizzes:\n{}",
+            lines.collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !notes.is_empty() {
+        let lines = notes.iter().map(|n| format!("- {}", excerpt(&n.body)));
+        sections.push(format!(
+            "Teacher notes:\n{}",
+            lines.collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !grades.is_empty() {
+        let lines = grades.iter().map(|g| match g.max_points {
+            Some(max) => format!("- {}: {} / {max}", g.title, g.points),
+            None => format!("- {}: {}", g.title, g.points),
+        });
+        sections.push(format!("Grades:\n{}", lines.collect::<Vec<_>>().join("\n")));
+    }
+
+    let (content, completion) = assist::complete_json::<DigestContent>(
+        state,
+        Prompt {
+            system: "You write a short weekly update for a student's family. Use warm, plain \
+                     language without jargon, celebrate progress, and mention concerns \
+                     gently. Only use facts from the material. Reply with only a JSON object \
+                     with keys \"headline\" (one sentence), \"summary\" (a short paragraph), \
+                     \"topics\", \"highlights\", and \"try_at_home\" (each an array of short \
+                     strings)."
+                .to_string(),
+            user: sections.join("\n\n"),
+            model,
+            temperature: Some(0.4),
+            max_tokens: Some(1_000),
+            user_id,
+            student_id: Some(student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO family_digests(
+            student_id, week_start, digest, interaction_count, quiz_count, note_count,
+            grade_count, generation_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(student_id, week_start) DO UPDATE SET
+            digest = excluded.digest,
+            interaction_count = excluded.interaction_count,
+            quiz_count = excluded.quiz_count,
+            note_count = excluded.note_count,
+            grade_count = excluded.grade_count,
+            generation_id = excluded.generation_id,
+            created_at = CURRENT_TIMESTAMP
+        RETURNING id
+        "#,
+    )
+    .bind(student_id)
+    .bind(week_start)
+    .bind(SqlJson(&content))
+    .bind(prompts.len() as i64)
+    .bind(quizzes.len() as i64)
+    .bind(notes.len() as i64)
+    .bind(grades.len() as i64)
+    .bind(completion.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let digest = sqlx::query_as::<_, FamilyDigest>(&format!("{DIGEST_SELECT} WHERE d.id = ?"))
+        .bind(id)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(Some(digest))
+}
@@ -0,0 +1,267 @@
+//! Detection of GPUs and system memory, used to tell which local models an
+//! install can actually run. `nvidia-smi` is preferred; without it, NVIDIA
+//! cards are read from `/proc/driver/nvidia` and AMD cards from sysfs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Serialize;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuSource {
+    NvidiaSmi,
+    ProcNvidia,
+    Sysfs,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Gpu {
+    pub index: usize,
+    pub name: String,
+    pub vendor: String,
+    /// `None` when the fallback source doesn't expose memory.
+    pub vram_total_bytes: Option<u64>,
+    pub vram_used_bytes: Option<u64>,
+    pub driver_version: Option<String>,
+    pub source: GpuSource,
+}
+
+/// Point-in-time load of one GPU. Utilization is per card, not per
+/// container.
+#[derive(Clone, Debug, Serialize)]
+pub struct GpuUsage {
+    pub index: usize,
+    pub utilization_percent: Option<f64>,
+    pub vram_used_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemMemory {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Hardware {
+    pub gpus: Vec<Gpu>,
+    pub memory: Option<SystemMemory>,
+    pub cpu_count: usize,
+}
+
+impl Hardware {
+    /// Sum of known VRAM across GPUs; tensor parallelism can split one
+    /// model over several cards.
+    pub fn vram_total_bytes(&self) -> u64 {
+        self.gpus
+            .iter()
+            .filter_map(|gpu| gpu.vram_total_bytes)
+            .sum()
+    }
+}
+
+pub fn detect() -> Hardware {
+    let gpus = nvidia_smi()
+        .or_else(proc_nvidia)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(sysfs_amd())
+        .enumerate()
+        .map(|(index, gpu)| Gpu { index, ..gpu })
+        .collect();
+
+    Hardware {
+        gpus,
+        memory: meminfo(),
+        cpu_count: std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+    }
+}
+
+/// Current GPU load from `nvidia-smi`, or amdgpu's sysfs attributes.
+pub fn gpu_usage() -> Vec<GpuUsage> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=utilization.gpu,memory.used",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success());
+
+    if let Some(output) = output {
+        return String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let mut fields = line.split(',').map(str::trim);
+                GpuUsage {
+                    index,
+                    utilization_percent: fields.next().and_then(|v| v.parse().ok()),
+                    vram_used_bytes: fields
+                        .next()
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(|v| v * MIB),
+                }
+            })
+            .collect();
+    }
+
+    amd_cards()
+        .iter()
+        .enumerate()
+        .map(|(index, card)| {
+            let device = card.join("device");
+            GpuUsage {
+                index,
+                utilization_percent: read_u64(&device.join("gpu_busy_percent")).map(|v| v as f64),
+                vram_used_bytes: read_u64(&device.join("mem_info_vram_used")),
+            }
+        })
+        .collect()
+}
+
+fn nvidia_smi() -> Option<Vec<Gpu>> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=name,memory.total,memory.used,driver_version",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let gpus = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [name, total, used, driver] = fields.as_slice() else {
+                return None;
+            };
+            let mib = |v: &str| v.parse::<u64>().ok().map(|v| v * MIB);
+            Some(Gpu {
+                index: 0,
+                name: name.to_string(),
+                vendor: "nvidia".to_string(),
+                vram_total_bytes: mib(total),
+                vram_used_bytes: mib(used),
+                driver_version: Some(driver.to_string()),
+                source: GpuSource::NvidiaSmi,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    (!gpus.is_empty()).then_some(gpus)
+}
+
+/// The NVIDIA driver lists each card under `/proc/driver/nvidia/gpus`, with
+/// the model name but no memory figures.
+fn proc_nvidia() -> Option<Vec<Gpu>> {
+    let driver_version = fs::read_to_string("/proc/driver/nvidia/version")
+        .ok()
+        .and_then(|version| {
+            version
+                .split_whitespace()
+                .skip_while(|word| *word != "Module")
+                .nth(1)
+                .map(str::to_string)
+        });
+
+    let mut dirs: Vec<_> = fs::read_dir("/proc/driver/nvidia/gpus")
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    dirs.sort();
+
+    let gpus = dirs
+        .iter()
+        .filter_map(|dir| {
+            let info = fs::read_to_string(dir.join("information")).ok()?;
+            let name = info
+                .lines()
+                .find_map(|line| line.strip_prefix("Model:"))?
+                .trim()
+                .to_string();
+            Some(Gpu {
+                index: 0,
+                name,
+                vendor: "nvidia".to_string(),
+                vram_total_bytes: None,
+                vram_used_bytes: None,
+                driver_version: driver_version.clone(),
+                source: GpuSource::ProcNvidia,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    (!gpus.is_empty()).then_some(gpus)
+}
+
+/// AMD cards expose VRAM through the amdgpu driver's sysfs attributes.
+fn sysfs_amd() -> Vec<Gpu> {
+    amd_cards()
+        .iter()
+        .filter_map(|card| {
+            let device = card.join("device");
+            let total = read_u64(&device.join("mem_info_vram_total"))?;
+            let name = fs::read_to_string(device.join("product_name"))
+                .ok()
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "AMD GPU".to_string());
+            Some(Gpu {
+                index: 0,
+                name,
+                vendor: "amd".to_string(),
+                vram_total_bytes: Some(total),
+                vram_used_bytes: read_u64(&device.join("mem_info_vram_used")),
+                driver_version: None,
+                source: GpuSource::Sysfs,
+            })
+        })
+        .collect()
+}
+
+/// DRM cards backed by amdgpu, which is the driver that exposes VRAM.
+fn amd_cards() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    let mut cards: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("card") && !n.contains('-'))
+        })
+        .filter(|path| path.join("device/mem_info_vram_total").exists())
+        .collect();
+    cards.sort();
+    cards
+}
+
+fn meminfo() -> Option<SystemMemory> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |key: &str| {
+        meminfo.lines().find_map(|line| {
+            let kib = line.strip_prefix(key)?.strip_prefix(':')?;
+            kib.trim().trim_end_matches("kB").trim().parse::<u64>().ok()
+        })
+    };
+
+    let total = field("MemTotal")?;
+    Some(SystemMemory {
+        total_bytes: total * 1024,
+        available_bytes: field("MemAvailable").unwrap_or(total) * 1024,
+    })
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
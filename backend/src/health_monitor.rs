@@ -0,0 +1,111 @@
+//! Periodic availability probe for every configured chat backend.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    app_state::AppState,
+    config::LlmBackendKind,
+    time::unix_now,
+    upstream::{Availability, Backend},
+};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Starts the monitor unless `LLM_HEALTH_INTERVAL_SECS` is `0`.
+pub fn spawn(state: AppState) {
+    let interval_secs = state.config.llm_health_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            for backend in state.upstreams.backends() {
+                probe(&state, &backend).await;
+            }
+            for backend in state.upstreams.deployments() {
+                probe(&state, &backend).await;
+            }
+        }
+    });
+}
+
+async fn probe(state: &AppState, backend: &Backend) {
+    let url = backend.url(&state.config.llm_models_path);
+    let started = Instant::now();
+    let result = fetch_models(state, &url, state.config.llm_backend_kind).await;
+
+    let mut health = backend.health();
+    let was = health.state;
+    health.last_checked_at = Some(unix_now());
+    health.last_latency_ms = Some(started.elapsed().as_millis() as u64);
+
+    match result {
+        Ok(models) => {
+            health.state = Availability::Up;
+            health.consecutive_failures = 0;
+            health.models = models;
+        }
+        Err(err) => {
+            health.state = Availability::Down;
+            health.consecutive_failures += 1;
+            health.last_error = Some(err);
+            health.models.clear();
+        }
+    }
+
+    if health.state != was {
+        match health.state {
+            Availability::Up => info!(base_url = %backend.base_url, "llm backend is up"),
+            _ => warn!(
+                base_url = %backend.base_url,
+                error = health.last_error.as_deref().unwrap_or_default(),
+                "llm backend is down"
+            ),
+        }
+    }
+
+    backend.set_health(health);
+}
+
+async fn fetch_models(
+    state: &AppState,
+    url: &str,
+    kind: LlmBackendKind,
+) -> Result<Vec<String>, String> {
+    let response = state
+        .llm_client
+        .get(url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("models endpoint returned {status}"));
+    }
+
+    let body: Value = response.json().await.map_err(|err| err.to_string())?;
+    let (list, field) = match kind {
+        LlmBackendKind::OpenAi => ("data", "id"),
+        LlmBackendKind::Ollama => ("models", "name"),
+    };
+
+    Ok(body
+        .get(list)
+        .and_then(Value::as_array)
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m.get(field).and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default())
+}
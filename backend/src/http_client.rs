@@ -0,0 +1,48 @@
+//! Outgoing HTTP clients, tuned by the `HTTP_CLIENT_*` settings.
+
+use std::time::Duration;
+
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy};
+
+use crate::config::Config;
+
+/// For the model servers: every request is bounded by
+/// `HTTP_CLIENT_TIMEOUT_SECS`.
+pub fn llm_client(cfg: &Config) -> reqwest::Result<Client> {
+    let mut builder = builder(cfg)?;
+    if cfg.http_client_timeout_secs > 0 {
+        builder = builder.timeout(Duration::from_secs(cfg.http_client_timeout_secs));
+    }
+    builder.build()
+}
+
+/// For model downloads, which can run for hours, so only connecting is
+/// bounded.
+pub fn download_client(cfg: &Config) -> reqwest::Result<Client> {
+    builder(cfg)?.build()
+}
+
+fn builder(cfg: &Config) -> reqwest::Result<ClientBuilder> {
+    let mut builder = Client::builder()
+        .pool_max_idle_per_host(cfg.http_client_pool_max_idle_per_host)
+        .pool_idle_timeout(
+            Some(Duration::from_secs(cfg.http_client_pool_idle_timeout_secs))
+                .filter(|idle| !idle.is_zero()),
+        );
+    if cfg.http_client_connect_timeout_secs > 0 {
+        builder =
+            builder.connect_timeout(Duration::from_secs(cfg.http_client_connect_timeout_secs));
+    }
+    if cfg.http_client_tcp_keepalive_secs > 0 {
+        builder = builder.tcp_keepalive(Duration::from_secs(cfg.http_client_tcp_keepalive_secs));
+    }
+    // Without it, reqwest still honours the usual HTTP(S)_PROXY variables.
+    if let Some(url) = &cfg.http_client_proxy {
+        let no_proxy = cfg
+            .http_client_no_proxy
+            .as_deref()
+            .and_then(NoProxy::from_string);
+        builder = builder.proxy(Proxy::all(url)?.no_proxy(no_proxy));
+    }
+    Ok(builder)
+}
@@ -0,0 +1,248 @@
+//! `Idempotency-Key` support for POST routes. The first response for a key
+//! is stored and replayed on retries, so a request resent over flaky Wi-Fi
+//! doesn't run inference or insert rows twice. Keys are per caller, so
+//! two users who happen to send the same key never see each other's
+//! responses. Routes that hand out credentials are never stored, so tokens
+//! and secrets only ever exist as hashes in the database.
+
+use std::fmt::Write;
+
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tracing::warn;
+
+use crate::{app_state::AppState, auth, db, error::AppError, validation::Validator};
+
+const KEY_HEADER: &str = "idempotency-key";
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_KEY_CHARS: usize = 255;
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+/// Larger responses are passed through rather than held in memory and in
+/// the database.
+const MAX_STORED_BYTES: u64 = 1024 * 1024;
+
+#[derive(sqlx::FromRow)]
+struct StoredResponse {
+    request_hash: String,
+    status: Option<i64>,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+}
+
+pub async fn middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(key) = req.headers().get(KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+    if req.method() != Method::POST || issues_credentials(req.uri().path()) {
+        return Ok(next.run(req).await);
+    }
+
+    let key = key
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|k| !k.is_empty() && k.chars().count() <= MAX_KEY_CHARS)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Idempotency-Key must be 1-{MAX_KEY_CHARS} visible ASCII characters"
+            ))
+        })?
+        .to_string();
+    let path = req.uri().path().to_string();
+    let scope = scope();
+
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|err| AppError::BadRequest(format!("failed to read request body: {err}")))?;
+    let request_hash = hash(&bytes);
+    let req = Request::from_parts(parts, Body::from(bytes));
+
+    let cutoff = format!("-{} seconds", state.config.idempotency_ttl_secs);
+    db::retry_busy(|| {
+        sqlx::query("DELETE FROM idempotency_keys WHERE created_at < datetime('now', ?)")
+            .bind(&cutoff)
+            .execute(&state.pool)
+    })
+    .await?;
+
+    // Claiming the key up front means a concurrent retry finds the row and
+    // waits for the client to try again instead of running in parallel.
+    let claimed = db::retry_busy(|| {
+        sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys(scope, key, path, request_hash)
+            VALUES(?, ?, ?, ?)
+            ON CONFLICT(scope, key, path) DO NOTHING
+            "#,
+        )
+        .bind(&scope)
+        .bind(&key)
+        .bind(&path)
+        .bind(&request_hash)
+        .execute(&state.pool)
+    })
+    .await?
+    .rows_affected()
+        == 1;
+
+    if !claimed {
+        let stored = sqlx::query_as::<_, StoredResponse>(
+            r#"
+            SELECT request_hash, status, content_type, body
+            FROM idempotency_keys
+            WHERE scope = ? AND key = ? AND path = ?
+            "#,
+        )
+        .bind(&scope)
+        .bind(&key)
+        .bind(&path)
+        .fetch_one(&state.pool)
+        .await?;
+
+        return replay(stored, &request_hash);
+    }
+
+    let response = next.run(req).await;
+    let streaming = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+
+    // Server errors are worth retrying, and streams and large downloads
+    // can't be buffered for replay, so none of them keep the key.
+    let fits = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|size| size <= MAX_STORED_BYTES);
+    if response.status().is_server_error() || streaming || !fits {
+        release(&state.pool, &scope, &key, &path).await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_STORED_BYTES as usize).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            release(&state.pool, &scope, &key, &path).await;
+            return Err(AppError::Internal(format!(
+                "failed to buffer response: {err}"
+            )));
+        }
+    };
+
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    db::retry_busy(|| {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = ?, content_type = ?, body = ?
+            WHERE scope = ? AND key = ? AND path = ?
+            "#,
+        )
+        .bind(i64::from(parts.status.as_u16()))
+        .bind(content_type)
+        .bind(bytes.as_ref())
+        .bind(&scope)
+        .bind(&key)
+        .bind(&path)
+        .execute(&state.pool)
+    })
+    .await?;
+
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+fn replay(stored: StoredResponse, request_hash: &str) -> Result<Response, AppError> {
+    if stored.request_hash != request_hash {
+        let mut v = Validator::default();
+        v.error(
+            "Idempotency-Key",
+            "was already used with a different request body",
+        );
+        v.finish()?;
+    }
+
+    let (Some(status), Some(body)) = (stored.status, stored.body) else {
+        return Err(AppError::Conflict(
+            "a request with this Idempotency-Key is still in progress".to_string(),
+        ));
+    };
+
+    let status = u16::try_from(status)
+        .ok()
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .ok_or_else(|| AppError::Internal(format!("stored invalid status {status}")))?;
+    let mut response = (status, body).into_response();
+    if let Some(content_type) = stored
+        .content_type
+        .and_then(|ct| HeaderValue::from_str(&ct).ok())
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, content_type);
+    }
+    response
+        .headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+
+    Ok(response)
+}
+
+/// Logins, tokens, API keys, password resets, and share links return a
+/// secret in the body, which must not sit in `idempotency_keys` (or backups
+/// of it) in plain text.
+fn issues_credentials(path: &str) -> bool {
+    path.starts_with("/auth/")
+        || path == "/admin/api-keys"
+        || path.ends_with("/password-reset")
+        || path.ends_with("/report-links")
+}
+
+/// Who a key belongs to: the logged-in user, or for anonymous requests the
+/// school they fall in.
+fn scope() -> String {
+    match auth::caller() {
+        Some(user) => format!("user:{}", user.id),
+        None => format!("school:{}", auth::school_id()),
+    }
+}
+
+async fn release(pool: &SqlitePool, scope: &str, key: &str, path: &str) {
+    let deleted = db::retry_busy(|| {
+        sqlx::query("DELETE FROM idempotency_keys WHERE scope = ? AND key = ? AND path = ?")
+            .bind(scope)
+            .bind(key)
+            .bind(path)
+            .execute(pool)
+    })
+    .await;
+    if let Err(err) = deleted {
+        warn!(error = %err, key, "failed to release idempotency key");
+    }
+}
+
+fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
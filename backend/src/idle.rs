@@ -0,0 +1,133 @@
+//! Frees the GPU when nobody is using it, e.g. overnight on shared school
+//! hardware. After `LLM_IDLE_SHUTDOWN_SECS` without requests the inference
+//! container is stopped (or Ollama is asked to unload its models), and the
+//! next chat request starts it again and waits for it to load.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, config::IdleAction, error::AppError, model_swap};
+
+#[derive(Default)]
+pub struct IdleState {
+    asleep: AtomicBool,
+    /// Held while going to sleep or waking so the two never overlap, and so
+    /// concurrent requests share one wake-up.
+    transition: Mutex<()>,
+}
+
+impl IdleState {
+    pub fn asleep(&self) -> bool {
+        self.asleep.load(Ordering::Relaxed)
+    }
+}
+
+/// Starts the idle check unless `LLM_IDLE_SHUTDOWN_SECS` is `0`.
+pub fn spawn(state: AppState) {
+    let idle_after = state.config.llm_idle_shutdown_secs;
+    if idle_after == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(idle_after.clamp(1, 60)));
+        loop {
+            ticker.tick().await;
+            let idle = state
+                .scheduler
+                .idle_secs()
+                .is_some_and(|secs| secs >= idle_after);
+            if idle && !state.idle.asleep() {
+                sleep(&state).await;
+            }
+        }
+    });
+}
+
+async fn sleep(state: &AppState) {
+    let _transition = state.idle.transition.lock().await;
+    // A request may have started while we waited for the lock.
+    if state.scheduler.idle_secs().is_none() {
+        return;
+    }
+
+    let result = match state.config.llm_idle_action {
+        IdleAction::Stop => match state.inference.as_deref() {
+            Some(container) => container.stop().await.map(|_| ()),
+            None => Ok(()),
+        },
+        IdleAction::Unload => unload_ollama(state).await,
+    };
+    match result {
+        Ok(()) => {
+            info!(action = ?state.config.llm_idle_action, "inference idle; released the model");
+            state.idle.asleep.store(true, Ordering::Relaxed);
+        }
+        Err(err) => warn!(error = %err, "failed to release idle model"),
+    }
+}
+
+/// Brings the model back if it was released for idleness. Requests that
+/// arrive while it loads wait for the same wake-up.
+pub async fn wake(state: &AppState) -> Result<(), AppError> {
+    if !state.idle.asleep() {
+        return Ok(());
+    }
+    let _transition = state.idle.transition.lock().await;
+    if !state.idle.asleep() {
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    if let (IdleAction::Stop, Some(container)) =
+        (state.config.llm_idle_action, state.inference.as_deref())
+    {
+        container.start().await?;
+        let deadline = started + Duration::from_secs(state.config.llm_warmup_timeout_secs);
+        model_swap::wait_until_serving(state, deadline, || {}).await?;
+    }
+
+    info!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "woke idle inference server"
+    );
+    state.idle.asleep.store(false, Ordering::Relaxed);
+    Ok(())
+}
+
+/// `keep_alive: 0` makes Ollama unload a model immediately.
+async fn unload_ollama(state: &AppState) -> Result<(), AppError> {
+    for backend in state.upstreams.backends() {
+        let loaded: Value = state
+            .llm_client
+            .get(backend.url("/api/ps"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let models = loaded
+            .get("models")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.get("name").and_then(Value::as_str));
+
+        for model in models {
+            state
+                .llm_client
+                .post(backend.url("/api/generate"))
+                .json(&json!({ "model": model, "keep_alive": 0 }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Periodic resource samples of the inference container, kept in memory
+//! for the admin dashboard.
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use serde::Serialize;
+use tracing::debug;
+
+use crate::{
+    app_state::AppState,
+    docker::ContainerUsage,
+    hardware::{self, GpuUsage},
+    time::unix_now,
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StatsSample {
+    pub sampled_at: u64,
+    /// `None` when the container couldn't be sampled, e.g. while stopped.
+    pub container: Option<ContainerUsage>,
+    pub gpus: Vec<GpuUsage>,
+}
+
+pub struct StatsHistory {
+    samples: Mutex<VecDeque<StatsSample>>,
+    retention_secs: u64,
+}
+
+impl StatsHistory {
+    pub fn new(retention_secs: u64) -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::new()),
+            retention_secs,
+        }
+    }
+
+    /// Samples taken at or after `since`, oldest first.
+    pub fn since(&self, since: u64) -> Vec<StatsSample> {
+        self.lock()
+            .iter()
+            .filter(|sample| sample.sampled_at >= since)
+            .cloned()
+            .collect()
+    }
+
+    fn push(&self, sample: StatsSample) {
+        let cutoff = sample.sampled_at.saturating_sub(self.retention_secs);
+        let mut samples = self.lock();
+        while samples.front().is_some_and(|s| s.sampled_at < cutoff) {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<StatsSample>> {
+        self.samples
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Starts sampling unless there's no managed container or
+/// `DOCKER_STATS_INTERVAL_SECS` is `0`.
+pub fn spawn(state: AppState) {
+    let interval_secs = state.config.docker_stats_interval_secs;
+    let Some(container) = state.inference.clone() else {
+        return;
+    };
+    if interval_secs == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            let usage = match container.usage().await {
+                Ok(usage) => Some(usage),
+                Err(err) => {
+                    debug!(error = %err, "failed to sample inference container stats");
+                    None
+                }
+            };
+            let gpus = tokio::task::spawn_blocking(hardware::gpu_usage)
+                .await
+                .unwrap_or_default();
+
+            state.inference_stats.push(StatsSample {
+                sampled_at: unix_now(),
+                container: usage,
+                gpus,
+            });
+        }
+    });
+}
@@ -0,0 +1,584 @@
+//! Persistence for `ai_interactions` rows written by the chat proxy.
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth, db,
+    pagination::Page,
+    routes::{schools::DEFAULT_SCHOOL_ID, students},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Interaction {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub student_id: Option<i64>,
+    pub prompt: String,
+    pub response: String,
+    pub model: Option<String>,
+    pub latency_ms: Option<i64>,
+    pub ttft_ms: Option<i64>,
+    pub total_ms: Option<i64>,
+    pub queue_ms: Option<i64>,
+    pub upstream_ms: Option<i64>,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub estimated_cost: Option<f64>,
+    pub experiment: Option<String>,
+    pub variant: Option<String>,
+    pub regenerated_from: Option<i64>,
+    /// Id of the conversation's first interaction.
+    pub conversation_id: Option<i64>,
+    /// The earlier turn this one follows.
+    pub parent_interaction_id: Option<i64>,
+    pub cancelled: bool,
+    pub request_id: Option<String>,
+    pub traceparent: Option<String>,
+    pub persona_id: Option<i64>,
+    /// The response filter rewrote part of the output.
+    pub filtered: bool,
+    /// Set when the retention job scrubbed the row.
+    pub anonymized_at: Option<String>,
+    pub created_at: String,
+}
+
+/// An [`Interaction`] with `prompt` and `response` parsed back into JSON.
+/// Rows whose text isn't JSON (e.g. a relayed stream) keep it as a string.
+#[derive(Debug, Serialize)]
+pub struct ParsedInteraction {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub student_id: Option<i64>,
+    /// The request's `messages` array.
+    pub prompt: Value,
+    /// The upstream completion body.
+    pub response: Value,
+    pub model: Option<String>,
+    pub latency_ms: Option<i64>,
+    pub ttft_ms: Option<i64>,
+    pub total_ms: Option<i64>,
+    pub queue_ms: Option<i64>,
+    pub upstream_ms: Option<i64>,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub estimated_cost: Option<f64>,
+    pub experiment: Option<String>,
+    pub variant: Option<String>,
+    pub regenerated_from: Option<i64>,
+    pub conversation_id: Option<i64>,
+    pub parent_interaction_id: Option<i64>,
+    pub cancelled: bool,
+    pub request_id: Option<String>,
+    pub traceparent: Option<String>,
+    pub persona_id: Option<i64>,
+    pub filtered: bool,
+    pub anonymized_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<Interaction> for ParsedInteraction {
+    fn from(row: Interaction) -> Self {
+        let parse = |text: String| serde_json::from_str(&text).unwrap_or(Value::String(text));
+        ParsedInteraction {
+            id: row.id,
+            user_id: row.user_id,
+            student_id: row.student_id,
+            prompt: parse(row.prompt),
+            response: parse(row.response),
+            model: row.model,
+            latency_ms: row.latency_ms,
+            ttft_ms: row.ttft_ms,
+            total_ms: row.total_ms,
+            queue_ms: row.queue_ms,
+            upstream_ms: row.upstream_ms,
+            prompt_tokens: row.prompt_tokens,
+            completion_tokens: row.completion_tokens,
+            estimated_cost: row.estimated_cost,
+            experiment: row.experiment,
+            variant: row.variant,
+            regenerated_from: row.regenerated_from,
+            conversation_id: row.conversation_id,
+            parent_interaction_id: row.parent_interaction_id,
+            cancelled: row.cancelled,
+            request_id: row.request_id,
+            traceparent: row.traceparent,
+            persona_id: row.persona_id,
+            filtered: row.filtered,
+            anonymized_at: row.anonymized_at,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// The model's reply in a parsed response: OpenAI `choices`, Ollama
+/// `message`, or the stored text itself for a relayed stream.
+pub fn reply_text(response: &Value) -> Option<&str> {
+    response
+        .pointer("/choices/0/message/content")
+        .or_else(|| response.pointer("/message/content"))
+        .unwrap_or(response)
+        .as_str()
+}
+
+/// Filters for [`list`]; `None` matches everything.
+#[derive(Debug, Default)]
+pub struct InteractionFilter {
+    pub student_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub model: Option<String>,
+    /// Inclusive lower bound on `created_at`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// Reviewer label from `interaction_tags`, matched case-insensitively.
+    pub tag: Option<String>,
+}
+
+const INTERACTION_COLUMNS: &str = r#"
+    id, user_id, student_id, prompt, response, model, latency_ms, ttft_ms,
+    total_ms, queue_ms, upstream_ms, prompt_tokens, completion_tokens, estimated_cost, experiment, variant,
+    regenerated_from, conversation_id, parent_interaction_id, cancelled, request_id,
+    traceparent, persona_id, filtered, anonymized_at, created_at
+"#;
+
+#[derive(Clone, Debug, Default)]
+pub struct NewInteraction {
+    pub user_id: Option<i64>,
+    pub student_id: Option<i64>,
+    pub prompt: String,
+    pub response: String,
+    pub model: Option<String>,
+    /// From the first upstream call to the last byte, retries included.
+    pub latency_ms: Option<i64>,
+    /// First streamed token, measured like `latency_ms`.
+    pub ttft_ms: Option<i64>,
+    /// From the proxy receiving the request to the response finishing.
+    pub total_ms: Option<i64>,
+    /// Waiting for an idle model server to wake and for a scheduler slot.
+    pub queue_ms: Option<i64>,
+    /// `latency_ms` minus time spent waiting out `Retry-After`.
+    pub upstream_ms: Option<i64>,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub estimated_cost: Option<f64>,
+    pub experiment: Option<String>,
+    pub variant: Option<String>,
+    /// First attempt of the prompt this row regenerates.
+    pub regenerated_from: Option<i64>,
+    /// `None` starts a new conversation with this row as its first turn.
+    pub conversation_id: Option<i64>,
+    pub parent_interaction_id: Option<i64>,
+    /// The client disconnected before the response finished.
+    pub cancelled: bool,
+    pub request_id: Option<String>,
+    pub traceparent: Option<String>,
+    pub persona_id: Option<i64>,
+    /// The response filter rewrote part of the output.
+    pub filtered: bool,
+    /// Captured with the request, since cancelled chats are written from a
+    /// spawned task. `None` means the default school.
+    pub school_id: Option<i64>,
+}
+
+impl NewInteraction {
+    /// Copies model name and token counts from an OpenAI-style completion or
+    /// final stream chunk, when present.
+    pub fn apply_usage(&mut self, completion: &Value) {
+        if let Some(model) = completion.get("model").and_then(Value::as_str) {
+            self.model = Some(model.to_string());
+        }
+        if let Some(usage) = completion.get("usage").filter(|u| u.is_object()) {
+            self.prompt_tokens = usage.get("prompt_tokens").and_then(Value::as_i64);
+            self.completion_tokens = usage.get("completion_tokens").and_then(Value::as_i64);
+        }
+    }
+}
+
+/// Retried on lock contention, since every chat ends here and losing the
+/// row would lose the record of what the model said.
+pub async fn insert(pool: &SqlitePool, row: NewInteraction) -> Result<i64, sqlx::Error> {
+    db::retry_busy(|| insert_once(pool, &row)).await
+}
+
+async fn insert_once(pool: &SqlitePool, row: &NewInteraction) -> Result<i64, sqlx::Error> {
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO ai_interactions (
+            user_id, student_id, prompt, response, model, latency_ms, ttft_ms,
+            total_ms, queue_ms, upstream_ms, prompt_tokens, completion_tokens,
+            estimated_cost, experiment, variant, regenerated_from, conversation_id,
+            parent_interaction_id, cancelled, request_id, traceparent, persona_id, filtered,
+            school_id
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(row.user_id)
+    .bind(row.student_id)
+    .bind(&row.prompt)
+    .bind(&row.response)
+    .bind(&row.model)
+    .bind(row.latency_ms)
+    .bind(row.ttft_ms)
+    .bind(row.total_ms)
+    .bind(row.queue_ms)
+    .bind(row.upstream_ms)
+    .bind(row.prompt_tokens)
+    .bind(row.completion_tokens)
+    .bind(row.estimated_cost)
+    .bind(&row.experiment)
+    .bind(&row.variant)
+    .bind(row.regenerated_from)
+    .bind(row.conversation_id)
+    .bind(row.parent_interaction_id)
+    .bind(row.cancelled)
+    .bind(&row.request_id)
+    .bind(&row.traceparent)
+    .bind(row.persona_id)
+    .bind(row.filtered)
+    .bind(row.school_id.unwrap_or(DEFAULT_SCHOOL_ID))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// A full-text match with the best-matching excerpt, `[marked]` around the
+/// matched terms.
+#[derive(Debug, sqlx::FromRow)]
+pub struct SearchHit {
+    #[sqlx(flatten)]
+    pub interaction: Interaction,
+    pub snippet: String,
+}
+
+/// Turns free text into an FTS5 query that requires every word, so user
+/// input can't trip over FTS5 operators or unbalanced quotes. Returns `None`
+/// when nothing searchable is left.
+pub fn match_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\""))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Leaves out interactions about a student the caller can't see (see
+/// [`students::ACCESS_FILTER`]); binds [`students::viewer`] three times.
+pub fn visible_filter(student_column: &str) -> String {
+    format!(
+        "({student_column} IS NULL OR {student_column} IN (SELECT id FROM students WHERE {}))",
+        students::ACCESS_FILTER
+    )
+}
+
+/// Interactions in the caller's school whose messages or reply match
+/// `query` (see [`match_query`]), best match first, optionally limited to
+/// one student.
+pub async fn search(
+    pool: &SqlitePool,
+    query: &str,
+    student_id: Option<i64>,
+    limit: i64,
+    offset: i64,
+) -> Result<Page<SearchHit>, sqlx::Error> {
+    let school_id = auth::school_id();
+    let viewer = students::viewer();
+    let visible = visible_filter("student_id");
+    let items = sqlx::query_as::<_, SearchHit>(&format!(
+        r#"
+        WITH hits AS (
+            SELECT rowid AS hit_id,
+                   snippet(interaction_search, -1, '[', ']', '…', 16) AS snippet,
+                   bm25(interaction_search) AS score
+            FROM interaction_search
+            WHERE interaction_search MATCH ?
+        )
+        SELECT {INTERACTION_COLUMNS}, snippet
+        FROM ai_interactions
+        JOIN hits ON hit_id = id
+        WHERE school_id = ? AND (? IS NULL OR student_id = ?) AND {visible}
+        ORDER BY score ASC, id DESC
+        LIMIT ? OFFSET ?
+        "#
+    ))
+    .bind(query)
+    .bind(school_id)
+    .bind(student_id)
+    .bind(student_id)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        r#"
+        SELECT COUNT(*)
+        FROM interaction_search s
+        JOIN ai_interactions i ON i.id = s.rowid
+        WHERE interaction_search MATCH ? AND i.school_id = ? AND (? IS NULL OR i.student_id = ?)
+          AND {visible}
+        "#,
+        visible = visible_filter("i.student_id"),
+    ))
+    .bind(query)
+    .bind(school_id)
+    .bind(student_id)
+    .bind(student_id)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Page {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// One student's interactions created in `[since, until)`, newest first.
+pub async fn list_for_student(
+    pool: &SqlitePool,
+    student_id: i64,
+    since: &str,
+    until: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Page<Interaction>, sqlx::Error> {
+    let school_id = auth::school_id();
+    let filter = "school_id = ? AND student_id = ? AND created_at >= ? AND created_at < ?";
+    let items = sqlx::query_as::<_, Interaction>(&format!(
+        r#"
+        SELECT {INTERACTION_COLUMNS}
+        FROM ai_interactions
+        WHERE {filter}
+        ORDER BY created_at DESC, id DESC
+        LIMIT ? OFFSET ?
+        "#
+    ))
+    .bind(school_id)
+    .bind(student_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*) FROM ai_interactions WHERE {filter}"
+    ))
+    .bind(school_id)
+    .bind(student_id)
+    .bind(since)
+    .bind(until)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Page {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Interactions in the caller's school matching `filter`, newest first.
+pub async fn list(
+    pool: &SqlitePool,
+    filter: &InteractionFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<Page<Interaction>, sqlx::Error> {
+    let school_id = auth::school_id();
+    let viewer = students::viewer();
+    let conditions = format!(
+        "school_id = ? AND (? IS NULL OR student_id = ?) AND (? IS NULL OR user_id = ?) \
+         AND (? IS NULL OR model = ?) AND (? IS NULL OR created_at >= ?) \
+         AND (? IS NULL OR created_at < ?) \
+         AND (? IS NULL OR id IN (SELECT interaction_id FROM interaction_tags WHERE tag = ?)) \
+         AND {}",
+        visible_filter("student_id"),
+    );
+    let items = sqlx::query_as::<_, Interaction>(&format!(
+        r#"
+        SELECT {INTERACTION_COLUMNS}
+        FROM ai_interactions
+        WHERE {conditions}
+        ORDER BY created_at DESC, id DESC
+        LIMIT ? OFFSET ?
+        "#
+    ))
+    .bind(school_id)
+    .bind(filter.student_id)
+    .bind(filter.student_id)
+    .bind(filter.user_id)
+    .bind(filter.user_id)
+    .bind(&filter.model)
+    .bind(&filter.model)
+    .bind(&filter.since)
+    .bind(&filter.since)
+    .bind(&filter.until)
+    .bind(&filter.until)
+    .bind(&filter.tag)
+    .bind(&filter.tag)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*) FROM ai_interactions WHERE {conditions}"
+    ))
+    .bind(school_id)
+    .bind(filter.student_id)
+    .bind(filter.student_id)
+    .bind(filter.user_id)
+    .bind(filter.user_id)
+    .bind(&filter.model)
+    .bind(&filter.model)
+    .bind(&filter.since)
+    .bind(&filter.since)
+    .bind(&filter.until)
+    .bind(&filter.until)
+    .bind(&filter.tag)
+    .bind(&filter.tag)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Page {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Every turn of a conversation in the order they happened, regenerated
+/// attempts included.
+pub async fn conversation(
+    pool: &SqlitePool,
+    conversation_id: i64,
+) -> Result<Vec<Interaction>, sqlx::Error> {
+    let viewer = students::viewer();
+    sqlx::query_as::<_, Interaction>(&format!(
+        "SELECT {INTERACTION_COLUMNS} FROM ai_interactions \
+         WHERE conversation_id = ? AND school_id = ? AND {} ORDER BY created_at ASC, id ASC",
+        visible_filter("student_id"),
+    ))
+    .bind(conversation_id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_all(pool)
+    .await
+}
+
+/// The chain of parent turns leading to `id`, first turn first, ending with
+/// `id` itself. Empty when `id` doesn't exist.
+pub async fn thread(pool: &SqlitePool, id: i64) -> Result<Vec<Interaction>, sqlx::Error> {
+    let viewer = students::viewer();
+    // A parent is always stored before its child, so id order is turn order.
+    sqlx::query_as::<_, Interaction>(&format!(
+        r#"
+        WITH RECURSIVE chain(id) AS (
+            SELECT id FROM ai_interactions WHERE id = ?
+            UNION ALL
+            SELECT i.parent_interaction_id
+            FROM chain c
+            JOIN ai_interactions i ON i.id = c.id
+            WHERE i.parent_interaction_id IS NOT NULL
+        )
+        SELECT {INTERACTION_COLUMNS}
+        FROM ai_interactions
+        WHERE id IN (SELECT id FROM chain) AND school_id = ? AND {visible}
+        ORDER BY id ASC
+        "#,
+        visible = visible_filter("student_id"),
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_all(pool)
+    .await
+}
+
+/// The first attempt at a prompt and every regeneration of it, in order.
+pub async fn attempts(pool: &SqlitePool, root_id: i64) -> Result<Vec<Interaction>, sqlx::Error> {
+    let viewer = students::viewer();
+    sqlx::query_as::<_, Interaction>(&format!(
+        "SELECT {INTERACTION_COLUMNS} FROM ai_interactions \
+         WHERE (id = ? OR regenerated_from = ?) AND school_id = ? AND {} ORDER BY id ASC",
+        visible_filter("student_id"),
+    ))
+    .bind(root_id)
+    .bind(root_id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> Result<Option<Interaction>, sqlx::Error> {
+    let viewer = students::viewer();
+    sqlx::query_as::<_, Interaction>(&format!(
+        "SELECT {INTERACTION_COLUMNS} FROM ai_interactions \
+         WHERE id = ? AND school_id = ? AND {}",
+        visible_filter("student_id"),
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_query_quotes_each_word() {
+        assert_eq!(
+            match_query("fractions AND (NOT decimals)*").as_deref(),
+            Some(r#""fractions" "AND" "NOT" "decimals""#)
+        );
+    }
+
+    #[test]
+    fn match_query_keeps_apostrophes_and_drops_quotes() {
+        assert_eq!(
+            match_query(r#"don't "quote" me"#).as_deref(),
+            Some(r#""don't" "quote" "me""#)
+        );
+    }
+
+    #[test]
+    fn match_query_without_words_is_none() {
+        assert_eq!(match_query(""), None);
+        assert_eq!(match_query(r#" "* - ( ) "#), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! JWT access and refresh tokens, for native and mobile clients that would
+//! rather not keep a cookie. Access tokens are short-lived and checked
+//! against a revocation list; refresh tokens rotate on every use, and
+//! presenting one that was already used revokes its whole family.
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, Credential, CurrentUser},
+    error::AppError,
+    time::unix_now,
+};
+
+const ACCESS: &str = "access";
+const REFRESH: &str = "refresh";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    /// `access` or `refresh`, so one can't stand in for the other.
+    typ: String,
+    jti: String,
+    /// Shared by every token descended from the same login.
+    fid: String,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// Seconds until the access token expires.
+    pub expires_in: u64,
+    pub refresh_expires_in: u64,
+}
+
+fn secret(state: &AppState) -> Result<&str, AppError> {
+    state.config.jwt_secret.as_deref().ok_or_else(|| {
+        AppError::NotFound("JWT authentication is not enabled (set JWT_SECRET)".to_string())
+    })
+}
+
+fn sign(secret: &str, claims: &Claims) -> Result<String, AppError> {
+    encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .map_err(|err| AppError::Internal(format!("failed to sign token: {err}")))
+}
+
+/// The token's claims if the signature is good, it hasn't expired (unless
+/// `allow_expired`), and it's the expected kind.
+fn verify(secret: &str, token: &str, typ: &str, allow_expired: bool) -> Option<Claims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = !allow_expired;
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .ok()
+    .map(|data| data.claims)
+    .filter(|claims| claims.typ == typ)
+}
+
+/// Issues an access/refresh pair, continuing `family_id` on refresh or
+/// starting a new family on login.
+pub async fn issue(
+    state: &AppState,
+    user_id: i64,
+    family_id: Option<String>,
+) -> Result<TokenPair, AppError> {
+    let secret = secret(state)?;
+    let now = unix_now();
+    let family_id = family_id.unwrap_or_else(auth::new_token);
+    let access_ttl = state.config.jwt_access_ttl_secs;
+    let refresh_ttl = state.config.jwt_refresh_ttl_days * 86400;
+
+    let access = Claims {
+        sub: user_id.to_string(),
+        typ: ACCESS.to_string(),
+        jti: auth::new_token(),
+        fid: family_id.clone(),
+        iat: now,
+        exp: now + access_ttl,
+    };
+    let refresh = Claims {
+        sub: user_id.to_string(),
+        typ: REFRESH.to_string(),
+        jti: auth::new_token(),
+        fid: family_id,
+        iat: now,
+        exp: now + refresh_ttl,
+    };
+
+    sqlx::query("DELETE FROM refresh_tokens WHERE expires_at <= CURRENT_TIMESTAMP")
+        .execute(&state.pool)
+        .await?;
+    sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= CURRENT_TIMESTAMP")
+        .execute(&state.pool)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens(jti, family_id, user_id, expires_at)
+        VALUES(?, ?, ?, datetime(?, 'unixepoch'))
+        "#,
+    )
+    .bind(&refresh.jti)
+    .bind(&refresh.fid)
+    .bind(user_id)
+    .bind(refresh.exp as i64)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(TokenPair {
+        access_token: sign(secret, &access)?,
+        refresh_token: sign(secret, &refresh)?,
+        token_type: "Bearer",
+        expires_in: access_ttl,
+        refresh_expires_in: refresh_ttl,
+    })
+}
+
+/// Resolves a bearer access token. `None` when JWTs are disabled or the
+/// token is invalid, expired, revoked, or for a deleted user.
+pub async fn authenticate(state: &AppState, token: &str) -> Result<Option<CurrentUser>, AppError> {
+    let Some(secret) = state.config.jwt_secret.as_deref() else {
+        return Ok(None);
+    };
+    let Some(claims) = verify(secret, token, ACCESS, false) else {
+        return Ok(None);
+    };
+    let Ok(user_id) = claims.sub.parse::<i64>() else {
+        return Ok(None);
+    };
+
+    let row = sqlx::query_as::<_, (i64, String, String, String, i64)>(
+        r#"
+        SELECT id, role, name, email, school_id
+        FROM users
+        WHERE id = ?
+          AND NOT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = ?)
+          AND NOT EXISTS(
+              SELECT 1 FROM refresh_tokens WHERE family_id = ? AND revoked_at IS NOT NULL
+          )
+        "#,
+    )
+    .bind(user_id)
+    .bind(&claims.jti)
+    .bind(&claims.fid)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    Ok(row.map(|(id, role, name, email, school_id)| CurrentUser {
+        id,
+        role,
+        name,
+        email,
+        school_id,
+        credential: Credential::AccessToken {
+            jti: claims.jti,
+            family_id: claims.fid,
+            expires_at: claims.exp,
+        },
+    }))
+}
+
+/// Trades a refresh token for a new pair. Each refresh token works once.
+pub async fn refresh(state: &AppState, token: &str) -> Result<TokenPair, AppError> {
+    let invalid = || AppError::Unauthorized("invalid or expired refresh token".to_string());
+    let claims = verify(secret(state)?, token, REFRESH, false).ok_or_else(invalid)?;
+
+    // Marking it used in the same statement that checks it means two
+    // concurrent refreshes can't both succeed.
+    let user_id: Option<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE refresh_tokens SET used_at = CURRENT_TIMESTAMP
+        WHERE jti = ?
+          AND used_at IS NULL
+          AND revoked_at IS NULL
+          AND expires_at > CURRENT_TIMESTAMP
+        RETURNING user_id
+        "#,
+    )
+    .bind(&claims.jti)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let Some(user_id) = user_id else {
+        let reused: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM refresh_tokens WHERE jti = ? AND used_at IS NOT NULL)",
+        )
+        .bind(&claims.jti)
+        .fetch_one(&state.pool)
+        .await?;
+        if reused {
+            // Either the client retried or the token leaked; the safe
+            // answer to both is to make everyone log in again.
+            warn!(family_id = %claims.fid, "refresh token reused; revoking its family");
+            revoke_family(state, &claims.fid).await?;
+        }
+        return Err(invalid());
+    };
+
+    issue(state, user_id, Some(claims.fid)).await
+}
+
+/// Revokes the refresh token's family, and with it every access token
+/// issued from that login. Expired tokens are accepted so a client can
+/// still clean up after going quiet.
+pub async fn revoke(state: &AppState, token: &str) -> Result<(), AppError> {
+    let claims = verify(secret(state)?, token, REFRESH, true)
+        .ok_or_else(|| AppError::Unauthorized("invalid refresh token".to_string()))?;
+
+    revoke_family(state, &claims.fid).await
+}
+
+pub async fn revoke_family(state: &AppState, family_id: &str) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP
+        WHERE family_id = ? AND revoked_at IS NULL
+        "#,
+    )
+    .bind(family_id)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Puts one access token on the revocation list until it would have
+/// expired anyway.
+pub async fn revoke_access_token(
+    state: &AppState,
+    jti: &str,
+    expires_at: u64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO revoked_tokens(jti, expires_at)
+        VALUES(?, datetime(?, 'unixepoch'))
+        ON CONFLICT(jti) DO NOTHING
+        "#,
+    )
+    .bind(jti)
+    .bind(expires_at as i64)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(())
+}
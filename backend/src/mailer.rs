@@ -0,0 +1,49 @@
+//! Outgoing email over SMTP (STARTTLS), used for password resets. Disabled
+//! unless `SMTP_HOST` is set.
+
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{config::Config, error::AppError};
+
+pub fn enabled(config: &Config) -> bool {
+    config.smtp_host.is_some()
+}
+
+/// Sends a plain-text email. Fails when SMTP isn't configured.
+pub async fn send(config: &Config, to: &str, subject: &str, body: String) -> Result<(), AppError> {
+    let (Some(host), Some(from)) = (&config.smtp_host, &config.smtp_from) else {
+        return Err(AppError::Internal(
+            "email is not enabled (set SMTP_HOST)".to_string(),
+        ));
+    };
+    let mailbox = |address: &str| {
+        address
+            .parse::<Mailbox>()
+            .map_err(|err| AppError::Internal(format!("invalid email address '{address}': {err}")))
+    };
+
+    let message = Message::builder()
+        .from(mailbox(from)?)
+        .to(mailbox(to)?)
+        .subject(subject)
+        .body(body)
+        .map_err(|err| AppError::Internal(format!("failed to build email: {err}")))?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+        .map_err(|err| AppError::Upstream(format!("invalid SMTP_HOST: {err}")))?
+        .port(config.smtp_port);
+    if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    transport
+        .build()
+        .send(message)
+        .await
+        .map_err(|err| AppError::Upstream(format!("failed to send email: {err}")))?;
+
+    Ok(())
+}
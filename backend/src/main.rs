@@ -1,48 +1,608 @@
+mod api_keys;
 mod app_state;
+mod assist;
+mod audit;
+mod auth;
+mod backup;
+mod cli;
 mod config;
+mod config_file;
 mod db;
+mod deployments;
+mod docker;
 mod error;
+mod experiment;
+mod family_digest;
+mod hardware;
+mod health_monitor;
+mod http_client;
+mod idempotency;
+mod idle;
+mod inference_stats;
+mod interactions;
+mod jwt;
+mod mailer;
+mod model_catalog;
+mod model_download;
+mod model_swap;
+mod oidc;
+mod ollama;
+mod pagination;
+mod rate_limit;
+mod readability;
+mod redact;
+mod reload;
+mod research_export;
+mod response_filter;
+mod retention;
 mod routes;
+mod scheduler;
+mod stream_telemetry;
+mod supervisor;
+mod text_diff;
+mod time;
+mod tls;
+mod trace;
+mod upstream;
+mod validation;
+mod warmup;
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, path::PathBuf};
 
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
+use cli::{Cli, Command};
 use config::Config;
 use routes::{
+    admin::{
+        create_backup, get_canary, get_config, get_experiment, purge_interactions, reload_config,
+        update_canary, update_experiment,
+    },
+    analytics::usage,
+    api_keys::{create_api_key, delete_api_key, list_api_keys},
+    assignments::{
+        create_assignment, delete_assignment, delete_grade, draft_feedback, get_assignment,
+        grade_summary, list_assignments, update_assignment, upsert_grade,
+    },
+    attendance::{delete_attendance, list_attendance, record_attendance, student_attendance},
+    audit_log::list_audit_log,
+    auth::{
+        issue_token, login, logout, me, oidc_callback, oidc_login, refresh_token, register,
+        revoke_token,
+    },
+    classes::{
+        create_class, delete_class, enroll_student, get_class, list_classes, list_roster,
+        unenroll_student, update_class,
+    },
+    consents::{get_student_consent, update_student_consent},
+    deployments::{
+        create_deployment, delete_deployment, get_deployment, list_deployments, start_deployment,
+        stop_deployment,
+    },
+    essays::{create_essay, delete_essay, get_draft, get_essay, list_essays, submit_draft},
+    exports::{create_export, download_export, get_export, list_exports},
+    family_digests::{
+        create_family_digest, get_family_digest, list_family_digests, list_student_family_digests,
+    },
+    few_shot::{create_example, delete_example, list_examples, update_example},
+    flashcards::{delete_deck, due_cards, generate_deck, get_deck, list_decks, review_card},
+    goals::{
+        add_evidence, create_goal, delete_evidence, delete_goal, draft_progress_note, get_goal,
+        list_goals, list_progress_notes, update_goal,
+    },
+    grade_policies::{delete_grade_policy, list_grade_policies, upsert_grade_policy},
+    guardians::{create_guardian, delete_guardian, get_guardian, list_guardians, update_guardian},
+    hardware::hardware_report,
     health::healthz,
-    llm::proxy_chat_completion,
-    students::{create_student, list_students},
+    homework_help::{close_session, get_session, list_sessions, open_session, send_message},
+    inference::{
+        inference_logs, inference_stats, inference_status, list_inference_events,
+        restart_inference, start_inference, stop_inference,
+    },
+    interaction_tags::{
+        add_interaction_tag, list_interaction_tags, list_tags_for_interaction,
+        remove_interaction_tag,
+    },
+    interactions::{
+        anonymize_interactions, create_feedback, export_fine_tune, get_conversation,
+        get_interaction, get_stream_telemetry, get_thread, get_variants, list_interactions,
+        regenerate_interaction, search_interactions, student_interactions,
+    },
+    lesson_plans::{
+        delete_lesson_plan, generate_lesson_plan, get_lesson_plan, list_lesson_plan_versions,
+        list_lesson_plans, regenerate_lesson_plan, update_lesson_plan,
+    },
+    llm::{llm_status, proxy_chat_completion, proxy_embeddings},
+    model_downloads::{
+        create_model_download, get_model_download, list_model_downloads, retry_model_download,
+    },
+    models::{list_local_models, load_model, model_load_status},
+    password_resets::{confirm_password_reset, issue_password_reset, request_password_reset},
+    personas::{create_persona, delete_persona, get_persona, list_personas, update_persona},
+    presence::{heartbeat, list_active, student_presence},
+    progress_reports::{create_progress_report, get_progress_report, list_progress_reports},
+    quizzes::{delete_quiz, generate_quiz, get_quiz, list_quizzes, update_quiz},
+    reading_levels::{assess_reading_level, list_reading_assessments, reading_trend},
+    report_links::{
+        create_report_link, list_report_links, revoke_report_link, shared_progress_report,
+    },
+    reports::{cost_report, experiment_report, feedback_report},
+    reservations::{create_reservation, delete_reservation, list_reservations},
+    review_queue::{decide_review_item, get_review_item, list_review_items},
+    rubrics::{
+        create_rubric, delete_rubric, get_rubric, grade_with_rubric, list_rubric_gradings,
+        list_rubrics, update_rubric,
+    },
+    school_years::{
+        archive_school_year, create_school_year, get_school_year, list_school_years,
+        update_school_year,
+    },
+    schools::{create_school, get_school, list_schools, update_school},
+    scratchpads::{get_scratchpad, list_scratchpad_versions, update_scratchpad},
+    sessions::{
+        list_my_sessions, list_user_sessions, revoke_my_session, revoke_my_sessions,
+        revoke_user_sessions,
+    },
+    standards::{
+        class_coverage, create_standard, delete_standard, import_standards,
+        list_interaction_standards, list_lesson_plan_standards, list_quiz_standards,
+        list_standards, tag_interaction, tag_lesson_plan, tag_quiz, untag_interaction,
+        untag_lesson_plan, untag_quiz,
+    },
+    student_notes::{create_note, delete_note, get_note, list_notes, update_note},
+    student_shares::{list_student_shares, share_student, unshare_student},
+    students::{
+        create_student, delete_student, export_students, get_student, import_students,
+        list_students, merge_students, restore_student, update_student,
+    },
+    tags::{
+        create_tag, delete_tag, delete_tag_policy, list_student_tags, list_tag_policies, list_tags,
+        tag_student, untag_student, upsert_tag_policy,
+    },
+    users::{create_user, delete_user, get_user, list_users, update_user},
+    vocabulary::{build_list, delete_list, delete_word, get_list, list_lists, practice_sentences},
 };
 use tokio::net::TcpListener;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Development convenience only; release builds never read `.env`.
+    // Variables already set win over the file.
+    #[cfg(debug_assertions)]
+    let _ = dotenvy::dotenv();
+
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
-    let cfg = Config::from_env()?;
-    let state = db::build_state(cfg).await?;
+    let cli = Cli::parse_with_env();
+    let (config_file, mut cfg) = cli.load_config()?;
+    let command = cli.command.unwrap_or_default();
+    if let Command::Serve(args) = &command {
+        args.apply(&mut cfg);
+    }
+    if cli.print_config {
+        return cli::print_config(&cfg, cli.config.as_deref());
+    }
+    match command {
+        Command::Serve(_) => serve(cfg, cli.config).await,
+        Command::Migrate(args) => cli::migrate(&cfg, args).await,
+        Command::Seed(args) => cli::seed(&cfg, args).await,
+        Command::Export(args) => cli::export(&cfg, args).await,
+        Command::CheckConfig => cli::check_config(&cfg, config_file.as_ref()),
+        Command::Backup(args) => cli::backup(&cfg, args).await,
+        Command::Restore(args) => cli::restore(&cfg, args).await,
+    }
+}
+
+async fn serve(
+    cfg: Config,
+    config_path: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let state = db::build_state(cfg, config_path).await?;
+    deployments::register_all(&state).await?;
+    // Before warmup, which waits on the model server the supervisor starts.
+    supervisor::spawn(state.clone());
+    warmup::run(&state).await;
+    health_monitor::spawn(state.clone());
+    family_digest::spawn(state.clone());
+    retention::spawn(state.clone());
+    backup::spawn(state.clone());
+    inference_stats::spawn(state.clone());
+    idle::spawn(state.clone());
+    reload::spawn(state.clone());
+    tokio::spawn(model_download::resume_pending(
+        state.pool.clone(),
+        state.download_client.clone(),
+        state.config.hf_token.clone(),
+    ));
 
     let addr: SocketAddr =
         format!("{}:{}", state.config.app_host, state.config.app_port).parse()?;
+    let tls = tls::rustls_config(&state.config).await?;
 
     let app = Router::new()
         .route("/healthz", get(healthz))
+        .route("/auth/register", post(register))
+        .route("/auth/login", post(login))
+        .route("/auth/logout", post(logout))
+        .route("/auth/me", get(me))
+        .route("/auth/password-reset", post(request_password_reset))
+        .route("/auth/password-reset/confirm", post(confirm_password_reset))
+        .route(
+            "/auth/sessions",
+            get(list_my_sessions).delete(revoke_my_sessions),
+        )
+        .route("/auth/sessions/:id", delete(revoke_my_session))
+        .route("/auth/token", post(issue_token))
+        .route("/auth/token/refresh", post(refresh_token))
+        .route("/auth/token/revoke", post(revoke_token))
+        .route("/auth/oidc/login", get(oidc_login))
+        .route("/auth/oidc/callback", get(oidc_callback))
         .route("/students", get(list_students).post(create_student))
+        .route(
+            "/students/:id",
+            get(get_student)
+                .patch(update_student)
+                .delete(delete_student),
+        )
+        .route("/students/:id/restore", post(restore_student))
+        .route("/students/:id/notes", get(list_notes).post(create_note))
+        .route(
+            "/students/:id/notes/:note_id",
+            get(get_note).put(update_note).delete(delete_note),
+        )
+        .route(
+            "/students/:id/guardians",
+            get(list_guardians).post(create_guardian),
+        )
+        .route(
+            "/guardians/:id",
+            get(get_guardian)
+                .put(update_guardian)
+                .delete(delete_guardian),
+        )
+        .route("/students/import", post(import_students))
+        .route("/students/export", get(export_students))
+        .route("/students/merge", post(merge_students))
+        .route(
+            "/school-years",
+            get(list_school_years).post(create_school_year),
+        )
+        .route(
+            "/school-years/:id",
+            get(get_school_year).put(update_school_year),
+        )
+        .route("/school-years/:id/archive", post(archive_school_year))
+        .route("/classes", get(list_classes).post(create_class))
+        .route(
+            "/classes/:id",
+            get(get_class).put(update_class).delete(delete_class),
+        )
+        .route("/classes/:id/students", get(list_roster))
+        .route(
+            "/classes/:id/students/:student_id",
+            put(enroll_student).delete(unenroll_student),
+        )
+        .route("/classes/:id/standards-coverage", get(class_coverage))
+        .route("/attendance", get(list_attendance))
+        .route("/students/:id/attendance", get(student_attendance))
+        .route(
+            "/students/:id/attendance/:date",
+            put(record_attendance).delete(delete_attendance),
+        )
+        .route(
+            "/students/:id/assignments",
+            get(list_assignments).post(create_assignment),
+        )
+        .route("/students/:id/grades", get(grade_summary))
+        .route("/students/:id/interactions", get(student_interactions))
+        .route(
+            "/students/:id/progress-report",
+            post(create_progress_report),
+        )
+        .route("/students/:id/progress-reports", get(list_progress_reports))
+        .route(
+            "/students/:id/progress-reports/:report_id",
+            get(get_progress_report),
+        )
+        .route(
+            "/students/:id/report-links",
+            get(list_report_links).post(create_report_link),
+        )
+        .route(
+            "/students/:id/report-links/:link_id",
+            delete(revoke_report_link),
+        )
+        .route("/shared/progress-report", get(shared_progress_report))
+        .route("/students/:id/family-digest", post(create_family_digest))
+        .route(
+            "/students/:id/family-digests",
+            get(list_student_family_digests),
+        )
+        .route("/family-digests", get(list_family_digests))
+        .route("/family-digests/:id", get(get_family_digest))
+        .route("/students/:id/goals", get(list_goals).post(create_goal))
+        .route(
+            "/goals/:id",
+            get(get_goal).put(update_goal).delete(delete_goal),
+        )
+        .route("/goals/:id/evidence", post(add_evidence))
+        .route("/goals/:id/evidence/:evidence_id", delete(delete_evidence))
+        .route("/goals/:id/progress-note", post(draft_progress_note))
+        .route("/goals/:id/progress-notes", get(list_progress_notes))
+        .route("/assess/reading-level", post(assess_reading_level))
+        .route(
+            "/students/:id/reading-assessments",
+            get(list_reading_assessments),
+        )
+        .route("/students/:id/reading-trend", get(reading_trend))
+        .route(
+            "/students/:id/vocabulary-lists",
+            get(list_lists).post(build_list),
+        )
+        .route("/vocabulary-lists/:id", get(get_list).delete(delete_list))
+        .route("/vocabulary-lists/:id/words/:word_id", delete(delete_word))
+        .route("/vocabulary-lists/:id/practice", post(practice_sentences))
+        .route(
+            "/assignments/:id",
+            get(get_assignment)
+                .put(update_assignment)
+                .delete(delete_assignment),
+        )
+        .route(
+            "/assignments/:id/grade",
+            put(upsert_grade).delete(delete_grade),
+        )
+        .route("/assignments/:id/grade/feedback", post(draft_feedback))
+        .route(
+            "/assignments/:id/rubric-gradings",
+            get(list_rubric_gradings),
+        )
+        .route("/assignments/:id/help-sessions", post(open_session))
+        .route("/help-sessions", get(list_sessions))
+        .route("/help-sessions/:id", get(get_session))
+        .route("/help-sessions/:id/messages", post(send_message))
+        .route("/help-sessions/:id/close", post(close_session))
+        .route("/students/:id/essays", get(list_essays).post(create_essay))
+        .route("/essays/:id", get(get_essay).delete(delete_essay))
+        .route("/essays/:id/drafts", post(submit_draft))
+        .route("/essays/:id/drafts/:version", get(get_draft))
+        .route("/rubrics", get(list_rubrics).post(create_rubric))
+        .route(
+            "/rubrics/:id",
+            get(get_rubric).put(update_rubric).delete(delete_rubric),
+        )
+        .route("/grade-with-rubric", post(grade_with_rubric))
+        .route("/generate/quiz", post(generate_quiz))
+        .route("/quizzes", get(list_quizzes))
+        .route(
+            "/quizzes/:id",
+            get(get_quiz).put(update_quiz).delete(delete_quiz),
+        )
+        .route("/quizzes/:id/standards", get(list_quiz_standards))
+        .route(
+            "/quizzes/:id/standards/:code",
+            put(tag_quiz).delete(untag_quiz),
+        )
+        .route("/generate/flashcards", post(generate_deck))
+        .route("/flashcard-decks", get(list_decks))
+        .route("/flashcard-decks/:id", get(get_deck).delete(delete_deck))
+        .route("/students/:id/flashcard-decks/:deck_id/due", get(due_cards))
+        .route(
+            "/students/:id/flashcards/:card_id/review",
+            post(review_card),
+        )
+        .route("/generate/lesson-plan", post(generate_lesson_plan))
+        .route("/lesson-plans", get(list_lesson_plans))
+        .route(
+            "/lesson-plans/:id",
+            get(get_lesson_plan)
+                .put(update_lesson_plan)
+                .delete(delete_lesson_plan),
+        )
+        .route("/lesson-plans/:id/versions", get(list_lesson_plan_versions))
+        .route("/lesson-plans/:id/regenerate", post(regenerate_lesson_plan))
+        .route(
+            "/lesson-plans/:id/standards",
+            get(list_lesson_plan_standards),
+        )
+        .route(
+            "/lesson-plans/:id/standards/:code",
+            put(tag_lesson_plan).delete(untag_lesson_plan),
+        )
+        .route("/standards", get(list_standards).post(create_standard))
+        .route("/standards/import", post(import_standards))
+        .route("/standards/:code", delete(delete_standard))
+        .route("/tags", get(list_tags).post(create_tag))
+        .route("/tags/:name", delete(delete_tag))
+        .route("/students/:id/tags", get(list_student_tags))
+        .route(
+            "/students/:id/tags/:name",
+            put(tag_student).delete(untag_student),
+        )
+        .route(
+            "/students/:id/consent",
+            get(get_student_consent).put(update_student_consent),
+        )
+        .route("/students/:id/shares", get(list_student_shares))
+        .route(
+            "/students/:id/shares/:user_id",
+            put(share_student).delete(unshare_student),
+        )
+        .route("/users", get(list_users).post(create_user))
+        .route(
+            "/users/:id",
+            get(get_user).put(update_user).delete(delete_user),
+        )
+        .route("/users/:id/password-reset", post(issue_password_reset))
+        .route(
+            "/users/:id/sessions",
+            get(list_user_sessions).delete(revoke_user_sessions),
+        )
         .route("/llm/chat", post(proxy_chat_completion))
+        .route("/llm/status", get(llm_status))
+        .route("/llm/embeddings", post(proxy_embeddings))
+        .route("/interactions", get(list_interactions))
+        .route("/interactions/search", get(search_interactions))
+        .route("/interactions/export", get(export_fine_tune))
+        .route("/interactions/anonymize", post(anonymize_interactions))
+        .route("/interactions/:id", get(get_interaction))
+        .route("/interactions/:id/thread", get(get_thread))
+        .route("/interactions/:id/variants", get(get_variants))
+        .route(
+            "/interactions/:id/stream-telemetry",
+            get(get_stream_telemetry),
+        )
+        .route("/conversations/:id", get(get_conversation))
+        .route("/review-queue", get(list_review_items))
+        .route("/review-queue/:id", get(get_review_item))
+        .route("/review-queue/:id/decision", post(decide_review_item))
+        .route("/interactions/:id/regenerate", post(regenerate_interaction))
+        .route("/interactions/:id/feedback", post(create_feedback))
+        .route("/interactions/:id/tags", get(list_tags_for_interaction))
+        .route(
+            "/interactions/:id/tags/:tag",
+            put(add_interaction_tag).delete(remove_interaction_tag),
+        )
+        .route("/interaction-tags", get(list_interaction_tags))
+        .route(
+            "/interactions/:id/standards",
+            get(list_interaction_standards),
+        )
+        .route(
+            "/interactions/:id/standards/:code",
+            put(tag_interaction).delete(untag_interaction),
+        )
+        .route("/personas", get(list_personas).post(create_persona))
+        .route(
+            "/personas/:id",
+            get(get_persona).put(update_persona).delete(delete_persona),
+        )
+        .route(
+            "/few-shot-examples",
+            get(list_examples).post(create_example),
+        )
+        .route(
+            "/few-shot-examples/:id",
+            put(update_example).delete(delete_example),
+        )
+        .route("/reports/costs", get(cost_report))
+        .route("/reports/experiments", get(experiment_report))
+        .route("/reports/feedback", get(feedback_report))
+        .route("/analytics/usage", get(usage))
+        .route("/presence/heartbeat", post(heartbeat))
+        .route("/presence/active", get(list_active))
+        .route("/presence/students/:id", get(student_presence))
+        .route(
+            "/scratchpads/:session_id",
+            get(get_scratchpad).put(update_scratchpad),
+        )
+        .route(
+            "/scratchpads/:session_id/versions",
+            get(list_scratchpad_versions),
+        )
+        .route("/admin/canary", get(get_canary).put(update_canary))
+        .route("/admin/config", get(get_config))
+        .route("/admin/config/reload", post(reload_config))
+        .route("/admin/inference", get(inference_status))
+        .route("/admin/hardware", get(hardware_report))
+        .route(
+            "/admin/deployments",
+            get(list_deployments).post(create_deployment),
+        )
+        .route(
+            "/admin/deployments/:name",
+            get(get_deployment).delete(delete_deployment),
+        )
+        .route("/admin/deployments/:name/start", post(start_deployment))
+        .route("/admin/deployments/:name/stop", post(stop_deployment))
+        .route("/admin/models/local", get(list_local_models))
+        .route(
+            "/admin/models/load",
+            get(model_load_status).post(load_model),
+        )
+        .route(
+            "/admin/models/downloads",
+            get(list_model_downloads).post(create_model_download),
+        )
+        .route("/admin/models/downloads/:id", get(get_model_download))
+        .route(
+            "/admin/models/downloads/:id/retry",
+            post(retry_model_download),
+        )
+        .route("/admin/inference/start", post(start_inference))
+        .route("/admin/inference/stop", post(stop_inference))
+        .route("/admin/inference/restart", post(restart_inference))
+        .route("/admin/inference/events", get(list_inference_events))
+        .route("/admin/inference/stats", get(inference_stats))
+        .route("/admin/inference/logs", get(inference_logs))
+        .route(
+            "/admin/experiment",
+            get(get_experiment).put(update_experiment),
+        )
+        .route(
+            "/admin/reservations",
+            get(list_reservations).post(create_reservation),
+        )
+        .route("/admin/reservations/:id", delete(delete_reservation))
+        .route("/admin/grade-policies", get(list_grade_policies))
+        .route(
+            "/admin/grade-policies/:grade_level",
+            put(upsert_grade_policy).delete(delete_grade_policy),
+        )
+        .route("/admin/tag-policies", get(list_tag_policies))
+        .route(
+            "/admin/tag-policies/:name",
+            put(upsert_tag_policy).delete(delete_tag_policy),
+        )
+        .route("/admin/exports", get(list_exports).post(create_export))
+        .route("/admin/exports/:id", get(get_export))
+        .route("/admin/exports/:id/download", get(download_export))
+        .route("/admin/retention/purge", post(purge_interactions))
+        .route("/admin/backup", post(create_backup))
+        .route("/admin/audit-log", get(list_audit_log))
+        .route("/admin/schools", get(list_schools).post(create_school))
+        .route("/admin/schools/:id", get(get_school).put(update_school))
+        .route("/admin/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/admin/api-keys/:id", delete(delete_api_key))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit::middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency::middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware,
+        ))
         .with_state(state)
         .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http());
-    let listener = TcpListener::bind(addr).await?;
-
-    info!(%addr, "backend listening");
-    axum::serve(listener, app).await?;
+        .layer(TraceLayer::new_for_http())
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
+    if let Some(tls) = tls {
+        info!(%addr, "backend listening (https)");
+        axum_server::bind_rustls(addr, tls)
+            .serve(app.into_make_service())
+            .await?;
+    } else {
+        let listener = TcpListener::bind(addr).await?;
+        info!(%addr, "backend listening");
+        axum::serve(listener, app).await?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,173 @@
+//! Scan of the local models directory for weights the inference server can
+//! load: GGUF files (llama.cpp) and safetensors directories (vLLM).
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Weights plus headroom for KV cache and activations at modest context
+/// lengths. A rough guide, not a guarantee.
+const MEMORY_OVERHEAD: f64 = 1.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFormat {
+    Gguf,
+    Safetensors,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalModel {
+    pub name: String,
+    pub path: String,
+    pub format: ModelFormat,
+    pub size_bytes: u64,
+    pub quantization: Option<String>,
+    pub estimated_memory_bytes: u64,
+    pub loaded: bool,
+}
+
+/// Lists models directly under `dir`. A `.gguf` file is one model; a
+/// directory is one model per `.gguf` file in it, or a single model if it
+/// holds `.safetensors` shards.
+pub fn scan(dir: &Path) -> std::io::Result<Vec<LocalModel>> {
+    let mut models = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            models.extend(gguf_model(&path));
+        } else if path.is_dir() {
+            models.extend(scan_model_dir(&path)?);
+        }
+    }
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+fn scan_model_dir(dir: &Path) -> std::io::Result<Vec<LocalModel>> {
+    let mut ggufs = Vec::new();
+    let mut safetensors_bytes = 0;
+    let mut has_safetensors = false;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        match extension(&path).as_deref() {
+            Some("gguf") => ggufs.extend(gguf_model(&path).map(|mut model| {
+                model.name = format!("{}/{}", file_name(dir), model.name);
+                model
+            })),
+            Some("safetensors") => {
+                has_safetensors = true;
+                safetensors_bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            }
+            _ => {}
+        }
+    }
+
+    if has_safetensors {
+        ggufs.push(model(
+            dir,
+            ModelFormat::Safetensors,
+            safetensors_bytes,
+            safetensors_quantization(dir),
+        ));
+    }
+    Ok(ggufs)
+}
+
+fn gguf_model(path: &Path) -> Option<LocalModel> {
+    if extension(path).as_deref() != Some("gguf") {
+        return None;
+    }
+    let size = fs::metadata(path).ok()?.len();
+    let quantization = path
+        .file_stem()
+        .and_then(|stem| gguf_quantization(&stem.to_string_lossy()))
+        .or_else(|| {
+            path.parent()
+                .and_then(|dir| gguf_quantization(&file_name(dir)))
+        });
+
+    Some(model(path, ModelFormat::Gguf, size, quantization))
+}
+
+fn model(
+    path: &Path,
+    format: ModelFormat,
+    size_bytes: u64,
+    quantization: Option<String>,
+) -> LocalModel {
+    LocalModel {
+        name: file_name(path),
+        path: path.to_string_lossy().into_owned(),
+        format,
+        size_bytes,
+        quantization,
+        estimated_memory_bytes: (size_bytes as f64 * MEMORY_OVERHEAD) as u64,
+        loaded: false,
+    }
+}
+
+/// llama.cpp quantization tags are only recorded in the file name, e.g.
+/// `qwen2.5-7b-instruct-q4_k_m` or `nomic-embed-code-q6-k`.
+fn gguf_quantization(stem: &str) -> Option<String> {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| {
+        Regex::new(r"(?i)(?:^|[-_.])(i?q\d(?:[_-](?:[0-9]|k|s|m|l|xs|xxs|nl))*|bf16|f16|f32)$")
+            .expect("valid quantization regex")
+    });
+
+    tag.captures(stem)
+        .map(|caps| caps[1].to_ascii_lowercase().replace('-', "_"))
+}
+
+/// From `config.json`: the `quantization_config` method and bit width for
+/// AWQ/GPTQ/FP8 checkpoints, otherwise the stored dtype.
+fn safetensors_quantization(dir: &Path) -> Option<String> {
+    let config: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("config.json")).ok()?).ok()?;
+
+    if let Some(quant) = config.get("quantization_config") {
+        let method = quant.get("quant_method").and_then(Value::as_str)?;
+        return Some(match quant.get("bits").and_then(Value::as_u64) {
+            Some(bits) => format!("{method}-{bits}bit"),
+            None => method.to_string(),
+        });
+    }
+
+    config
+        .get("torch_dtype")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Marks models whose path matches one the inference server reports or has
+/// mounted. Paths are compared after resolving symlinks where possible.
+pub fn mark_loaded(models: &mut [LocalModel], loaded_paths: &[String]) {
+    let canonical = |p: &str| fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p));
+    let loaded: Vec<PathBuf> = loaded_paths.iter().map(|p| canonical(p.as_str())).collect();
+
+    for model in models {
+        let path = canonical(&model.path);
+        model.loaded = loaded.contains(&path) || loaded_paths.contains(&model.name);
+    }
+}
@@ -0,0 +1,234 @@
+//! Background Hugging Face file downloads into `MODELS_DIR`.
+//!
+//! Data is written to `<dest>.part` and renamed when complete. A download
+//! that is interrupted (network error or restart) keeps its partial file and
+//! continues from it with a `Range` request the next time it runs.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use reqwest::{header, Client, StatusCode};
+use sqlx::SqlitePool;
+use tokio::{fs, io::AsyncWriteExt};
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DownloadJob {
+    pub id: i64,
+    pub repo_id: String,
+    pub filename: String,
+    pub revision: String,
+    pub dest_path: String,
+}
+
+/// `MODELS_DIR/<repo name>/<filename>`, matching `scripts/get-hf-model.sh`.
+pub fn dest_path(models_dir: &str, repo_id: &str, filename: &str) -> PathBuf {
+    let repo_name = repo_id.rsplit('/').next().unwrap_or(repo_id);
+    Path::new(models_dir).join(repo_name).join(filename)
+}
+
+/// Re-queues downloads that were queued or running when the server last
+/// stopped.
+pub async fn resume_pending(pool: SqlitePool, client: Client, hf_token: Option<String>) {
+    let jobs = match sqlx::query_as::<_, DownloadJob>(
+        r#"
+        SELECT id, repo_id, filename, revision, dest_path
+        FROM model_downloads
+        WHERE status IN ('queued', 'running')
+        ORDER BY id ASC
+        "#,
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            warn!(error = %err, "failed to load pending model downloads");
+            return;
+        }
+    };
+
+    for job in jobs {
+        info!(job_id = job.id, repo_id = %job.repo_id, "resuming model download");
+        tokio::spawn(run(pool.clone(), job, client.clone(), hf_token.clone()));
+    }
+}
+
+pub async fn run(pool: SqlitePool, job: DownloadJob, client: Client, hf_token: Option<String>) {
+    let job_id = job.id;
+
+    let result = download(&pool, &job, &client, hf_token.as_deref()).await;
+    let recorded = match &result {
+        Ok(()) => {
+            info!(job_id, path = %job.dest_path, "model download completed");
+            finish(&pool, job_id, "completed", None).await
+        }
+        Err(err) => {
+            warn!(job_id, error = %err, "model download failed");
+            finish(&pool, job_id, "failed", Some(&err.to_string())).await
+        }
+    };
+
+    if let Err(err) = recorded {
+        warn!(job_id, error = %err, "failed to record model download outcome");
+    }
+}
+
+async fn download(
+    pool: &SqlitePool,
+    job: &DownloadJob,
+    client: &Client,
+    hf_token: Option<&str>,
+) -> Result<(), AppError> {
+    let dest = PathBuf::from(&job.dest_path);
+    let part = dest.with_file_name(format!(
+        "{}.part",
+        dest.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    ));
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).await.map_err(io_error)?;
+    }
+
+    let mut offset = fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0);
+    let url = format!(
+        "https://huggingface.co/{}/resolve/{}/{}",
+        job.repo_id, job.revision, job.filename
+    );
+
+    let mut request = client.get(&url);
+    if let Some(token) = hf_token {
+        request = request.bearer_auth(token);
+    }
+    if offset > 0 {
+        request = request.header(header::RANGE, format!("bytes={offset}-"));
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file already holds the whole object.
+        fs::rename(&part, &dest).await.map_err(io_error)?;
+        return Ok(());
+    }
+    if !status.is_success() {
+        return Err(AppError::Upstream(format!("{url} returned {status}")));
+    }
+    if status != StatusCode::PARTIAL_CONTENT {
+        // Server ignored the range; start over.
+        offset = 0;
+    }
+    let total = response.content_length().map(|len| len + offset);
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(&part)
+        .await
+        .map_err(io_error)?;
+
+    mark_running(pool, job.id, total, offset).await?;
+
+    let mut downloaded = offset;
+    let mut last_report = Instant::now();
+    let mut body = Box::pin(response.bytes_stream());
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await.map_err(io_error)?;
+        downloaded += chunk.len() as u64;
+
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            report_progress(pool, job.id, downloaded).await?;
+            last_report = Instant::now();
+        }
+    }
+    file.flush().await.map_err(io_error)?;
+    report_progress(pool, job.id, downloaded).await?;
+
+    if let Some(total) = total {
+        if downloaded != total {
+            return Err(AppError::Upstream(format!(
+                "download ended at {downloaded} of {total} bytes"
+            )));
+        }
+    }
+
+    fs::rename(&part, &dest).await.map_err(io_error)?;
+    Ok(())
+}
+
+async fn mark_running(
+    pool: &SqlitePool,
+    job_id: i64,
+    total: Option<u64>,
+    downloaded: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE model_downloads
+        SET status = 'running', bytes_total = ?, bytes_downloaded = ?, error = NULL,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(total.map(|t| t as i64))
+    .bind(downloaded as i64)
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+async fn report_progress(
+    pool: &SqlitePool,
+    job_id: i64,
+    downloaded: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE model_downloads SET bytes_downloaded = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(downloaded as i64)
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+async fn finish(
+    pool: &SqlitePool,
+    job_id: i64,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE model_downloads
+        SET status = ?, error = ?, updated_at = CURRENT_TIMESTAMP,
+            finished_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(status)
+    .bind(error)
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+fn io_error(err: std::io::Error) -> AppError {
+    AppError::Internal(format!("model file error: {err}"))
+}
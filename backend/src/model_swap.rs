@@ -0,0 +1,248 @@
+//! Switching the model the inference server runs without shell access.
+//! New chat requests are turned away and in-flight ones drained, then the
+//! managed container is recreated with the new model mounted (or, for
+//! Ollama, the model is loaded through its API) and the swap waits until
+//! the server answers again.
+
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, error::AppError, time::unix_now};
+
+/// Where `compose.yml` mounts the model inside the inference container.
+pub const CONTAINER_MODEL_PATH: &str = "/model";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapPhase {
+    Draining,
+    Recreating,
+    Loading,
+    Ready,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SwapStatus {
+    pub model: String,
+    pub phase: SwapPhase,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    /// Time spent waiting for the server to load the model so far.
+    pub load_elapsed_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// How to bring up the new model.
+pub enum SwapTarget {
+    /// Recreate the managed container with `mount_source` at `/model`.
+    Container {
+        mount_source: String,
+        model_arg: String,
+        args: Option<Vec<String>>,
+    },
+    /// Ask each Ollama backend to load the model.
+    Ollama,
+}
+
+/// The current or most recent swap.
+#[derive(Default)]
+pub struct ModelSwap {
+    status: Mutex<Option<SwapStatus>>,
+}
+
+impl ModelSwap {
+    pub fn status(&self) -> Option<SwapStatus> {
+        self.lock().clone()
+    }
+
+    /// Claims the swap slot, failing while another swap is running.
+    pub fn begin(&self, model: &str) -> Result<SwapStatus, AppError> {
+        let mut current = self.lock();
+        if let Some(running) = current
+            .as_ref()
+            .filter(|s| !matches!(s.phase, SwapPhase::Ready | SwapPhase::Failed))
+        {
+            return Err(AppError::Conflict(format!(
+                "already switching to '{}'",
+                running.model
+            )));
+        }
+
+        let status = SwapStatus {
+            model: model.to_string(),
+            phase: SwapPhase::Draining,
+            started_at: unix_now(),
+            finished_at: None,
+            load_elapsed_ms: None,
+            error: None,
+        };
+        *current = Some(status.clone());
+        Ok(status)
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut SwapStatus)) {
+        if let Some(status) = self.lock().as_mut() {
+            apply(status);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<SwapStatus>> {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub async fn run(state: AppState, model: String, target: SwapTarget) {
+    let result = swap(&state, &model, target).await;
+    state.scheduler.resume();
+
+    match &result {
+        Ok(()) => info!(model, "model switch complete"),
+        Err(err) => warn!(model, error = %err, "model switch failed"),
+    }
+    state.model_swap.update(|status| {
+        status.finished_at = Some(unix_now());
+        match result {
+            Ok(()) => status.phase = SwapPhase::Ready,
+            Err(err) => {
+                status.phase = SwapPhase::Failed;
+                status.error = Some(err.to_string());
+            }
+        }
+    });
+}
+
+async fn swap(state: &AppState, model: &str, target: SwapTarget) -> Result<(), AppError> {
+    let _drained = state.scheduler.drain().await?;
+
+    if let SwapTarget::Container {
+        mount_source,
+        model_arg,
+        args,
+    } = &target
+    {
+        let container = state
+            .inference
+            .as_deref()
+            .ok_or_else(|| AppError::NotFound("no inference container configured".to_string()))?;
+        state
+            .model_swap
+            .update(|status| status.phase = SwapPhase::Recreating);
+        container
+            .recreate(CONTAINER_MODEL_PATH, mount_source, |cmd| match args {
+                Some(args) => *cmd = args.clone(),
+                None => set_model_arg(cmd, model_arg),
+            })
+            .await?;
+    }
+
+    state
+        .model_swap
+        .update(|status| status.phase = SwapPhase::Loading);
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(state.config.llm_warmup_timeout_secs);
+
+    match target {
+        SwapTarget::Container { .. } => {
+            wait_until_serving(state, deadline, || {
+                state.model_swap.update(|status| {
+                    status.load_elapsed_ms = Some(started.elapsed().as_millis() as u64);
+                });
+            })
+            .await
+        }
+        SwapTarget::Ollama => load_ollama(state, model, deadline - started).await,
+    }
+}
+
+/// The host directory to mount at `/model` for the weights at `path`, and
+/// the in-container path to pass as `--model`. A GGUF file is loaded from
+/// its directory; safetensors servers take the directory itself.
+pub fn model_mount(path: &Path) -> (String, String) {
+    if path.is_dir() {
+        return (
+            path.to_string_lossy().into_owned(),
+            CONTAINER_MODEL_PATH.to_string(),
+        );
+    }
+    (
+        path.parent().unwrap_or(path).to_string_lossy().into_owned(),
+        format!(
+            "{CONTAINER_MODEL_PATH}/{}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ),
+    )
+}
+
+/// Points `--model`/`-m` at the new model, adding the flag if missing.
+pub fn set_model_arg(cmd: &mut Vec<String>, model_arg: &str) {
+    if let Some(i) = cmd.iter().position(|arg| arg == "--model" || arg == "-m") {
+        if let Some(value) = cmd.get_mut(i + 1) {
+            *value = model_arg.to_string();
+            return;
+        }
+    }
+    if let Some(arg) = cmd.iter_mut().find(|arg| arg.starts_with("--model=")) {
+        *arg = format!("--model={model_arg}");
+        return;
+    }
+    cmd.extend(["--model".to_string(), model_arg.to_string()]);
+}
+
+/// Polls each primary's models endpoint until all of them answer, calling
+/// `on_poll` after each round.
+pub async fn wait_until_serving(
+    state: &AppState,
+    deadline: Instant,
+    mut on_poll: impl FnMut(),
+) -> Result<(), AppError> {
+    loop {
+        let mut ready = true;
+        for backend in state.upstreams.backends() {
+            let response = state
+                .llm_client
+                .get(backend.url(&state.config.llm_models_path))
+                .timeout(POLL_INTERVAL)
+                .send()
+                .await;
+            ready &= response.is_ok_and(|r| r.status().is_success());
+        }
+        on_poll();
+        if ready {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(AppError::Upstream(
+                "inference server did not come back before LLM_WARMUP_TIMEOUT_SECS".to_string(),
+            ));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// An empty generate request makes Ollama load the model and return once
+/// it's in memory.
+async fn load_ollama(state: &AppState, model: &str, timeout: Duration) -> Result<(), AppError> {
+    for backend in state.upstreams.backends() {
+        state
+            .llm_client
+            .post(backend.url("/api/generate"))
+            .json(&json!({ "model": model }))
+            .timeout(timeout)
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,325 @@
+//! OpenID Connect sign-in for schools whose teacher accounts live in Google
+//! Workspace or Microsoft Entra ID. Uses the authorization code flow with
+//! PKCE; the ID token is checked against the provider's published keys and
+//! its subject is linked to a local user.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{app_state::AppState, auth, error::AppError};
+
+/// How long the provider has to send the user back.
+const LOGIN_TTL_MINUTES: u32 = 10;
+const SCOPES: &str = "openid email profile";
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdClaims {
+    iss: String,
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+    nonce: Option<String>,
+}
+
+struct Settings<'a> {
+    issuer: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+    redirect_url: &'a str,
+}
+
+fn settings(state: &AppState) -> Result<Settings<'_>, AppError> {
+    let config = &state.config;
+    match (
+        &config.oidc_issuer,
+        &config.oidc_client_id,
+        &config.oidc_client_secret,
+        &config.oidc_redirect_url,
+    ) {
+        (Some(issuer), Some(client_id), Some(client_secret), Some(redirect_url)) => Ok(Settings {
+            issuer,
+            client_id,
+            client_secret,
+            redirect_url,
+        }),
+        _ => Err(AppError::NotFound(
+            "single sign-on is not enabled (set OIDC_ISSUER)".to_string(),
+        )),
+    }
+}
+
+/// Fetched on every sign-in rather than cached, so rotated keys and
+/// endpoints are picked up without a restart.
+async fn discover(state: &AppState, issuer: &str) -> Result<Discovery, AppError> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let discovery: Discovery = state
+        .llm_client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|err| AppError::Upstream(format!("OIDC discovery failed: {err}")))?
+        .json()
+        .await
+        .map_err(|err| AppError::Upstream(format!("OIDC discovery failed: {err}")))?;
+
+    if discovery.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(AppError::Upstream(format!(
+            "OIDC discovery returned issuer '{}', expected '{issuer}'",
+            discovery.issuer
+        )));
+    }
+    Ok(discovery)
+}
+
+/// Starts a sign-in: remembers the state, nonce, and PKCE verifier, and
+/// returns the provider URL to send the browser to.
+pub async fn authorize_url(state: &AppState) -> Result<String, AppError> {
+    let settings = settings(state)?;
+    let discovery = discover(state, settings.issuer).await?;
+
+    let login_state = auth::new_token();
+    let nonce = auth::new_token();
+    let code_verifier = auth::new_token();
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    sqlx::query("DELETE FROM oidc_logins WHERE created_at < datetime('now', ?)")
+        .bind(format!("-{LOGIN_TTL_MINUTES} minutes"))
+        .execute(&state.pool)
+        .await?;
+    sqlx::query("INSERT INTO oidc_logins(state, nonce, code_verifier) VALUES(?, ?, ?)")
+        .bind(&login_state)
+        .bind(&nonce)
+        .bind(&code_verifier)
+        .execute(&state.pool)
+        .await?;
+
+    let url = Url::parse_with_params(
+        &discovery.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", settings.client_id),
+            ("redirect_uri", settings.redirect_url),
+            ("scope", SCOPES),
+            ("state", login_state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|err| AppError::Upstream(format!("invalid authorization endpoint: {err}")))?;
+
+    Ok(url.into())
+}
+
+/// Finishes a sign-in from the provider's callback and returns the local
+/// user it maps to.
+pub async fn complete(state: &AppState, code: &str, login_state: &str) -> Result<i64, AppError> {
+    let settings = settings(state)?;
+
+    let pending = sqlx::query_as::<_, (String, String)>(
+        r#"
+        DELETE FROM oidc_logins
+        WHERE state = ? AND created_at >= datetime('now', ?)
+        RETURNING nonce, code_verifier
+        "#,
+    )
+    .bind(login_state)
+    .bind(format!("-{LOGIN_TTL_MINUTES} minutes"))
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((nonce, code_verifier)) = pending else {
+        return Err(AppError::BadRequest(
+            "sign-in expired or was already used; start again".to_string(),
+        ));
+    };
+
+    let discovery = discover(state, settings.issuer).await?;
+    let tokens: TokenResponse = state
+        .llm_client
+        .post(&discovery.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", settings.redirect_url),
+            ("client_id", settings.client_id),
+            ("client_secret", settings.client_secret),
+            ("code_verifier", code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|err| AppError::Upstream(format!("OIDC token exchange failed: {err}")))?
+        .json()
+        .await
+        .map_err(|err| AppError::Upstream(format!("OIDC token exchange failed: {err}")))?;
+
+    let claims = verify_id_token(state, &discovery, settings.client_id, &tokens.id_token).await?;
+    if claims.nonce.as_deref() != Some(nonce.as_str()) {
+        return Err(AppError::Unauthorized(
+            "ID token nonce mismatch".to_string(),
+        ));
+    }
+
+    link_user(state, &claims).await
+}
+
+async fn verify_id_token(
+    state: &AppState,
+    discovery: &Discovery,
+    client_id: &str,
+    id_token: &str,
+) -> Result<IdClaims, AppError> {
+    let invalid = |err: jsonwebtoken::errors::Error| {
+        AppError::Unauthorized(format!("invalid ID token: {err}"))
+    };
+
+    let header = decode_header(id_token).map_err(invalid)?;
+    // Providers sign with their private keys; a shared-secret algorithm
+    // here would mean someone is trying to forge one.
+    if !matches!(
+        header.alg,
+        Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512
+            | Algorithm::ES256
+            | Algorithm::ES384
+    ) {
+        return Err(AppError::Unauthorized(format!(
+            "ID token uses unsupported algorithm {:?}",
+            header.alg
+        )));
+    }
+
+    let jwks: JwkSet = state
+        .llm_client
+        .get(&discovery.jwks_uri)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|err| AppError::Upstream(format!("failed to fetch OIDC keys: {err}")))?
+        .json()
+        .await
+        .map_err(|err| AppError::Upstream(format!("failed to fetch OIDC keys: {err}")))?;
+    let jwk = header
+        .kid
+        .as_deref()
+        .and_then(|kid| jwks.find(kid))
+        .ok_or_else(|| AppError::Unauthorized("ID token signed with an unknown key".to_string()))?;
+    let key = DecodingKey::from_jwk(jwk).map_err(invalid)?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[client_id]);
+    validation.set_issuer(&[&discovery.issuer]);
+    decode::<IdClaims>(id_token, &key, &validation)
+        .map(|data| data.claims)
+        .map_err(invalid)
+}
+
+/// An identity already linked signs in as its user. Otherwise the email is
+/// matched to an existing user, or a new `parent` account is created when
+/// `OIDC_AUTO_CREATE=true`.
+async fn link_user(state: &AppState, claims: &IdClaims) -> Result<i64, AppError> {
+    let allowed_domains = &state.config.oidc_allowed_domains;
+    // With a domain allow-list the school's own tenant controls those
+    // addresses, so a provider that omits `email_verified` is trusted.
+    let email = claims
+        .email
+        .as_deref()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|_| {
+            claims.email_verified == Some(true)
+                || (claims.email_verified.is_none() && !allowed_domains.is_empty())
+        });
+
+    if !allowed_domains.is_empty() {
+        let domain = email
+            .as_deref()
+            .and_then(|e| e.rsplit_once('@'))
+            .map(|(_, domain)| domain);
+        if !domain.is_some_and(|d| allowed_domains.iter().any(|a| a.eq_ignore_ascii_case(d))) {
+            return Err(AppError::Forbidden(
+                "this account's domain is not allowed to sign in".to_string(),
+            ));
+        }
+    }
+
+    let linked: Option<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE user_identities SET last_login_at = CURRENT_TIMESTAMP
+        WHERE issuer = ? AND subject = ?
+        RETURNING user_id
+        "#,
+    )
+    .bind(&claims.iss)
+    .bind(&claims.sub)
+    .fetch_optional(&state.pool)
+    .await?;
+    if let Some(user_id) = linked {
+        return Ok(user_id);
+    }
+
+    let email = email.ok_or_else(|| {
+        AppError::Forbidden("the identity provider did not supply a verified email".to_string())
+    })?;
+    let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+        .bind(&email)
+        .fetch_optional(&state.pool)
+        .await?;
+    let user_id = match existing {
+        Some(user_id) => user_id,
+        None if state.config.oidc_auto_create => {
+            let name = claims.name.as_deref().unwrap_or(&email);
+            sqlx::query_scalar(
+                "INSERT INTO users(role, name, email) VALUES('parent', ?, ?) RETURNING id",
+            )
+            .bind(name.trim())
+            .bind(&email)
+            .fetch_one(&state.pool)
+            .await?
+        }
+        None => {
+            return Err(AppError::Forbidden(format!(
+                "no account for {email}; ask an admin to create one"
+            )))
+        }
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities(issuer, subject, user_id, email, last_login_at)
+        VALUES(?, ?, ?, ?, CURRENT_TIMESTAMP)
+        "#,
+    )
+    .bind(&claims.iss)
+    .bind(&claims.sub)
+    .bind(user_id)
+    .bind(&email)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(user_id)
+}
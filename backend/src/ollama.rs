@@ -0,0 +1,204 @@
+//! Translation between OpenAI-style chat payloads and Ollama's `/api/chat`.
+
+use serde_json::{json, Map, Value};
+
+use crate::time::unix_now;
+
+/// OpenAI sampling fields and their Ollama `options` equivalents.
+const OPTION_FIELDS: &[(&str, &str)] = &[
+    ("temperature", "temperature"),
+    ("top_p", "top_p"),
+    ("max_tokens", "num_predict"),
+    ("stop", "stop"),
+    ("seed", "seed"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+];
+
+pub fn chat_request(payload: &Value) -> Value {
+    let messages = payload
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(|messages| messages.iter().map(translate_message).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let mut request = json!({
+        "model": payload.get("model").cloned().unwrap_or(Value::Null),
+        "messages": messages,
+        // Ollama streams unless told otherwise; OpenAI does the opposite.
+        "stream": payload.get("stream").and_then(Value::as_bool).unwrap_or(false),
+    });
+
+    let mut options = Map::new();
+    for (from, to) in OPTION_FIELDS {
+        if let Some(value) = payload.get(*from) {
+            options.insert((*to).to_string(), value.clone());
+        }
+    }
+    if !options.is_empty() {
+        request["options"] = Value::Object(options);
+    }
+
+    if let Some(tools) = payload.get("tools") {
+        request["tools"] = tools.clone();
+    }
+
+    if payload
+        .pointer("/response_format/type")
+        .and_then(Value::as_str)
+        == Some("json_object")
+    {
+        request["format"] = json!("json");
+    }
+
+    request
+}
+
+/// OpenAI content may be a list of text/image parts; Ollama wants a plain
+/// string plus a separate list of base64 images.
+fn translate_message(message: &Value) -> Value {
+    let role = message.get("role").cloned().unwrap_or(json!("user"));
+    let Some(parts) = message.get("content").and_then(Value::as_array) else {
+        return json!({
+            "role": role,
+            "content": message.get("content").cloned().unwrap_or(json!("")),
+        });
+    };
+
+    let mut text = Vec::new();
+    let mut images = Vec::new();
+    for part in parts {
+        match part.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(t) = part.get("text").and_then(Value::as_str) {
+                    text.push(t);
+                }
+            }
+            Some("image_url") => {
+                let url = part
+                    .pointer("/image_url/url")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                if let Some((_, data)) = url.split_once(";base64,") {
+                    images.push(data.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = json!({ "role": role, "content": text.join("\n") });
+    if !images.is_empty() {
+        out["images"] = json!(images);
+    }
+    out
+}
+
+pub fn chat_response(response: &Value) -> Value {
+    json!({
+        "id": "chatcmpl-ollama",
+        "object": "chat.completion",
+        "created": unix_now(),
+        "model": response.get("model").cloned().unwrap_or(Value::Null),
+        "choices": [{
+            "index": 0,
+            "message": response_message(response),
+            "finish_reason": finish_reason(response),
+        }],
+        "usage": usage(response),
+    })
+}
+
+fn usage(response: &Value) -> Value {
+    let prompt_tokens = response
+        .get("prompt_eval_count")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    let completion_tokens = response
+        .get("eval_count")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    })
+}
+
+/// Converts one NDJSON line of an Ollama stream into an OpenAI
+/// `chat.completion.chunk`.
+pub fn stream_chunk(chunk: &Value) -> Value {
+    let done = chunk.get("done").and_then(Value::as_bool).unwrap_or(false);
+    let delta = if done {
+        json!({})
+    } else {
+        json!({ "content": message_content(chunk) })
+    };
+
+    let mut out = json!({
+        "id": "chatcmpl-ollama",
+        "object": "chat.completion.chunk",
+        "created": unix_now(),
+        "model": chunk.get("model").cloned().unwrap_or(Value::Null),
+        "choices": [{
+            "index": 0,
+            "delta": delta,
+            "finish_reason": if done { finish_reason(chunk) } else { Value::Null },
+        }],
+    });
+    if done {
+        out["usage"] = usage(chunk);
+    }
+    out
+}
+
+/// Ollama returns tool call arguments as an object; OpenAI encodes them as
+/// a JSON string.
+fn response_message(response: &Value) -> Value {
+    let mut message = json!({
+        "role": "assistant",
+        "content": message_content(response),
+    });
+
+    if let Some(calls) = response
+        .pointer("/message/tool_calls")
+        .and_then(Value::as_array)
+    {
+        let calls: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| {
+                let arguments = call
+                    .pointer("/function/arguments")
+                    .cloned()
+                    .unwrap_or(json!({}));
+                json!({
+                    "id": format!("call_{i}"),
+                    "type": "function",
+                    "function": {
+                        "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                        "arguments": arguments.to_string(),
+                    },
+                })
+            })
+            .collect();
+        message["tool_calls"] = json!(calls);
+    }
+
+    message
+}
+
+fn message_content(response: &Value) -> Value {
+    response
+        .pointer("/message/content")
+        .cloned()
+        .unwrap_or(json!(""))
+}
+
+fn finish_reason(response: &Value) -> Value {
+    match response.get("done_reason").and_then(Value::as_str) {
+        Some("length") => json!("length"),
+        Some(_) | None => json!("stop"),
+    }
+}
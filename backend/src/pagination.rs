@@ -0,0 +1,28 @@
+//! `limit`/`offset` paging shared by list endpoints.
+
+use serde::Serialize;
+
+use crate::validation::Validator;
+
+pub const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 500;
+
+/// One page of results plus the total across all pages.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Resolves optional `limit`/`offset` query params, recording range errors.
+pub fn resolve(v: &mut Validator, limit: Option<i64>, offset: Option<i64>) -> (i64, i64) {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = offset.unwrap_or(0);
+    v.range("limit", limit, 1, MAX_LIMIT);
+    if offset < 0 {
+        v.error("offset", "must not be negative");
+    }
+    (limit, offset)
+}
@@ -0,0 +1,165 @@
+//! Per-caller limits so one client can't starve the shared GPU. Requests
+//! per minute are counted in memory for every authenticated caller; tokens
+//! per hour are summed from the caller's recorded interactions when a chat
+//! is about to go upstream.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::http::{HeaderMap, HeaderValue};
+
+use crate::{app_state::AppState, config::Config, error::AppError, time::unix_now};
+
+const WINDOW: Duration = Duration::from_secs(60);
+/// Stale windows are swept once the map grows past this many callers.
+const SWEEP_AT: usize = 10_000;
+
+pub const LIMIT_HEADER: &str = "ratelimit-limit";
+const REMAINING_HEADER: &str = "ratelimit-remaining";
+const RESET_HEADER: &str = "ratelimit-reset";
+
+/// Where a caller stands in the current window, for the response headers.
+#[derive(Clone, Copy, Debug)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Seconds until the window resets.
+    pub reset_secs: u64,
+}
+
+impl Quota {
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(LIMIT_HEADER, HeaderValue::from(self.limit));
+        headers.insert(REMAINING_HEADER, HeaderValue::from(self.remaining));
+        headers.insert(RESET_HEADER, HeaderValue::from(self.reset_secs));
+    }
+}
+
+struct Window {
+    started: Instant,
+    count: u64,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<String, Window>>,
+    /// The configured limits, kept here so a config reload can change them.
+    requests_per_minute: AtomicU64,
+    tokens_per_hour: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn from_config(cfg: &Config) -> Self {
+        let limiter = Self::default();
+        limiter.set_limits(cfg);
+        limiter
+    }
+
+    pub fn set_limits(&self, cfg: &Config) {
+        self.requests_per_minute
+            .store(cfg.rate_limit_requests_per_minute, Ordering::Relaxed);
+        self.tokens_per_hour
+            .store(cfg.rate_limit_tokens_per_hour, Ordering::Relaxed);
+    }
+
+    /// `RATE_LIMIT_REQUESTS_PER_MINUTE`; `0` is unlimited.
+    pub fn requests_per_minute(&self) -> u64 {
+        self.requests_per_minute.load(Ordering::Relaxed)
+    }
+
+    /// `RATE_LIMIT_TOKENS_PER_HOUR`; `0` is unlimited.
+    pub fn tokens_per_hour(&self) -> u64 {
+        self.tokens_per_hour.load(Ordering::Relaxed)
+    }
+
+    /// Counts a request against `key`'s fixed one-minute window. `None`
+    /// when `limit` is `0` (unlimited).
+    pub fn check(&self, key: &str, limit: u64) -> Result<Option<Quota>, AppError> {
+        if limit == 0 {
+            return Ok(None);
+        }
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() >= SWEEP_AT {
+            windows.retain(|_, w| now.duration_since(w.started) < WINDOW);
+        }
+
+        let window = windows.entry(key.to_string()).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= WINDOW {
+            window.started = now;
+            window.count = 0;
+        }
+        let reset_secs = WINDOW
+            .saturating_sub(now.duration_since(window.started))
+            .as_secs()
+            .max(1);
+
+        if window.count >= limit {
+            return Err(AppError::RateLimited {
+                quota: Quota {
+                    limit,
+                    remaining: 0,
+                    reset_secs,
+                },
+                message: format!("more than {limit} requests per minute"),
+            });
+        }
+        window.count += 1;
+
+        Ok(Some(Quota {
+            limit,
+            remaining: limit - window.count,
+            reset_secs,
+        }))
+    }
+}
+
+/// Rejects a chat once `user_id` has used `RATE_LIMIT_TOKENS_PER_HOUR`
+/// prompt and completion tokens in the last hour.
+pub async fn check_tokens(state: &AppState, user_id: i64) -> Result<(), AppError> {
+    let limit = state.rate_limiter.tokens_per_hour();
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let (used, oldest): (i64, Option<i64>) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(COALESCE(prompt_tokens, 0) + COALESCE(completion_tokens, 0)), 0),
+            CAST(strftime('%s', MIN(created_at)) AS INTEGER)
+        FROM ai_interactions
+        WHERE user_id = ? AND created_at > datetime('now', '-1 hour')
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    if (used as u64) < limit {
+        return Ok(());
+    }
+
+    // Room opens up as the oldest interaction in the window ages out.
+    let reset_secs = oldest
+        .map(|oldest| (oldest as u64 + 3600).saturating_sub(unix_now()))
+        .unwrap_or(0)
+        .max(1);
+    Err(AppError::RateLimited {
+        quota: Quota {
+            limit,
+            remaining: 0,
+            reset_secs,
+        },
+        message: format!("more than {limit} tokens per hour"),
+    })
+}
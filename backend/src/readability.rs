@@ -0,0 +1,175 @@
+//! Classic readability formulas. Each grade-level formula estimates the US
+//! school grade a reader needs to follow the text; syllables are counted
+//! with a vowel-group heuristic, which is close enough for English prose.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Readability {
+    pub words: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+    /// Words of three or more syllables.
+    pub complex_words: usize,
+    /// 0-100; higher is easier.
+    pub flesch_reading_ease: f64,
+    pub flesch_kincaid_grade: f64,
+    pub gunning_fog: f64,
+    pub smog: f64,
+    pub coleman_liau: f64,
+    pub automated_readability_index: f64,
+    /// Mean of the five grade-level formulas.
+    pub average_grade: f64,
+}
+
+/// Scores `text`, or `None` if it has no words.
+pub fn score(text: &str) -> Option<Readability> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let sentences = count_sentences(text).max(1);
+    let letters: usize = words.iter().map(|w| w.chars().count()).sum();
+    let word_syllables: Vec<usize> = words.iter().map(|w| syllables(w)).collect();
+    let syllables: usize = word_syllables.iter().sum();
+    let complex_words = word_syllables.iter().filter(|&&s| s >= 3).count();
+
+    let w = words.len() as f64;
+    let s = sentences as f64;
+    let words_per_sentence = w / s;
+    let syllables_per_word = syllables as f64 / w;
+
+    let flesch_reading_ease = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+    let flesch_kincaid_grade = 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59;
+    let gunning_fog = 0.4 * (words_per_sentence + 100.0 * complex_words as f64 / w);
+    let smog = 1.043 * (complex_words as f64 * 30.0 / s).sqrt() + 3.1291;
+    let coleman_liau = 0.0588 * (letters as f64 / w * 100.0) - 0.296 * (s / w * 100.0) - 15.8;
+    let automated_readability_index =
+        4.71 * (letters as f64 / w) + 0.5 * words_per_sentence - 21.43;
+    let grades = [
+        flesch_kincaid_grade,
+        gunning_fog,
+        smog,
+        coleman_liau,
+        automated_readability_index,
+    ];
+    let average_grade = grades.iter().sum::<f64>() / grades.len() as f64;
+
+    Some(Readability {
+        words: words.len(),
+        sentences,
+        syllables,
+        complex_words,
+        flesch_reading_ease: round(flesch_reading_ease.clamp(0.0, 100.0)),
+        flesch_kincaid_grade: round(flesch_kincaid_grade.max(0.0)),
+        gunning_fog: round(gunning_fog),
+        smog: round(smog),
+        coleman_liau: round(coleman_liau.max(0.0)),
+        automated_readability_index: round(automated_readability_index.max(0.0)),
+        average_grade: round(average_grade.max(0.0)),
+    })
+}
+
+/// Runs of `.`, `!`, or `?` end a sentence.
+fn count_sentences(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_terminator = false;
+    for c in text.chars() {
+        let terminator = matches!(c, '.' | '!' | '?');
+        if terminator && !in_terminator {
+            count += 1;
+        }
+        in_terminator = terminator;
+    }
+    count
+}
+
+/// Vowel groups, less a silent final `e`; at least one per word.
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let chars: Vec<char> = word.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    if chars.is_empty() {
+        return 1;
+    }
+
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &chars {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    let n = chars.len();
+    if count > 1 && chars[n - 1] == 'e' && !(n >= 2 && chars[n - 2] == 'l') {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_syllables_by_vowel_group() {
+        assert_eq!(syllables("cat"), 1);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("beautiful"), 3);
+        assert_eq!(syllables("rhythm"), 1);
+        assert_eq!(syllables("42"), 1);
+    }
+
+    #[test]
+    fn runs_of_terminators_end_one_sentence() {
+        assert_eq!(count_sentences("Hi!! Ok. Yes?"), 3);
+        assert_eq!(count_sentences("Wait... what"), 1);
+        assert_eq!(count_sentences("no ending"), 0);
+    }
+
+    #[test]
+    fn text_without_words_has_no_score() {
+        assert!(score("").is_none());
+        assert!(score(" -- ... ").is_none());
+    }
+
+    #[test]
+    fn simple_text_scores_easy_and_clamps() {
+        let r = score("The cat sat. The dog ran.").unwrap();
+        assert_eq!(
+            (r.words, r.sentences, r.syllables, r.complex_words),
+            (6, 2, 6, 0)
+        );
+        assert_eq!(r.flesch_reading_ease, 100.0);
+        assert_eq!(r.flesch_kincaid_grade, 0.0);
+    }
+
+    #[test]
+    fn harder_text_scores_a_higher_grade() {
+        let easy = score("The cat sat on the mat. It was warm.").unwrap();
+        let hard = score(
+            "Photosynthesis transforms electromagnetic radiation into chemical energy, \
+             sustaining practically every terrestrial ecosystem simultaneously.",
+        )
+        .unwrap();
+        assert!(hard.complex_words > 0);
+        assert!(hard.average_grade > easy.average_grade + 5.0);
+        assert!(hard.flesch_reading_ease < easy.flesch_reading_ease);
+    }
+}
@@ -0,0 +1,158 @@
+//! Best-effort PII scrubbing for free text.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+pub struct Redactor {
+    names: HashSet<String>,
+}
+
+impl Redactor {
+    /// Builds a redactor that masks every part of the given names that is at
+    /// least three characters long, so "Avery Smith" also catches "Avery".
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        let names = names
+            .into_iter()
+            .flat_map(|name| {
+                name.split_whitespace()
+                    .filter(|part| part.chars().count() >= 3)
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Self { names }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+
+        for piece in text.split_inclusive(char::is_whitespace) {
+            let word = piece.trim_end();
+            let trailing = &piece[word.len()..];
+
+            let core = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if core.is_empty() {
+                out.push_str(piece);
+                continue;
+            }
+            let prefix_len = word.len()
+                - word
+                    .trim_start_matches(|c: char| !c.is_alphanumeric())
+                    .len();
+            let prefix = &word[..prefix_len];
+            let suffix = &word[prefix_len + core.len()..];
+
+            let replacement = if is_email(core) {
+                Some("[EMAIL]")
+            } else if is_phone(core) {
+                Some("[PHONE]")
+            } else if self.names.contains(&core.to_lowercase()) {
+                Some("[NAME]")
+            } else {
+                None
+            };
+
+            match replacement {
+                Some(mask) => {
+                    out.push_str(prefix);
+                    out.push_str(mask);
+                    out.push_str(suffix);
+                }
+                None => out.push_str(word),
+            }
+            out.push_str(trailing);
+        }
+
+        out
+    }
+
+    /// Redacts every string inside a JSON document, keeping it valid JSON.
+    /// Text that isn't JSON is redacted as plain text.
+    pub fn redact_json(&self, text: &str) -> String {
+        match serde_json::from_str::<Value>(text) {
+            Ok(mut value) => {
+                self.redact_value(&mut value);
+                value.to_string()
+            }
+            Err(_) => self.redact(text),
+        }
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.redact(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(fields) => fields
+                .values_mut()
+                .for_each(|field| self.redact_value(field)),
+            _ => {}
+        }
+    }
+}
+
+fn is_email(word: &str) -> bool {
+    word.split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+}
+
+fn is_phone(word: &str) -> bool {
+    let digits = word.chars().filter(char::is_ascii_digit).count();
+    digits >= 7
+        && word
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-().".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::new(["Avery Smith".to_string(), "Jo Li".to_string()])
+    }
+
+    #[test]
+    fn masks_each_name_part_case_insensitively() {
+        assert_eq!(
+            redactor().redact("avery and SMITH met Avery."),
+            "[NAME] and [NAME] met [NAME]."
+        );
+    }
+
+    #[test]
+    fn skips_name_parts_shorter_than_three_characters() {
+        assert_eq!(redactor().redact("Jo Li is here"), "Jo Li is here");
+    }
+
+    #[test]
+    fn masks_emails_and_phones_keeping_punctuation() {
+        assert_eq!(
+            redactor().redact("Mail <avery@example.com>, call 555-123-4567!"),
+            "Mail <[EMAIL]>, call [PHONE]!"
+        );
+        assert_eq!(
+            redactor().redact("Page 12-34 of 2024"),
+            "Page 12-34 of 2024"
+        );
+    }
+
+    #[test]
+    fn preserves_whitespace() {
+        assert_eq!(
+            redactor().redact("  Avery\n\tSmith  "),
+            "  [NAME]\n\t[NAME]  "
+        );
+    }
+
+    #[test]
+    fn redacts_json_strings_only() {
+        let out = redactor().redact_json(r#"{"who":["Avery",42],"note":{"by":"a@b.co"}}"#);
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value["who"][0], "[NAME]");
+        assert_eq!(value["who"][1], 42);
+        assert_eq!(value["note"]["by"], "[EMAIL]");
+        assert_eq!(redactor().redact_json("hi Avery"), "hi [NAME]");
+    }
+}
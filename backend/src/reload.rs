@@ -0,0 +1,89 @@
+//! Re-reads the settings that can change without a restart: the LLM
+//! backends, canary split, and load balancing; the rate limits; and the
+//! response filter rules. Runs on SIGHUP and `POST /admin/config/reload`.
+//! Chats already in flight finish on the backend they started on.
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    app_state::AppState, config::Config, config_file::ConfigFile, error::AppError,
+    response_filter::ResponseFilter, upstream::UpstreamStatus, validation::FieldErrors,
+};
+
+/// The reloadable settings now in effect.
+#[derive(Debug, Serialize)]
+pub struct ReloadResult {
+    pub upstreams: UpstreamStatus,
+    pub rate_limit_requests_per_minute: u64,
+    pub rate_limit_tokens_per_hour: u64,
+    pub response_filter_rules: usize,
+}
+
+/// Loads the config the way startup does and applies the reloadable parts.
+/// Nothing changes unless the whole config is valid.
+pub fn reload(state: &AppState) -> Result<ReloadResult, AppError> {
+    let file = state
+        .config_path
+        .as_deref()
+        .map(ConfigFile::load)
+        .transpose()
+        .map_err(|err| AppError::BadRequest(err.to_string()))?;
+    let cfg = Config::load(file.as_ref()).map_err(|err| {
+        let mut errors = FieldErrors::new();
+        for problem in err.problems {
+            let (field, message) = problem
+                .split_once(": ")
+                .unwrap_or(("config", problem.as_str()));
+            errors
+                .entry(field.to_string())
+                .or_default()
+                .push(message.to_string());
+        }
+        AppError::Validation(errors)
+    })?;
+    let response_filter = ResponseFilter::load(cfg.llm_response_filter_file.as_deref())
+        .map_err(AppError::BadRequest)?;
+
+    state.upstreams.reload(&cfg);
+    state.rate_limiter.set_limits(&cfg);
+    state.response_filter.replace(response_filter);
+
+    let result = ReloadResult {
+        upstreams: state.upstreams.status(),
+        rate_limit_requests_per_minute: state.rate_limiter.requests_per_minute(),
+        rate_limit_tokens_per_hour: state.rate_limiter.tokens_per_hour(),
+        response_filter_rules: state.response_filter.rule_count(),
+    };
+    info!(
+        backends = result.upstreams.backends.len(),
+        response_filter_rules = result.response_filter_rules,
+        "reloaded config"
+    );
+    Ok(result)
+}
+
+/// Reloads on every SIGHUP. A bad config is logged and the old settings
+/// stay in effect.
+#[cfg(unix)]
+pub fn spawn(state: AppState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(err) => {
+            warn!(error = %err, "failed to listen for SIGHUP; config reload is API-only");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            if let Err(err) = reload(&state) {
+                warn!(error = ?err, "config reload failed; keeping the current settings");
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn(_state: AppState) {}
@@ -0,0 +1,221 @@
+//! Background job that writes a de-identified interaction dataset as JSONL.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    path::PathBuf,
+};
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+
+use crate::{error::AppError, redact::Redactor};
+
+#[derive(Debug, sqlx::FromRow)]
+struct InteractionRow {
+    id: i64,
+    user_id: Option<i64>,
+    student_id: Option<i64>,
+    grade_level: Option<String>,
+    prompt: String,
+    response: String,
+    created_at: String,
+}
+
+pub struct ExportParams {
+    pub job_id: i64,
+    pub k_threshold: i64,
+    pub dir: String,
+    pub salt: String,
+    /// Only this school's interactions are exported.
+    pub school_id: i64,
+}
+
+struct ExportOutcome {
+    path: PathBuf,
+    rows: usize,
+    suppressed: usize,
+}
+
+pub async fn run(pool: SqlitePool, params: ExportParams) {
+    let job_id = params.job_id;
+
+    if let Err(err) = mark_running(&pool, job_id).await {
+        warn!(job_id, error = %err, "failed to start export job");
+        return;
+    }
+
+    let result = export(&pool, &params).await;
+    let recorded = match result {
+        Ok(outcome) => {
+            info!(job_id, rows = outcome.rows, "research export completed");
+            finish(
+                &pool,
+                job_id,
+                "completed",
+                Some(&outcome),
+                None,
+                &format!(
+                    "{} rows, {} with suppressed grade",
+                    outcome.rows, outcome.suppressed
+                ),
+            )
+            .await
+        }
+        Err(err) => {
+            warn!(job_id, error = %err, "research export failed");
+            let message = err.to_string();
+            finish(&pool, job_id, "failed", None, Some(&message), &message).await
+        }
+    };
+
+    if let Err(err) = recorded {
+        warn!(job_id, error = %err, "failed to record export job outcome");
+    }
+}
+
+async fn export(pool: &SqlitePool, params: &ExportParams) -> Result<ExportOutcome, AppError> {
+    let rows = sqlx::query_as::<_, InteractionRow>(
+        r#"
+        SELECT i.id, i.user_id, i.student_id, s.grade_level, i.prompt, i.response, i.created_at
+        FROM ai_interactions i
+        LEFT JOIN students s ON s.id = i.student_id
+        WHERE i.school_id = ?
+        ORDER BY i.id ASC
+        "#,
+    )
+    .bind(params.school_id)
+    .fetch_all(pool)
+    .await?;
+
+    let names: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM students UNION SELECT name FROM users")
+            .fetch_all(pool)
+            .await?;
+    let redactor = Redactor::new(names);
+
+    // k-anonymity on the only quasi-identifier we hold: a grade shared by
+    // fewer than k distinct students is dropped from the output.
+    let mut students_per_grade: HashMap<&str, HashSet<i64>> = HashMap::new();
+    for row in &rows {
+        if let (Some(grade), Some(student_id)) = (row.grade_level.as_deref(), row.student_id) {
+            students_per_grade
+                .entry(grade)
+                .or_default()
+                .insert(student_id);
+        }
+    }
+    let k = usize::try_from(params.k_threshold).unwrap_or(usize::MAX);
+
+    let mut out = String::new();
+    let mut suppressed = 0;
+    for row in &rows {
+        let grade_level = row.grade_level.as_deref().filter(|grade| {
+            students_per_grade
+                .get(grade)
+                .is_some_and(|students| students.len() >= k)
+        });
+        if row.grade_level.is_some() && grade_level.is_none() {
+            suppressed += 1;
+        }
+
+        let record = json!({
+            "interaction": pseudonym(&params.salt, "interaction", row.id),
+            "user": row.user_id.map(|id| pseudonym(&params.salt, "user", id)),
+            "student": row.student_id.map(|id| pseudonym(&params.salt, "student", id)),
+            "grade_level": grade_level,
+            "date": row.created_at.get(..10).unwrap_or(&row.created_at),
+            "prompt": redactor.redact(&row.prompt),
+            "response": redactor.redact(&row.response),
+        });
+        out.push_str(&record.to_string());
+        out.push('\n');
+    }
+
+    tokio::fs::create_dir_all(&params.dir)
+        .await
+        .map_err(io_error)?;
+    let path = PathBuf::from(&params.dir).join(format!("research-export-{}.jsonl", params.job_id));
+    tokio::fs::write(&path, out).await.map_err(io_error)?;
+
+    Ok(ExportOutcome {
+        path,
+        rows: rows.len(),
+        suppressed,
+    })
+}
+
+/// Salted SHA-256 so ids stay joinable within one export but cannot be
+/// reversed without the salt.
+fn pseudonym(salt: &str, kind: &str, id: i64) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update(":")
+        .chain_update(kind)
+        .chain_update(":")
+        .chain_update(id.to_string())
+        .finalize();
+
+    digest[..16].iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn io_error(err: std::io::Error) -> AppError {
+    AppError::Internal(format!("export file error: {err}"))
+}
+
+async fn mark_running(pool: &SqlitePool, job_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE export_jobs SET status = 'running' WHERE id = ?")
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+    log_event(pool, job_id, "started", None).await
+}
+
+async fn finish(
+    pool: &SqlitePool,
+    job_id: i64,
+    status: &str,
+    outcome: Option<&ExportOutcome>,
+    error: Option<&str>,
+    detail: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE export_jobs
+        SET status = ?, row_count = ?, suppressed_count = ?, file_path = ?, error = ?,
+            finished_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(status)
+    .bind(outcome.map(|o| o.rows as i64))
+    .bind(outcome.map(|o| o.suppressed as i64))
+    .bind(outcome.map(|o| o.path.to_string_lossy().into_owned()))
+    .bind(error)
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+
+    log_event(pool, job_id, status, Some(detail)).await
+}
+
+pub async fn log_event(
+    pool: &SqlitePool,
+    job_id: i64,
+    event: &str,
+    detail: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO export_job_events(job_id, event, detail) VALUES(?, ?, ?)")
+        .bind(job_id)
+        .bind(event)
+        .bind(detail)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
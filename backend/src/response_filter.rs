@@ -0,0 +1,170 @@
+//! Post-processing of model output before it is returned or stored.
+
+use std::{
+    borrow::Cow,
+    sync::{RwLock, RwLockReadGuard},
+};
+
+use regex::Regex;
+use serde_json::Value;
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Regex,
+    replacement: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ResponseFilter {
+    /// Swapped by a config reload.
+    rules: RwLock<Vec<Rule>>,
+}
+
+impl ResponseFilter {
+    /// Parses one rule per line: a regex, optionally followed by
+    /// ` => replacement`. Without a replacement, matches are removed. Blank
+    /// lines and lines starting with `#` are skipped.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (pattern, replacement) = match line.rsplit_once(" =>") {
+                Some((pattern, replacement)) => (pattern.trim_end(), replacement.trim_start()),
+                None => (line, ""),
+            };
+            let pattern = Regex::new(pattern)
+                .map_err(|err| format!("invalid response filter on line {}: {err}", i + 1))?;
+
+            rules.push(Rule {
+                pattern,
+                replacement: replacement.to_string(),
+            });
+        }
+
+        Ok(Self {
+            rules: RwLock::new(rules),
+        })
+    }
+
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read LLM_RESPONSE_FILTER_FILE '{path}': {err}"))?;
+
+        Self::parse(&source)
+    }
+
+    /// Takes `other`'s rules, for a config reload.
+    pub fn replace(&self, other: ResponseFilter) {
+        let rules = other.rules.into_inner().unwrap_or_else(|e| e.into_inner());
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules().len()
+    }
+
+    fn rules(&self) -> RwLockReadGuard<'_, Vec<Rule>> {
+        self.rules.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut out = Cow::Borrowed(text);
+        for rule in self.rules().iter() {
+            let replaced = match rule.pattern.replace_all(&out, rule.replacement.as_str()) {
+                Cow::Owned(replaced) => replaced,
+                Cow::Borrowed(_) => continue,
+            };
+            out = Cow::Owned(replaced);
+        }
+        out
+    }
+
+    /// Filters the message content of every choice in an OpenAI-style
+    /// completion, or the delta content of a stream chunk. Returns whether
+    /// any content changed.
+    pub fn apply_completion(&self, completion: &mut Value) -> bool {
+        if self.rules().is_empty() {
+            return false;
+        }
+        let Some(choices) = completion.get_mut("choices").and_then(Value::as_array_mut) else {
+            return false;
+        };
+
+        let mut changed = false;
+        for choice in choices {
+            for pointer in ["/message/content", "/delta/content"] {
+                let Some(content) = choice.pointer_mut(pointer) else {
+                    continue;
+                };
+                let filtered = match content.as_str().map(|s| self.apply(s)) {
+                    Some(Cow::Owned(filtered)) => filtered,
+                    _ => continue,
+                };
+                *content = Value::String(filtered);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_rules_skipping_blanks_and_comments() {
+        let filter = ResponseFilter::parse("# comment\n\n(?i)secret => [hidden]\n\\s+$\n").unwrap();
+        assert_eq!(filter.rule_count(), 2);
+        assert_eq!(filter.apply("A Secret here  "), "A [hidden] here");
+    }
+
+    #[test]
+    fn reports_the_line_of_an_invalid_pattern() {
+        let err = ResponseFilter::parse("ok\n(unclosed").unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn borrows_when_nothing_matches() {
+        let filter = ResponseFilter::parse("foo").unwrap();
+        assert!(matches!(filter.apply("bar"), Cow::Borrowed("bar")));
+    }
+
+    #[test]
+    fn filters_message_and_delta_content() {
+        let filter = ResponseFilter::parse("bad => good").unwrap();
+        let mut completion = json!({
+            "choices": [
+                { "message": { "content": "bad news" } },
+                { "delta": { "content": "fine" } },
+                { "delta": { "content": "so bad" } },
+            ]
+        });
+        assert!(filter.apply_completion(&mut completion));
+        assert_eq!(completion["choices"][0]["message"]["content"], "good news");
+        assert_eq!(completion["choices"][1]["delta"]["content"], "fine");
+        assert_eq!(completion["choices"][2]["delta"]["content"], "so good");
+
+        let mut clean = json!({ "choices": [{ "message": { "content": "fine" } }] });
+        assert!(!filter.apply_completion(&mut clean));
+    }
+
+    #[test]
+    fn replace_swaps_rules() {
+        let filter = ResponseFilter::default();
+        assert_eq!(filter.apply("x"), "x");
+        filter.replace(ResponseFilter::parse("x => y").unwrap());
+        assert_eq!(filter.apply("x"), "y");
+    }
+}
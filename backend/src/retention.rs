@@ -0,0 +1,106 @@
+//! Retention window for `ai_interactions`. Every
+//! `INTERACTION_PURGE_INTERVAL_SECS` the job removes rows older than
+//! `INTERACTION_RETENTION_DAYS`, either deleting them outright or, in
+//! `anonymize` mode, keeping the accounting columns (model, tokens, cost,
+//! latency) and scrubbing everything that identifies a person or repeats
+//! what they wrote. Records that point at a deleted interaction keep their
+//! own copy of the generated text; their `generation_id` becomes NULL.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, config::RetentionMode, error::AppError};
+
+/// Stored in place of `prompt` and `response`; parses as JSON `null`.
+const SCRUBBED: &str = "null";
+
+#[derive(Debug, Serialize)]
+pub struct PurgeResult {
+    pub mode: RetentionMode,
+    pub retention_days: u32,
+    /// Rows created before this timestamp were purged.
+    pub cutoff: String,
+    pub deleted: u64,
+    pub anonymized: u64,
+}
+
+pub fn spawn(state: AppState) {
+    let interval_secs = state.config.interaction_purge_interval_secs;
+    let days = state.config.interaction_retention_days;
+    if interval_secs == 0 || days == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            match purge(&state, days, state.config.interaction_retention_mode).await {
+                Ok(result) if result.deleted + result.anonymized > 0 => {
+                    info!(
+                        cutoff = %result.cutoff,
+                        deleted = result.deleted,
+                        anonymized = result.anonymized,
+                        "interaction retention purge"
+                    );
+                }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "interaction retention purge failed"),
+            }
+        }
+    });
+}
+
+/// Purges interactions created more than `days` days ago. Already-anonymized
+/// rows are left alone in `anonymize` mode and deleted in `delete` mode.
+pub async fn purge(
+    state: &AppState,
+    days: u32,
+    mode: RetentionMode,
+) -> Result<PurgeResult, AppError> {
+    let cutoff: String = sqlx::query_scalar("SELECT datetime('now', ?)")
+        .bind(format!("-{days} days"))
+        .fetch_one(&state.pool)
+        .await?;
+
+    let mut result = PurgeResult {
+        mode,
+        retention_days: days,
+        cutoff,
+        deleted: 0,
+        anonymized: 0,
+    };
+    match mode {
+        RetentionMode::Delete => {
+            result.deleted = sqlx::query("DELETE FROM ai_interactions WHERE created_at < ?")
+                .bind(&result.cutoff)
+                .execute(&state.pool)
+                .await?
+                .rows_affected();
+        }
+        RetentionMode::Anonymize => {
+            result.anonymized = sqlx::query(
+                r#"
+                UPDATE ai_interactions
+                SET user_id = NULL,
+                    student_id = NULL,
+                    prompt = ?,
+                    response = ?,
+                    request_id = NULL,
+                    traceparent = NULL,
+                    anonymized_at = CURRENT_TIMESTAMP
+                WHERE created_at < ? AND anonymized_at IS NULL
+                "#,
+            )
+            .bind(SCRUBBED)
+            .bind(SCRUBBED)
+            .bind(&result.cutoff)
+            .execute(&state.pool)
+            .await?
+            .rows_affected();
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    app_state::AppState,
+    audit::AuditAfter,
+    auth::CurrentUser,
+    backup,
+    config::RetentionMode,
+    error::AppError,
+    experiment::ExperimentStatus,
+    reload::{self, ReloadResult},
+    retention::{self, PurgeResult},
+    routes::schools,
+    upstream::UpstreamStatus,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePercentRequest {
+    pub percent: u8,
+}
+
+impl Validate for UpdatePercentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.range("percent", self.percent, 0, 100);
+    }
+}
+
+pub async fn get_canary(State(state): State<AppState>) -> Json<UpstreamStatus> {
+    Json(state.upstreams.status())
+}
+
+pub async fn update_canary(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<UpdatePercentRequest>,
+) -> Result<Json<UpstreamStatus>, AppError> {
+    user.require_admin()?;
+    if payload.percent > 0 && !state.upstreams.has_canary() {
+        return Err(AppError::BadRequest(
+            "no canary backend configured (set LLM_CANARY_BASE_URL)".to_string(),
+        ));
+    }
+
+    state.upstreams.set_canary_percent(payload.percent);
+
+    Ok(Json(state.upstreams.status()))
+}
+
+/// The settings the server is running with, secrets redacted, and where
+/// each came from. Values changed by a reload show in `/admin/canary`.
+pub async fn get_config(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Response, AppError> {
+    user.require_admin()?;
+
+    Ok(Json(state.config.effective(state.config_path.as_deref())).into_response())
+}
+
+/// Re-reads the config file and applies the settings that can change
+/// without a restart; the same as sending SIGHUP.
+pub async fn reload_config(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<ReloadResult>, AppError> {
+    user.require_admin()?;
+    reload::reload(&state).map(Json)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BackupQuery {
+    /// Stream the snapshot back instead of returning its metadata. The file
+    /// is kept in `BACKUP_DIR` either way.
+    #[serde(default)]
+    pub download: bool,
+}
+
+/// The snapshot holds every school's records, so only an admin of the
+/// default school may take one. Downloads are recorded in the audit log.
+pub async fn create_backup(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<BackupQuery>,
+) -> Result<Response, AppError> {
+    if !schools::is_district_admin(&user) {
+        return Err(AppError::Forbidden(
+            "only an admin of the default school can back up the database".to_string(),
+        ));
+    }
+    let backup = backup::snapshot(&state.pool, Path::new(&state.config.backup_dir)).await?;
+    if !query.download {
+        return Ok(Json(backup).into_response());
+    }
+
+    let file = tokio::fs::File::open(&backup.path)
+        .await
+        .map_err(|err| AppError::Internal(format!("backup file error: {err}")))?;
+    let audited = serde_json::json!({
+        "path": backup.path,
+        "bytes": backup.bytes,
+        "created_at": backup.created_at,
+        "downloaded": true,
+    });
+    let filename = Path::new(&backup.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "backup.db".to_string());
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/vnd.sqlite3".to_string()),
+            (header::CONTENT_LENGTH, backup.bytes.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Extension(AuditAfter(audited)),
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+pub async fn get_experiment(
+    State(state): State<AppState>,
+) -> Result<Json<ExperimentStatus>, AppError> {
+    let experiment = state.experiment.as_ref().ok_or_else(no_experiment)?;
+
+    Ok(Json(experiment.status()))
+}
+
+pub async fn update_experiment(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<UpdatePercentRequest>,
+) -> Result<Json<ExperimentStatus>, AppError> {
+    user.require_admin()?;
+    let experiment = state.experiment.as_ref().ok_or_else(no_experiment)?;
+    experiment.set_percent(payload.percent);
+
+    Ok(Json(experiment.status()))
+}
+
+/// Overrides for a manual purge; unset fields fall back to
+/// `INTERACTION_RETENTION_DAYS` and `INTERACTION_RETENTION_MODE`.
+#[derive(Debug, Deserialize)]
+pub struct PurgeRequest {
+    pub days: Option<u32>,
+    pub mode: Option<RetentionMode>,
+}
+
+impl Validate for PurgeRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(days) = self.days {
+            v.range("days", days, 1, 36500);
+        }
+    }
+}
+
+pub async fn purge_interactions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<PurgeRequest>,
+) -> Result<Json<PurgeResult>, AppError> {
+    user.require_admin()?;
+    let days = match payload.days {
+        Some(days) => days,
+        None if state.config.interaction_retention_days > 0 => {
+            state.config.interaction_retention_days
+        }
+        None => {
+            return Err(AppError::BadRequest(
+                "no retention window configured (set INTERACTION_RETENTION_DAYS or send days)"
+                    .to_string(),
+            ))
+        }
+    };
+    let mode = payload
+        .mode
+        .unwrap_or(state.config.interaction_retention_mode);
+
+    Ok(Json(retention::purge(&state, days, mode).await?))
+}
+
+fn no_experiment() -> AppError {
+    AppError::NotFound("no experiment configured (set LLM_EXPERIMENT_NAME)".to_string())
+}
@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{app_state::AppState, auth, error::AppError, validation::Validator};
+
+const DEFAULT_TOP_STUDENTS: i64 = 50;
+const MAX_TOP_STUDENTS: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Inclusive lower bound on `created_at`, e.g. `2026-09-01`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// Only interactions by students enrolled in this class.
+    pub class_id: Option<i64>,
+    /// How many of the busiest students to list.
+    pub top_students: Option<i64>,
+}
+
+/// Counts, tokens, and latency for one day, student, model, or persona.
+#[derive(Debug, Default, Serialize, sqlx::FromRow)]
+pub struct UsageBucket {
+    /// The day (`2026-10-14`), student id, model name, or persona id;
+    /// `none` for interactions without a student or persona.
+    pub key: String,
+    /// Student or persona name.
+    pub label: Option<String>,
+    pub requests: i64,
+    pub students: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub avg_latency_ms: Option<f64>,
+    pub p50_latency_ms: Option<i64>,
+    pub p90_latency_ms: Option<i64>,
+    pub p99_latency_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub totals: UsageBucket,
+    /// Oldest day first.
+    pub days: Vec<UsageBucket>,
+    /// Busiest first, at most `top_students`.
+    pub students: Vec<UsageBucket>,
+    pub models: Vec<UsageBucket>,
+    pub personas: Vec<UsageBucket>,
+}
+
+/// How one breakdown groups and labels interactions (`i`, with the student
+/// as `st` and the persona as `pe`).
+struct Grouping {
+    key: &'static str,
+    label: &'static str,
+    order: &'static str,
+}
+
+const TOTALS: Grouping = Grouping {
+    key: "'all'",
+    label: "NULL",
+    order: "key ASC",
+};
+const BY_DAY: Grouping = Grouping {
+    key: "date(i.created_at)",
+    label: "NULL",
+    order: "key ASC",
+};
+const BY_STUDENT: Grouping = Grouping {
+    key: "COALESCE(CAST(i.student_id AS TEXT), 'none')",
+    label: "st.name",
+    order: "requests DESC, key ASC",
+};
+const BY_MODEL: Grouping = Grouping {
+    key: "COALESCE(i.model, 'unknown')",
+    label: "NULL",
+    order: "requests DESC, key ASC",
+};
+const BY_PERSONA: Grouping = Grouping {
+    key: "COALESCE(CAST(i.persona_id AS TEXT), 'none')",
+    label: "pe.name",
+    order: "requests DESC, key ASC",
+};
+
+/// Usage for an admin dashboard, all computed in SQL: totals plus
+/// breakdowns by day, student, model, and persona. Latency percentiles are
+/// nearest-rank over the interactions that recorded a latency.
+pub async fn usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, AppError> {
+    let top_students = query.top_students.unwrap_or(DEFAULT_TOP_STUDENTS);
+    let mut v = Validator::default();
+    v.range("top_students", top_students, 1, MAX_TOP_STUDENTS);
+    v.finish()?;
+
+    let pool = &state.pool;
+    let totals = breakdown(pool, &query, &TOTALS, 1)
+        .await?
+        .pop()
+        // Grouping an empty range yields no row at all.
+        .unwrap_or_else(|| UsageBucket {
+            key: "all".to_string(),
+            ..UsageBucket::default()
+        });
+
+    Ok(Json(UsageReport {
+        totals,
+        days: breakdown(pool, &query, &BY_DAY, -1).await?,
+        students: breakdown(pool, &query, &BY_STUDENT, top_students).await?,
+        models: breakdown(pool, &query, &BY_MODEL, -1).await?,
+        personas: breakdown(pool, &query, &BY_PERSONA, -1).await?,
+    }))
+}
+
+/// `limit` of -1 means no limit, as in SQLite.
+async fn breakdown(
+    pool: &SqlitePool,
+    query: &UsageQuery,
+    grouping: &Grouping,
+    limit: i64,
+) -> Result<Vec<UsageBucket>, AppError> {
+    let Grouping { key, label, order } = grouping;
+    let sql = format!(
+        r#"
+        WITH scoped AS (
+            SELECT {key} AS bucket, {label} AS bucket_label, i.student_id,
+                   i.prompt_tokens, i.completion_tokens, i.latency_ms
+            FROM ai_interactions i
+            LEFT JOIN students st ON st.id = i.student_id
+            LEFT JOIN personas pe ON pe.id = i.persona_id
+            WHERE i.school_id = ?
+              AND (? IS NULL OR i.created_at >= ?)
+              AND (? IS NULL OR i.created_at < ?)
+              AND (? IS NULL OR i.student_id IN (SELECT student_id FROM enrollments
+                                                 WHERE class_id = ?))
+        ),
+        ranked AS (
+            SELECT bucket, latency_ms,
+                   ROW_NUMBER() OVER (PARTITION BY bucket ORDER BY latency_ms) AS rn,
+                   COUNT(*) OVER (PARTITION BY bucket) AS n
+            FROM scoped
+            WHERE latency_ms IS NOT NULL
+        ),
+        percentiles AS (
+            SELECT bucket,
+                   MIN(CASE WHEN rn >= 0.50 * n THEN latency_ms END) AS p50,
+                   MIN(CASE WHEN rn >= 0.90 * n THEN latency_ms END) AS p90,
+                   MIN(CASE WHEN rn >= 0.99 * n THEN latency_ms END) AS p99
+            FROM ranked
+            GROUP BY bucket
+        )
+        SELECT s.bucket AS key, MAX(s.bucket_label) AS label,
+               COUNT(*) AS requests,
+               COUNT(DISTINCT s.student_id) AS students,
+               COALESCE(SUM(s.prompt_tokens), 0) AS prompt_tokens,
+               COALESCE(SUM(s.completion_tokens), 0) AS completion_tokens,
+               AVG(s.latency_ms) AS avg_latency_ms,
+               MAX(p.p50) AS p50_latency_ms,
+               MAX(p.p90) AS p90_latency_ms,
+               MAX(p.p99) AS p99_latency_ms
+        FROM scoped s
+        LEFT JOIN percentiles p ON p.bucket = s.bucket
+        GROUP BY s.bucket
+        ORDER BY {order}
+        LIMIT ?
+        "#
+    );
+
+    let rows = sqlx::query_as::<_, UsageBucket>(&sql)
+        .bind(auth::school_id())
+        .bind(&query.since)
+        .bind(&query.since)
+        .bind(&query.until)
+        .bind(&query.until)
+        .bind(query.class_id)
+        .bind(query.class_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    api_keys::{self, SCOPES, SCOPE_FULL},
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    routes::users,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub user_id: i64,
+    pub user_email: String,
+    pub name: String,
+    pub scope: String,
+    /// The start of the key, e.g. `sk-3f9a01c2`.
+    pub key_prefix: String,
+    pub created_by: Option<i64>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+/// The only response that ever contains the key itself.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// `full` (default), `read_only`, or `chat`.
+    pub scope: Option<String>,
+    /// The user the key acts as; defaults to the caller. Only admins can
+    /// create keys for someone else.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for CreateApiKeyRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        if let Some(scope) = &self.scope {
+            v.one_of("scope", scope, SCOPES);
+        }
+    }
+}
+
+const API_KEY_SELECT: &str = r#"
+    SELECT k.id, k.user_id, u.email AS user_email, k.name, k.scope, k.key_prefix,
+           k.created_by, k.created_at, k.last_used_at
+    FROM api_keys k
+    JOIN users u ON u.id = k.user_id
+"#;
+
+/// Admins see every key in their school; everyone else sees their own.
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<ApiKey>>, AppError> {
+    let rows = sqlx::query_as::<_, ApiKey>(&format!(
+        "{API_KEY_SELECT} WHERE u.school_id = ? AND (? OR k.user_id = ?) \
+         ORDER BY k.created_at DESC, k.id DESC"
+    ))
+    .bind(auth::school_id())
+    .bind(user.is_admin())
+    .bind(user.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKey>, AppError> {
+    let owner = payload.user_id.unwrap_or(user.id);
+    if owner != user.id {
+        if !user.is_admin() {
+            return Err(AppError::Forbidden(
+                "only admins can create API keys for other users".to_string(),
+            ));
+        }
+        users::check_reference(&state.pool, "user_id", owner).await?;
+    }
+
+    let secret = api_keys::new_key();
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO api_keys(user_id, name, scope, key_prefix, key_hash, created_by)
+        VALUES(?, ?, ?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(owner)
+    .bind(payload.name.trim())
+    .bind(payload.scope.as_deref().unwrap_or(SCOPE_FULL))
+    .bind(api_keys::display_prefix(&secret))
+    .bind(auth::token_hash(&secret))
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(CreatedApiKey {
+        api_key: load(&state.pool, id).await?,
+        secret,
+    }))
+}
+
+/// Revokes the key at once. Admins can revoke any key in their school.
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM api_keys
+        WHERE id = ?
+          AND user_id IN (SELECT id FROM users WHERE school_id = ?)
+          AND (? OR user_id = ?)
+        "#,
+    )
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(user.is_admin())
+    .bind(user.id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("API key {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<ApiKey, AppError> {
+    sqlx::query_as::<_, ApiKey>(&format!("{API_KEY_SELECT} WHERE k.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("API key {id}")))
+}
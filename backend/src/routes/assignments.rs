@@ -0,0 +1,463 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    routes::{school_years, students, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const ASSIGNMENT_STATUSES: &[&str] = &["pending", "in_progress", "completed"];
+const MAX_TEXT_CHARS: usize = 10_000;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Assignment {
+    pub id: i64,
+    pub student_id: i64,
+    pub title: String,
+    pub subject: Option<String>,
+    pub description: Option<String>,
+    pub due_date: Option<String>,
+    pub status: String,
+    pub max_points: Option<f64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Grade {
+    pub id: i64,
+    pub assignment_id: i64,
+    pub points: f64,
+    pub comment: Option<String>,
+    pub graded_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssignmentDetail {
+    #[serde(flatten)]
+    pub assignment: Assignment,
+    pub grade: Option<Grade>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GradeTotals {
+    /// `null` in the overall totals.
+    pub subject: Option<String>,
+    pub graded: i64,
+    pub points: f64,
+    /// Sum of `max_points` over graded assignments that have one.
+    pub max_points: f64,
+    /// `points / max_points` as a percentage, over assignments with
+    /// `max_points` only.
+    pub percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GradeSummary {
+    pub student_id: i64,
+    pub overall: GradeTotals,
+    pub subjects: Vec<GradeTotals>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentRequest {
+    pub title: String,
+    pub subject: Option<String>,
+    pub description: Option<String>,
+    pub due_date: Option<String>,
+    pub status: Option<String>,
+    pub max_points: Option<f64>,
+}
+
+impl Validate for AssignmentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("title", &self.title);
+        v.max_chars("title", &self.title, 200);
+        if let Some(subject) = &self.subject {
+            v.required("subject", subject);
+            v.max_chars("subject", subject, 100);
+        }
+        if let Some(description) = &self.description {
+            v.max_chars("description", description, MAX_TEXT_CHARS);
+        }
+        if let Some(due_date) = &self.due_date {
+            v.date("due_date", due_date);
+        }
+        if let Some(status) = &self.status {
+            v.one_of("status", status, ASSIGNMENT_STATUSES);
+        }
+        if let Some(max_points) = self.max_points {
+            v.range("max_points", max_points, 0.0, 100_000.0);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GradeRequest {
+    pub points: f64,
+    pub comment: Option<String>,
+}
+
+impl Validate for GradeRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.range("points", self.points, 0.0, 100_000.0);
+        if let Some(comment) = &self.comment {
+            v.max_chars("comment", comment, MAX_TEXT_CHARS);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentListQuery {
+    pub status: Option<String>,
+    pub subject: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedbackDraftRequest {
+    /// What the teacher wants the comment to mention.
+    pub notes: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for FeedbackDraftRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(notes) = &self.notes {
+            v.max_chars("notes", notes, 2_000);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackDraft {
+    pub assignment_id: i64,
+    /// Not saved; send it back through `PUT /assignments/:id/grade` to keep it.
+    pub comment: String,
+    pub interaction_id: i64,
+}
+
+const ASSIGNMENT_COLUMNS: &str =
+    "id, student_id, title, subject, description, due_date, status, max_points, created_at";
+const GRADE_COLUMNS: &str = "id, assignment_id, points, comment, graded_at, updated_at";
+
+/// By due date, undated assignments last.
+pub async fn list_assignments(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    Query(query): Query<AssignmentListQuery>,
+) -> Result<Json<Vec<Assignment>>, AppError> {
+    let mut v = Validator::default();
+    if let Some(status) = &query.status {
+        v.one_of("status", status, ASSIGNMENT_STATUSES);
+    }
+    v.finish()?;
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, Assignment>(&format!(
+        r#"
+        SELECT {ASSIGNMENT_COLUMNS}
+        FROM assignments
+        WHERE student_id = ? AND (? IS NULL OR status = ?) AND (? IS NULL OR subject = ?)
+        ORDER BY due_date IS NULL, due_date ASC, id ASC
+        "#
+    ))
+    .bind(student_id)
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(&query.subject)
+    .bind(&query.subject)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_assignment(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<AssignmentRequest>,
+) -> Result<Json<Assignment>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    school_years::ensure_student_writable(&state.pool, student_id).await?;
+
+    let created = sqlx::query_as::<_, Assignment>(&format!(
+        r#"
+        INSERT INTO assignments(student_id, title, subject, description, due_date, status, max_points)
+        VALUES(?, ?, ?, ?, ?, ?, ?)
+        RETURNING {ASSIGNMENT_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(payload.title.trim())
+    .bind(&payload.subject)
+    .bind(&payload.description)
+    .bind(&payload.due_date)
+    .bind(payload.status.as_deref().unwrap_or("pending"))
+    .bind(payload.max_points)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn get_assignment(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<AssignmentDetail>, AppError> {
+    let assignment = load(&state.pool, id).await?;
+    let grade = load_grade(&state.pool, id).await?;
+
+    Ok(Json(AssignmentDetail { assignment, grade }))
+}
+
+pub async fn update_assignment(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<AssignmentRequest>,
+) -> Result<Json<Assignment>, AppError> {
+    load(&state.pool, id).await?;
+
+    let updated = sqlx::query_as::<_, Assignment>(&format!(
+        r#"
+        UPDATE assignments
+        SET title = ?, subject = ?, description = ?, due_date = ?, status = ?, max_points = ?
+        WHERE id = ?
+        RETURNING {ASSIGNMENT_COLUMNS}
+        "#
+    ))
+    .bind(payload.title.trim())
+    .bind(&payload.subject)
+    .bind(&payload.description)
+    .bind(&payload.due_date)
+    .bind(payload.status.as_deref().unwrap_or("pending"))
+    .bind(payload.max_points)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("assignment {id}")))
+}
+
+pub async fn delete_assignment(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM assignments WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("assignment {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sets the grade, replacing any earlier one, and marks the assignment
+/// completed.
+pub async fn upsert_grade(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<GradeRequest>,
+) -> Result<Json<Grade>, AppError> {
+    load(&state.pool, id).await?;
+
+    let mut tx = state.pool.begin().await?;
+    let updated = sqlx::query("UPDATE assignments SET status = 'completed' WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("assignment {id}")));
+    }
+
+    let grade = sqlx::query_as::<_, Grade>(&format!(
+        r#"
+        INSERT INTO grades(assignment_id, points, comment)
+        VALUES(?, ?, ?)
+        ON CONFLICT(assignment_id) DO UPDATE SET
+            points = excluded.points,
+            comment = excluded.comment,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING {GRADE_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(payload.points)
+    .bind(&payload.comment)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(grade))
+}
+
+pub async fn delete_grade(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM grades WHERE assignment_id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("grade for assignment {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Points earned overall and per subject, over graded assignments.
+pub async fn grade_summary(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<GradeSummary>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let totals = |group: &str| {
+        format!(
+            r#"
+            SELECT {group} AS subject,
+                   COUNT(*) AS graded,
+                   COALESCE(SUM(g.points), 0.0) AS points,
+                   COALESCE(SUM(a.max_points), 0.0) AS max_points,
+                   100.0 * SUM(CASE WHEN a.max_points IS NOT NULL THEN g.points END)
+                       / NULLIF(SUM(a.max_points), 0) AS percent
+            FROM assignments a
+            JOIN grades g ON g.assignment_id = a.id
+            WHERE a.student_id = ?
+            "#
+        )
+    };
+
+    let overall = sqlx::query_as::<_, GradeTotals>(&totals("NULL"))
+        .bind(student_id)
+        .fetch_one(&state.pool)
+        .await?;
+    let subjects = sqlx::query_as::<_, GradeTotals>(&format!(
+        "{} GROUP BY a.subject ORDER BY a.subject ASC",
+        totals("a.subject")
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(GradeSummary {
+        student_id,
+        overall,
+        subjects,
+    }))
+}
+
+/// Asks the local model for a short feedback comment on the assignment's
+/// grade.
+pub async fn draft_feedback(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<FeedbackDraftRequest>,
+) -> Result<Json<FeedbackDraft>, AppError> {
+    let assignment = load(&state.pool, id).await?;
+    let grade = load_grade(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("grade for assignment {id}")))?;
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let grade_level = students::grade_level(&state.pool, assignment.student_id).await?;
+
+    let mut details = vec![format!("Assignment: {}", assignment.title)];
+    if let Some(subject) = &assignment.subject {
+        details.push(format!("Subject: {subject}"));
+    }
+    if let Some(grade_level) = &grade_level {
+        details.push(format!("Student grade level: {grade_level}"));
+    }
+    if let Some(description) = &assignment.description {
+        details.push(format!("Description: {description}"));
+    }
+    details.push(match assignment.max_points {
+        Some(max) => format!("Score: {} out of {max}", grade.points),
+        None => format!("Score: {}", grade.points),
+    });
+    if let Some(comment) = &grade.comment {
+        details.push(format!("Current comment: {comment}"));
+    }
+    if let Some(notes) = &payload.notes {
+        details.push(format!("Teacher notes: {notes}"));
+    }
+
+    let completion = assist::complete(
+        &state,
+        Prompt {
+            system: "You help a teacher write feedback on graded schoolwork. Write 2-4 \
+                     sentences addressed to the student: name one specific strength, one \
+                     concrete next step, and keep the tone encouraging and age-appropriate. \
+                     Reply with the comment only."
+                .to_string(),
+            user: details.join("\n"),
+            model: payload.model,
+            temperature: Some(0.7),
+            max_tokens: Some(300),
+            user_id: payload.user_id,
+            student_id: Some(assignment.student_id),
+            json: false,
+        },
+        trace,
+    )
+    .await?;
+
+    Ok(Json(FeedbackDraft {
+        assignment_id: id,
+        comment: completion.text,
+        interaction_id: completion.interaction_id,
+    }))
+}
+
+pub async fn load(pool: &SqlitePool, id: i64) -> Result<Assignment, AppError> {
+    let assignment = sqlx::query_as::<_, Assignment>(&format!(
+        "SELECT {ASSIGNMENT_COLUMNS} FROM assignments WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("assignment {id}")))?;
+
+    students::ensure_record_visible(pool, assignment.student_id, || format!("assignment {id}"))
+        .await?;
+    Ok(assignment)
+}
+
+async fn load_grade(pool: &SqlitePool, assignment_id: i64) -> Result<Option<Grade>, AppError> {
+    let grade = sqlx::query_as::<_, Grade>(&format!(
+        "SELECT {GRADE_COLUMNS} FROM grades WHERE assignment_id = ?"
+    ))
+    .bind(assignment_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(grade)
+}
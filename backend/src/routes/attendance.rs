@@ -0,0 +1,211 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::students,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const ATTENDANCE_STATUSES: &[&str] = &["present", "absent", "tardy"];
+
+/// One student's attendance on one day.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AttendanceRecord {
+    pub id: i64,
+    pub student_id: i64,
+    pub date: String,
+    pub status: String,
+    pub note: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AttendanceSummary {
+    pub present: i64,
+    pub absent: i64,
+    pub tardy: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StudentAttendance {
+    pub student_id: i64,
+    pub summary: AttendanceSummary,
+    pub records: Vec<AttendanceRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordAttendanceRequest {
+    pub status: String,
+    pub note: Option<String>,
+}
+
+impl Validate for RecordAttendanceRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("status", &self.status, ATTENDANCE_STATUSES);
+        if let Some(note) = &self.note {
+            v.max_chars("note", note, 1_000);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttendanceQuery {
+    /// First day included, `YYYY-MM-DD`.
+    pub from: Option<String>,
+    /// Last day included, `YYYY-MM-DD`.
+    pub to: Option<String>,
+    pub status: Option<String>,
+    /// Only students enrolled in this class.
+    pub class_id: Option<i64>,
+}
+
+impl AttendanceQuery {
+    fn check(&self) -> Result<(), AppError> {
+        let mut v = Validator::default();
+        if let Some(from) = &self.from {
+            v.date("from", from);
+        }
+        if let Some(to) = &self.to {
+            v.date("to", to);
+        }
+        if let Some(status) = &self.status {
+            v.one_of("status", status, ATTENDANCE_STATUSES);
+        }
+        v.finish()
+    }
+}
+
+const ATTENDANCE_COLUMNS: &str = "id, student_id, date, status, note, created_at, updated_at";
+
+/// Records across students, by date then student. Soft-deleted students are
+/// left out.
+pub async fn list_attendance(
+    State(state): State<AppState>,
+    Query(query): Query<AttendanceQuery>,
+) -> Result<Json<Vec<AttendanceRecord>>, AppError> {
+    query.check()?;
+
+    let rows = sqlx::query_as::<_, AttendanceRecord>(&format!(
+        r#"
+        SELECT {ATTENDANCE_COLUMNS}
+        FROM attendance
+        WHERE date >= ? AND date <= ?
+          AND (? IS NULL OR status = ?)
+          AND (? IS NULL OR student_id IN (SELECT student_id FROM enrollments WHERE class_id = ?))
+          AND student_id IN (SELECT id FROM students WHERE school_id = ? AND deleted_at IS NULL)
+        ORDER BY date ASC, student_id ASC
+        "#
+    ))
+    .bind(query.from.as_deref().unwrap_or(""))
+    .bind(query.to.as_deref().unwrap_or("9999"))
+    .bind(&query.status)
+    .bind(&query.status)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// One student's records in the range, newest first, with totals per
+/// status.
+pub async fn student_attendance(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    Query(query): Query<AttendanceQuery>,
+) -> Result<Json<StudentAttendance>, AppError> {
+    query.check()?;
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let records = sqlx::query_as::<_, AttendanceRecord>(&format!(
+        r#"
+        SELECT {ATTENDANCE_COLUMNS}
+        FROM attendance
+        WHERE student_id = ? AND date >= ? AND date <= ? AND (? IS NULL OR status = ?)
+        ORDER BY date DESC
+        "#
+    ))
+    .bind(student_id)
+    .bind(query.from.as_deref().unwrap_or(""))
+    .bind(query.to.as_deref().unwrap_or("9999"))
+    .bind(&query.status)
+    .bind(&query.status)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut summary = AttendanceSummary::default();
+    for record in &records {
+        match record.status.as_str() {
+            "present" => summary.present += 1,
+            "absent" => summary.absent += 1,
+            _ => summary.tardy += 1,
+        }
+    }
+
+    Ok(Json(StudentAttendance {
+        student_id,
+        summary,
+        records,
+    }))
+}
+
+/// Sets the student's status for the day, replacing any earlier entry.
+pub async fn record_attendance(
+    State(state): State<AppState>,
+    Path((student_id, date)): Path<(i64, String)>,
+    ValidJson(payload): ValidJson<RecordAttendanceRequest>,
+) -> Result<Json<AttendanceRecord>, AppError> {
+    let mut v = Validator::default();
+    v.date("date", &date);
+    v.finish()?;
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let saved = sqlx::query_as::<_, AttendanceRecord>(&format!(
+        r#"
+        INSERT INTO attendance(student_id, date, status, note)
+        VALUES(?, ?, ?, ?)
+        ON CONFLICT(student_id, date) DO UPDATE SET
+            status = excluded.status,
+            note = excluded.note,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING {ATTENDANCE_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(&date)
+    .bind(&payload.status)
+    .bind(&payload.note)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+pub async fn delete_attendance(
+    State(state): State<AppState>,
+    Path((student_id, date)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM attendance WHERE student_id = ? AND date = ?")
+        .bind(student_id)
+        .bind(&date)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "attendance for student {student_id} on {date}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    pagination::{self, Page},
+    validation::Validator,
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor_user_id: Option<i64>,
+    pub actor_email: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub route: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub status: i64,
+    pub request_id: Option<String>,
+    pub before: Option<SqlJson<Value>>,
+    pub after: Option<SqlJson<Value>>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_user_id: Option<i64>,
+    /// Table name, e.g. `students`.
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub method: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+const AUDIT_COLUMNS: &str = r#"
+    id, actor_user_id, actor_email, method, path, route, entity, entity_id, status,
+    request_id, before_json AS before, after_json AS after, created_at
+"#;
+
+/// The caller's school's entries, newest first.
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Page<AuditEntry>>, AppError> {
+    user.require_admin()?;
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    if let Some(since) = &query.since {
+        v.timestamp("since", since);
+    }
+    if let Some(until) = &query.until {
+        v.timestamp("until", until);
+    }
+    v.finish()?;
+    let method = query.method.as_deref().map(str::to_uppercase);
+    let school_id = auth::school_id();
+
+    let filter = r#"
+        school_id = ?
+        AND (? IS NULL OR actor_user_id = ?)
+        AND (? IS NULL OR entity = ?)
+        AND (? IS NULL OR entity_id = ?)
+        AND (? IS NULL OR method = ?)
+        AND (? IS NULL OR created_at >= ?)
+        AND (? IS NULL OR created_at < ?)
+    "#;
+    let items = sqlx::query_as::<_, AuditEntry>(&format!(
+        "SELECT {AUDIT_COLUMNS} FROM audit_log WHERE {filter} ORDER BY id DESC LIMIT ? OFFSET ?"
+    ))
+    .bind(school_id)
+    .bind(query.actor_user_id)
+    .bind(query.actor_user_id)
+    .bind(&query.entity)
+    .bind(&query.entity)
+    .bind(&query.entity_id)
+    .bind(&query.entity_id)
+    .bind(&method)
+    .bind(&method)
+    .bind(&query.since)
+    .bind(&query.since)
+    .bind(&query.until)
+    .bind(&query.until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let total =
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM audit_log WHERE {filter}"))
+            .bind(school_id)
+            .bind(query.actor_user_id)
+            .bind(query.actor_user_id)
+            .bind(&query.entity)
+            .bind(&query.entity)
+            .bind(&query.entity_id)
+            .bind(&query.entity_id)
+            .bind(&method)
+            .bind(&method)
+            .bind(&query.since)
+            .bind(&query.since)
+            .bind(&query.until)
+            .bind(&query.until)
+            .fetch_one(&state.pool)
+            .await?;
+
+    Ok(Json(Page {
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
@@ -0,0 +1,338 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth::{self, Credential, CurrentUser},
+    error::AppError,
+    jwt::{self, TokenPair},
+    oidc,
+    routes::{
+        schools,
+        users::{User, USER_COLUMNS, USER_ROLES},
+    },
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MIN_PASSWORD_CHARS: usize = 8;
+const MAX_PASSWORD_CHARS: usize = 256;
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    /// Defaults to `parent`.
+    pub role: Option<String>,
+    /// Defaults to the caller's school; only admins of the default school
+    /// may name another one.
+    pub school_id: Option<i64>,
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(school_id) = self.school_id {
+            v.range("school_id", school_id, 1, i64::MAX);
+        }
+        if let Some(role) = &self.role {
+            v.one_of("role", role, USER_ROLES);
+        }
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        v.required("email", &self.email);
+        v.max_chars("email", &self.email, 320);
+        if !self.email.trim().is_empty() && !self.email.contains('@') {
+            v.error("email", "must be an email address");
+        }
+        check_new_password(v, "password", &self.password);
+    }
+}
+
+/// Length rules for a password being set.
+pub fn check_new_password(v: &mut Validator, field: &str, password: &str) {
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        v.error(
+            field,
+            format!("must be at least {MIN_PASSWORD_CHARS} characters"),
+        );
+    }
+    v.max_chars(field, password, MAX_PASSWORD_CHARS);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+impl Validate for LoginRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("email", &self.email);
+        v.required("password", &self.password);
+        v.max_chars("password", &self.password, MAX_PASSWORD_CHARS);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+impl Validate for RefreshRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("refresh_token", &self.refresh_token);
+    }
+}
+
+/// What the identity provider sends back to `/auth/oidc/callback`.
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Session {
+    /// Also set as the `session` cookie; send it as a bearer token instead
+    /// from clients that don't keep cookies.
+    pub token: String,
+    pub expires_at: String,
+    pub user: User,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenLogin {
+    #[serde(flatten)]
+    pub tokens: TokenPair,
+    pub user: User,
+}
+
+/// Creates a login. Admins (and anyone, while no account has a password
+/// yet) can create any role and can give an existing passwordless user a
+/// password; with `AUTH_OPEN_REGISTRATION=true` anyone else can create a
+/// new non-admin account.
+pub async fn register(
+    State(state): State<AppState>,
+    caller: Option<CurrentUser>,
+    ValidJson(payload): ValidJson<RegisterRequest>,
+) -> Result<Json<User>, AppError> {
+    let email = payload.email.trim().to_lowercase();
+    let role = payload.role.as_deref().unwrap_or("parent");
+
+    let bootstrap: bool = sqlx::query_scalar(
+        "SELECT NOT EXISTS(SELECT 1 FROM users WHERE password_hash IS NOT NULL)",
+    )
+    .fetch_one(&state.pool)
+    .await?;
+    let privileged = bootstrap || caller.as_ref().is_some_and(CurrentUser::is_admin);
+    if !privileged {
+        if !state.config.auth_open_registration {
+            return Err(AppError::Forbidden(
+                "only an admin can create accounts".to_string(),
+            ));
+        }
+        if role == "admin" {
+            return Err(AppError::Forbidden(
+                "only an admin can create admin accounts".to_string(),
+            ));
+        }
+    }
+
+    let school_id = schools::for_user(&state.pool, payload.school_id).await?;
+    let password_hash = auth::hash_password(payload.password).await?;
+
+    // The upsert only claims an existing row when it has no password, is in
+    // the same school, and the caller is privileged; otherwise nothing
+    // comes back.
+    let user = sqlx::query_as::<_, User>(&format!(
+        r#"
+        INSERT INTO users(role, name, email, password_hash, school_id) VALUES(?, ?, ?, ?, ?)
+        ON CONFLICT(email) DO UPDATE SET
+            role = excluded.role,
+            name = excluded.name,
+            password_hash = excluded.password_hash
+        WHERE users.password_hash IS NULL AND users.school_id = excluded.school_id AND ?
+        RETURNING {USER_COLUMNS}
+        "#
+    ))
+    .bind(role)
+    .bind(payload.name.trim())
+    .bind(&email)
+    .bind(&password_hash)
+    .bind(school_id)
+    .bind(privileged)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    user.map(Json)
+        .ok_or_else(|| AppError::Conflict(format!("email '{email}' already has an account")))
+}
+
+/// The user's id if the password matches.
+async fn check_password(state: &AppState, payload: LoginRequest) -> Result<i64, AppError> {
+    let email = payload.email.trim().to_lowercase();
+    let invalid = || AppError::Unauthorized("invalid email or password".to_string());
+
+    let account = sqlx::query_as::<_, (i64, Option<String>)>(
+        "SELECT id, password_hash FROM users WHERE email = ?",
+    )
+    .bind(&email)
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((user_id, Some(password_hash))) = account else {
+        return Err(invalid());
+    };
+    if !auth::verify_password(password_hash, payload.password).await? {
+        return Err(invalid());
+    }
+
+    Ok(user_id)
+}
+
+pub async fn record_login(state: &AppState, user_id: i64) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>(&format!(
+        "UPDATE users SET last_login_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING {USER_COLUMNS}"
+    ))
+    .bind(user_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(user)
+}
+
+/// Starts a session, returned both as a cookie and in the body.
+pub async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<LoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = check_password(&state, payload).await?;
+    let (token, expires_at) = auth::start_session(&state, user_id, &headers).await?;
+    let user = record_login(&state, user_id).await?;
+
+    Ok((
+        [(
+            header::SET_COOKIE,
+            auth::session_cookie(&state, Some(&token)),
+        )],
+        Json(Session {
+            token,
+            expires_at,
+            user,
+        }),
+    ))
+}
+
+/// Ends the session, or for a JWT revokes the access token and the
+/// refresh tokens from the same login.
+pub async fn logout(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    match &user.credential {
+        Credential::Session(session_id) => {
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(session_id)
+                .execute(&state.pool)
+                .await?;
+        }
+        Credential::AccessToken {
+            jti,
+            family_id,
+            expires_at,
+        } => {
+            jwt::revoke_access_token(&state, jti, *expires_at).await?;
+            jwt::revoke_family(&state, family_id).await?;
+        }
+        // Keys stay valid until revoked through `/admin/api-keys`.
+        Credential::ApiKey { .. } => {}
+    }
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, auth::session_cookie(&state, None))],
+    ))
+}
+
+pub async fn me(State(state): State<AppState>, user: CurrentUser) -> Result<Json<User>, AppError> {
+    sqlx::query_as::<_, User>(&format!("SELECT {USER_COLUMNS} FROM users WHERE id = ?"))
+        .bind(user.id)
+        .fetch_optional(&state.pool)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("user {}", user.id)))
+}
+
+/// Password login for clients that use JWTs instead of a session cookie.
+pub async fn issue_token(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<LoginRequest>,
+) -> Result<Json<TokenLogin>, AppError> {
+    let user_id = check_password(&state, payload).await?;
+    let tokens = jwt::issue(&state, user_id, None).await?;
+    let user = record_login(&state, user_id).await?;
+
+    Ok(Json(TokenLogin { tokens, user }))
+}
+
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<RefreshRequest>,
+) -> Result<Json<TokenPair>, AppError> {
+    jwt::refresh(&state, &payload.refresh_token).await.map(Json)
+}
+
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<RefreshRequest>,
+) -> Result<StatusCode, AppError> {
+    jwt::revoke(&state, &payload.refresh_token).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sends the browser to the identity provider.
+pub async fn oidc_login(State(state): State<AppState>) -> Result<Redirect, AppError> {
+    oidc::authorize_url(&state)
+        .await
+        .map(|url| Redirect::to(&url))
+}
+
+/// Signs the user in after the provider redirects back, then sends them on
+/// to `OIDC_POST_LOGIN_REDIRECT` with a session cookie.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(error) = &query.error {
+        let description = query.error_description.as_deref().unwrap_or_default();
+        return Err(AppError::BadRequest(format!(
+            "sign-in failed: {error} {description}"
+        )));
+    }
+    let (Some(code), Some(login_state)) = (&query.code, &query.state) else {
+        return Err(AppError::BadRequest(
+            "code and state are required".to_string(),
+        ));
+    };
+
+    let user_id = oidc::complete(&state, code, login_state).await?;
+    let (token, _) = auth::start_session(&state, user_id, &headers).await?;
+    record_login(&state, user_id).await?;
+
+    Ok((
+        [(
+            header::SET_COOKIE,
+            auth::session_cookie(&state, Some(&token)),
+        )],
+        Redirect::to(&state.config.oidc_post_login_redirect),
+    ))
+}
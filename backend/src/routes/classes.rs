@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::{
+        school_years,
+        students::{self, Student, STUDENT_COLUMNS},
+    },
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Class {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// Enrolled students, not counting soft-deleted ones.
+    pub student_count: i64,
+    pub school_year_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Enrollment {
+    pub class_id: i64,
+    pub student_id: i64,
+    pub enrolled_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClassRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub school_year_id: Option<i64>,
+}
+
+impl Validate for ClassRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        if let Some(description) = &self.description {
+            v.max_chars("description", description, 2_000);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClassListQuery {
+    /// Only classes in this school year, archived or not.
+    pub school_year_id: Option<i64>,
+    /// Also list classes in archived school years.
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+const CLASS_SELECT: &str = r#"
+    SELECT c.id, c.name, c.description,
+           (SELECT COUNT(*) FROM enrollments e JOIN students s ON s.id = e.student_id
+            WHERE e.class_id = c.id AND s.deleted_at IS NULL) AS student_count,
+           c.school_year_id, c.created_at, c.updated_at
+    FROM classes c
+"#;
+
+/// Classes in archived school years are left out unless asked for.
+pub async fn list_classes(
+    State(state): State<AppState>,
+    Query(query): Query<ClassListQuery>,
+) -> Result<Json<Vec<Class>>, AppError> {
+    let rows = sqlx::query_as::<_, Class>(&format!(
+        r#"
+        {CLASS_SELECT}
+        WHERE c.school_id = ?
+          AND (? IS NULL OR c.school_year_id = ?)
+          AND (? IS NOT NULL OR ? OR c.school_year_id IS NULL
+               OR c.school_year_id NOT IN
+               (SELECT id FROM school_years WHERE archived_at IS NOT NULL))
+        ORDER BY c.name ASC
+        "#
+    ))
+    .bind(auth::school_id())
+    .bind(query.school_year_id)
+    .bind(query.school_year_id)
+    .bind(query.school_year_id)
+    .bind(query.include_archived)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_class(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Class>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn create_class(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<ClassRequest>,
+) -> Result<Json<Class>, AppError> {
+    let name = payload.name.trim().to_string();
+    if let Some(school_year_id) = payload.school_year_id {
+        school_years::check_reference(&state.pool, "school_year_id", school_year_id).await?;
+    }
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO classes(name, description, school_year_id, school_id) \
+         VALUES(?, ?, ?, ?) RETURNING id",
+    )
+    .bind(&name)
+    .bind(&payload.description)
+    .bind(payload.school_year_id)
+    .bind(auth::school_id())
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| AppError::unique_conflict(err, || format!("class '{name}' already exists")))?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn update_class(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<ClassRequest>,
+) -> Result<Json<Class>, AppError> {
+    let name = payload.name.trim().to_string();
+    if let Some(school_year_id) = payload.school_year_id {
+        school_years::check_reference(&state.pool, "school_year_id", school_year_id).await?;
+    }
+    school_years::ensure_class_writable(&state.pool, id).await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE classes
+        SET name = ?, description = ?, school_year_id = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND school_id = ?
+        "#,
+    )
+    .bind(&name)
+    .bind(&payload.description)
+    .bind(payload.school_year_id)
+    .bind(id)
+    .bind(auth::school_id())
+    .execute(&state.pool)
+    .await
+    .map_err(|err| AppError::unique_conflict(err, || format!("class '{name}' already exists")))?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("class {id}")));
+    }
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Deletes the class and its enrollments; the students themselves stay.
+pub async fn delete_class(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    school_years::ensure_class_writable(&state.pool, id).await?;
+    let result = sqlx::query("DELETE FROM classes WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("class {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The class roster, by name.
+pub async fn list_roster(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<Student>>, AppError> {
+    ensure_exists(&state.pool, id).await?;
+
+    let rows = sqlx::query_as::<_, Student>(&format!(
+        r#"
+        SELECT {STUDENT_COLUMNS}
+        FROM students
+        WHERE deleted_at IS NULL
+          AND id IN (SELECT student_id FROM enrollments WHERE class_id = ?)
+        ORDER BY name ASC, id ASC
+        "#
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Enrolls the student. Enrolling someone already in the class returns the
+/// existing enrollment.
+pub async fn enroll_student(
+    State(state): State<AppState>,
+    Path((class_id, student_id)): Path<(i64, i64)>,
+) -> Result<Json<Enrollment>, AppError> {
+    ensure_exists(&state.pool, class_id).await?;
+    students::ensure_exists(&state.pool, student_id).await?;
+    school_years::ensure_class_writable(&state.pool, class_id).await?;
+
+    sqlx::query(
+        "INSERT INTO enrollments(class_id, student_id) VALUES(?, ?) \
+         ON CONFLICT(class_id, student_id) DO NOTHING",
+    )
+    .bind(class_id)
+    .bind(student_id)
+    .execute(&state.pool)
+    .await?;
+
+    let enrollment = sqlx::query_as::<_, Enrollment>(
+        "SELECT class_id, student_id, enrolled_at FROM enrollments \
+         WHERE class_id = ? AND student_id = ?",
+    )
+    .bind(class_id)
+    .bind(student_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(enrollment))
+}
+
+pub async fn unenroll_student(
+    State(state): State<AppState>,
+    Path((class_id, student_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    ensure_exists(&state.pool, class_id).await?;
+    school_years::ensure_class_writable(&state.pool, class_id).await?;
+    let result = sqlx::query("DELETE FROM enrollments WHERE class_id = ? AND student_id = ?")
+        .bind(class_id)
+        .bind(student_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "student {student_id} in class {class_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<Class, AppError> {
+    sqlx::query_as::<_, Class>(&format!(
+        "{CLASS_SELECT} WHERE c.id = ? AND c.school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("class {id}")))
+}
+
+/// Fails with 404 unless the class exists in the caller's school.
+pub async fn ensure_exists(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM classes WHERE id = ? AND school_id = ?)")
+            .bind(id)
+            .bind(auth::school_id())
+            .fetch_one(pool)
+            .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("class {id}")));
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::{guardians::CONSENT_STATUSES, students},
+    validation::{ValidJson, Validate, Validator},
+};
+
+/// Whether the family has agreed to the student using the AI tutor.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StudentConsent {
+    pub student_id: i64,
+    /// `pending` (until recorded), `granted`, `declined`, or `withdrawn`.
+    pub status: String,
+    pub consent_date: Option<String>,
+    /// Where the signed form is kept.
+    pub document_ref: Option<String>,
+    pub recorded_by: Option<i64>,
+    /// `null` while nothing has been recorded.
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConsentRequest {
+    pub status: String,
+    pub consent_date: Option<String>,
+    pub document_ref: Option<String>,
+}
+
+impl Validate for ConsentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("status", &self.status, CONSENT_STATUSES);
+        if let Some(date) = &self.consent_date {
+            v.date("consent_date", date);
+        }
+        if let Some(document_ref) = &self.document_ref {
+            v.required("document_ref", document_ref);
+            v.max_chars("document_ref", document_ref, 500);
+        }
+    }
+}
+
+pub async fn get_student_consent(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<StudentConsent>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    load(&state.pool, student_id).await.map(Json)
+}
+
+/// Replaces the student's consent record.
+pub async fn update_student_consent(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<ConsentRequest>,
+) -> Result<Json<StudentConsent>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO student_consents(student_id, status, consent_date, document_ref, recorded_by)
+        VALUES(?, ?, ?, ?, ?)
+        ON CONFLICT(student_id) DO UPDATE SET
+            status = excluded.status,
+            consent_date = excluded.consent_date,
+            document_ref = excluded.document_ref,
+            recorded_by = excluded.recorded_by,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(student_id)
+    .bind(&payload.status)
+    .bind(&payload.consent_date)
+    .bind(payload.document_ref.as_deref().map(str::trim))
+    .bind(auth::caller().map(|caller| caller.id))
+    .execute(&state.pool)
+    .await?;
+
+    load(&state.pool, student_id).await.map(Json)
+}
+
+/// With `REQUIRE_AI_CONSENT=true`, refuses to send anything about a student
+/// to the model until their consent is `granted`.
+pub async fn ensure_granted(state: &AppState, student_id: i64) -> Result<(), AppError> {
+    if !state.config.require_ai_consent {
+        return Ok(());
+    }
+    let consent = load(&state.pool, student_id).await?;
+    if consent.status != "granted" {
+        return Err(AppError::Forbidden(format!(
+            "student {student_id} has no granted consent for AI use (status: {})",
+            consent.status
+        )));
+    }
+    Ok(())
+}
+
+async fn load(pool: &SqlitePool, student_id: i64) -> Result<StudentConsent, AppError> {
+    let row = sqlx::query_as::<_, StudentConsent>(
+        r#"
+        SELECT student_id, status, consent_date, document_ref, recorded_by, updated_at
+        FROM student_consents
+        WHERE student_id = ?
+        "#,
+    )
+    .bind(student_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.unwrap_or(StudentConsent {
+        student_id,
+        status: "pending".to_string(),
+        consent_date: None,
+        document_ref: None,
+        recorded_by: None,
+        updated_at: None,
+    }))
+}
@@ -0,0 +1,253 @@
+use std::path::{Component, Path as FsPath};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlJson;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    config::Config,
+    deployments::{self, Deployment, DEPLOYMENT_COLUMNS},
+    docker::ContainerStatus,
+    error::AppError,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize)]
+pub struct DeploymentStatus {
+    #[serde(flatten)]
+    pub deployment: Deployment,
+    pub base_url: String,
+    /// `None` when Docker couldn't be reached or the container is missing.
+    pub container: Option<ContainerStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDeploymentRequest {
+    /// Also the `model` value chat and embeddings requests use to reach it.
+    pub name: String,
+    pub image: String,
+    /// Host path of a GGUF file or safetensors directory.
+    pub model_path: String,
+    pub host_port: i64,
+    pub container_port: Option<i64>,
+    /// `all` or comma-separated device ids.
+    pub gpus: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Validate for CreateDeploymentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 60);
+        if !self
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            v.error("name", "may only contain letters, digits, '-', '_', '.'");
+        }
+        v.required("image", &self.image);
+        v.max_chars("image", &self.image, 200);
+        v.required("model_path", &self.model_path);
+        if !self.model_path.starts_with('/') {
+            v.error("model_path", "must be an absolute host path");
+        }
+        v.range("host_port", self.host_port, 1, 65_535);
+        if let Some(port) = self.container_port {
+            v.range("container_port", port, 1, 65_535);
+        }
+        if let Some(gpus) = &self.gpus {
+            v.required("gpus", gpus);
+        }
+    }
+}
+
+pub async fn list_deployments(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DeploymentStatus>>, AppError> {
+    let mut statuses = Vec::new();
+    for deployment in deployments::list(&state.pool).await? {
+        statuses.push(with_status(&state, deployment).await);
+    }
+    Ok(Json(statuses))
+}
+
+pub async fn get_deployment(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<DeploymentStatus>, AppError> {
+    let deployment = load(&state, &name).await?;
+    Ok(Json(with_status(&state, deployment).await))
+}
+
+/// Stores the deployment, then creates and starts its container. If Docker
+/// fails the row is removed again so the request can be retried as is.
+pub async fn create_deployment(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<CreateDeploymentRequest>,
+) -> Result<Json<DeploymentStatus>, AppError> {
+    user.require_admin()?;
+    check_allowed(&state.config, &payload)?;
+
+    let deployment = sqlx::query_as::<_, Deployment>(&format!(
+        r#"
+        INSERT INTO model_deployments(name, image, model_path, host_port, container_port, gpus, args)
+        VALUES(?, ?, ?, ?, ?, ?, ?)
+        RETURNING {DEPLOYMENT_COLUMNS}
+        "#
+    ))
+    .bind(&payload.name)
+    .bind(&payload.image)
+    .bind(&payload.model_path)
+    .bind(payload.host_port)
+    .bind(payload.container_port.unwrap_or(8000))
+    .bind(payload.gpus.as_deref())
+    .bind(SqlJson(&payload.args))
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || {
+            format!(
+                "a deployment named '{}' or on port {} already exists",
+                payload.name, payload.host_port
+            )
+        })
+    })?;
+
+    let started = match deployment.container(&state) {
+        Ok(container) => container.ensure_running(&deployment.spec()).await,
+        Err(err) => Err(err),
+    };
+    let container = match started {
+        Ok(container) => container,
+        Err(err) => {
+            sqlx::query("DELETE FROM model_deployments WHERE id = ?")
+                .bind(deployment.id)
+                .execute(&state.pool)
+                .await?;
+            return Err(err);
+        }
+    };
+
+    let base_url = deployment.base_url(&state.config.docker_deployment_host);
+    state
+        .upstreams
+        .set_deployment(&deployment.name, base_url.clone());
+
+    Ok(Json(DeploymentStatus {
+        deployment,
+        base_url,
+        container: Some(container),
+    }))
+}
+
+pub async fn start_deployment(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<Json<ContainerStatus>, AppError> {
+    user.require_admin()?;
+    let deployment = load(&state, &name).await?;
+    deployment
+        .container(&state)?
+        .ensure_running(&deployment.spec())
+        .await
+        .map(Json)
+}
+
+pub async fn stop_deployment(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<Json<ContainerStatus>, AppError> {
+    user.require_admin()?;
+    let deployment = load(&state, &name).await?;
+    deployment.container(&state)?.stop().await.map(Json)
+}
+
+/// Removes the container as well as the deployment.
+pub async fn delete_deployment(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let deployment = load(&state, &name).await?;
+    deployment.container(&state)?.remove().await?;
+    state.upstreams.remove_deployment(&deployment.name);
+
+    sqlx::query("DELETE FROM model_deployments WHERE id = ?")
+        .bind(deployment.id)
+        .execute(&state.pool)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The container runs with GPUs and a host bind mount, so only known images
+/// and models under `MODELS_DIR` may be used.
+fn check_allowed(cfg: &Config, payload: &CreateDeploymentRequest) -> Result<(), AppError> {
+    let mut v = Validator::default();
+    let image = payload.image.trim();
+    let allowed = cfg.docker_deployment_images.iter().any(|entry| {
+        image == entry
+            || image
+                .strip_prefix(entry.as_str())
+                .is_some_and(|tag| tag.starts_with(':') && !entry.contains(':'))
+    });
+    if !allowed {
+        v.error("image", "is not listed in DOCKER_DEPLOYMENT_IMAGES");
+    }
+
+    let model_path = FsPath::new(&payload.model_path);
+    let escapes = model_path
+        .components()
+        .any(|c| matches!(c, Component::ParentDir | Component::CurDir));
+    if escapes || !model_path.starts_with(&cfg.models_dir) {
+        v.error(
+            "model_path",
+            format!("must be inside MODELS_DIR ({})", cfg.models_dir),
+        );
+    }
+    v.finish()
+}
+
+async fn load(state: &AppState, name: &str) -> Result<Deployment, AppError> {
+    sqlx::query_as::<_, Deployment>(&format!(
+        "SELECT {DEPLOYMENT_COLUMNS} FROM model_deployments WHERE name = ?"
+    ))
+    .bind(name)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("deployment '{name}'")))
+}
+
+async fn with_status(state: &AppState, deployment: Deployment) -> DeploymentStatus {
+    let container = match deployment.container(state) {
+        Ok(container) => container.status().await,
+        Err(err) => Err(err),
+    };
+    let container = match container {
+        Ok(status) => Some(status),
+        Err(AppError::NotFound(_)) => None,
+        Err(err) => {
+            warn!(name = %deployment.name, error = %err, "failed to inspect deployment container");
+            None
+        }
+    };
+
+    DeploymentStatus {
+        base_url: deployment.base_url(&state.config.docker_deployment_host),
+        deployment,
+        container,
+    }
+}
@@ -0,0 +1,369 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    routes::{assignments, students, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_ESSAY_CHARS: usize = 30_000;
+const MAX_COMMENTS: usize = 30;
+pub const FEEDBACK_CATEGORIES: &[&str] = &[
+    "grammar",
+    "clarity",
+    "structure",
+    "evidence",
+    "style",
+    "other",
+];
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Essay {
+    pub id: i64,
+    pub student_id: i64,
+    pub assignment_id: Option<i64>,
+    pub title: String,
+    pub draft_count: i64,
+    pub created_at: String,
+}
+
+/// A comment anchored to a passage of the draft. `offset` is the character
+/// position of `excerpt` in the body, or `null` if the model misquoted it.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InlineComment {
+    pub excerpt: String,
+    pub offset: Option<usize>,
+    pub category: String,
+    pub comment: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EssayFeedback {
+    pub summary: String,
+    /// What changed since the previous draft; `null` for the first one.
+    pub revision_summary: Option<String>,
+    pub comments: Vec<InlineComment>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct EssayDraft {
+    pub essay_id: i64,
+    pub version: i64,
+    pub body: String,
+    pub word_count: i64,
+    pub feedback: SqlJson<EssayFeedback>,
+    /// The `ai_interactions` row for the feedback request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EssayDetail {
+    #[serde(flatten)]
+    pub essay: Essay,
+    /// Oldest first.
+    pub drafts: Vec<EssayDraft>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EssayRequest {
+    pub title: String,
+    pub assignment_id: Option<i64>,
+}
+
+impl Validate for EssayRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("title", &self.title);
+        v.max_chars("title", &self.title, 200);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DraftRequest {
+    pub body: String,
+    pub model: Option<String>,
+    /// Teacher the feedback request is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for DraftRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("body", &self.body);
+        v.max_chars("body", &self.body, MAX_ESSAY_CHARS);
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct ModelFeedback {
+    summary: String,
+    #[serde(default)]
+    revision_summary: Option<String>,
+    #[serde(default)]
+    comments: Vec<ModelComment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelComment {
+    excerpt: String,
+    #[serde(default)]
+    category: String,
+    comment: String,
+}
+
+const ESSAY_SELECT: &str = r#"
+    SELECT e.id, e.student_id, e.assignment_id, e.title,
+           (SELECT COUNT(*) FROM essay_drafts d WHERE d.essay_id = e.id) AS draft_count,
+           e.created_at
+    FROM essays e
+"#;
+
+const DRAFT_COLUMNS: &str =
+    "essay_id, version, body, word_count, feedback, generation_id, created_at";
+
+pub async fn list_essays(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<Essay>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, Essay>(&format!(
+        "{ESSAY_SELECT} WHERE e.student_id = ? ORDER BY e.created_at DESC, e.id DESC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_essay(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<EssayRequest>,
+) -> Result<Json<Essay>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    if let Some(assignment_id) = payload.assignment_id {
+        let assignment = assignments::load(&state.pool, assignment_id).await?;
+        if assignment.student_id != student_id {
+            let mut v = Validator::default();
+            v.error("assignment_id", "belongs to a different student");
+            v.finish()?;
+        }
+    }
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO essays(student_id, assignment_id, title) VALUES(?, ?, ?) RETURNING id",
+    )
+    .bind(student_id)
+    .bind(payload.assignment_id)
+    .bind(payload.title.trim())
+    .fetch_one(&state.pool)
+    .await?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+/// The essay with every draft and its feedback, oldest first, so teachers
+/// can follow the revisions.
+pub async fn get_essay(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<EssayDetail>, AppError> {
+    let essay = load(&state.pool, id).await?;
+    let drafts = sqlx::query_as::<_, EssayDraft>(&format!(
+        "SELECT {DRAFT_COLUMNS} FROM essay_drafts WHERE essay_id = ? ORDER BY version ASC"
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(EssayDetail { essay, drafts }))
+}
+
+pub async fn delete_essay(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM essays WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("essay {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_draft(
+    State(state): State<AppState>,
+    Path((id, version)): Path<(i64, i64)>,
+) -> Result<Json<EssayDraft>, AppError> {
+    load(&state.pool, id).await?;
+
+    sqlx::query_as::<_, EssayDraft>(&format!(
+        "SELECT {DRAFT_COLUMNS} FROM essay_drafts WHERE essay_id = ? AND version = ?"
+    ))
+    .bind(id)
+    .bind(version)
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or_else(|| AppError::NotFound(format!("draft {version} of essay {id}")))
+}
+
+/// Adds the next draft. The model gets the previous draft and its feedback
+/// too, so it can say what improved and what still needs work. Nothing is
+/// stored if the feedback request fails.
+pub async fn submit_draft(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<DraftRequest>,
+) -> Result<Json<EssayDraft>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let essay = load(&state.pool, id).await?;
+    let grade_level = students::grade_level(&state.pool, essay.student_id).await?;
+    let previous = sqlx::query_as::<_, EssayDraft>(&format!(
+        r#"
+        SELECT {DRAFT_COLUMNS}
+        FROM essay_drafts
+        WHERE essay_id = ?
+        ORDER BY version DESC
+        LIMIT 1
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let body = payload.body.trim();
+    let mut request = vec![
+        format!("Essay title: {}", essay.title),
+        format!(
+            "Student grade level: {}",
+            grade_level.as_deref().unwrap_or("unknown")
+        ),
+    ];
+    if let Some(previous) = &previous {
+        request.push(format!(
+            "Previous draft (version {}):\n---\n{}\n---\nFeedback given on it: {}",
+            previous.version, previous.body, previous.feedback.summary
+        ));
+    }
+    request.push(format!("Current draft:\n---\n{body}\n---"));
+
+    let (feedback, completion) = assist::complete_json::<ModelFeedback>(
+        &state,
+        Prompt {
+            system: format!(
+                "You give writing feedback on student essay drafts. Be encouraging and \
+                 specific. Quote short passages of the current draft exactly and comment \
+                 on each; categories are {}. If there is a previous draft, say in \
+                 revision_summary what the student changed and whether it helped; \
+                 otherwise set it to null. Reply with only a JSON object: \
+                 {{\"summary\": string, \"revision_summary\": string or null, \
+                 \"comments\": [{{\"excerpt\": string, \"category\": string, \
+                 \"comment\": string}}]}}.",
+                FEEDBACK_CATEGORIES.join(", ")
+            ),
+            user: request.join("\n\n"),
+            model: payload.model,
+            temperature: Some(0.3),
+            max_tokens: Some(3_000),
+            user_id: payload.user_id,
+            student_id: Some(essay.student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let feedback = EssayFeedback {
+        summary: feedback.summary.trim().to_string(),
+        revision_summary: previous
+            .as_ref()
+            .and(feedback.revision_summary)
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()),
+        comments: feedback
+            .comments
+            .into_iter()
+            .filter(|c| !c.excerpt.trim().is_empty() && !c.comment.trim().is_empty())
+            .take(MAX_COMMENTS)
+            .map(|c| anchor(body, c))
+            .collect(),
+    };
+    let word_count = body.split_whitespace().count() as i64;
+
+    // Numbering inside the insert keeps concurrent submissions from
+    // colliding on a version.
+    let saved = sqlx::query_as::<_, EssayDraft>(&format!(
+        r#"
+        INSERT INTO essay_drafts(essay_id, version, body, word_count, feedback, generation_id)
+        SELECT ?, COALESCE(MAX(version), 0) + 1, ?, ?, ?, ?
+        FROM essay_drafts
+        WHERE essay_id = ?
+        RETURNING {DRAFT_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(body)
+    .bind(word_count)
+    .bind(SqlJson(&feedback))
+    .bind(completion.interaction_id)
+    .bind(id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+/// Locates the quoted passage in the draft and normalizes the category.
+fn anchor(body: &str, comment: ModelComment) -> InlineComment {
+    let excerpt = comment.excerpt.trim().to_string();
+    let offset = body.find(&excerpt).map(|byte| body[..byte].chars().count());
+    let category = comment.category.trim().to_lowercase();
+    let category = if FEEDBACK_CATEGORIES.contains(&category.as_str()) {
+        category
+    } else {
+        "other".to_string()
+    };
+
+    InlineComment {
+        excerpt,
+        offset,
+        category,
+        comment: comment.comment.trim().to_string(),
+    }
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<Essay, AppError> {
+    let essay = sqlx::query_as::<_, Essay>(&format!("{ESSAY_SELECT} WHERE e.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("essay {id}")))?;
+
+    students::ensure_record_visible(pool, essay.student_id, || format!("essay {id}")).await?;
+    Ok(essay)
+}
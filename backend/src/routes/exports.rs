@@ -0,0 +1,177 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    research_export::{self, ExportParams},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const DEFAULT_K_THRESHOLD: i64 = 5;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportJob {
+    pub id: i64,
+    pub requested_by: Option<i64>,
+    pub k_threshold: i64,
+    pub status: String,
+    pub row_count: Option<i64>,
+    pub suppressed_count: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportJobEvent {
+    pub event: String,
+    pub detail: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportJobDetail {
+    #[serde(flatten)]
+    pub job: ExportJob,
+    pub events: Vec<ExportJobEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateExportRequest {
+    pub k_threshold: Option<i64>,
+}
+
+impl Validate for CreateExportRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(k) = self.k_threshold {
+            v.range("k_threshold", k, 2, 1000);
+        }
+    }
+}
+
+const JOB_COLUMNS: &str = "id, requested_by, k_threshold, status, row_count, suppressed_count, \
+                           error, created_at, finished_at";
+
+pub async fn create_export(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<CreateExportRequest>,
+) -> Result<Json<ExportJob>, AppError> {
+    user.require_admin()?;
+    let Some(salt) = state.config.export_hash_salt.clone() else {
+        return Err(AppError::BadRequest(
+            "EXPORT_HASH_SALT must be set before exporting".to_string(),
+        ));
+    };
+
+    let k_threshold = payload.k_threshold.unwrap_or(DEFAULT_K_THRESHOLD);
+    let school_id = auth::school_id();
+
+    let job = sqlx::query_as::<_, ExportJob>(&format!(
+        "INSERT INTO export_jobs(requested_by, k_threshold, school_id) VALUES(?, ?, ?) \
+         RETURNING {JOB_COLUMNS}"
+    ))
+    .bind(user.id)
+    .bind(k_threshold)
+    .bind(school_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let detail = format!("requested by user {}", user.id);
+    research_export::log_event(&state.pool, job.id, "queued", Some(&detail)).await?;
+
+    tokio::spawn(research_export::run(
+        state.pool.clone(),
+        ExportParams {
+            job_id: job.id,
+            k_threshold,
+            dir: state.config.export_dir.clone(),
+            salt,
+            school_id,
+        },
+    ));
+
+    Ok(Json(job))
+}
+
+pub async fn list_exports(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<ExportJob>>, AppError> {
+    user.require_admin()?;
+    let rows = sqlx::query_as::<_, ExportJob>(&format!(
+        "SELECT {JOB_COLUMNS} FROM export_jobs WHERE school_id = ? ORDER BY id DESC"
+    ))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_export(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Json<ExportJobDetail>, AppError> {
+    user.require_admin()?;
+    let job = sqlx::query_as::<_, ExportJob>(&format!(
+        "SELECT {JOB_COLUMNS} FROM export_jobs WHERE id = ? AND school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("export job {id}")))?;
+
+    let events = sqlx::query_as::<_, ExportJobEvent>(
+        "SELECT event, detail, created_at FROM export_job_events WHERE job_id = ? ORDER BY id ASC",
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ExportJobDetail { job, events }))
+}
+
+pub async fn download_export(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_admin()?;
+    let file_path = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT file_path FROM export_jobs WHERE id = ? AND school_id = ? AND status = 'completed'",
+    )
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .flatten();
+    let file_path =
+        file_path.ok_or_else(|| AppError::NotFound(format!("completed export job {id}")))?;
+
+    let body = tokio::fs::read(&file_path)
+        .await
+        .map_err(|err| AppError::Internal(format!("export file error: {err}")))?;
+
+    research_export::log_event(&state.pool, id, "downloaded", None).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"research-export-{id}.jsonl\""),
+            ),
+        ],
+        body,
+    ))
+}
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    family_digest::{self, FamilyDigest, DIGEST_SELECT},
+    routes::{students, users},
+    trace::TraceHeaders,
+    validation::{FieldErrors, ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct FamilyDigestRequest {
+    /// Monday of the week to cover; defaults to the last full week.
+    pub week_start: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for FamilyDigestRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(week_start) = &self.week_start {
+            v.date("week_start", week_start);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FamilyDigestListQuery {
+    pub week_start: Option<String>,
+}
+
+/// Writes the student's digest now instead of waiting for the weekly job,
+/// replacing any digest already stored for that week.
+pub async fn create_family_digest(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<FamilyDigestRequest>,
+) -> Result<Json<FamilyDigest>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+
+    let week_start = match payload.week_start {
+        Some(week_start) => {
+            let monday: bool = sqlx::query_scalar("SELECT strftime('%w', ?) = '1'")
+                .bind(&week_start)
+                .fetch_one(&state.pool)
+                .await?;
+            if !monday {
+                let mut v = Validator::default();
+                v.error("week_start", "must be a Monday");
+                v.finish()?;
+            }
+            week_start
+        }
+        None => {
+            sqlx::query_scalar("SELECT date('now', 'weekday 0', '-13 days')")
+                .fetch_one(&state.pool)
+                .await?
+        }
+    };
+
+    let digest = family_digest::generate(
+        &state,
+        student_id,
+        &week_start,
+        payload.model,
+        payload.user_id,
+        trace,
+    )
+    .await?;
+
+    digest.map(Json).ok_or_else(|| {
+        let mut errors = FieldErrors::new();
+        errors.insert(
+            "week_start".to_string(),
+            vec![format!(
+                "the student has no activity in the week of {week_start}"
+            )],
+        );
+        AppError::Validation(errors)
+    })
+}
+
+/// The student's digests, most recent week first.
+pub async fn list_student_family_digests(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<FamilyDigest>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, FamilyDigest>(&format!(
+        "{DIGEST_SELECT} WHERE d.student_id = ? ORDER BY d.week_start DESC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Digests across students for exporting or emailing, most recent week
+/// first, then by student name.
+pub async fn list_family_digests(
+    State(state): State<AppState>,
+    Query(query): Query<FamilyDigestListQuery>,
+) -> Result<Json<Vec<FamilyDigest>>, AppError> {
+    let mut v = Validator::default();
+    if let Some(week_start) = &query.week_start {
+        v.date("week_start", week_start);
+    }
+    v.finish()?;
+
+    let rows = sqlx::query_as::<_, FamilyDigest>(&format!(
+        r#"
+        {DIGEST_SELECT}
+        WHERE s.school_id = ? AND (? IS NULL OR d.week_start = ?)
+        ORDER BY d.week_start DESC, s.name ASC
+        "#
+    ))
+    .bind(auth::school_id())
+    .bind(&query.week_start)
+    .bind(&query.week_start)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_family_digest(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<FamilyDigest>, AppError> {
+    sqlx::query_as::<_, FamilyDigest>(&format!(
+        "{DIGEST_SELECT} WHERE d.id = ? AND s.school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or_else(|| AppError::NotFound(format!("family digest {id}")))
+}
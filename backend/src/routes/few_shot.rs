@@ -0,0 +1,229 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    routes::students::GRADE_LEVELS,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_TEXT_CHARS: usize = 10_000;
+/// Examples considered per request before ranking.
+const CANDIDATE_LIMIT: i64 = 500;
+pub const DEFAULT_COUNT: usize = 3;
+pub const MAX_COUNT: usize = 10;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FewShotExample {
+    pub id: i64,
+    pub subject: String,
+    pub grade_level: Option<String>,
+    pub question: String,
+    pub answer: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FewShotExampleRequest {
+    pub subject: String,
+    pub grade_level: Option<String>,
+    pub question: String,
+    pub answer: String,
+}
+
+impl Validate for FewShotExampleRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("subject", &self.subject);
+        v.max_chars("subject", &self.subject, 100);
+        if let Some(grade) = &self.grade_level {
+            v.one_of("grade_level", grade, GRADE_LEVELS);
+        }
+        v.required("question", &self.question);
+        v.max_chars("question", &self.question, MAX_TEXT_CHARS);
+        v.required("answer", &self.answer);
+        v.max_chars("answer", &self.answer, MAX_TEXT_CHARS);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FewShotQuery {
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+}
+
+const EXAMPLE_COLUMNS: &str = "id, subject, grade_level, question, answer, created_at, updated_at";
+
+pub async fn list_examples(
+    State(state): State<AppState>,
+    Query(query): Query<FewShotQuery>,
+) -> Result<Json<Vec<FewShotExample>>, AppError> {
+    let rows = sqlx::query_as::<_, FewShotExample>(&format!(
+        r#"
+        SELECT {EXAMPLE_COLUMNS}
+        FROM few_shot_examples
+        WHERE (? IS NULL OR subject = ?) AND (? IS NULL OR grade_level = ?)
+        ORDER BY subject ASC, id ASC
+        "#
+    ))
+    .bind(&query.subject)
+    .bind(&query.subject)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_example(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<FewShotExampleRequest>,
+) -> Result<Json<FewShotExample>, AppError> {
+    user.require_admin()?;
+    let created = sqlx::query_as::<_, FewShotExample>(&format!(
+        r#"
+        INSERT INTO few_shot_examples(subject, grade_level, question, answer)
+        VALUES(?, ?, ?, ?)
+        RETURNING {EXAMPLE_COLUMNS}
+        "#
+    ))
+    .bind(payload.subject.trim())
+    .bind(&payload.grade_level)
+    .bind(&payload.question)
+    .bind(&payload.answer)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn update_example(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<FewShotExampleRequest>,
+) -> Result<Json<FewShotExample>, AppError> {
+    user.require_admin()?;
+    let updated = sqlx::query_as::<_, FewShotExample>(&format!(
+        r#"
+        UPDATE few_shot_examples
+        SET subject = ?, grade_level = ?, question = ?, answer = ?,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING {EXAMPLE_COLUMNS}
+        "#
+    ))
+    .bind(payload.subject.trim())
+    .bind(&payload.grade_level)
+    .bind(&payload.question)
+    .bind(&payload.answer)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("few-shot example {id}")))
+}
+
+pub async fn delete_example(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let result = sqlx::query("DELETE FROM few_shot_examples WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("few-shot example {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Picks the `count` examples for `subject` that share the most words with
+/// `query`, preferring ones written for the student's grade over ones that
+/// apply to every grade.
+pub async fn select(
+    pool: &SqlitePool,
+    subject: &str,
+    grade_level: Option<&str>,
+    query: &str,
+    count: usize,
+) -> Result<Vec<FewShotExample>, AppError> {
+    let candidates = sqlx::query_as::<_, FewShotExample>(&format!(
+        r#"
+        SELECT {EXAMPLE_COLUMNS}
+        FROM few_shot_examples
+        WHERE subject = ? AND (grade_level IS NULL OR ? IS NULL OR grade_level = ?)
+        ORDER BY id DESC
+        LIMIT ?
+        "#
+    ))
+    .bind(subject)
+    .bind(grade_level)
+    .bind(grade_level)
+    .bind(CANDIDATE_LIMIT)
+    .fetch_all(pool)
+    .await?;
+
+    let query_words = words(query);
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .map(|example| {
+            let overlap = words(&example.question).intersection(&query_words).count();
+            let same_grade = grade_level.is_some() && example.grade_level.as_deref() == grade_level;
+            ((overlap, same_grade), example)
+        })
+        .collect();
+    // Stable sort keeps newest-first among equal scores.
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+
+    Ok(scored
+        .into_iter()
+        .take(count)
+        .map(|(_, example)| example)
+        .collect())
+}
+
+/// Inserts examples as user/assistant turns after any leading system
+/// messages, so they read as earlier exchanges in the conversation.
+pub fn inject(payload: &mut Value, examples: &[FewShotExample]) {
+    let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    let at = messages
+        .iter()
+        .take_while(|m| m.get("role").and_then(Value::as_str) == Some("system"))
+        .count();
+    let turns = examples.iter().flat_map(|example| {
+        [
+            json!({ "role": "user", "content": example.question }),
+            json!({ "role": "assistant", "content": example.answer }),
+        ]
+    });
+    messages.splice(at..at, turns);
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
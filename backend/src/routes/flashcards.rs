@@ -0,0 +1,435 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    auth,
+    error::AppError,
+    routes::{
+        students::{self, GRADE_LEVELS},
+        users,
+    },
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const MAX_CARDS: usize = 50;
+const DEFAULT_CARDS: usize = 10;
+const MAX_DOCUMENT_CHARS: usize = 20_000;
+/// Days until the next review for boxes 1 through 5.
+const BOX_INTERVALS: [i64; 5] = [1, 2, 4, 8, 16];
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Deck {
+    pub id: i64,
+    pub title: String,
+    pub topic: Option<String>,
+    pub grade_level: Option<String>,
+    pub card_count: i64,
+    /// The `ai_interactions` row for the generation request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Flashcard {
+    pub id: i64,
+    pub deck_id: i64,
+    pub position: i64,
+    pub front: String,
+    pub back: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeckDetail {
+    #[serde(flatten)]
+    pub deck: Deck,
+    pub cards: Vec<Flashcard>,
+}
+
+/// A card as the student sees it, with their review state. Cards they have
+/// never reviewed have no `box` or `due_date`.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StudyCard {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub card: Flashcard,
+    #[sqlx(rename = "box")]
+    #[serde(rename = "box")]
+    pub box_number: Option<i64>,
+    pub due_date: Option<String>,
+    pub review_count: i64,
+    pub correct_count: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Review {
+    pub student_id: i64,
+    pub card_id: i64,
+    #[sqlx(rename = "box")]
+    #[serde(rename = "box")]
+    pub box_number: i64,
+    pub due_date: String,
+    pub review_count: i64,
+    pub correct_count: i64,
+    pub last_correct: bool,
+    pub last_reviewed_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateDeckRequest {
+    /// Either a topic or the text of a document to draw cards from.
+    pub topic: Option<String>,
+    pub document: Option<String>,
+    pub card_count: Option<usize>,
+    /// Targets the student's grade level; overrides `grade_level`.
+    pub student_id: Option<i64>,
+    pub grade_level: Option<String>,
+    /// Defaults to the topic; required for documents.
+    pub title: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for GenerateDeckRequest {
+    fn validate(&self, v: &mut Validator) {
+        match (&self.topic, &self.document) {
+            (Some(topic), None) => {
+                v.required("topic", topic);
+                v.max_chars("topic", topic, 500);
+            }
+            (None, Some(document)) => {
+                v.required("document", document);
+                v.max_chars("document", document, MAX_DOCUMENT_CHARS);
+                if self.title.is_none() {
+                    v.error("title", "is required when generating from a document");
+                }
+            }
+            _ => v.error("topic", "exactly one of topic or document is required"),
+        }
+        if let Some(count) = self.card_count {
+            v.range("card_count", count, 1, MAX_CARDS);
+        }
+        if let Some(grade) = &self.grade_level {
+            v.one_of("grade_level", grade, GRADE_LEVELS);
+        }
+        if let Some(title) = &self.title {
+            v.required("title", title);
+            v.max_chars("title", title, 200);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewRequest {
+    pub correct: bool,
+}
+
+impl Validate for ReviewRequest {
+    fn validate(&self, _v: &mut Validator) {}
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct GeneratedDeck {
+    cards: Vec<GeneratedCard>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedCard {
+    front: String,
+    back: String,
+}
+
+const DECK_SELECT: &str = r#"
+    SELECT d.id, d.title, d.topic, d.grade_level,
+           (SELECT COUNT(*) FROM flashcards c WHERE c.deck_id = d.id) AS card_count,
+           d.generation_id, d.created_at
+    FROM flashcard_decks d
+"#;
+
+const REVIEW_COLUMNS: &str = "student_id, card_id, box, due_date, review_count, correct_count, \
+                              last_correct, last_reviewed_at";
+
+/// Asks the local model for question/answer cards on a topic or drawn from a
+/// document's text, and stores them as a deck.
+pub async fn generate_deck(
+    State(state): State<AppState>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<GenerateDeckRequest>,
+) -> Result<Json<DeckDetail>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let grade_level = match payload.student_id {
+        Some(student_id) => {
+            students::ensure_exists(&state.pool, student_id).await?;
+            students::grade_level(&state.pool, student_id)
+                .await?
+                .or(payload.grade_level.clone())
+        }
+        None => payload.grade_level.clone(),
+    };
+    let count = payload.card_count.unwrap_or(DEFAULT_CARDS);
+    let topic = payload.topic.as_deref().map(str::trim);
+
+    let mut request = vec![
+        format!("Number of cards: {count}"),
+        format!(
+            "Grade level: {}",
+            grade_level.as_deref().unwrap_or("unspecified")
+        ),
+    ];
+    match (topic, &payload.document) {
+        (Some(topic), _) => request.push(format!("Topic: {topic}")),
+        (None, Some(document)) => request.push(format!(
+            "Use only facts from this document:\n---\n{}\n---",
+            document.trim()
+        )),
+        (None, None) => {}
+    }
+
+    let (generated, completion) = assist::complete_json::<GeneratedDeck>(
+        &state,
+        Prompt {
+            system: "You write study flashcards for school students. Each card has a short \
+                     question or term on the front and a concise answer on the back, at a \
+                     level suited to the grade. Reply with only a JSON object: \
+                     {\"cards\": [{\"front\": string, \"back\": string}]}."
+                .to_string(),
+            user: request.join("\n"),
+            model: payload.model,
+            temperature: Some(0.4),
+            max_tokens: Some(3_000),
+            user_id: payload.user_id,
+            student_id: payload.student_id,
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let cards = generated
+        .cards
+        .into_iter()
+        .map(|card| (card.front.trim().to_string(), card.back.trim().to_string()))
+        .filter(|(front, back)| !front.is_empty() && !back.is_empty())
+        .take(MAX_CARDS)
+        .collect::<Vec<_>>();
+    if cards.is_empty() {
+        return Err(AppError::Upstream(format!(
+            "model returned no usable cards; interaction {}",
+            completion.interaction_id
+        )));
+    }
+
+    let title = payload
+        .title
+        .as_deref()
+        .or(topic)
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let mut tx = state.pool.begin().await?;
+    let deck_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO flashcard_decks(title, topic, grade_level, generation_id, school_id)
+        VALUES(?, ?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(&title)
+    .bind(topic)
+    .bind(&grade_level)
+    .bind(completion.interaction_id)
+    .bind(auth::school_id())
+    .fetch_one(&mut *tx)
+    .await?;
+    for (position, (front, back)) in cards.iter().enumerate() {
+        sqlx::query("INSERT INTO flashcards(deck_id, position, front, back) VALUES(?, ?, ?, ?)")
+            .bind(deck_id)
+            .bind(position as i64)
+            .bind(front)
+            .bind(back)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    load_detail(&state.pool, deck_id).await.map(Json)
+}
+
+/// Newest first.
+pub async fn list_decks(State(state): State<AppState>) -> Result<Json<Vec<Deck>>, AppError> {
+    let rows = sqlx::query_as::<_, Deck>(&format!(
+        "{DECK_SELECT} WHERE d.school_id = ? ORDER BY d.created_at DESC, d.id DESC"
+    ))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_deck(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<DeckDetail>, AppError> {
+    load_detail(&state.pool, id).await.map(Json)
+}
+
+/// Deletes the deck, its cards, and every student's review history for them.
+pub async fn delete_deck(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM flashcard_decks WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("flashcard deck {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Cards the student should study now: those due today or earlier, most
+/// overdue first, followed by cards they have not seen yet in deck order.
+pub async fn due_cards(
+    State(state): State<AppState>,
+    Path((student_id, deck_id)): Path<(i64, i64)>,
+) -> Result<Json<Vec<StudyCard>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    ensure_deck(&state.pool, deck_id).await?;
+
+    let rows = sqlx::query_as::<_, StudyCard>(
+        r#"
+        SELECT c.id, c.deck_id, c.position, c.front, c.back,
+               r.box, r.due_date,
+               COALESCE(r.review_count, 0) AS review_count,
+               COALESCE(r.correct_count, 0) AS correct_count
+        FROM flashcards c
+        LEFT JOIN flashcard_reviews r ON r.card_id = c.id AND r.student_id = ?
+        WHERE c.deck_id = ? AND (r.due_date IS NULL OR r.due_date <= date('now'))
+        ORDER BY r.due_date IS NULL, r.due_date ASC, c.position ASC
+        "#,
+    )
+    .bind(student_id)
+    .bind(deck_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Records one review. A correct answer moves the card up a box (1, 2, 4, 8,
+/// then 16 days until it is due again); a miss sends it back to box 1.
+pub async fn review_card(
+    State(state): State<AppState>,
+    Path((student_id, card_id)): Path<(i64, i64)>,
+    ValidJson(payload): ValidJson<ReviewRequest>,
+) -> Result<Json<Review>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let mut tx = state.pool.begin().await?;
+    let exists = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT 1
+        FROM flashcards c
+        JOIN flashcard_decks d ON d.id = c.deck_id
+        WHERE c.id = ? AND d.school_id = ?
+        "#,
+    )
+    .bind(card_id)
+    .bind(auth::school_id())
+    .fetch_optional(&mut *tx)
+    .await?;
+    if exists.is_none() {
+        return Err(AppError::NotFound(format!("flashcard {card_id}")));
+    }
+
+    let current = sqlx::query_scalar::<_, i64>(
+        "SELECT box FROM flashcard_reviews WHERE student_id = ? AND card_id = ?",
+    )
+    .bind(student_id)
+    .bind(card_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let next_box = match (current, payload.correct) {
+        (_, false) => 1,
+        (None, true) => 2,
+        (Some(current), true) => (current + 1).min(BOX_INTERVALS.len() as i64),
+    };
+    let interval = BOX_INTERVALS[(next_box - 1) as usize];
+
+    let review = sqlx::query_as::<_, Review>(&format!(
+        r#"
+        INSERT INTO flashcard_reviews(
+            student_id, card_id, box, due_date, review_count, correct_count, last_correct
+        )
+        VALUES(?, ?, ?, date('now', ?), 1, ?, ?)
+        ON CONFLICT(student_id, card_id) DO UPDATE SET
+            box = excluded.box,
+            due_date = excluded.due_date,
+            review_count = review_count + 1,
+            correct_count = correct_count + excluded.correct_count,
+            last_correct = excluded.last_correct,
+            last_reviewed_at = CURRENT_TIMESTAMP
+        RETURNING {REVIEW_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(card_id)
+    .bind(next_box)
+    .bind(format!("+{interval} days"))
+    .bind(i64::from(payload.correct))
+    .bind(payload.correct)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(review))
+}
+
+async fn load_detail(pool: &SqlitePool, id: i64) -> Result<DeckDetail, AppError> {
+    let deck =
+        sqlx::query_as::<_, Deck>(&format!("{DECK_SELECT} WHERE d.id = ? AND d.school_id = ?"))
+            .bind(id)
+            .bind(auth::school_id())
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("flashcard deck {id}")))?;
+    let cards = sqlx::query_as::<_, Flashcard>(
+        "SELECT id, deck_id, position, front, back FROM flashcards \
+         WHERE deck_id = ? ORDER BY position ASC",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(DeckDetail { deck, cards })
+}
+
+async fn ensure_deck(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    sqlx::query_scalar::<_, i64>("SELECT 1 FROM flashcard_decks WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .fetch_optional(pool)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| AppError::NotFound(format!("flashcard deck {id}")))
+}
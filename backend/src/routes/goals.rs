@@ -0,0 +1,456 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    routes::{students, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const GOAL_STATUSES: &[&str] = &["active", "met", "discontinued"];
+const MAX_TEXT_CHARS: usize = 2_000;
+/// Evidence entries sent to the model, most recent first.
+const MAX_EVIDENCE: i64 = 60;
+
+/// An IEP or learning goal with how it is measured and when it is due.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Goal {
+    pub id: i64,
+    pub student_id: i64,
+    pub title: String,
+    pub area: Option<String>,
+    pub measurement: String,
+    pub baseline: Option<String>,
+    pub target: String,
+    pub target_date: String,
+    pub status: String,
+    pub evidence_count: i64,
+    pub last_observed_on: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Evidence {
+    pub id: i64,
+    pub goal_id: i64,
+    pub observed_on: String,
+    pub value: Option<f64>,
+    pub note: String,
+    pub recorded_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoalDetail {
+    #[serde(flatten)]
+    pub goal: Goal,
+    /// Oldest first.
+    pub evidence: Vec<Evidence>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ProgressNote {
+    pub id: i64,
+    pub goal_id: i64,
+    pub narrative: String,
+    pub period_start: Option<String>,
+    pub evidence_count: i64,
+    /// The `ai_interactions` row for the draft request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GoalRequest {
+    pub title: String,
+    pub area: Option<String>,
+    pub measurement: String,
+    pub baseline: Option<String>,
+    pub target: String,
+    pub target_date: String,
+    pub status: Option<String>,
+}
+
+impl Validate for GoalRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("title", &self.title);
+        v.max_chars("title", &self.title, 200);
+        if let Some(area) = &self.area {
+            v.max_chars("area", area, 100);
+        }
+        v.required("measurement", &self.measurement);
+        v.max_chars("measurement", &self.measurement, MAX_TEXT_CHARS);
+        if let Some(baseline) = &self.baseline {
+            v.max_chars("baseline", baseline, MAX_TEXT_CHARS);
+        }
+        v.required("target", &self.target);
+        v.max_chars("target", &self.target, MAX_TEXT_CHARS);
+        v.date("target_date", &self.target_date);
+        if let Some(status) = &self.status {
+            v.one_of("status", status, GOAL_STATUSES);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvidenceRequest {
+    pub observed_on: String,
+    pub value: Option<f64>,
+    pub note: String,
+    pub recorded_by: Option<String>,
+}
+
+impl Validate for EvidenceRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.date("observed_on", &self.observed_on);
+        if let Some(value) = self.value {
+            if !value.is_finite() {
+                v.error("value", "must be a finite number");
+            }
+        }
+        v.required("note", &self.note);
+        v.max_chars("note", &self.note, MAX_TEXT_CHARS);
+        if let Some(recorded_by) = &self.recorded_by {
+            v.max_chars("recorded_by", recorded_by, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressNoteRequest {
+    /// Only evidence observed on or after this date; defaults to all of it.
+    pub since: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the draft is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for ProgressNoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(since) = &self.since {
+            v.date("since", since);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+const GOAL_SELECT: &str = r#"
+    SELECT g.id, g.student_id, g.title, g.area, g.measurement, g.baseline, g.target,
+           g.target_date, g.status,
+           (SELECT COUNT(*) FROM goal_evidence e WHERE e.goal_id = g.id) AS evidence_count,
+           (SELECT MAX(observed_on) FROM goal_evidence e WHERE e.goal_id = g.id)
+               AS last_observed_on,
+           g.created_at, g.updated_at
+    FROM goals g
+"#;
+
+const EVIDENCE_COLUMNS: &str = "id, goal_id, observed_on, value, note, recorded_by, created_at";
+const NOTE_COLUMNS: &str =
+    "id, goal_id, narrative, period_start, evidence_count, generation_id, created_at";
+
+/// By target date, soonest first.
+pub async fn list_goals(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<Goal>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, Goal>(&format!(
+        "{GOAL_SELECT} WHERE g.student_id = ? ORDER BY g.target_date ASC, g.id ASC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_goal(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<GoalRequest>,
+) -> Result<Json<Goal>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO goals(student_id, title, area, measurement, baseline, target, target_date, status)
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(student_id)
+    .bind(payload.title.trim())
+    .bind(&payload.area)
+    .bind(payload.measurement.trim())
+    .bind(&payload.baseline)
+    .bind(payload.target.trim())
+    .bind(&payload.target_date)
+    .bind(payload.status.as_deref().unwrap_or("active"))
+    .fetch_one(&state.pool)
+    .await?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn get_goal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<GoalDetail>, AppError> {
+    let goal = load(&state.pool, id).await?;
+    let evidence = sqlx::query_as::<_, Evidence>(&format!(
+        r#"
+        SELECT {EVIDENCE_COLUMNS}
+        FROM goal_evidence
+        WHERE goal_id = ?
+        ORDER BY observed_on ASC, id ASC
+        "#
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(GoalDetail { goal, evidence }))
+}
+
+pub async fn update_goal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<GoalRequest>,
+) -> Result<Json<Goal>, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE goals
+        SET title = ?, area = ?, measurement = ?, baseline = ?, target = ?, target_date = ?,
+            status = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#,
+    )
+    .bind(payload.title.trim())
+    .bind(&payload.area)
+    .bind(payload.measurement.trim())
+    .bind(&payload.baseline)
+    .bind(payload.target.trim())
+    .bind(&payload.target_date)
+    .bind(payload.status.as_deref().unwrap_or("active"))
+    .bind(id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("goal {id}")));
+    }
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn delete_goal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM goals WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("goal {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn add_evidence(
+    State(state): State<AppState>,
+    Path(goal_id): Path<i64>,
+    ValidJson(payload): ValidJson<EvidenceRequest>,
+) -> Result<Json<Evidence>, AppError> {
+    load(&state.pool, goal_id).await?;
+
+    let created = sqlx::query_as::<_, Evidence>(&format!(
+        r#"
+        INSERT INTO goal_evidence(goal_id, observed_on, value, note, recorded_by)
+        VALUES(?, ?, ?, ?, ?)
+        RETURNING {EVIDENCE_COLUMNS}
+        "#
+    ))
+    .bind(goal_id)
+    .bind(&payload.observed_on)
+    .bind(payload.value)
+    .bind(payload.note.trim())
+    .bind(&payload.recorded_by)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn delete_evidence(
+    State(state): State<AppState>,
+    Path((goal_id, evidence_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, goal_id).await?;
+
+    let result = sqlx::query("DELETE FROM goal_evidence WHERE id = ? AND goal_id = ?")
+        .bind(evidence_id)
+        .bind(goal_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "evidence {evidence_id} for goal {goal_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Past drafts, newest first.
+pub async fn list_progress_notes(
+    State(state): State<AppState>,
+    Path(goal_id): Path<i64>,
+) -> Result<Json<Vec<ProgressNote>>, AppError> {
+    load(&state.pool, goal_id).await?;
+
+    let rows = sqlx::query_as::<_, ProgressNote>(&format!(
+        r#"
+        SELECT {NOTE_COLUMNS}
+        FROM goal_progress_notes
+        WHERE goal_id = ?
+        ORDER BY created_at DESC, id DESC
+        "#
+    ))
+    .bind(goal_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Drafts a progress-monitoring narrative from the goal's logged evidence
+/// with the local model and stores it for the teacher to review and edit
+/// before it goes into a report.
+pub async fn draft_progress_note(
+    State(state): State<AppState>,
+    Path(goal_id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<ProgressNoteRequest>,
+) -> Result<Json<ProgressNote>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let goal = load(&state.pool, goal_id).await?;
+
+    let mut evidence = sqlx::query_as::<_, Evidence>(&format!(
+        r#"
+        SELECT {EVIDENCE_COLUMNS}
+        FROM goal_evidence
+        WHERE goal_id = ? AND (? IS NULL OR observed_on >= ?)
+        ORDER BY observed_on DESC, id DESC
+        LIMIT ?
+        "#
+    ))
+    .bind(goal_id)
+    .bind(&payload.since)
+    .bind(&payload.since)
+    .bind(MAX_EVIDENCE)
+    .fetch_all(&state.pool)
+    .await?;
+    if evidence.is_empty() {
+        let mut v = Validator::default();
+        v.error("since", "the goal has no evidence logged in this period");
+        v.finish()?;
+    }
+    evidence.reverse();
+
+    let grade_level = students::grade_level(&state.pool, goal.student_id).await?;
+    let mut request = vec![
+        format!("Goal: {}", goal.title),
+        format!("Area: {}", goal.area.as_deref().unwrap_or("unspecified")),
+        format!("Measured by: {}", goal.measurement),
+        format!(
+            "Baseline: {}",
+            goal.baseline.as_deref().unwrap_or("not recorded")
+        ),
+        format!("Target: {} by {}", goal.target, goal.target_date),
+        format!(
+            "Student grade level: {}",
+            grade_level.as_deref().unwrap_or("unknown")
+        ),
+    ];
+    let lines = evidence.iter().map(|e| match e.value {
+        Some(value) => format!("- {}: {value} ({})", e.observed_on, e.note),
+        None => format!("- {}: {}", e.observed_on, e.note),
+    });
+    request.push(format!(
+        "Evidence, oldest first:\n{}",
+        lines.collect::<Vec<_>>().join("\n")
+    ));
+
+    let completion = assist::complete(
+        &state,
+        Prompt {
+            system: "You draft IEP progress-monitoring notes for a special education teacher. \
+                     In one or two short paragraphs, state the current level of performance \
+                     against the baseline and target, describe the trend in the data, say \
+                     whether the student is on track to meet the goal by the target date, \
+                     and suggest a next step. Use only the evidence given; do not invent \
+                     numbers. Write in plain, objective language."
+                .to_string(),
+            user: request.join("\n"),
+            model: payload.model,
+            temperature: Some(0.3),
+            max_tokens: Some(800),
+            user_id: payload.user_id,
+            student_id: Some(goal.student_id),
+            json: false,
+        },
+        trace,
+    )
+    .await?;
+
+    let saved = sqlx::query_as::<_, ProgressNote>(&format!(
+        r#"
+        INSERT INTO goal_progress_notes(goal_id, narrative, period_start, evidence_count, generation_id)
+        VALUES(?, ?, ?, ?, ?)
+        RETURNING {NOTE_COLUMNS}
+        "#
+    ))
+    .bind(goal_id)
+    .bind(&completion.text)
+    .bind(&payload.since)
+    .bind(evidence.len() as i64)
+    .bind(completion.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<Goal, AppError> {
+    let goal = sqlx::query_as::<_, Goal>(&format!("{GOAL_SELECT} WHERE g.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("goal {id}")))?;
+
+    students::ensure_record_visible(pool, goal.student_id, || format!("goal {id}")).await?;
+    Ok(goal)
+}
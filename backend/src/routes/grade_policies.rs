@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    routes::{students::GRADE_LEVELS, tags},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MODES: &[&str] = &["clamp", "reject"];
+
+/// Generation limits shared by grade and tag policies. In `clamp` mode
+/// requests are adjusted to fit; in `reject` mode they fail with `422`.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PolicyLimits {
+    pub max_temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+    pub allowed_models: Option<SqlJson<Vec<String>>>,
+    pub mode: String,
+}
+
+/// Generation limits for students in one grade.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct GradePolicy {
+    pub grade_level: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub limits: PolicyLimits,
+    pub updated_at: String,
+}
+
+/// A grade or tag policy that covers a particular student.
+#[derive(Debug)]
+pub struct StudentPolicy {
+    /// e.g. `grade 5` or `tag ELL`, for error messages.
+    pub scope: String,
+    pub limits: PolicyLimits,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertPolicyRequest {
+    pub max_temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+    pub allowed_models: Option<Vec<String>>,
+    pub mode: Option<String>,
+}
+
+impl Validate for UpsertPolicyRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(t) = self.max_temperature {
+            v.range("max_temperature", t, 0.0, 2.0);
+        }
+        if let Some(n) = self.max_tokens {
+            v.range("max_tokens", n, 1, 32_768);
+        }
+        if let Some(models) = &self.allowed_models {
+            if models.is_empty() {
+                v.error(
+                    "allowed_models",
+                    "must not be empty (omit it to allow any model)",
+                );
+            }
+            for (i, model) in models.iter().enumerate() {
+                v.required(&format!("allowed_models[{i}]"), model);
+            }
+        }
+        if let Some(mode) = &self.mode {
+            v.one_of("mode", mode, MODES);
+        }
+    }
+}
+
+const POLICY_COLUMNS: &str =
+    "grade_level, max_temperature, max_tokens, allowed_models, mode, updated_at";
+
+pub async fn list_grade_policies(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<GradePolicy>>, AppError> {
+    let rows = sqlx::query_as::<_, GradePolicy>(&format!(
+        "SELECT {POLICY_COLUMNS} FROM grade_policies ORDER BY grade_level ASC"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn upsert_grade_policy(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(grade_level): Path<String>,
+    ValidJson(payload): ValidJson<UpsertPolicyRequest>,
+) -> Result<Json<GradePolicy>, AppError> {
+    user.require_admin()?;
+    let mut v = Validator::default();
+    v.one_of("grade_level", &grade_level, GRADE_LEVELS);
+    v.finish()?;
+
+    let saved = sqlx::query_as::<_, GradePolicy>(&format!(
+        r#"
+        INSERT INTO grade_policies(grade_level, max_temperature, max_tokens, allowed_models, mode)
+        VALUES(?, ?, ?, ?, ?)
+        ON CONFLICT(grade_level) DO UPDATE SET
+            max_temperature = excluded.max_temperature,
+            max_tokens = excluded.max_tokens,
+            allowed_models = excluded.allowed_models,
+            mode = excluded.mode,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING {POLICY_COLUMNS}
+        "#
+    ))
+    .bind(&grade_level)
+    .bind(payload.max_temperature)
+    .bind(payload.max_tokens)
+    .bind(payload.allowed_models.map(SqlJson))
+    .bind(payload.mode.as_deref().unwrap_or("clamp"))
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+pub async fn delete_grade_policy(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(grade_level): Path<String>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let result = sqlx::query("DELETE FROM grade_policies WHERE grade_level = ?")
+        .bind(&grade_level)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "grade policy for {grade_level}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Policies covering the student: their grade's first, then one per tag
+/// by tag name. Each is applied in turn, so the strictest limits win.
+pub async fn for_student(
+    pool: &SqlitePool,
+    student_id: i64,
+) -> Result<Vec<StudentPolicy>, AppError> {
+    let grade = sqlx::query_as::<_, GradePolicy>(
+        r#"
+        SELECT p.grade_level, p.max_temperature, p.max_tokens, p.allowed_models,
+               p.mode, p.updated_at
+        FROM grade_policies p
+        JOIN students s ON s.grade_level = p.grade_level
+        WHERE s.id = ?
+        "#,
+    )
+    .bind(student_id)
+    .fetch_optional(pool)
+    .await?;
+    let tags = tags::policies_for_student(pool, student_id).await?;
+
+    let grade = grade.map(|policy| StudentPolicy {
+        scope: format!("grade {}", policy.grade_level),
+        limits: policy.limits,
+    });
+    let tags = tags.into_iter().map(|policy| StudentPolicy {
+        scope: format!("tag {}", policy.tag),
+        limits: policy.limits,
+    });
+    Ok(grade.into_iter().chain(tags).collect())
+}
+
+impl StudentPolicy {
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.limits.allows_model(model)
+    }
+
+    pub fn apply(&self, payload: &mut Value) -> Result<(), AppError> {
+        self.limits.apply(payload, &self.scope)
+    }
+}
+
+impl PolicyLimits {
+    pub fn allows_model(&self, model: &str) -> bool {
+        match &self.allowed_models {
+            Some(allowed) => allowed.iter().any(|m| m == model),
+            None => true,
+        }
+    }
+
+    /// Brings a chat payload within the policy, or reports each violation
+    /// as a field error in `reject` mode.
+    pub fn apply(&self, payload: &mut Value, scope: &str) -> Result<(), AppError> {
+        let reject = self.mode == "reject";
+        let mut v = Validator::default();
+
+        if let Some(max) = self.max_temperature {
+            match payload.get("temperature").and_then(Value::as_f64) {
+                Some(t) if t > max && reject => v.error(
+                    "payload.temperature",
+                    format!("must be at most {max} for {scope}"),
+                ),
+                Some(t) if t > max => payload["temperature"] = json!(max),
+                Some(_) => {}
+                None => payload["temperature"] = json!(max),
+            }
+        }
+
+        if let Some(max) = self.max_tokens {
+            match payload.get("max_tokens").and_then(Value::as_i64) {
+                Some(n) if n > max && reject => v.error(
+                    "payload.max_tokens",
+                    format!("must be at most {max} for {scope}"),
+                ),
+                Some(n) if n > max => payload["max_tokens"] = json!(max),
+                Some(_) => {}
+                None => payload["max_tokens"] = json!(max),
+            }
+        }
+
+        if let Some(allowed) = &self.allowed_models {
+            let model = payload.get("model").and_then(Value::as_str);
+            if !model.is_some_and(|m| self.allows_model(m)) {
+                if reject {
+                    v.error(
+                        "payload.model",
+                        format!("must be one of: {}", allowed.join(", ")),
+                    );
+                } else if let Some(first) = allowed.first() {
+                    payload["model"] = json!(first);
+                }
+            }
+        }
+
+        v.finish()
+    }
+}
@@ -0,0 +1,187 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    routes::students,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const CONSENT_STATUSES: &[&str] = &["pending", "granted", "declined", "withdrawn"];
+
+/// A parent or other contact for a student. Unlike the owning user account,
+/// guardians don't log in; they're who digests and consent requests go to.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Guardian {
+    pub id: i64,
+    pub student_id: i64,
+    pub name: String,
+    /// Free-form, e.g. `mother` or `grandparent`.
+    pub relationship: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub consent_status: String,
+    /// When `consent_status` last changed; `null` while still pending.
+    pub consent_updated_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GuardianRequest {
+    pub name: String,
+    pub relationship: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub consent_status: Option<String>,
+}
+
+impl Validate for GuardianRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        v.required("relationship", &self.relationship);
+        v.max_chars("relationship", &self.relationship, 50);
+        if let Some(email) = &self.email {
+            v.max_chars("email", email, 320);
+            if !email.contains('@') {
+                v.error("email", "must be an email address");
+            }
+        }
+        if let Some(phone) = &self.phone {
+            v.required("phone", phone);
+            v.max_chars("phone", phone, 40);
+        }
+        if let Some(status) = &self.consent_status {
+            v.one_of("consent_status", status, CONSENT_STATUSES);
+        }
+    }
+}
+
+const GUARDIAN_COLUMNS: &str = "id, student_id, name, relationship, email, phone, \
+                                consent_status, consent_updated_at, created_at, updated_at";
+
+pub async fn list_guardians(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<Guardian>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, Guardian>(&format!(
+        "SELECT {GUARDIAN_COLUMNS} FROM guardians WHERE student_id = ? ORDER BY name ASC, id ASC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_guardian(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<GuardianRequest>,
+) -> Result<Json<Guardian>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    let status = payload.consent_status.as_deref().unwrap_or("pending");
+
+    let created = sqlx::query_as::<_, Guardian>(&format!(
+        r#"
+        INSERT INTO guardians(
+            student_id, name, relationship, email, phone, consent_status, consent_updated_at
+        )
+        VALUES(?, ?, ?, ?, ?, ?, CASE WHEN ? = 'pending' THEN NULL ELSE CURRENT_TIMESTAMP END)
+        RETURNING {GUARDIAN_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(payload.name.trim())
+    .bind(payload.relationship.trim())
+    .bind(payload.email.as_deref().map(|e| e.trim().to_lowercase()))
+    .bind(payload.phone.as_deref().map(str::trim))
+    .bind(status)
+    .bind(status)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn get_guardian(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Guardian>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Replaces the contact. `consent_updated_at` only moves when the status
+/// actually changes; omitting `consent_status` keeps the current one.
+pub async fn update_guardian(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<GuardianRequest>,
+) -> Result<Json<Guardian>, AppError> {
+    load(&state.pool, id).await?;
+
+    let updated = sqlx::query_as::<_, Guardian>(&format!(
+        r#"
+        UPDATE guardians
+        SET name = ?, relationship = ?, email = ?, phone = ?,
+            consent_status = COALESCE(?, consent_status),
+            consent_updated_at = CASE WHEN COALESCE(?, consent_status) <> consent_status
+                                      THEN CURRENT_TIMESTAMP ELSE consent_updated_at END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING {GUARDIAN_COLUMNS}
+        "#
+    ))
+    .bind(payload.name.trim())
+    .bind(payload.relationship.trim())
+    .bind(payload.email.as_deref().map(|e| e.trim().to_lowercase()))
+    .bind(payload.phone.as_deref().map(str::trim))
+    .bind(&payload.consent_status)
+    .bind(&payload.consent_status)
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("guardian {id}")))?;
+
+    Ok(Json(updated))
+}
+
+pub async fn delete_guardian(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM guardians WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("guardian {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<Guardian, AppError> {
+    let guardian = sqlx::query_as::<_, Guardian>(&format!(
+        "SELECT {GUARDIAN_COLUMNS} FROM guardians WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("guardian {id}")))?;
+
+    students::ensure_record_visible(pool, guardian.student_id, || format!("guardian {id}")).await?;
+    Ok(guardian)
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    hardware::{self, Hardware},
+    model_catalog::{self, LocalModel},
+};
+
+#[derive(Debug, Serialize)]
+pub struct ModelFit {
+    pub name: String,
+    pub estimated_memory_bytes: u64,
+    /// Fits in the combined VRAM of the detected GPUs.
+    pub fits_vram: bool,
+    /// Fits in system RAM, i.e. runnable on CPU with llama.cpp.
+    pub fits_ram: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HardwareReport {
+    #[serde(flatten)]
+    pub hardware: Hardware,
+    pub vram_total_bytes: u64,
+    pub models: Vec<ModelFit>,
+}
+
+pub async fn hardware_report(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<HardwareReport>, AppError> {
+    user.require_admin()?;
+    let dir = PathBuf::from(&state.config.models_dir);
+    let (hardware, models) = tokio::task::spawn_blocking(move || {
+        let models = model_catalog::scan(&dir).unwrap_or_else(|err| {
+            warn!(error = %err, dir = %dir.display(), "failed to scan models directory");
+            Vec::new()
+        });
+        (hardware::detect(), models)
+    })
+    .await
+    .map_err(|err| AppError::Internal(format!("hardware detection failed: {err}")))?;
+
+    let vram_total_bytes = hardware.vram_total_bytes();
+    let ram_total_bytes = hardware.memory.as_ref().map_or(0, |m| m.total_bytes);
+    let models = models
+        .into_iter()
+        .map(
+            |LocalModel {
+                 name,
+                 estimated_memory_bytes,
+                 ..
+             }| ModelFit {
+                name,
+                estimated_memory_bytes,
+                fits_vram: estimated_memory_bytes <= vram_total_bytes,
+                fits_ram: estimated_memory_bytes <= ram_total_bytes,
+            },
+        )
+        .collect();
+
+    Ok(Json(HardwareReport {
+        hardware,
+        vram_total_bytes,
+        models,
+    }))
+}
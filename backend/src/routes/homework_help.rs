@@ -0,0 +1,399 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::{
+        assignments, consents,
+        llm::{self, ChatOutcome, LlmProxyRequest},
+        students, users,
+    },
+    trace::TraceHeaders,
+    validation::{self, ValidJson, Validate, Validator},
+};
+
+const MAX_MESSAGE_CHARS: usize = 4_000;
+/// Earlier turns sent back to the model with each new message.
+const MAX_HISTORY: i64 = 20;
+
+/// Always the first system message; a persona can't override it.
+const HINTS_ONLY_PROMPT: &str = "You are a homework helper. Help the student work through \
+    the assignment themselves: ask guiding questions, point out the next step, give a hint, \
+    or explain the underlying idea with a different example. Never give the final answer, \
+    complete a step for them, or write any part of what they will hand in, even if they ask \
+    or insist. Keep replies short and encouraging. Reply with only a JSON object: \
+    {\"reply\": string, \"reveals_answer\": boolean}, where reveals_answer is true if the \
+    reply gives away a final answer or does the work for the student.";
+
+/// Shown in place of a reply that gave the answer away.
+const WITHHELD_REPLY: &str = "Let's work through this together instead of me just telling you. \
+    What have you tried so far, and where did you get stuck?";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HelpSession {
+    pub id: i64,
+    pub assignment_id: i64,
+    pub assignment_title: String,
+    pub student_id: i64,
+    /// `open` or `closed`.
+    pub status: String,
+    pub message_count: i64,
+    /// Tutor replies that were withheld for giving the answer away.
+    pub withheld_count: i64,
+    pub created_at: String,
+    pub closed_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HelpMessage {
+    pub id: i64,
+    pub session_id: i64,
+    /// `student` or `tutor`.
+    pub role: String,
+    pub content: String,
+    pub withheld: Option<String>,
+    pub interaction_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HelpSessionDetail {
+    #[serde(flatten)]
+    pub session: HelpSession,
+    /// Oldest first.
+    pub messages: Vec<HelpMessage>,
+}
+
+/// What the student sees after sending a message.
+#[derive(Debug, Serialize)]
+pub struct HelpExchange {
+    pub message: HelpMessage,
+    pub reply: StudentReply,
+}
+
+/// A tutor reply without `withheld`, which is for teachers only.
+#[derive(Debug, Serialize)]
+pub struct StudentReply {
+    pub id: i64,
+    pub content: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessageRequest {
+    pub content: String,
+    pub model: Option<String>,
+    /// Account sending the message, e.g. the student's login.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for MessageRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("content", &self.content);
+        v.max_chars("content", &self.content, MAX_MESSAGE_CHARS);
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HelpSessionListQuery {
+    pub assignment_id: Option<i64>,
+    pub student_id: Option<i64>,
+    /// Only sessions with at least one withheld reply.
+    #[serde(default)]
+    pub withheld: bool,
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct TutorReply {
+    reply: String,
+    #[serde(default)]
+    reveals_answer: bool,
+}
+
+const SESSION_SELECT: &str = r#"
+    SELECT h.id, h.assignment_id, a.title AS assignment_title, h.student_id, h.status,
+           (SELECT COUNT(*) FROM help_messages m WHERE m.session_id = h.id) AS message_count,
+           (SELECT COUNT(*) FROM help_messages m
+            WHERE m.session_id = h.id AND m.withheld IS NOT NULL) AS withheld_count,
+           h.created_at, h.closed_at
+    FROM help_sessions h
+    JOIN assignments a ON a.id = h.assignment_id
+"#;
+
+const MESSAGE_COLUMNS: &str = "id, session_id, role, content, withheld, interaction_id, created_at";
+
+/// Opens a help session for the assignment's student.
+pub async fn open_session(
+    State(state): State<AppState>,
+    Path(assignment_id): Path<i64>,
+) -> Result<Json<HelpSession>, AppError> {
+    let assignment = assignments::load(&state.pool, assignment_id).await?;
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO help_sessions(assignment_id, student_id) VALUES(?, ?) RETURNING id",
+    )
+    .bind(assignment_id)
+    .bind(assignment.student_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+/// For teacher review, newest first.
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Query(query): Query<HelpSessionListQuery>,
+) -> Result<Json<Vec<HelpSession>>, AppError> {
+    let viewer = students::viewer();
+    let rows = sqlx::query_as::<_, HelpSession>(&format!(
+        r#"
+        {SESSION_SELECT}
+        WHERE (? IS NULL OR h.assignment_id = ?)
+          AND (? IS NULL OR h.student_id = ?)
+          AND (NOT ? OR EXISTS(SELECT 1 FROM help_messages m
+                               WHERE m.session_id = h.id AND m.withheld IS NOT NULL))
+          AND h.student_id IN (SELECT id FROM students
+                               WHERE school_id = ? AND {access_filter})
+        ORDER BY h.created_at DESC, h.id DESC
+        "#,
+        access_filter = students::ACCESS_FILTER,
+    ))
+    .bind(query.assignment_id)
+    .bind(query.assignment_id)
+    .bind(query.student_id)
+    .bind(query.student_id)
+    .bind(query.withheld)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// The full transcript, including withheld replies, for teacher review.
+pub async fn get_session(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<HelpSessionDetail>, AppError> {
+    let session = load(&state.pool, id).await?;
+    let messages = sqlx::query_as::<_, HelpMessage>(&format!(
+        "SELECT {MESSAGE_COLUMNS} FROM help_messages WHERE session_id = ? ORDER BY id ASC"
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(HelpSessionDetail { session, messages }))
+}
+
+pub async fn close_session(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<HelpSession>, AppError> {
+    load(&state.pool, id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE help_sessions SET status = 'closed', closed_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND status = 'open'
+        "#,
+    )
+    .bind(id)
+    .execute(&state.pool)
+    .await?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Sends the student's message with the conversation so far. The hints-only
+/// prompt always leads, and a reply the model marks as giving the answer
+/// away is swapped for a nudge before the student sees it.
+pub async fn send_message(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<MessageRequest>,
+) -> Result<Json<HelpExchange>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let session = load(&state.pool, id).await?;
+    if session.status != "open" {
+        return Err(AppError::Conflict(format!("help session {id} is closed")));
+    }
+    consents::ensure_granted(&state, session.student_id).await?;
+    let assignment = assignments::load(&state.pool, session.assignment_id).await?;
+
+    let mut context = vec![format!("Assignment: {}", assignment.title)];
+    if let Some(subject) = &assignment.subject {
+        context.push(format!("Subject: {subject}"));
+    }
+    if let Some(description) = &assignment.description {
+        context.push(format!("Instructions: {description}"));
+    }
+    let mut messages = vec![
+        json!({ "role": "system", "content": HINTS_ONLY_PROMPT }),
+        json!({ "role": "system", "content": context.join("\n") }),
+    ];
+    let mut history = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT role, content
+        FROM help_messages
+        WHERE session_id = ?
+        ORDER BY id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(id)
+    .bind(MAX_HISTORY)
+    .fetch_all(&state.pool)
+    .await?;
+    history.reverse();
+    for (role, content) in history {
+        let role = if role == "student" {
+            "user"
+        } else {
+            "assistant"
+        };
+        messages.push(json!({ "role": role, "content": content }));
+    }
+    let content = payload.content.trim();
+    messages.push(json!({ "role": "user", "content": content }));
+
+    let mut request = json!({
+        "messages": messages,
+        "temperature": 0.4,
+        "max_tokens": 600,
+        "response_format": { "type": "json_object" },
+    });
+    if let Some(model) = payload.model {
+        request["model"] = json!(model);
+    }
+    // Threads the session's turns into one conversation.
+    let previous_turn = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT interaction_id
+        FROM help_messages
+        WHERE session_id = ? AND interaction_id IS NOT NULL
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let body = LlmProxyRequest {
+        user_id: payload.user_id,
+        student_id: Some(session.student_id),
+        session_id: None,
+        class_name: None,
+        persona_id: None,
+        few_shot: None,
+        conversation_id: None,
+        parent_interaction_id: previous_turn,
+        payload: request,
+    };
+    validation::validate(&body)?;
+
+    let completion = match llm::chat(state.clone(), body, trace, None).await? {
+        ChatOutcome::Complete(completion) => completion,
+        ChatOutcome::Stream(_) => {
+            return Err(AppError::Internal(
+                "homework help unexpectedly streamed".to_string(),
+            ))
+        }
+    };
+    let reply = parse_reply(&completion.upstream).ok_or_else(|| {
+        AppError::Upstream(format!(
+            "model reply did not match the expected format; interaction {}",
+            completion.interaction_id
+        ))
+    })?;
+    let (shown, withheld) = if reply.reveals_answer {
+        (WITHHELD_REPLY.to_string(), Some(reply.reply))
+    } else {
+        (reply.reply, None)
+    };
+
+    // Both sides of the exchange are stored together, once the reply is in.
+    let mut tx = state.pool.begin().await?;
+    let message = sqlx::query_as::<_, HelpMessage>(&format!(
+        r#"
+        INSERT INTO help_messages(session_id, role, content)
+        VALUES(?, 'student', ?)
+        RETURNING {MESSAGE_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(content)
+    .fetch_one(&mut *tx)
+    .await?;
+    let tutor = sqlx::query_as::<_, HelpMessage>(&format!(
+        r#"
+        INSERT INTO help_messages(session_id, role, content, withheld, interaction_id)
+        VALUES(?, 'tutor', ?, ?, ?)
+        RETURNING {MESSAGE_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(&shown)
+    .bind(&withheld)
+    .bind(completion.interaction_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(HelpExchange {
+        message,
+        reply: StudentReply {
+            id: tutor.id,
+            content: tutor.content,
+            created_at: tutor.created_at,
+        },
+    }))
+}
+
+/// Models sometimes wrap JSON in prose or a code fence, so the outermost
+/// object is used.
+fn parse_reply(upstream: &Value) -> Option<TutorReply> {
+    let text = upstream["choices"][0]["message"]["content"].as_str()?;
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    if start >= end {
+        return None;
+    }
+    let reply = serde_json::from_str::<TutorReply>(&text[start..=end]).ok()?;
+    let trimmed = reply.reply.trim().to_string();
+    (!trimmed.is_empty()).then_some(TutorReply {
+        reply: trimmed,
+        reveals_answer: reply.reveals_answer,
+    })
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<HelpSession, AppError> {
+    let session = sqlx::query_as::<_, HelpSession>(&format!("{SESSION_SELECT} WHERE h.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("help session {id}")))?;
+
+    students::ensure_record_visible(pool, session.student_id, || format!("help session {id}"))
+        .await?;
+    Ok(session)
+}
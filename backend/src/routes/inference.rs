@@ -0,0 +1,158 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use bollard::container::LogOutput;
+use serde::{Deserialize, Serialize};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    docker::{ContainerStatus, InferenceContainer},
+    error::AppError,
+    inference_stats::StatsSample,
+    time::unix_now,
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct InferenceEvent {
+    pub id: i64,
+    pub container: String,
+    pub event: String,
+    pub detail: Option<String>,
+    pub created_at: String,
+}
+
+pub async fn inference_status(
+    State(state): State<AppState>,
+) -> Result<Json<ContainerStatus>, AppError> {
+    container(&state)?.status().await.map(Json)
+}
+
+pub async fn start_inference(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<ContainerStatus>, AppError> {
+    user.require_admin()?;
+    container(&state)?.start().await.map(Json)
+}
+
+pub async fn stop_inference(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<ContainerStatus>, AppError> {
+    user.require_admin()?;
+    container(&state)?.stop().await.map(Json)
+}
+
+pub async fn restart_inference(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<ContainerStatus>, AppError> {
+    user.require_admin()?;
+    container(&state)?.restart().await.map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    /// How far back the history goes; defaults to 15 minutes.
+    pub minutes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InferenceStats {
+    pub interval_secs: u64,
+    pub latest: Option<StatsSample>,
+    /// Oldest first.
+    pub history: Vec<StatsSample>,
+}
+
+pub async fn inference_stats(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<InferenceStats>, AppError> {
+    user.require_admin()?;
+    container(&state)?;
+    let minutes = query.minutes.unwrap_or(15);
+    let history = state
+        .inference_stats
+        .since(unix_now().saturating_sub(minutes.saturating_mul(60)));
+
+    Ok(Json(InferenceStats {
+        interval_secs: state.config.docker_stats_interval_secs,
+        latest: history.last().cloned(),
+        history,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    /// Lines of existing output to send first; defaults to 200.
+    pub tail: Option<u32>,
+    /// Keep the stream open for new output; defaults to true.
+    pub follow: Option<bool>,
+}
+
+/// Streams the container's output as SSE: one `stdout` or `stderr` event
+/// per line, each prefixed with Docker's timestamp. Stream failures arrive
+/// as an `error` event.
+pub async fn inference_logs(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<LogsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    user.require_admin()?;
+    let container = container(&state)?;
+    // Surfaces a missing container as a 404 rather than inside the stream.
+    container.status().await?;
+
+    let events = container
+        .logs(query.tail.unwrap_or(200), query.follow.unwrap_or(true))
+        .map(|line| {
+            let event = match line {
+                Ok(LogOutput::StdErr { message }) => Event::default()
+                    .event("stderr")
+                    .data(String::from_utf8_lossy(&message).trim_end()),
+                Ok(output) => Event::default()
+                    .event("stdout")
+                    .data(output.to_string().trim_end()),
+                Err(err) => Event::default().event("error").data(err.to_string()),
+            };
+            Ok(event)
+        });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(Duration::from_secs(15))))
+}
+
+/// Supervisor actions, newest first.
+pub async fn list_inference_events(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<InferenceEvent>>, AppError> {
+    user.require_admin()?;
+    let events = sqlx::query_as::<_, InferenceEvent>(
+        r#"
+        SELECT id, container, event, detail, created_at
+        FROM inference_events
+        ORDER BY id DESC
+        LIMIT 100
+        "#,
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(events))
+}
+
+fn container(state: &AppState) -> Result<&InferenceContainer, AppError> {
+    state.inference.as_deref().ok_or_else(|| {
+        AppError::NotFound(
+            "no inference container configured (set DOCKER_LLM_CONTAINER)".to_string(),
+        )
+    })
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    routes::{interactions, tags},
+    validation::{ValidJson, Validate, Validator},
+};
+
+/// A reviewer's label on an interaction, such as `great-explanation` or
+/// `hallucination`, with an optional note on why.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct InteractionTag {
+    pub interaction_id: i64,
+    pub tag: String,
+    pub note: Option<String>,
+    pub tagged_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TagCount {
+    pub tag: String,
+    pub interactions: i64,
+    pub last_used_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagInteractionRequest {
+    pub note: Option<String>,
+    pub tagged_by: Option<String>,
+}
+
+impl Validate for TagInteractionRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(note) = &self.note {
+            v.max_chars("note", note, 2000);
+        }
+        if let Some(tagged_by) = &self.tagged_by {
+            v.required("tagged_by", tagged_by);
+            v.max_chars("tagged_by", tagged_by, 200);
+        }
+    }
+}
+
+const INTERACTION_TAG_COLUMNS: &str = "interaction_id, tag, note, tagged_by, created_at";
+
+/// Every label in use with how many interactions carry it, most used first.
+pub async fn list_interaction_tags(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<TagCount>>, AppError> {
+    let rows = sqlx::query_as::<_, TagCount>(
+        r#"
+        SELECT MIN(tag) AS tag, COUNT(*) AS interactions, MAX(created_at) AS last_used_at
+        FROM interaction_tags
+        GROUP BY tag
+        ORDER BY interactions DESC, tag ASC
+        "#,
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn list_tags_for_interaction(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<InteractionTag>>, AppError> {
+    interactions::ensure_exists(&state.pool, id).await?;
+    for_interaction(&state.pool, id).await.map(Json)
+}
+
+/// Adds the label, or replaces its note and reviewer if it's already there.
+/// Returns all of the interaction's labels.
+pub async fn add_interaction_tag(
+    State(state): State<AppState>,
+    Path((id, tag)): Path<(i64, String)>,
+    ValidJson(payload): ValidJson<TagInteractionRequest>,
+) -> Result<Json<Vec<InteractionTag>>, AppError> {
+    let mut v = Validator::default();
+    tags::check_name(&mut v, "tag", &tag);
+    v.finish()?;
+    interactions::ensure_exists(&state.pool, id).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO interaction_tags(interaction_id, tag, note, tagged_by)
+        VALUES(?, ?, ?, ?)
+        ON CONFLICT(interaction_id, tag) DO UPDATE SET
+            note = excluded.note,
+            tagged_by = excluded.tagged_by
+        "#,
+    )
+    .bind(id)
+    .bind(&tag)
+    .bind(
+        payload
+            .note
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty()),
+    )
+    .bind(payload.tagged_by.as_deref().map(str::trim))
+    .execute(&state.pool)
+    .await?;
+
+    for_interaction(&state.pool, id).await.map(Json)
+}
+
+pub async fn remove_interaction_tag(
+    State(state): State<AppState>,
+    Path((id, tag)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM interaction_tags WHERE interaction_id = ? AND tag = ?")
+        .bind(id)
+        .bind(&tag)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "tag '{tag}' on interaction {id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn for_interaction(
+    pool: &SqlitePool,
+    interaction_id: i64,
+) -> Result<Vec<InteractionTag>, AppError> {
+    let rows = sqlx::query_as::<_, InteractionTag>(&format!(
+        "SELECT {INTERACTION_TAG_COLUMNS} FROM interaction_tags \
+         WHERE interaction_id = ? ORDER BY tag ASC"
+    ))
+    .bind(interaction_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
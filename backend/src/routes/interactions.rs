@@ -0,0 +1,823 @@
+use std::convert::Infallible;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    interactions::{self, Interaction, InteractionFilter, ParsedInteraction},
+    pagination::{self, Page},
+    redact::Redactor,
+    routes::{
+        interaction_tags::{self, InteractionTag},
+        llm::{self, ChatOutcome, LlmProxyRequest},
+        school_years,
+        student_notes::StudentNote,
+        students,
+    },
+    stream_telemetry::{self, StreamTelemetry},
+    text_diff::{self, Diff},
+    trace::TraceHeaders,
+    validation::{self, ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRequest {
+    /// Defaults to the model that produced the original response.
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub class_name: Option<String>,
+}
+
+impl Validate for RegenerateRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+        if let Some(temperature) = self.temperature {
+            v.range("temperature", temperature, 0.0, 2.0);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StudentInteractionsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Inclusive lower bound on `created_at`, e.g. `2026-10-12`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// Only interactions within this school year's dates; overrides
+    /// `since` and `until`.
+    pub school_year_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InteractionListQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub student_id: Option<i64>,
+    pub user_id: Option<i64>,
+    /// Exact model name as recorded, e.g. `llama3.1:8b`.
+    pub model: Option<String>,
+    /// Inclusive lower bound on `created_at`, e.g. `2026-10-12`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// Only interactions carrying this reviewer label.
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InteractionSearchQuery {
+    pub q: String,
+    pub student_id: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub interaction: ParsedInteraction,
+    /// The best-matching excerpt, with matched words in `[brackets]`.
+    pub snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FineTuneExportQuery {
+    /// Only `jsonl` for now.
+    pub format: Option<String>,
+    /// Inclusive lower bound on `created_at`, e.g. `2026-10-12`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// `up`: rated up and never down; `down`: rated down at least once;
+    /// `rated`: any rating.
+    pub feedback: Option<String>,
+    /// Only interactions carrying this reviewer label.
+    pub tag: Option<String>,
+    pub student_id: Option<i64>,
+}
+
+/// Scope for an on-demand anonymization; an empty body covers every
+/// interaction not yet anonymized.
+#[derive(Debug, Deserialize)]
+pub struct AnonymizeRequest {
+    pub student_id: Option<i64>,
+    /// Only interactions created before this, e.g. `2026-06-30`.
+    pub before: Option<String>,
+}
+
+impl Validate for AnonymizeRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(before) = &self.before {
+            v.required("before", before);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnonymizeResponse {
+    pub anonymized: u64,
+}
+
+/// Rows rewritten per transaction, so a large backlog doesn't hold the
+/// write lock for the whole run.
+const ANONYMIZE_BATCH: i64 = 200;
+
+pub const RATINGS: &[&str] = &["up", "down"];
+
+#[derive(Debug, Deserialize)]
+pub struct FeedbackRequest {
+    /// `up` or `down`.
+    pub rating: String,
+    pub comment: Option<String>,
+    /// Who left the rating, e.g. `Ms. Rivera`.
+    pub reviewer: Option<String>,
+}
+
+impl Validate for FeedbackRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("rating", &self.rating, RATINGS);
+        if let Some(comment) = &self.comment {
+            v.max_chars("comment", comment, 2000);
+        }
+        if let Some(reviewer) = &self.reviewer {
+            v.required("reviewer", reviewer);
+            v.max_chars("reviewer", reviewer, 200);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Feedback {
+    pub id: i64,
+    pub interaction_id: i64,
+    pub rating: String,
+    pub comment: Option<String>,
+    pub reviewer: Option<String>,
+    pub created_at: String,
+}
+
+const FEEDBACK_COLUMNS: &str = "id, interaction_id, rating, comment, reviewer, created_at";
+
+#[derive(Debug, Serialize)]
+pub struct InteractionDetail {
+    #[serde(flatten)]
+    pub interaction: ParsedInteraction,
+    pub moderation: Moderation,
+    /// Later attempts at the same prompt, oldest first.
+    pub regenerations: Vec<i64>,
+    /// The homework help session this turn belongs to.
+    pub help_session_id: Option<i64>,
+    /// Teacher notes that point at this interaction.
+    pub notes: Vec<StudentNote>,
+    /// Codes of the curriculum standards it's tagged with.
+    pub standards: Vec<String>,
+    /// Ratings left on the response, oldest first.
+    pub feedback: Vec<Feedback>,
+    /// Reviewer labels, by name.
+    pub tags: Vec<InteractionTag>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Moderation {
+    /// `LLM_RESPONSE_FILTER_FILE` rewrote part of the response.
+    pub response_filtered: bool,
+    /// The inference server stopped a choice with `content_filter`.
+    pub upstream_content_filter: bool,
+    /// The review queue entry, when flagged.
+    pub review_item_id: Option<i64>,
+    /// `pending`, `approved`, or `dismissed`.
+    pub review_status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegenerateResponse {
+    pub original: Interaction,
+    pub regenerated: Interaction,
+    pub upstream: Value,
+}
+
+pub async fn regenerate_interaction(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<RegenerateRequest>,
+) -> Result<Json<RegenerateResponse>, AppError> {
+    let original = interactions::get(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("interaction {id} not found")))?;
+
+    let messages = serde_json::from_str::<Value>(&original.prompt)
+        .ok()
+        .filter(Value::is_array)
+        .ok_or_else(|| {
+            AppError::BadRequest(format!("interaction {id} has no replayable messages"))
+        })?;
+
+    let mut chat_payload = json!({ "messages": messages });
+    if let Some(model) = payload.model.as_ref().or(original.model.as_ref()) {
+        chat_payload["model"] = json!(model);
+    }
+    if let Some(temperature) = payload.temperature {
+        chat_payload["temperature"] = json!(temperature);
+    }
+
+    let body = LlmProxyRequest {
+        user_id: original.user_id,
+        student_id: original.student_id,
+        session_id: None,
+        class_name: payload.class_name,
+        persona_id: original.persona_id,
+        few_shot: None,
+        // Same place in the thread as the original; a first turn without a
+        // parent starts its own conversation.
+        conversation_id: None,
+        parent_interaction_id: original.parent_interaction_id,
+        payload: chat_payload,
+    };
+    validation::validate(&body)?;
+
+    // Every attempt links to the first one so retries group together.
+    let root = original.regenerated_from.unwrap_or(original.id);
+    let completion = match llm::chat(state.clone(), body, trace, Some(root)).await? {
+        ChatOutcome::Complete(completion) => completion,
+        ChatOutcome::Stream(_) => {
+            return Err(AppError::Internal(
+                "regeneration unexpectedly streamed".to_string(),
+            ))
+        }
+    };
+
+    let regenerated = interactions::get(&state.pool, completion.interaction_id)
+        .await?
+        .ok_or_else(|| AppError::Internal("regenerated interaction was not stored".to_string()))?;
+
+    Ok(Json(RegenerateResponse {
+        original,
+        regenerated,
+        upstream: completion.upstream,
+    }))
+}
+
+/// Every stored interaction, newest first, with the prompt and response
+/// parsed back into JSON.
+pub async fn list_interactions(
+    State(state): State<AppState>,
+    Query(query): Query<InteractionListQuery>,
+) -> Result<Json<Page<ParsedInteraction>>, AppError> {
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    if let Some(model) = &query.model {
+        v.required("model", model);
+    }
+    if let Some(tag) = &query.tag {
+        v.required("tag", tag);
+    }
+    v.finish()?;
+
+    let filter = InteractionFilter {
+        student_id: query.student_id,
+        user_id: query.user_id,
+        model: query.model,
+        since: query.since,
+        until: query.until,
+        tag: query.tag,
+    };
+    let page = interactions::list(&state.pool, &filter, limit, offset).await?;
+
+    Ok(Json(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(ParsedInteraction::from)
+            .collect(),
+        total: page.total,
+        limit: page.limit,
+        offset: page.offset,
+    }))
+}
+
+/// Full-text search over what was asked and answered, best match first.
+/// Every word in `q` must appear; common endings are stemmed, so
+/// `explaining` also finds `explained`.
+pub async fn search_interactions(
+    State(state): State<AppState>,
+    Query(query): Query<InteractionSearchQuery>,
+) -> Result<Json<Page<SearchResult>>, AppError> {
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    v.max_chars("q", &query.q, 500);
+    let match_query = interactions::match_query(&query.q).unwrap_or_default();
+    if match_query.is_empty() {
+        v.error("q", "must contain at least one word");
+    }
+    v.finish()?;
+
+    let page =
+        interactions::search(&state.pool, &match_query, query.student_id, limit, offset).await?;
+
+    Ok(Json(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(|hit| SearchResult {
+                interaction: hit.interaction.into(),
+                snippet: hit.snippet,
+            })
+            .collect(),
+        total: page.total,
+        limit: page.limit,
+        offset: page.offset,
+    }))
+}
+
+/// Streams interactions as OpenAI chat fine-tuning examples, one
+/// `{"messages": [...]}` object per line: the stored request messages
+/// followed by the model's reply as the `assistant` turn. Cancelled,
+/// anonymized, and unparseable rows are skipped.
+pub async fn export_fine_tune(
+    State(state): State<AppState>,
+    Query(query): Query<FineTuneExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut v = Validator::default();
+    v.one_of(
+        "format",
+        query.format.as_deref().unwrap_or("jsonl"),
+        &["jsonl"],
+    );
+    if let Some(feedback) = &query.feedback {
+        v.one_of("feedback", feedback, &["up", "down", "rated"]);
+    }
+    if let Some(tag) = &query.tag {
+        v.required("tag", tag);
+    }
+    v.finish()?;
+
+    let (tx, rx) = mpsc::channel::<Result<String, Infallible>>(32);
+    let pool = state.pool.clone();
+    let school_id = auth::school_id();
+    let viewer = students::viewer();
+    let query_sql = format!(
+        r#"
+        SELECT i.prompt, i.response
+        FROM ai_interactions i
+        WHERE i.school_id = ? AND i.cancelled = 0 AND i.anonymized_at IS NULL
+          AND {visible}
+          AND (? IS NULL OR i.created_at >= ?)
+          AND (? IS NULL OR i.created_at < ?)
+          AND (? IS NULL OR i.student_id = ?)
+          AND (? IS NULL OR i.id IN (SELECT interaction_id FROM interaction_tags
+                                     WHERE tag = ?))
+          AND (CASE ?
+               WHEN 'up' THEN EXISTS(SELECT 1 FROM interaction_feedback f
+                                     WHERE f.interaction_id = i.id AND f.rating = 'up')
+                          AND NOT EXISTS(SELECT 1 FROM interaction_feedback f
+                                         WHERE f.interaction_id = i.id AND f.rating = 'down')
+               WHEN 'down' THEN EXISTS(SELECT 1 FROM interaction_feedback f
+                                       WHERE f.interaction_id = i.id AND f.rating = 'down')
+               WHEN 'rated' THEN EXISTS(SELECT 1 FROM interaction_feedback f
+                                        WHERE f.interaction_id = i.id)
+               ELSE 1 END)
+        ORDER BY i.id ASC
+        "#,
+        visible = interactions::visible_filter("i.student_id"),
+    );
+    tokio::spawn(async move {
+        let mut rows = sqlx::query_as::<_, (String, String)>(&query_sql)
+            .bind(school_id)
+            .bind(viewer)
+            .bind(viewer)
+            .bind(viewer)
+            .bind(&query.since)
+            .bind(&query.since)
+            .bind(&query.until)
+            .bind(&query.until)
+            .bind(query.student_id)
+            .bind(query.student_id)
+            .bind(&query.tag)
+            .bind(&query.tag)
+            .bind(&query.feedback)
+            .fetch(&pool);
+
+        while let Some(row) = rows.next().await {
+            let (prompt, response) = match row {
+                Ok(row) => row,
+                Err(err) => {
+                    // Headers are already sent, so the download just ends
+                    // early; the log says why.
+                    warn!(error = %err, "fine-tune export failed");
+                    return;
+                }
+            };
+            let Some(line) = fine_tune_example(&prompt, &response) else {
+                continue;
+            };
+            if tx.send(Ok(line)).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"interactions.jsonl\"",
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    ))
+}
+
+/// One JSONL line, or `None` when the prompt isn't a `messages` array or
+/// the response has no reply text (OpenAI `choices` or Ollama `message`).
+fn fine_tune_example(prompt: &str, response: &str) -> Option<String> {
+    let Value::Array(mut messages) = serde_json::from_str::<Value>(prompt).ok()? else {
+        return None;
+    };
+    let response = serde_json::from_str::<Value>(response).ok()?;
+    let reply = interactions::reply_text(&response).filter(|reply| !reply.trim().is_empty())?;
+
+    messages.push(json!({ "role": "assistant", "content": reply }));
+    Some(format!("{}\n", json!({ "messages": messages })))
+}
+
+/// Runs stored prompts and responses through the PII redaction pass used by
+/// research exports (student, user, and guardian names, emails, phone
+/// numbers), unlinks the user and student, and marks the rows
+/// `anonymized_at`. Model, token, cost, and latency columns are kept for
+/// reporting.
+pub async fn anonymize_interactions(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<AnonymizeRequest>,
+) -> Result<Json<AnonymizeResponse>, AppError> {
+    if let Some(student_id) = payload.student_id {
+        // Soft-deleted students still count; privacy requests often follow
+        // a withdrawal.
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM students WHERE id = ? AND school_id = ?)",
+        )
+        .bind(student_id)
+        .bind(auth::school_id())
+        .fetch_one(&state.pool)
+        .await?;
+        if !exists {
+            return Err(AppError::NotFound(format!("student {student_id}")));
+        }
+    }
+
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM students UNION SELECT name FROM users UNION SELECT name FROM guardians",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+    let redactor = Redactor::new(names);
+
+    let mut anonymized = 0;
+    loop {
+        let mut tx = state.pool.begin().await?;
+        let batch = sqlx::query_as::<_, (i64, String, String)>(
+            r#"
+            SELECT id, prompt, response
+            FROM ai_interactions
+            WHERE school_id = ? AND anonymized_at IS NULL
+              AND (? IS NULL OR student_id = ?)
+              AND (? IS NULL OR created_at < ?)
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(auth::school_id())
+        .bind(payload.student_id)
+        .bind(payload.student_id)
+        .bind(&payload.before)
+        .bind(&payload.before)
+        .bind(ANONYMIZE_BATCH)
+        .fetch_all(&mut *tx)
+        .await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        for (id, prompt, response) in &batch {
+            sqlx::query(
+                r#"
+                UPDATE ai_interactions
+                SET prompt = ?, response = ?, user_id = NULL, student_id = NULL,
+                    request_id = NULL, traceparent = NULL, anonymized_at = CURRENT_TIMESTAMP
+                WHERE id = ?
+                "#,
+            )
+            .bind(redactor.redact_json(prompt))
+            .bind(redactor.redact_json(response))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        anonymized += batch.len() as u64;
+    }
+
+    Ok(Json(AnonymizeResponse { anonymized }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Variant {
+    #[serde(flatten)]
+    pub interaction: ParsedInteraction,
+    /// The reply text that was compared.
+    pub reply: Option<String>,
+    /// Word-level changes from the first attempt's reply; `None` for the
+    /// first attempt itself and for replies without text.
+    pub diff: Option<Diff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Variants {
+    /// The first attempt, which every regeneration links back to.
+    pub original_id: i64,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Conversation {
+    pub conversation_id: i64,
+    pub turns: Vec<ParsedInteraction>,
+}
+
+/// Every attempt at an interaction's prompt, first one first, each
+/// regeneration diffed word by word against the first attempt's reply.
+/// Works from any attempt's id.
+pub async fn get_variants(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Variants>, AppError> {
+    let interaction = interactions::get(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("interaction {id}")))?;
+    let original_id = interaction.regenerated_from.unwrap_or(interaction.id);
+
+    let attempts: Vec<ParsedInteraction> = interactions::attempts(&state.pool, original_id)
+        .await?
+        .into_iter()
+        .map(ParsedInteraction::from)
+        .collect();
+    let original_reply = attempts
+        .first()
+        .filter(|first| first.id == original_id)
+        .and_then(|first| interactions::reply_text(&first.response))
+        .map(str::to_string);
+
+    let variants = attempts
+        .into_iter()
+        .map(|attempt| {
+            let reply = interactions::reply_text(&attempt.response).map(str::to_string);
+            let diff = match (&original_reply, &reply) {
+                (Some(old), Some(new)) if attempt.id != original_id => {
+                    Some(text_diff::diff(old, new))
+                }
+                _ => None,
+            };
+            Variant {
+                interaction: attempt,
+                reply,
+                diff,
+            }
+        })
+        .collect();
+
+    Ok(Json(Variants {
+        original_id,
+        variants,
+    }))
+}
+
+/// Chunk arrival times for a streamed interaction, with reply characters
+/// bucketed per second. Empty unless `LLM_STREAM_TELEMETRY` was on when it
+/// streamed.
+pub async fn get_stream_telemetry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<StreamTelemetry>, AppError> {
+    ensure_exists(&state.pool, id).await?;
+    Ok(Json(stream_telemetry::load(&state.pool, id).await?))
+}
+
+/// Every turn of a conversation in order, regenerated attempts included
+/// (they share `parent_interaction_id` with the turn they retry).
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Conversation>, AppError> {
+    let turns = interactions::conversation(&state.pool, id).await?;
+    if turns.is_empty() {
+        return Err(AppError::NotFound(format!("conversation {id}")));
+    }
+
+    Ok(Json(Conversation {
+        conversation_id: id,
+        turns: turns.into_iter().map(ParsedInteraction::from).collect(),
+    }))
+}
+
+/// The turns leading up to an interaction, first turn first, following
+/// `parent_interaction_id` links; the last item is the interaction itself.
+pub async fn get_thread(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ParsedInteraction>>, AppError> {
+    let turns = interactions::thread(&state.pool, id).await?;
+    if turns.is_empty() {
+        return Err(AppError::NotFound(format!("interaction {id}")));
+    }
+
+    Ok(Json(
+        turns.into_iter().map(ParsedInteraction::from).collect(),
+    ))
+}
+
+pub async fn get_interaction(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<InteractionDetail>, AppError> {
+    let interaction: ParsedInteraction = interactions::get(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("interaction {id}")))?
+        .into();
+
+    let regenerations = sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM ai_interactions WHERE regenerated_from = ? ORDER BY id ASC",
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+    let help_session_id = sqlx::query_scalar::<_, i64>(
+        "SELECT session_id FROM help_messages WHERE interaction_id = ? LIMIT 1",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let notes = sqlx::query_as::<_, StudentNote>(
+        r#"
+        SELECT id, student_id, author, body, interaction_id, created_at, updated_at
+        FROM student_notes
+        WHERE interaction_id = ?
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+    let standards = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT s.code
+        FROM interaction_standards x
+        JOIN standards s ON s.id = x.standard_id
+        WHERE x.interaction_id = ?
+        ORDER BY s.code ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let feedback = sqlx::query_as::<_, Feedback>(&format!(
+        "SELECT {FEEDBACK_COLUMNS} FROM interaction_feedback WHERE interaction_id = ? ORDER BY id ASC"
+    ))
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let tags = interaction_tags::for_interaction(&state.pool, id).await?;
+
+    let upstream_content_filter =
+        interaction.response["choices"]
+            .as_array()
+            .is_some_and(|choices| {
+                choices
+                    .iter()
+                    .any(|c| c["finish_reason"].as_str() == Some("content_filter"))
+            });
+    let review = sqlx::query_as::<_, (i64, String)>(
+        "SELECT id, status FROM review_items WHERE interaction_id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let moderation = Moderation {
+        response_filtered: interaction.filtered,
+        upstream_content_filter,
+        review_item_id: review.as_ref().map(|(id, _)| *id),
+        review_status: review.map(|(_, status)| status),
+    };
+
+    Ok(Json(InteractionDetail {
+        interaction,
+        moderation,
+        regenerations,
+        help_session_id,
+        notes,
+        standards,
+        feedback,
+        tags,
+    }))
+}
+
+/// Records a thumbs up or down on a response. Several reviewers may rate
+/// the same interaction; `GET /reports/feedback` rolls them up.
+pub async fn create_feedback(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<FeedbackRequest>,
+) -> Result<Json<Feedback>, AppError> {
+    ensure_exists(&state.pool, id).await?;
+
+    let created = sqlx::query_as::<_, Feedback>(&format!(
+        r#"
+        INSERT INTO interaction_feedback(interaction_id, rating, comment, reviewer)
+        VALUES(?, ?, ?, ?)
+        RETURNING {FEEDBACK_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(&payload.rating)
+    .bind(
+        payload
+            .comment
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty()),
+    )
+    .bind(payload.reviewer.as_deref().map(str::trim))
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+/// Fails with 404 unless the interaction exists in the caller's school and
+/// is not about a student the caller can't see.
+pub async fn ensure_exists(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    let viewer = students::viewer();
+    let exists: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS(SELECT 1 FROM ai_interactions WHERE id = ? AND school_id = ? AND {})",
+        interactions::visible_filter("student_id"),
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_one(pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("interaction {id}")));
+    }
+    Ok(())
+}
+
+/// What a student asked the tutor and what it answered, newest first, with
+/// token usage per interaction.
+pub async fn student_interactions(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    Query(query): Query<StudentInteractionsQuery>,
+) -> Result<Json<Page<Interaction>>, AppError> {
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    v.finish()?;
+    students::ensure_exists(&state.pool, student_id).await?;
+    let (since, until) = match query.school_year_id {
+        Some(school_year_id) => school_years::date_range(&state.pool, school_year_id).await?,
+        None => (
+            query.since.unwrap_or_default(),
+            query.until.unwrap_or_else(|| "9999".to_string()),
+        ),
+    };
+
+    let page =
+        interactions::list_for_student(&state.pool, student_id, &since, &until, limit, offset)
+            .await?;
+
+    Ok(Json(page))
+}
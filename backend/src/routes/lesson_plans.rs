@@ -0,0 +1,514 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    auth,
+    error::AppError,
+    routes::{students::GRADE_LEVELS, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_ITEMS: usize = 20;
+const MAX_ITEM_CHARS: usize = 2_000;
+
+/// The structured plan the model is asked for and teachers edit.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LessonPlanContent {
+    pub objectives: Vec<String>,
+    #[serde(default)]
+    pub materials: Vec<String>,
+    pub activities: Vec<Activity>,
+    pub assessment: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Activity {
+    pub title: String,
+    #[serde(default)]
+    pub minutes: Option<i64>,
+    pub description: String,
+}
+
+/// A plan at its latest version.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LessonPlan {
+    pub id: i64,
+    pub title: String,
+    pub subject: String,
+    pub grade_level: String,
+    pub version: i64,
+    pub content: SqlJson<LessonPlanContent>,
+    pub author: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LessonPlanVersion {
+    pub version: i64,
+    pub content: SqlJson<LessonPlanContent>,
+    /// `model` for generated drafts, `teacher` for edits.
+    pub author: String,
+    /// The `ai_interactions` row for generated drafts.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateLessonPlanRequest {
+    pub subject: String,
+    pub grade_level: String,
+    /// What the lesson covers; defaults to the subject.
+    pub topic: Option<String>,
+    pub duration_minutes: Option<i64>,
+    /// Extra guidance such as available materials or class size.
+    pub notes: Option<String>,
+    /// Defaults to the topic.
+    pub title: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for GenerateLessonPlanRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("subject", &self.subject);
+        v.max_chars("subject", &self.subject, 100);
+        v.one_of("grade_level", &self.grade_level, GRADE_LEVELS);
+        if let Some(topic) = &self.topic {
+            v.required("topic", topic);
+            v.max_chars("topic", topic, 500);
+        }
+        if let Some(minutes) = self.duration_minutes {
+            v.range("duration_minutes", minutes, 5, 480);
+        }
+        if let Some(notes) = &self.notes {
+            v.max_chars("notes", notes, MAX_ITEM_CHARS);
+        }
+        if let Some(title) = &self.title {
+            v.required("title", title);
+            v.max_chars("title", title, 200);
+        }
+        check_model(v, self.model.as_deref());
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateLessonPlanRequest {
+    /// What to change, e.g. "shorter intro, add a group activity".
+    pub instructions: String,
+    pub model: Option<String>,
+    pub user_id: Option<i64>,
+    /// Version the revision was based on; rejected with 409 if it is stale.
+    pub base_version: Option<i64>,
+}
+
+impl Validate for RegenerateLessonPlanRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("instructions", &self.instructions);
+        v.max_chars("instructions", &self.instructions, MAX_ITEM_CHARS);
+        check_model(v, self.model.as_deref());
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateLessonPlanRequest {
+    pub title: Option<String>,
+    pub content: LessonPlanContent,
+    /// Version the edit was based on; rejected with 409 if it is stale.
+    pub base_version: Option<i64>,
+}
+
+impl Validate for UpdateLessonPlanRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(title) = &self.title {
+            v.required("title", title);
+            v.max_chars("title", title, 200);
+        }
+        check_content(v, &self.content);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LessonPlanListQuery {
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+}
+
+fn check_model(v: &mut Validator, model: Option<&str>) {
+    if let Some(model) = model {
+        v.required("model", model);
+        v.max_chars("model", model, 200);
+    }
+}
+
+fn check_content(v: &mut Validator, content: &LessonPlanContent) {
+    if content.objectives.is_empty() {
+        v.error("content.objectives", "must not be empty");
+    }
+    if content.activities.is_empty() {
+        v.error("content.activities", "must not be empty");
+    }
+    for (field, items) in [
+        ("content.objectives", &content.objectives),
+        ("content.materials", &content.materials),
+    ] {
+        if items.len() > MAX_ITEMS {
+            v.error(field, format!("must have at most {MAX_ITEMS} items"));
+        }
+        for (i, item) in items.iter().enumerate() {
+            v.required(&format!("{field}[{i}]"), item);
+            v.max_chars(&format!("{field}[{i}]"), item, MAX_ITEM_CHARS);
+        }
+    }
+    if content.activities.len() > MAX_ITEMS {
+        v.error(
+            "content.activities",
+            format!("must have at most {MAX_ITEMS} items"),
+        );
+    }
+    for (i, activity) in content.activities.iter().enumerate() {
+        let field = format!("content.activities[{i}]");
+        v.required(&format!("{field}.title"), &activity.title);
+        v.max_chars(&format!("{field}.title"), &activity.title, 200);
+        v.required(&format!("{field}.description"), &activity.description);
+        v.max_chars(
+            &format!("{field}.description"),
+            &activity.description,
+            MAX_ITEM_CHARS,
+        );
+        if let Some(minutes) = activity.minutes {
+            v.range(&format!("{field}.minutes"), minutes, 1, 480);
+        }
+    }
+    v.required("content.assessment", &content.assessment);
+    v.max_chars("content.assessment", &content.assessment, MAX_ITEM_CHARS);
+}
+
+const SYSTEM_PROMPT: &str = "You write lesson plans for teachers. Make objectives \
+    measurable, list only materials a typical classroom or home has, give activities \
+    with realistic minute counts, and describe how learning will be assessed. Reply \
+    with only a JSON object: {\"objectives\": [string], \"materials\": [string], \
+    \"activities\": [{\"title\": string, \"minutes\": integer, \"description\": string}], \
+    \"assessment\": string}.";
+
+const PLAN_SELECT: &str = r#"
+    SELECT p.id, p.title, p.subject, p.grade_level, v.version, v.content, v.author,
+           p.created_at, v.created_at AS updated_at
+    FROM lesson_plans p
+    JOIN lesson_plan_versions v ON v.plan_id = p.id
+        AND v.version = (SELECT MAX(version) FROM lesson_plan_versions WHERE plan_id = p.id)
+"#;
+
+/// Drafts a lesson plan with the local model and saves it as version 1.
+pub async fn generate_lesson_plan(
+    State(state): State<AppState>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<GenerateLessonPlanRequest>,
+) -> Result<Json<LessonPlan>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let subject = payload.subject.trim();
+    let topic = payload.topic.as_deref().map(str::trim).unwrap_or(subject);
+
+    let mut request = vec![
+        format!("Subject: {subject}"),
+        format!("Grade level: {}", payload.grade_level),
+        format!("Topic: {topic}"),
+    ];
+    if let Some(minutes) = payload.duration_minutes {
+        request.push(format!("Lesson length: {minutes} minutes"));
+    }
+    if let Some(notes) = &payload.notes {
+        request.push(format!("Teacher notes: {}", notes.trim()));
+    }
+
+    let (content, generation_id) = draft(
+        &state,
+        request.join("\n"),
+        payload.model,
+        payload.user_id,
+        trace,
+    )
+    .await?;
+
+    let mut tx = state.pool.begin().await?;
+    let plan_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO lesson_plans(title, subject, grade_level, school_id)
+        VALUES(?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(payload.title.as_deref().map(str::trim).unwrap_or(topic))
+    .bind(subject)
+    .bind(&payload.grade_level)
+    .bind(auth::school_id())
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO lesson_plan_versions(plan_id, version, content, author, generation_id)
+        VALUES(?, 1, ?, 'model', ?)
+        "#,
+    )
+    .bind(plan_id)
+    .bind(SqlJson(&content))
+    .bind(generation_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    load(&state.pool, plan_id).await.map(Json)
+}
+
+/// Asks the model to revise the current version and saves the result as the
+/// next version.
+pub async fn regenerate_lesson_plan(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<RegenerateLessonPlanRequest>,
+) -> Result<Json<LessonPlan>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let current = load(&state.pool, id).await?;
+    check_base(current.version, payload.base_version)?;
+
+    let request = format!(
+        "Subject: {}\nGrade level: {}\nCurrent plan:\n{}\n\nRevise the plan: {}",
+        current.subject,
+        current.grade_level,
+        serde_json::to_string_pretty(&current.content.0)
+            .map_err(|err| AppError::Internal(err.to_string()))?,
+        payload.instructions.trim()
+    );
+    let (content, generation_id) =
+        draft(&state, request, payload.model, payload.user_id, trace).await?;
+
+    save_version(
+        &state.pool,
+        id,
+        &content,
+        "model",
+        Some(generation_id),
+        Some(current.version),
+    )
+    .await?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn list_lesson_plans(
+    State(state): State<AppState>,
+    Query(query): Query<LessonPlanListQuery>,
+) -> Result<Json<Vec<LessonPlan>>, AppError> {
+    let mut v = Validator::default();
+    if let Some(grade) = &query.grade_level {
+        v.one_of("grade_level", grade, GRADE_LEVELS);
+    }
+    v.finish()?;
+
+    let rows = sqlx::query_as::<_, LessonPlan>(&format!(
+        r#"
+        {PLAN_SELECT}
+        WHERE p.school_id = ?
+          AND (? IS NULL OR lower(p.subject) = lower(?))
+          AND (? IS NULL OR p.grade_level = ?)
+        ORDER BY v.created_at DESC, p.id DESC
+        "#
+    ))
+    .bind(auth::school_id())
+    .bind(&query.subject)
+    .bind(&query.subject)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_lesson_plan(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<LessonPlan>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Saves a teacher's edit as a new version.
+pub async fn update_lesson_plan(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UpdateLessonPlanRequest>,
+) -> Result<Json<LessonPlan>, AppError> {
+    save_version(
+        &state.pool,
+        id,
+        &payload.content,
+        "teacher",
+        None,
+        payload.base_version,
+    )
+    .await?;
+    if let Some(title) = &payload.title {
+        sqlx::query("UPDATE lesson_plans SET title = ? WHERE id = ? AND school_id = ?")
+            .bind(title.trim())
+            .bind(id)
+            .bind(auth::school_id())
+            .execute(&state.pool)
+            .await?;
+    }
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn delete_lesson_plan(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM lesson_plans WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("lesson plan {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Every version, oldest first.
+pub async fn list_lesson_plan_versions(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<LessonPlanVersion>>, AppError> {
+    load(&state.pool, id).await?;
+
+    let rows = sqlx::query_as::<_, LessonPlanVersion>(
+        r#"
+        SELECT version, content, author, generation_id, created_at
+        FROM lesson_plan_versions
+        WHERE plan_id = ?
+        ORDER BY version ASC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+async fn draft(
+    state: &AppState,
+    request: String,
+    model: Option<String>,
+    user_id: Option<i64>,
+    trace: TraceHeaders,
+) -> Result<(LessonPlanContent, i64), AppError> {
+    let (content, completion) = assist::complete_json::<LessonPlanContent>(
+        state,
+        Prompt {
+            system: SYSTEM_PROMPT.to_string(),
+            user: request,
+            model,
+            temperature: Some(0.5),
+            max_tokens: Some(3_000),
+            user_id,
+            student_id: None,
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let mut v = Validator::default();
+    check_content(&mut v, &content);
+    if v.finish().is_err() {
+        return Err(AppError::Upstream(format!(
+            "generated lesson plan is incomplete; interaction {}",
+            completion.interaction_id
+        )));
+    }
+
+    Ok((content, completion.interaction_id))
+}
+
+fn check_base(current: i64, base_version: Option<i64>) -> Result<(), AppError> {
+    match base_version {
+        Some(base) if base != current => Err(AppError::Conflict(format!(
+            "lesson plan is at version {current}, edit was based on {base}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+async fn save_version(
+    pool: &SqlitePool,
+    plan_id: i64,
+    content: &LessonPlanContent,
+    author: &str,
+    generation_id: Option<i64>,
+    base_version: Option<i64>,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Touch the plan first so the transaction takes the write lock before
+    // reading the current version.
+    let touched =
+        sqlx::query("UPDATE lesson_plans SET title = title WHERE id = ? AND school_id = ?")
+            .bind(plan_id)
+            .bind(auth::school_id())
+            .execute(&mut *tx)
+            .await?;
+    if touched.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("lesson plan {plan_id}")));
+    }
+
+    let current = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(MAX(version), 0) FROM lesson_plan_versions WHERE plan_id = ?",
+    )
+    .bind(plan_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    check_base(current, base_version)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO lesson_plan_versions(plan_id, version, content, author, generation_id)
+        VALUES(?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(plan_id)
+    .bind(current + 1)
+    .bind(SqlJson(content))
+    .bind(author)
+    .bind(generation_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<LessonPlan, AppError> {
+    sqlx::query_as::<_, LessonPlan>(&format!("{PLAN_SELECT} WHERE p.id = ? AND p.school_id = ?"))
+        .bind(id)
+        .bind(auth::school_id())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("lesson plan {id}")))
+}
@@ -1,73 +1,784 @@
-use axum::{extract::State, Json};
+use std::{
+    convert::Infallible,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tracing::warn;
 
-use crate::{app_state::AppState, error::AppError};
+use crate::{
+    app_state::AppState,
+    auth,
+    config::LlmBackendKind,
+    error::AppError,
+    experiment::Variant,
+    idle,
+    interactions::{self, NewInteraction},
+    ollama, rate_limit,
+    routes::{
+        consents, few_shot, grade_policies, personas, scratchpads,
+        students::{self, StudentProfile},
+        tags, users,
+    },
+    scheduler::InferencePermit,
+    stream_telemetry::{self, ChunkSample},
+    trace::TraceHeaders,
+    upstream::{Backend, BackendHealthStatus, BackendLease},
+    validation::{ValidJson, Validate, Validator},
+};
 
 #[derive(Debug, Deserialize)]
 pub struct LlmProxyRequest {
+    /// Account making the request; must exist when given. Ignored when the
+    /// request is authenticated, which records the caller instead.
     pub user_id: Option<i64>,
     pub student_id: Option<i64>,
+    /// Conversation whose scratchpad is shared with the model.
+    pub session_id: Option<String>,
+    /// Class the request belongs to, for reservation priority.
+    pub class_name: Option<String>,
+    /// Persona whose system prompt and parameters apply to this request.
+    pub persona_id: Option<i64>,
+    /// Injects matching examples from the few-shot bank.
+    pub few_shot: Option<FewShotOptions>,
+    /// Continues this conversation after its latest turn.
+    pub conversation_id: Option<i64>,
+    /// The turn this request follows; its conversation is used.
+    pub parent_interaction_id: Option<i64>,
     pub payload: Value,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct FewShotOptions {
+    pub subject: String,
+    pub count: Option<usize>,
+}
+
+const MESSAGE_ROLES: &[&str] = &["system", "user", "assistant", "tool"];
+
+impl Validate for LlmProxyRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(session_id) = &self.session_id {
+            v.required("session_id", session_id);
+            v.max_chars("session_id", session_id, scratchpads::MAX_SESSION_ID_CHARS);
+        }
+
+        if let Some(few_shot) = &self.few_shot {
+            v.required("few_shot.subject", &few_shot.subject);
+            v.max_chars("few_shot.subject", &few_shot.subject, 100);
+            if let Some(count) = few_shot.count {
+                v.range("few_shot.count", count, 1, few_shot::MAX_COUNT);
+            }
+        }
+
+        let Some(payload) = self.payload.as_object() else {
+            v.error("payload", "must be a JSON object");
+            return;
+        };
+
+        match payload.get("messages").and_then(Value::as_array) {
+            Some(messages) if !messages.is_empty() => {
+                for (i, message) in messages.iter().enumerate() {
+                    let field = format!("payload.messages[{i}]");
+                    match message.get("role").and_then(Value::as_str) {
+                        Some(role) => v.one_of(&format!("{field}.role"), role, MESSAGE_ROLES),
+                        None => v.error(format!("{field}.role"), "is required"),
+                    }
+                    match message.get("content") {
+                        Some(Value::String(_) | Value::Array(_) | Value::Null) => {}
+                        Some(_) => v.error(format!("{field}.content"), "must be a string or array"),
+                        None => v.error(format!("{field}.content"), "is required"),
+                    }
+                }
+            }
+            _ => v.error("payload.messages", "must be a non-empty array"),
+        }
+
+        if let Some(temperature) = payload.get("temperature") {
+            match temperature.as_f64() {
+                Some(t) => v.range("payload.temperature", t, 0.0, 2.0),
+                None => v.error("payload.temperature", "must be a number"),
+            }
+        }
+        if let Some(max_tokens) = payload.get("max_tokens") {
+            match max_tokens.as_i64() {
+                Some(n) => v.range("payload.max_tokens", n, 1, 32_768),
+                None => v.error("payload.max_tokens", "must be an integer"),
+            }
+        }
+        if let Some(stream) = payload.get("stream") {
+            if !stream.is_boolean() {
+                v.error("payload.stream", "must be a boolean");
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct LlmProxyResponse {
+    /// Stored `ai_interactions` row, e.g. for `/interactions/:id/regenerate`.
+    pub interaction_id: i64,
+    /// Send back as `conversation_id` to continue the thread.
+    pub conversation_id: i64,
     pub upstream: Value,
 }
 
+/// Result of a proxied chat request: either a live SSE relay, or a complete
+/// response that has already been stored.
+pub enum ChatOutcome {
+    Stream(Response),
+    Complete(LlmProxyResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct LlmStatusResponse {
+    pub monitor_interval_secs: u64,
+    /// The model was released after `LLM_IDLE_SHUTDOWN_SECS` without
+    /// requests and loads again on the next chat.
+    pub idle_asleep: bool,
+    pub backends: Vec<BackendHealthStatus>,
+}
+
+pub async fn llm_status(State(state): State<AppState>) -> Json<LlmStatusResponse> {
+    Json(LlmStatusResponse {
+        monitor_interval_secs: state.config.llm_health_interval_secs,
+        idle_asleep: state.idle.asleep(),
+        backends: state.upstreams.health_status(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingsRequest {
+    /// OpenAI-style embeddings body, e.g. `{ "model": ..., "input": [...] }`.
+    pub payload: Value,
+}
+
+impl Validate for EmbeddingsRequest {
+    fn validate(&self, v: &mut Validator) {
+        let Some(payload) = self.payload.as_object() else {
+            v.error("payload", "must be a JSON object");
+            return;
+        };
+        match payload.get("input") {
+            Some(Value::String(input)) => v.required("payload.input", input),
+            Some(Value::Array(inputs)) if !inputs.is_empty() => {}
+            _ => v.error("payload.input", "must be a string or a non-empty array"),
+        }
+    }
+}
+
+/// Forwards an embeddings request to the deployment named by `model`, or
+/// to the primary backend. Embeddings are not stored as interactions.
+pub async fn proxy_embeddings(
+    State(state): State<AppState>,
+    ValidJson(body): ValidJson<EmbeddingsRequest>,
+) -> Result<Json<Value>, AppError> {
+    let backend = body
+        .payload
+        .get("model")
+        .and_then(Value::as_str)
+        .and_then(|model| state.upstreams.deployment(model))
+        .unwrap_or_else(|| state.upstreams.pick());
+    let url = backend.url(state.config.llm_backend_kind.embeddings_path());
+
+    let started = Instant::now();
+    let response = state.llm_client.post(url).json(&body.payload).send().await;
+    let status = response.as_ref().ok().map(|r| r.status());
+    let upstream_json: Result<Value, AppError> = match response {
+        Ok(response) => response.json().await.map_err(AppError::from),
+        Err(err) => Err(err.into()),
+    };
+    let ok = status.is_some_and(|s| s.is_success()) && upstream_json.is_ok();
+    backend.metrics.record(started.elapsed(), ok);
+
+    let upstream_json = upstream_json?;
+    if !ok {
+        return Err(AppError::Upstream(upstream_json.to_string()));
+    }
+    Ok(Json(upstream_json))
+}
+
 pub async fn proxy_chat_completion(
     State(state): State<AppState>,
-    Json(body): Json<LlmProxyRequest>,
-) -> Result<Json<LlmProxyResponse>, AppError> {
-    if !body.payload.is_object() {
-        return Err(AppError::BadRequest(
-            "payload must be a JSON object".to_string(),
-        ));
-    }
-
-    let url = format!(
-        "{}{}",
-        state.config.llm_base_url.trim_end_matches('/'),
-        state.config.llm_chat_path
-    );
+    trace: TraceHeaders,
+    ValidJson(body): ValidJson<LlmProxyRequest>,
+) -> Result<Response, AppError> {
+    match chat(state, body, trace, None).await? {
+        ChatOutcome::Stream(response) => Ok(response),
+        ChatOutcome::Complete(completion) => Ok(Json(completion).into_response()),
+    }
+}
+
+/// Sends a validated chat request upstream and records the interaction,
+/// linking it to `regenerated_from` when it replays an earlier prompt.
+pub async fn chat(
+    state: AppState,
+    body: LlmProxyRequest,
+    trace: TraceHeaders,
+    regenerated_from: Option<i64>,
+) -> Result<ChatOutcome, AppError> {
+    let received = Instant::now();
+    let caller = auth::caller();
+    if let Some(caller) = &caller {
+        rate_limit::check_tokens(&state, caller.id).await?;
+    } else if let Some(user_id) = body.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let user_id = caller.map(|caller| caller.id).or(body.user_id);
+    if let Some(student_id) = body.student_id {
+        students::check_reference(&state.pool, "student_id", student_id).await?;
+        consents::ensure_granted(&state, student_id).await?;
+    }
+    let (conversation_id, parent_interaction_id) = resolve_thread(
+        &state.pool,
+        body.conversation_id,
+        body.parent_interaction_id,
+    )
+    .await?;
+
+    let kind = state.config.llm_backend_kind;
+    let stream = body.payload.get("stream").and_then(Value::as_bool) == Some(true);
+    let mut payload = body.payload.clone();
+    if let Some(session_id) = &body.session_id {
+        let scratchpad = scratchpads::latest(&state.pool, session_id).await?;
+        // Tool calls are only applied to complete responses, so streams get
+        // the scratchpad as read-only context.
+        scratchpads::attach(&mut payload, scratchpad.as_ref(), !stream);
+    }
+
+    if stream && kind == LlmBackendKind::OpenAi && payload.get("stream_options").is_none() {
+        // Ask for a final usage chunk so streamed requests can be costed.
+        payload["stream_options"] = json!({ "include_usage": true });
+    }
 
-    let response = state
-        .llm_client
-        .post(url)
-        .json(&body.payload)
-        .send()
+    let profile = match body.student_id {
+        Some(student_id) => students::profile(&state.pool, student_id).await?,
+        None => StudentProfile::default(),
+    };
+    profile.apply(&mut payload);
+    let grade = profile.grade_level;
+    if let Some(persona_id) = body.persona_id {
+        let persona = personas::load(&state.pool, persona_id).await?;
+        persona.check_grade(grade.as_deref())?;
+        if let (Some(student_id), Some(_)) = (body.student_id, &persona.allowed_tags) {
+            let student_tags = tags::names_for_student(&state.pool, student_id).await?;
+            persona.check_tags(&student_tags)?;
+        }
+        persona.apply(&mut payload);
+    }
+    if let Some(options) = &body.few_shot {
+        let query = last_user_message(&payload).unwrap_or_default();
+        let examples = few_shot::select(
+            &state.pool,
+            options.subject.trim(),
+            grade.as_deref(),
+            &query,
+            options.count.unwrap_or(few_shot::DEFAULT_COUNT),
+        )
         .await?;
+        few_shot::inject(&mut payload, &examples);
+    }
+
+    let policies = match body.student_id {
+        Some(student_id) => grade_policies::for_student(&state.pool, student_id).await?,
+        None => Vec::new(),
+    };
+    for policy in &policies {
+        policy.apply(&mut payload)?;
+    }
 
+    let assignment = state
+        .experiment
+        .as_ref()
+        .map(|experiment| (experiment, experiment.assign(body.student_id)));
+    let treatment = matches!(assignment, Some((_, Variant::Treatment)));
+    if let Some((experiment, Variant::Treatment)) = assignment {
+        // The experiment never moves a student onto a model their grade or
+        // tag policies exclude.
+        if let Some(model) = &experiment.model {
+            if policies.iter().all(|p| p.allows_model(model)) {
+                payload["model"] = json!(model);
+            }
+        }
+    }
+    let requested_model = payload
+        .get("model")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let upstream_payload = match kind {
+        LlmBackendKind::OpenAi => payload,
+        LlmBackendKind::Ollama => ollama::chat_request(&payload),
+    };
+
+    let queued = Instant::now();
+    idle::wake(&state).await?;
+    let permit = state
+        .scheduler
+        .acquire(&state.pool, body.class_name.as_deref())
+        .await?;
+    let experiment_backend = if treatment {
+        state.upstreams.experiment()
+    } else {
+        None
+    };
+    let backend = experiment_backend
+        .or_else(|| {
+            requested_model
+                .as_deref()
+                .and_then(|model| state.upstreams.deployment(model))
+        })
+        .unwrap_or_else(|| state.upstreams.pick());
+    let url = backend.url(&state.config.llm_chat_path);
+    let mut timing = Timing {
+        received,
+        started: Instant::now(),
+        retry_wait: Duration::ZERO,
+    };
+
+    let record = NewInteraction {
+        user_id,
+        student_id: body.student_id,
+        prompt: prompt_text(&body.payload),
+        model: requested_model,
+        experiment: assignment.map(|(experiment, _)| experiment.name.clone()),
+        variant: assignment.map(|(_, variant)| variant.as_str().to_string()),
+        regenerated_from,
+        conversation_id,
+        parent_interaction_id,
+        persona_id: body.persona_id,
+        request_id: trace.request_id.clone(),
+        school_id: Some(auth::school_id()),
+        traceparent: trace.traceparent.clone(),
+        queue_ms: Some(timing.started.duration_since(queued).as_millis() as i64),
+        ..Default::default()
+    };
+    let mut cancel = CancelGuard::new(state.pool.clone(), &record, timing);
+
+    let response = cancel
+        .watch(send_upstream(
+            &state,
+            &backend,
+            &url,
+            &upstream_payload,
+            &trace,
+            &mut timing,
+        ))
+        .await?;
     let status = response.status();
-    let upstream_json: Value = response.json().await?;
+
+    if stream && status.is_success() {
+        return Ok(ChatOutcome::Stream(stream_chat(
+            state, permit, backend, kind, response, record, timing,
+        )));
+    }
+
+    let upstream_json: Result<Value, _> = cancel.watch(response.json()).await;
+    drop(permit);
+    let latency = timing.started.elapsed();
+    backend
+        .metrics
+        .record(latency, status.is_success() && upstream_json.is_ok());
+    let upstream_json = upstream_json?;
 
     if !status.is_success() {
         return Err(AppError::Upstream(upstream_json.to_string()));
     }
 
-    let prompt_text = body
-        .payload
-        .get("messages")
-        .map(ToString::to_string)
-        .unwrap_or_else(|| body.payload.to_string());
+    let mut upstream_json = match kind {
+        LlmBackendKind::OpenAi => upstream_json,
+        LlmBackendKind::Ollama => ollama::chat_response(&upstream_json),
+    };
+    let filtered = state.response_filter.apply_completion(&mut upstream_json);
 
-    let response_text = upstream_json.to_string();
+    if let Some(session_id) = &body.session_id {
+        scratchpads::apply_tool_calls(&state.pool, session_id, body.student_id, &upstream_json)
+            .await?;
+    }
 
-    sqlx::query(
-        r#"
-        INSERT INTO ai_interactions (user_id, student_id, prompt, response)
-        VALUES (?, ?, ?, ?)
-        "#,
-    )
-    .bind(body.user_id)
-    .bind(body.student_id)
-    .bind(prompt_text)
-    .bind(response_text)
-    .execute(&state.pool)
-    .await?;
+    let mut record = NewInteraction {
+        response: upstream_json.to_string(),
+        filtered,
+        ..record
+    };
+    timing.finish(&mut record, latency);
+    record.apply_usage(&upstream_json);
+    record.estimated_cost = state.config.estimate_cost(
+        record.model.as_deref(),
+        record.prompt_tokens,
+        record.completion_tokens,
+    );
+    let interaction_id = interactions::insert(&state.pool, record).await?;
 
-    Ok(Json(LlmProxyResponse {
+    Ok(ChatOutcome::Complete(LlmProxyResponse {
+        interaction_id,
+        conversation_id: conversation_id.unwrap_or(interaction_id),
         upstream: upstream_json,
     }))
 }
+
+/// Works out where a new turn goes: after `parent_interaction_id` when
+/// given, otherwise after the latest turn of `conversation_id`. With
+/// neither, the turn starts a new conversation. Returns the conversation and
+/// parent to store.
+async fn resolve_thread(
+    pool: &SqlitePool,
+    conversation_id: Option<i64>,
+    parent_interaction_id: Option<i64>,
+) -> Result<(Option<i64>, Option<i64>), AppError> {
+    let mut v = Validator::default();
+
+    if let Some(parent_id) = parent_interaction_id {
+        let parent_conversation = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT conversation_id FROM ai_interactions WHERE id = ? AND school_id = ?",
+        )
+        .bind(parent_id)
+        .bind(auth::school_id())
+        .fetch_optional(pool)
+        .await?;
+        match parent_conversation {
+            None => v.error(
+                "parent_interaction_id",
+                format!("interaction {parent_id} does not exist"),
+            ),
+            Some(parent_conversation)
+                if conversation_id.is_some_and(|id| Some(id) != parent_conversation) =>
+            {
+                v.error(
+                    "conversation_id",
+                    "does not match the parent interaction's conversation",
+                )
+            }
+            Some(_) => {}
+        }
+        v.finish()?;
+        return Ok((parent_conversation.flatten(), Some(parent_id)));
+    }
+
+    let Some(conversation_id) = conversation_id else {
+        return Ok((None, None));
+    };
+    let latest = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT MAX(id) FROM ai_interactions WHERE conversation_id = ? AND school_id = ?",
+    )
+    .bind(conversation_id)
+    .bind(auth::school_id())
+    .fetch_one(pool)
+    .await?;
+    if latest.is_none() {
+        v.error(
+            "conversation_id",
+            format!("conversation {conversation_id} does not exist"),
+        );
+    }
+    v.finish()?;
+    Ok((Some(conversation_id), latest))
+}
+
+/// Relays the upstream stream to the client as OpenAI-style SSE chunks and
+/// persists the assembled completion once the stream ends.
+fn stream_chat(
+    state: AppState,
+    permit: InferencePermit,
+    backend: BackendLease,
+    kind: LlmBackendKind,
+    response: reqwest::Response,
+    mut record: NewInteraction,
+    timing: Timing,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(32);
+
+    tokio::spawn(async move {
+        let mut upstream = Box::pin(response.bytes_stream());
+        let mut buf = Vec::new();
+        let mut completion = String::new();
+        let mut ttft = None;
+        let mut samples = Vec::new();
+        let mut ok = true;
+        let mut cancelled = false;
+
+        'relay: loop {
+            // Watching for the client going away lets us stop reading (and
+            // close the upstream connection) even while the model is silent.
+            let next = tokio::select! {
+                next = upstream.next() => next,
+                _ = tx.closed() => {
+                    cancelled = true;
+                    break;
+                }
+            };
+            let Some(bytes) = next else {
+                break;
+            };
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!(error = %err, "upstream stream failed");
+                    ok = false;
+                    break;
+                }
+            };
+            buf.extend_from_slice(&bytes);
+
+            while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buf.drain(..=pos).collect();
+                let Some(mut chunk) =
+                    decode_stream_line(kind, String::from_utf8_lossy(&line).trim())
+                else {
+                    continue;
+                };
+                // Patterns that span chunk boundaries are not caught here.
+                record.filtered |= state.response_filter.apply_completion(&mut chunk);
+
+                let mut content_chars = 0;
+                if let Some(delta) = chunk
+                    .pointer("/choices/0/delta/content")
+                    .and_then(Value::as_str)
+                {
+                    completion.push_str(delta);
+                    content_chars = delta.chars().count();
+                    ttft.get_or_insert_with(|| timing.started.elapsed());
+                }
+                record.apply_usage(&chunk);
+                if state.config.llm_stream_telemetry {
+                    samples.push(ChunkSample {
+                        offset_ms: timing.started.elapsed().as_millis() as i64,
+                        bytes: line.len() as i64,
+                        content_chars: content_chars as i64,
+                    });
+                }
+
+                if tx
+                    .send(Ok(Event::default().data(chunk.to_string())))
+                    .await
+                    .is_err()
+                {
+                    cancelled = true;
+                    break 'relay;
+                }
+            }
+        }
+
+        drop(upstream);
+        if !cancelled {
+            let _ = tx.send(Ok(Event::default().data("[DONE]"))).await;
+        }
+        drop(permit);
+        let latency = timing.started.elapsed();
+        backend.metrics.record(latency, ok);
+
+        record.response = completion;
+        record.cancelled = cancelled;
+        timing.finish(&mut record, latency);
+        record.ttft_ms = ttft.map(|t| t.as_millis() as i64);
+        record.estimated_cost = state.config.estimate_cost(
+            record.model.as_deref(),
+            record.prompt_tokens,
+            record.completion_tokens,
+        );
+
+        let interaction_id = match interactions::insert(&state.pool, record).await {
+            Ok(id) => id,
+            Err(err) => {
+                warn!(error = %err, "failed to persist streamed interaction");
+                return;
+            }
+        };
+        if !samples.is_empty() {
+            if let Err(err) = stream_telemetry::record(&state.pool, interaction_id, &samples).await
+            {
+                warn!(interaction_id, error = %err, "failed to persist stream telemetry");
+            }
+        }
+    });
+
+    Sse::new(ReceiverStream::new(rx)).into_response()
+}
+
+/// Parses one line of an upstream stream into an OpenAI chunk. OpenAI-style
+/// servers send SSE `data:` lines; Ollama sends NDJSON.
+fn decode_stream_line(kind: LlmBackendKind, line: &str) -> Option<Value> {
+    match kind {
+        LlmBackendKind::OpenAi => {
+            let data = line.strip_prefix("data:")?.trim();
+            if data == "[DONE]" {
+                return None;
+            }
+            serde_json::from_str(data).ok()
+        }
+        LlmBackendKind::Ollama => {
+            if line.is_empty() {
+                return None;
+            }
+            serde_json::from_str::<Value>(line)
+                .ok()
+                .map(|chunk| ollama::stream_chunk(&chunk))
+        }
+    }
+}
+
+/// Sends the chat request, waiting out `429`/`503` responses while the
+/// `Retry-After` delay still fits in the retry budget.
+async fn send_upstream(
+    state: &AppState,
+    backend: &Backend,
+    url: &str,
+    payload: &Value,
+    trace: &TraceHeaders,
+    timing: &mut Timing,
+) -> Result<reqwest::Response, AppError> {
+    let started = timing.started;
+    let retry_budget = Duration::from_millis(state.config.llm_retry_budget_ms);
+
+    loop {
+        let request = trace.apply(state.llm_client.post(url).json(payload));
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                backend.metrics.record(started.elapsed(), false);
+                return Err(err.into());
+            }
+        };
+        if !is_overloaded(response.status()) {
+            return Ok(response);
+        }
+
+        let retry_after = retry_after_secs(response.headers());
+        let wait = Duration::from_secs(retry_after.unwrap_or(1));
+        if started.elapsed() + wait > retry_budget {
+            backend.metrics.record(started.elapsed(), false);
+            let message = response.text().await.unwrap_or_default();
+            return Err(AppError::Overloaded {
+                retry_after,
+                message,
+            });
+        }
+        warn!(
+            url,
+            wait_secs = wait.as_secs(),
+            "upstream overloaded, retrying"
+        );
+        tokio::time::sleep(wait).await;
+        timing.retry_wait += wait;
+    }
+}
+
+/// Clock readings for one chat request, turned into the interaction's
+/// timing columns once it finishes.
+#[derive(Clone, Copy)]
+struct Timing {
+    /// When the proxy got the request.
+    received: Instant,
+    /// When the first upstream call went out, after queueing.
+    started: Instant,
+    /// Spent sleeping on `Retry-After` between upstream attempts.
+    retry_wait: Duration,
+}
+
+impl Timing {
+    /// `latency` is measured from `started`.
+    fn finish(&self, record: &mut NewInteraction, latency: Duration) {
+        record.latency_ms = Some(latency.as_millis() as i64);
+        record.upstream_ms = Some(latency.saturating_sub(self.retry_wait).as_millis() as i64);
+        record.total_ms = Some(self.received.elapsed().as_millis() as i64);
+    }
+}
+
+/// Records the interaction as cancelled if the handler future is dropped,
+/// i.e. the client disconnected, while a `watch`ed upstream call is pending.
+/// Dropping the future also drops the in-flight reqwest call, which closes
+/// the upstream connection so the server can stop generating.
+struct CancelGuard {
+    pool: SqlitePool,
+    record: NewInteraction,
+    timing: Timing,
+    armed: bool,
+}
+
+impl CancelGuard {
+    fn new(pool: SqlitePool, record: &NewInteraction, timing: Timing) -> Self {
+        Self {
+            pool,
+            record: record.clone(),
+            timing,
+            armed: false,
+        }
+    }
+
+    async fn watch<F: Future>(&mut self, fut: F) -> F::Output {
+        self.armed = true;
+        let out = fut.await;
+        self.armed = false;
+        out
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        let mut record = std::mem::take(&mut self.record);
+        record.cancelled = true;
+        // Retry waits after the guard was armed aren't known here, so
+        // `upstream_ms` may include them.
+        self.timing
+            .finish(&mut record, self.timing.started.elapsed());
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            if let Err(err) = interactions::insert(&pool, record).await {
+                warn!(error = %err, "failed to persist cancelled interaction");
+            }
+        });
+    }
+}
+
+/// Statuses vLLM, llama.cpp, and Ollama use when their queue is full.
+fn is_overloaded(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// `Retry-After` in delay-seconds form; HTTP-date values are ignored.
+fn retry_after_secs(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn last_user_message(payload: &Value) -> Option<String> {
+    payload
+        .get("messages")?
+        .as_array()?
+        .iter()
+        .rev()
+        .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))?
+        .get("content")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn prompt_text(payload: &Value) -> String {
+    payload
+        .get("messages")
+        .map(ToString::to_string)
+        .unwrap_or_else(|| payload.to_string())
+}
@@ -1,3 +1,50 @@
+pub mod admin;
+pub mod analytics;
+pub mod api_keys;
+pub mod assignments;
+pub mod attendance;
+pub mod audit_log;
+pub mod auth;
+pub mod classes;
+pub mod consents;
+pub mod deployments;
+pub mod essays;
+pub mod exports;
+pub mod family_digests;
+pub mod few_shot;
+pub mod flashcards;
+pub mod goals;
+pub mod grade_policies;
+pub mod guardians;
+pub mod hardware;
 pub mod health;
+pub mod homework_help;
+pub mod inference;
+pub mod interaction_tags;
+pub mod interactions;
+pub mod lesson_plans;
 pub mod llm;
+pub mod model_downloads;
+pub mod models;
+pub mod password_resets;
+pub mod personas;
+pub mod presence;
+pub mod progress_reports;
+pub mod quizzes;
+pub mod reading_levels;
+pub mod report_links;
+pub mod reports;
+pub mod reservations;
+pub mod review_queue;
+pub mod rubrics;
+pub mod school_years;
+pub mod schools;
+pub mod scratchpads;
+pub mod sessions;
+pub mod standards;
+pub mod student_notes;
+pub mod student_shares;
 pub mod students;
+pub mod tags;
+pub mod users;
+pub mod vocabulary;
@@ -0,0 +1,190 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    model_download::{self, DownloadJob},
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ModelDownload {
+    pub id: i64,
+    pub repo_id: String,
+    pub filename: String,
+    pub revision: String,
+    pub dest_path: String,
+    pub status: String,
+    pub bytes_total: Option<i64>,
+    pub bytes_downloaded: i64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateModelDownloadRequest {
+    /// Hugging Face repo, e.g. `Qwen/Qwen2.5-7B-Instruct-GGUF`.
+    pub repo_id: String,
+    /// Path of the file within the repo.
+    pub filename: String,
+    pub revision: Option<String>,
+}
+
+impl Validate for CreateModelDownloadRequest {
+    fn validate(&self, v: &mut Validator) {
+        let valid_part = |part: &str| {
+            !part.is_empty()
+                && part != "."
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+        };
+
+        match self.repo_id.split_once('/') {
+            Some((owner, name)) if valid_part(owner) && valid_part(name) => {}
+            _ => v.error("repo_id", "must look like 'owner/name'"),
+        }
+        if !self.filename.split('/').all(valid_part) {
+            v.error(
+                "filename",
+                "must be a relative path of letters, digits, '.', '_', '-'",
+            );
+        }
+        if let Some(revision) = &self.revision {
+            if !valid_part(revision) {
+                v.error("revision", "must be a branch, tag, or commit hash");
+            }
+        }
+    }
+}
+
+const DOWNLOAD_COLUMNS: &str = "id, repo_id, filename, revision, dest_path, status, bytes_total, \
+                                bytes_downloaded, error, created_at, updated_at, finished_at";
+
+pub async fn create_model_download(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<CreateModelDownloadRequest>,
+) -> Result<Json<ModelDownload>, AppError> {
+    user.require_admin()?;
+    let dest = model_download::dest_path(
+        &state.config.models_dir,
+        &payload.repo_id,
+        &payload.filename,
+    );
+    let dest = dest.to_string_lossy().into_owned();
+
+    let active: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM model_downloads WHERE dest_path = ? AND status IN ('queued', 'running')",
+    )
+    .bind(&dest)
+    .fetch_optional(&state.pool)
+    .await?;
+    if let Some(id) = active {
+        return Err(AppError::Conflict(format!(
+            "download {id} is already writing {dest}"
+        )));
+    }
+
+    let created = sqlx::query_as::<_, ModelDownload>(&format!(
+        r#"
+        INSERT INTO model_downloads(repo_id, filename, revision, dest_path)
+        VALUES(?, ?, ?, ?)
+        RETURNING {DOWNLOAD_COLUMNS}
+        "#
+    ))
+    .bind(&payload.repo_id)
+    .bind(&payload.filename)
+    .bind(payload.revision.as_deref().unwrap_or("main"))
+    .bind(&dest)
+    .fetch_one(&state.pool)
+    .await?;
+
+    spawn(&state, &created);
+
+    Ok(Json(created))
+}
+
+pub async fn list_model_downloads(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ModelDownload>>, AppError> {
+    let rows = sqlx::query_as::<_, ModelDownload>(&format!(
+        "SELECT {DOWNLOAD_COLUMNS} FROM model_downloads ORDER BY id DESC"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_model_download(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ModelDownload>, AppError> {
+    load(&state, id).await.map(Json)
+}
+
+/// Restarts a failed download from where its partial file left off.
+pub async fn retry_model_download(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Json<ModelDownload>, AppError> {
+    user.require_admin()?;
+    let retried = sqlx::query_as::<_, ModelDownload>(&format!(
+        r#"
+        UPDATE model_downloads
+        SET status = 'queued', error = NULL, finished_at = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND status = 'failed'
+        RETURNING {DOWNLOAD_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    let Some(retried) = retried else {
+        let current = load(&state, id).await?;
+        return Err(AppError::Conflict(format!(
+            "download {id} is {}, only failed downloads can be retried",
+            current.status
+        )));
+    };
+
+    spawn(&state, &retried);
+
+    Ok(Json(retried))
+}
+
+async fn load(state: &AppState, id: i64) -> Result<ModelDownload, AppError> {
+    sqlx::query_as::<_, ModelDownload>(&format!(
+        "SELECT {DOWNLOAD_COLUMNS} FROM model_downloads WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("model download {id}")))
+}
+
+fn spawn(state: &AppState, download: &ModelDownload) {
+    tokio::spawn(model_download::run(
+        state.pool.clone(),
+        DownloadJob {
+            id: download.id,
+            repo_id: download.repo_id.clone(),
+            filename: download.filename.clone(),
+            revision: download.revision.clone(),
+            dest_path: download.dest_path.clone(),
+        },
+        state.download_client.clone(),
+        state.config.hf_token.clone(),
+    ));
+}
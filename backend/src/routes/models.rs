@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    config::LlmBackendKind,
+    error::AppError,
+    model_catalog::{self, LocalModel},
+    model_swap::{self, SwapStatus, SwapTarget, CONTAINER_MODEL_PATH},
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize)]
+pub struct LocalModelCatalog {
+    pub models_dir: String,
+    pub models: Vec<LocalModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoadModelRequest {
+    /// A name from `GET /admin/models/local`, or an Ollama model tag.
+    pub model: String,
+    /// Replaces the server arguments instead of only pointing `--model` at
+    /// the new weights.
+    pub args: Option<Vec<String>>,
+}
+
+impl Validate for LoadModelRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("model", &self.model);
+        v.max_chars("model", &self.model, 500);
+        if self.args.as_ref().is_some_and(Vec::is_empty) {
+            v.error("args", "must not be empty");
+        }
+    }
+}
+
+pub async fn list_local_models(
+    State(state): State<AppState>,
+) -> Result<Json<LocalModelCatalog>, AppError> {
+    let models_dir = state.config.models_dir.clone();
+    let mut models = scan(&state).await?;
+
+    // Ids served by each backend, plus the host directory mounted into the
+    // managed container, since vLLM reports the in-container path.
+    let mut loaded: Vec<String> = state
+        .upstreams
+        .backends()
+        .into_iter()
+        .flat_map(|backend| backend.health().models)
+        .collect();
+    if let Some(container) = &state.inference {
+        match container.mount_source(CONTAINER_MODEL_PATH).await {
+            Ok(Some(source)) => loaded.push(source),
+            Ok(None) => {}
+            Err(err) => warn!(error = %err, "failed to inspect inference container mounts"),
+        }
+    }
+    model_catalog::mark_loaded(&mut models, &loaded);
+
+    Ok(Json(LocalModelCatalog { models_dir, models }))
+}
+
+/// Starts switching the inference server to another model and returns
+/// immediately; poll `GET /admin/models/load` for progress.
+pub async fn load_model(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<LoadModelRequest>,
+) -> Result<Json<SwapStatus>, AppError> {
+    user.require_admin()?;
+    let target = match state.config.llm_backend_kind {
+        LlmBackendKind::Ollama => SwapTarget::Ollama,
+        LlmBackendKind::OpenAi => {
+            if state.inference.is_none() {
+                return Err(AppError::NotFound(
+                    "no inference container configured (set DOCKER_LLM_CONTAINER)".to_string(),
+                ));
+            }
+            let model = scan(&state)
+                .await?
+                .into_iter()
+                .find(|m| m.name == payload.model)
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("model '{}' in MODELS_DIR", payload.model))
+                })?;
+
+            let (mount_source, model_arg) = model_swap::model_mount(Path::new(&model.path));
+            SwapTarget::Container {
+                mount_source,
+                model_arg,
+                args: payload.args,
+            }
+        }
+    };
+
+    let status = state.model_swap.begin(&payload.model)?;
+    tokio::spawn(model_swap::run(state.clone(), payload.model, target));
+
+    Ok(Json(status))
+}
+
+pub async fn model_load_status(
+    State(state): State<AppState>,
+) -> Result<Json<SwapStatus>, AppError> {
+    state
+        .model_swap
+        .status()
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("no model switch has run".to_string()))
+}
+
+async fn scan(state: &AppState) -> Result<Vec<LocalModel>, AppError> {
+    let models_dir = state.config.models_dir.clone();
+    let dir = PathBuf::from(&models_dir);
+    tokio::task::spawn_blocking(move || model_catalog::scan(&dir))
+        .await
+        .map_err(|err| AppError::Internal(format!("model scan failed: {err}")))?
+        .map_err(|err| AppError::Internal(format!("failed to read {models_dir}: {err}")))
+}
@@ -0,0 +1,219 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    mailer,
+    routes::{auth::check_new_password, sessions},
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+impl Validate for PasswordResetRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("email", &self.email);
+        v.max_chars("email", &self.email, 320);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPasswordResetRequest {
+    pub token: String,
+    pub password: String,
+}
+
+impl Validate for ConfirmPasswordResetRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("token", &self.token);
+        check_new_password(v, "password", &self.password);
+    }
+}
+
+/// A reset issued by an admin, to pass on to the user however they like.
+#[derive(Debug, Serialize)]
+pub struct IssuedPasswordReset {
+    pub user_id: i64,
+    pub email: String,
+    pub reset_token: String,
+    /// `PASSWORD_RESET_URL` with the token, when configured.
+    pub reset_url: Option<String>,
+    pub expires_at: String,
+}
+
+/// Emails a reset link. Answers `202` whether or not the email belongs to
+/// anyone, so it can't be used to discover accounts.
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<PasswordResetRequest>,
+) -> Result<StatusCode, AppError> {
+    if !mailer::enabled(&state.config) {
+        return Err(AppError::NotFound(
+            "password reset email is not enabled (set SMTP_HOST); ask an admin to reset it"
+                .to_string(),
+        ));
+    }
+
+    let email = payload.email.trim().to_lowercase();
+    let user_id: Option<i64> = sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+        .bind(&email)
+        .fetch_optional(&state.pool)
+        .await?;
+    let Some(user_id) = user_id else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    let (token, expires_at) = create_token(&state, user_id, None).await?;
+    let body = format!(
+        "Someone asked to reset the password for {email}.\n\n\
+         {}\n\n\
+         This expires at {expires_at} UTC and works once. If you didn't ask, ignore this email.\n",
+        reset_instructions(&state, &token)
+    );
+    // Sent in the background so the response time doesn't give away
+    // whether the account exists.
+    let config = state.config.clone();
+    tokio::spawn(async move {
+        if let Err(err) = mailer::send(&config, &email, "Reset your password", body).await {
+            warn!(user_id, error = %err, "failed to send password reset email");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Sets a new password with a reset token, then signs the user out
+/// everywhere.
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<ConfirmPasswordResetRequest>,
+) -> Result<StatusCode, AppError> {
+    let password_hash = auth::hash_password(payload.password).await?;
+
+    let mut tx = state.pool.begin().await?;
+    let user_id: Option<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE password_resets SET used_at = CURRENT_TIMESTAMP
+        WHERE token_hash = ? AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        RETURNING user_id
+        "#,
+    )
+    .bind(auth::token_hash(payload.token.trim()))
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(user_id) = user_id else {
+        return Err(AppError::BadRequest(
+            "reset token is invalid, expired, or already used".to_string(),
+        ));
+    };
+
+    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    sessions::revoke_all(&state.pool, user_id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Issues a reset for a user in the caller's school and returns the token,
+/// for when email isn't set up or doesn't reach them; admins only.
+pub async fn issue_password_reset(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Json<IssuedPasswordReset>, AppError> {
+    if !user.is_admin() {
+        return Err(AppError::Forbidden(
+            "only admins can issue password resets".to_string(),
+        ));
+    }
+    let email: String =
+        sqlx::query_scalar("SELECT email FROM users WHERE id = ? AND school_id = ?")
+            .bind(id)
+            .bind(auth::school_id())
+            .fetch_optional(&state.pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("user {id}")))?;
+
+    let (reset_token, expires_at) = create_token(&state, id, Some(user.id)).await?;
+
+    Ok(Json(IssuedPasswordReset {
+        user_id: id,
+        email,
+        reset_url: reset_url(&state, &reset_token),
+        reset_token,
+        expires_at,
+    }))
+}
+
+/// Replaces any unused reset for the user with a new one, returning its
+/// token and expiry.
+async fn create_token(
+    state: &AppState,
+    user_id: i64,
+    issued_by: Option<i64>,
+) -> Result<(String, String), AppError> {
+    purge(&state.pool, user_id).await?;
+
+    let token = auth::new_token();
+    let expires_at: String = sqlx::query_scalar(
+        r#"
+        INSERT INTO password_resets(user_id, token_hash, issued_by, expires_at)
+        VALUES(?, ?, ?, datetime('now', ?))
+        RETURNING expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth::token_hash(&token))
+    .bind(issued_by)
+    .bind(format!(
+        "+{} minutes",
+        state.config.password_reset_ttl_minutes
+    ))
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((token, expires_at))
+}
+
+/// Drops the user's unused resets, and everyone's expired ones.
+async fn purge(pool: &SqlitePool, user_id: i64) -> Result<(), AppError> {
+    sqlx::query(
+        "DELETE FROM password_resets \
+         WHERE (user_id = ? AND used_at IS NULL) OR expires_at <= CURRENT_TIMESTAMP",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+fn reset_url(state: &AppState, token: &str) -> Option<String> {
+    state.config.password_reset_url.as_deref().map(|url| {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}token={token}")
+    })
+}
+
+fn reset_instructions(state: &AppState, token: &str) -> String {
+    match reset_url(state, token) {
+        Some(url) => format!("Choose a new password here: {url}"),
+        None => format!("Your reset code is: {token}"),
+    }
+}
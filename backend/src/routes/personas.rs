@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    routes::{students::GRADE_LEVELS, tags},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_SYSTEM_PROMPT_CHARS: usize = 20_000;
+
+/// A named system prompt plus generation overrides, e.g. "Socratic tutor".
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Persona {
+    pub id: i64,
+    pub name: String,
+    pub system_prompt: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+    pub allowed_grades: Option<SqlJson<Vec<String>>>,
+    /// Only students with at least one of these tags may use the persona.
+    pub allowed_tags: Option<SqlJson<Vec<String>>>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PersonaRequest {
+    pub name: String,
+    pub system_prompt: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i64>,
+    pub allowed_grades: Option<Vec<String>>,
+    pub allowed_tags: Option<Vec<String>>,
+}
+
+impl Validate for PersonaRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 100);
+        v.required("system_prompt", &self.system_prompt);
+        v.max_chars(
+            "system_prompt",
+            &self.system_prompt,
+            MAX_SYSTEM_PROMPT_CHARS,
+        );
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+        if let Some(t) = self.temperature {
+            v.range("temperature", t, 0.0, 2.0);
+        }
+        if let Some(n) = self.max_tokens {
+            v.range("max_tokens", n, 1, 32_768);
+        }
+        if let Some(grades) = &self.allowed_grades {
+            for (i, grade) in grades.iter().enumerate() {
+                v.one_of(&format!("allowed_grades[{i}]"), grade, GRADE_LEVELS);
+            }
+        }
+        if let Some(tags) = &self.allowed_tags {
+            for (i, tag) in tags.iter().enumerate() {
+                tags::check_name(v, &format!("allowed_tags[{i}]"), tag);
+            }
+        }
+    }
+}
+
+const PERSONA_COLUMNS: &str = "id, name, system_prompt, model, temperature, max_tokens, \
+                               allowed_grades, allowed_tags, created_at, updated_at";
+
+pub async fn list_personas(State(state): State<AppState>) -> Result<Json<Vec<Persona>>, AppError> {
+    let rows = sqlx::query_as::<_, Persona>(&format!(
+        "SELECT {PERSONA_COLUMNS} FROM personas ORDER BY name ASC"
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_persona(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Persona>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn create_persona(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<PersonaRequest>,
+) -> Result<Json<Persona>, AppError> {
+    user.require_admin()?;
+    let name = payload.name.trim().to_string();
+
+    let created = sqlx::query_as::<_, Persona>(&format!(
+        r#"
+        INSERT INTO personas(
+            name, system_prompt, model, temperature, max_tokens, allowed_grades, allowed_tags
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?)
+        RETURNING {PERSONA_COLUMNS}
+        "#
+    ))
+    .bind(&name)
+    .bind(&payload.system_prompt)
+    .bind(&payload.model)
+    .bind(payload.temperature)
+    .bind(payload.max_tokens)
+    .bind(payload.allowed_grades.map(SqlJson))
+    .bind(payload.allowed_tags.map(SqlJson))
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| AppError::unique_conflict(err, || format!("persona '{name}' already exists")))?;
+
+    Ok(Json(created))
+}
+
+pub async fn update_persona(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<PersonaRequest>,
+) -> Result<Json<Persona>, AppError> {
+    user.require_admin()?;
+    let name = payload.name.trim().to_string();
+
+    let updated = sqlx::query_as::<_, Persona>(&format!(
+        r#"
+        UPDATE personas
+        SET name = ?, system_prompt = ?, model = ?, temperature = ?, max_tokens = ?,
+            allowed_grades = ?, allowed_tags = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING {PERSONA_COLUMNS}
+        "#
+    ))
+    .bind(&name)
+    .bind(&payload.system_prompt)
+    .bind(&payload.model)
+    .bind(payload.temperature)
+    .bind(payload.max_tokens)
+    .bind(payload.allowed_grades.map(SqlJson))
+    .bind(payload.allowed_tags.map(SqlJson))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|err| AppError::unique_conflict(err, || format!("persona '{name}' already exists")))?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("persona {id}")))
+}
+
+pub async fn delete_persona(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let result = sqlx::query("DELETE FROM personas WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("persona {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn load(pool: &SqlitePool, id: i64) -> Result<Persona, AppError> {
+    sqlx::query_as::<_, Persona>(&format!(
+        "SELECT {PERSONA_COLUMNS} FROM personas WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("persona {id}")))
+}
+
+impl Persona {
+    /// Rejects students whose grade the persona is not meant for. Students
+    /// without a recorded grade are allowed.
+    pub fn check_grade(&self, grade_level: Option<&str>) -> Result<(), AppError> {
+        let (Some(allowed), Some(grade)) = (&self.allowed_grades, grade_level) else {
+            return Ok(());
+        };
+        if allowed.iter().any(|g| g == grade) {
+            return Ok(());
+        }
+
+        let mut v = Validator::default();
+        v.error(
+            "persona_id",
+            format!("persona '{}' is not available for grade {grade}", self.name),
+        );
+        v.finish()
+    }
+
+    /// Rejects students who have none of the persona's `allowed_tags`.
+    pub fn check_tags(&self, student_tags: &[String]) -> Result<(), AppError> {
+        let Some(allowed) = &self.allowed_tags else {
+            return Ok(());
+        };
+        if allowed
+            .iter()
+            .any(|tag| student_tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        {
+            return Ok(());
+        }
+
+        let mut v = Validator::default();
+        v.error(
+            "persona_id",
+            format!(
+                "persona '{}' is only available to students tagged {}",
+                self.name,
+                allowed.join(", ")
+            ),
+        );
+        v.finish()
+    }
+
+    /// Puts the persona's system prompt first and overrides any parameters
+    /// it sets.
+    pub fn apply(&self, payload: &mut Value) {
+        if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+            messages.insert(
+                0,
+                json!({ "role": "system", "content": self.system_prompt }),
+            );
+        }
+        if let Some(model) = &self.model {
+            payload["model"] = json!(model);
+        }
+        if let Some(temperature) = self.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            payload["max_tokens"] = json!(max_tokens);
+        }
+    }
+}
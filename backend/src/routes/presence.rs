@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth, db,
+    error::AppError,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PresenceInterval {
+    pub id: i64,
+    pub student_id: i64,
+    pub started_at: String,
+    pub last_seen_at: String,
+    pub duration_secs: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ActiveStudent {
+    pub student_id: i64,
+    pub name: String,
+    pub started_at: String,
+    pub last_seen_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StudentPresence {
+    pub student_id: i64,
+    pub total_secs: i64,
+    pub intervals: Vec<PresenceInterval>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeartbeatRequest {
+    pub student_id: i64,
+}
+
+impl Validate for HeartbeatRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.range("student_id", self.student_id, 1, i64::MAX);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PresenceQuery {
+    /// Only include intervals that started at or after this timestamp.
+    pub since: Option<String>,
+}
+
+const INTERVAL_COLUMNS: &str = "id, student_id, started_at, last_seen_at, \
+     CAST((julianday(last_seen_at) - julianday(started_at)) * 86400 AS INTEGER) AS duration_secs";
+
+/// Extends the student's open interval, or starts a new one if the last
+/// heartbeat is older than `PRESENCE_TIMEOUT_SECS`.
+pub async fn heartbeat(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<HeartbeatRequest>,
+) -> Result<Json<PresenceInterval>, AppError> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM students WHERE id = ? AND school_id = ?)")
+            .bind(payload.student_id)
+            .bind(auth::school_id())
+            .fetch_one(&state.pool)
+            .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!(
+            "student {}",
+            payload.student_id
+        )));
+    }
+
+    let cutoff = format!("-{} seconds", state.config.presence_timeout_secs);
+
+    let extend = format!(
+        r#"
+        UPDATE presence_intervals
+        SET last_seen_at = CURRENT_TIMESTAMP
+        WHERE id = (
+            SELECT id FROM presence_intervals
+            WHERE student_id = ? AND last_seen_at >= datetime('now', ?)
+            ORDER BY id DESC
+            LIMIT 1
+        )
+        RETURNING {INTERVAL_COLUMNS}
+        "#
+    );
+    // Every open dashboard sends these, so they're retried on contention.
+    let extended = db::retry_busy(|| {
+        sqlx::query_as::<_, PresenceInterval>(&extend)
+            .bind(payload.student_id)
+            .bind(&cutoff)
+            .fetch_optional(&state.pool)
+    })
+    .await?;
+
+    if let Some(interval) = extended {
+        return Ok(Json(interval));
+    }
+
+    let start = format!(
+        "INSERT INTO presence_intervals(student_id) VALUES(?) RETURNING {INTERVAL_COLUMNS}"
+    );
+    let started = db::retry_busy(|| {
+        sqlx::query_as::<_, PresenceInterval>(&start)
+            .bind(payload.student_id)
+            .fetch_one(&state.pool)
+    })
+    .await?;
+
+    Ok(Json(started))
+}
+
+pub async fn list_active(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ActiveStudent>>, AppError> {
+    let cutoff = format!("-{} seconds", state.config.presence_timeout_secs);
+
+    let rows = sqlx::query_as::<_, ActiveStudent>(
+        r#"
+        SELECT s.id AS student_id, s.name, p.started_at, p.last_seen_at
+        FROM presence_intervals p
+        JOIN students s ON s.id = p.student_id
+        WHERE p.last_seen_at >= datetime('now', ?) AND s.school_id = ?
+        ORDER BY s.name ASC
+        "#,
+    )
+    .bind(&cutoff)
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn student_presence(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    Query(query): Query<PresenceQuery>,
+) -> Result<Json<StudentPresence>, AppError> {
+    let intervals = sqlx::query_as::<_, PresenceInterval>(&format!(
+        r#"
+        SELECT {INTERVAL_COLUMNS}
+        FROM presence_intervals
+        WHERE student_id = ? AND started_at >= ?
+          AND student_id IN (SELECT id FROM students WHERE school_id = ?)
+        ORDER BY started_at ASC
+        "#
+    ))
+    .bind(student_id)
+    .bind(query.since.as_deref().unwrap_or(""))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    let total_secs = intervals.iter().map(|i| i.duration_secs).sum();
+
+    Ok(Json(StudentPresence {
+        student_id,
+        total_secs,
+        intervals,
+    }))
+}
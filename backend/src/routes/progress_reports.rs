@@ -0,0 +1,330 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    routes::{students, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const DEFAULT_DAYS: i64 = 14;
+/// Per-source caps so the prompt fits a small model's context.
+const MAX_INTERACTIONS: i64 = 40;
+const MAX_NOTES: i64 = 20;
+const MAX_GRADES: i64 = 30;
+const EXCERPT_CHARS: usize = 400;
+
+/// The model's structured summary.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReportContent {
+    pub summary: String,
+    #[serde(default)]
+    pub strengths: Vec<String>,
+    #[serde(default)]
+    pub areas_for_growth: Vec<String>,
+    #[serde(default)]
+    pub recommendations: Vec<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ProgressReport {
+    pub id: i64,
+    pub student_id: i64,
+    pub period_start: String,
+    pub period_end: String,
+    pub report: SqlJson<ReportContent>,
+    /// How much of each source the report was built from.
+    pub interaction_count: i64,
+    pub note_count: i64,
+    pub grade_count: i64,
+    /// The `ai_interactions` row for the generation request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressReportRequest {
+    /// How far back to look; defaults to 14 days.
+    pub days: Option<i64>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for ProgressReportRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(days) = self.days {
+            v.range("days", days, 1, 365);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct InteractionExcerpt {
+    prompt: String,
+    response: String,
+    created_at: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct NoteExcerpt {
+    author: String,
+    body: String,
+    created_at: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GradeExcerpt {
+    title: String,
+    subject: Option<String>,
+    points: f64,
+    max_points: Option<f64>,
+    graded_at: String,
+}
+
+const REPORT_COLUMNS: &str = "id, student_id, period_start, period_end, report, \
+                              interaction_count, note_count, grade_count, generation_id, \
+                              created_at";
+
+/// Past reports, newest first, for comparing progress over time.
+pub async fn list_progress_reports(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<ProgressReport>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, ProgressReport>(&format!(
+        r#"
+        SELECT {REPORT_COLUMNS}
+        FROM progress_reports
+        WHERE student_id = ?
+        ORDER BY created_at DESC, id DESC
+        "#
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_progress_report(
+    State(state): State<AppState>,
+    Path((student_id, report_id)): Path<(i64, i64)>,
+) -> Result<Json<ProgressReport>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    sqlx::query_as::<_, ProgressReport>(&format!(
+        "SELECT {REPORT_COLUMNS} FROM progress_reports WHERE id = ? AND student_id = ?"
+    ))
+    .bind(report_id)
+    .bind(student_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or_else(|| {
+        AppError::NotFound(format!(
+            "progress report {report_id} for student {student_id}"
+        ))
+    })
+}
+
+/// Summarizes the student's recent interactions, notes, and grades with the
+/// local model and stores the result.
+pub async fn create_progress_report(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<ProgressReportRequest>,
+) -> Result<Json<ProgressReport>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let days = payload.days.unwrap_or(DEFAULT_DAYS);
+
+    let (period_start, period_end): (String, String) =
+        sqlx::query_as("SELECT datetime('now', ?), datetime('now')")
+            .bind(format!("-{days} days"))
+            .fetch_one(&state.pool)
+            .await?;
+
+    let interactions = sqlx::query_as::<_, InteractionExcerpt>(
+        r#"
+        SELECT prompt, response, created_at
+        FROM ai_interactions
+        WHERE student_id = ? AND created_at >= ?
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(&period_start)
+    .bind(MAX_INTERACTIONS)
+    .fetch_all(&state.pool)
+    .await?;
+    let notes = sqlx::query_as::<_, NoteExcerpt>(
+        r#"
+        SELECT author, body, created_at
+        FROM student_notes
+        WHERE student_id = ? AND created_at >= ?
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(&period_start)
+    .bind(MAX_NOTES)
+    .fetch_all(&state.pool)
+    .await?;
+    let grades = sqlx::query_as::<_, GradeExcerpt>(
+        r#"
+        SELECT a.title, a.subject, g.points, a.max_points, g.graded_at
+        FROM grades g
+        JOIN assignments a ON a.id = g.assignment_id
+        WHERE a.student_id = ? AND g.graded_at >= ?
+        ORDER BY g.graded_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(&period_start)
+    .bind(MAX_GRADES)
+    .fetch_all(&state.pool)
+    .await?;
+
+    if interactions.is_empty() && notes.is_empty() && grades.is_empty() {
+        let mut v = Validator::default();
+        v.error(
+            "days",
+            format!("the student has no interactions, notes, or grades in the last {days} days"),
+        );
+        v.finish()?;
+    }
+
+    let grade_level = students::grade_level(&state.pool, student_id).await?;
+    let mut sections = vec![format!(
+        "Period: {period_start} to {period_end} UTC. Grade level: {}.",
+        grade_level.as_deref().unwrap_or("unknown")
+    )];
+    if !interactions.is_empty() {
+        let lines = interactions.iter().map(|i| {
+            format!(
+                "- [{}] Student asked: {}\n  Tutor answered: {}",
+                i.created_at,
+                excerpt(&question(&i.prompt)),
+                excerpt(&i.response)
+            )
+        });
+        sections.push(format!(
+            "AI tutor conversations:\n{}",
+            lines.collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !notes.is_empty() {
+        let lines = notes
+            .iter()
+            .map(|n| format!("- [{}] {}: {}", n.created_at, n.author, excerpt(&n.body)));
+        sections.push(format!(
+            "Teacher notes:\n{}",
+            lines.collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !grades.is_empty() {
+        let lines = grades.iter().map(|g| {
+            let score = match g.max_points {
+                Some(max) => format!("{} / {max}", g.points),
+                None => g.points.to_string(),
+            };
+            format!(
+                "- [{}] {} ({}): {score}",
+                g.graded_at,
+                g.title,
+                g.subject.as_deref().unwrap_or("no subject")
+            )
+        });
+        sections.push(format!("Grades:\n{}", lines.collect::<Vec<_>>().join("\n")));
+    }
+
+    let (content, completion) = assist::complete_json::<ReportContent>(
+        &state,
+        Prompt {
+            system: "You write progress reports for a teacher from a student's recent \
+                     schoolwork. Be specific and cite evidence from the material; do not \
+                     invent facts. Reply with only a JSON object with keys \"summary\" \
+                     (a short paragraph), \"strengths\", \"areas_for_growth\", and \
+                     \"recommendations\" (each an array of short strings)."
+                .to_string(),
+            user: sections.join("\n\n"),
+            model: payload.model,
+            temperature: Some(0.3),
+            max_tokens: Some(1_200),
+            user_id: payload.user_id,
+            student_id: Some(student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let saved = sqlx::query_as::<_, ProgressReport>(&format!(
+        r#"
+        INSERT INTO progress_reports(
+            student_id, period_start, period_end, report, interaction_count, note_count,
+            grade_count, generation_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING {REPORT_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(&period_start)
+    .bind(&period_end)
+    .bind(SqlJson(&content))
+    .bind(interactions.len() as i64)
+    .bind(notes.len() as i64)
+    .bind(grades.len() as i64)
+    .bind(completion.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+/// The last user message of a stored prompt, which holds the chat's
+/// `messages` array as JSON.
+pub fn question(prompt: &str) -> String {
+    serde_json::from_str::<Value>(prompt)
+        .ok()
+        .and_then(|messages| {
+            messages
+                .as_array()?
+                .iter()
+                .rev()
+                .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))?
+                .get("content")?
+                .as_str()
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| prompt.to_string())
+}
+
+pub fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    format!("{cut}...")
+}
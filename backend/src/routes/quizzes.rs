@@ -0,0 +1,360 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    auth,
+    error::AppError,
+    routes::{
+        students::{self, GRADE_LEVELS},
+        users,
+    },
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const MAX_QUESTIONS: usize = 30;
+const DEFAULT_QUESTIONS: usize = 5;
+
+/// A multiple-choice question; `answer` indexes into `choices`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuizQuestion {
+    pub question: String,
+    pub choices: Vec<String>,
+    pub answer: usize,
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Quiz {
+    pub id: i64,
+    pub title: String,
+    pub topic: String,
+    pub grade_level: Option<String>,
+    pub student_id: Option<i64>,
+    pub questions: SqlJson<Vec<QuizQuestion>>,
+    /// The `ai_interactions` row for the generation request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateQuizRequest {
+    pub topic: String,
+    pub question_count: Option<usize>,
+    /// Targets the student's grade level; overrides `grade_level`.
+    pub student_id: Option<i64>,
+    pub grade_level: Option<String>,
+    /// Defaults to the topic.
+    pub title: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the generation is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for GenerateQuizRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("topic", &self.topic);
+        v.max_chars("topic", &self.topic, 500);
+        if let Some(count) = self.question_count {
+            v.range("question_count", count, 1, MAX_QUESTIONS);
+        }
+        if let Some(grade) = &self.grade_level {
+            v.one_of("grade_level", grade, GRADE_LEVELS);
+        }
+        if self.student_id.is_none() && self.grade_level.is_none() {
+            v.error("grade_level", "is required unless student_id is given");
+        }
+        if let Some(title) = &self.title {
+            v.required("title", title);
+            v.max_chars("title", title, 200);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuizRequest {
+    pub title: String,
+    pub questions: Vec<QuizQuestion>,
+}
+
+impl Validate for UpdateQuizRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("title", &self.title);
+        v.max_chars("title", &self.title, 200);
+        check_questions(v, &self.questions);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuizListQuery {
+    pub grade_level: Option<String>,
+    pub student_id: Option<i64>,
+    /// Substring match on the topic.
+    pub topic: Option<String>,
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct GeneratedQuiz {
+    questions: Vec<QuizQuestion>,
+}
+
+const QUIZ_COLUMNS: &str = "id, title, topic, grade_level, student_id, questions, generation_id, \
+                            created_at, updated_at";
+
+/// Checks the question schema for both generated and edited quizzes.
+fn check_questions(v: &mut Validator, questions: &[QuizQuestion]) {
+    if questions.is_empty() {
+        v.error("questions", "must not be empty");
+    }
+    if questions.len() > MAX_QUESTIONS {
+        v.error(
+            "questions",
+            format!("must have at most {MAX_QUESTIONS} questions"),
+        );
+    }
+    for (i, q) in questions.iter().enumerate() {
+        let field = format!("questions[{i}]");
+        v.required(&format!("{field}.question"), &q.question);
+        v.max_chars(&format!("{field}.question"), &q.question, 2_000);
+        v.range(&format!("{field}.choices"), q.choices.len(), 2, 6);
+        for (j, choice) in q.choices.iter().enumerate() {
+            v.required(&format!("{field}.choices[{j}]"), choice);
+            v.max_chars(&format!("{field}.choices[{j}]"), choice, 500);
+        }
+        if q.answer >= q.choices.len() {
+            v.error(
+                format!("{field}.answer"),
+                "must be the index of one of the choices",
+            );
+        }
+        if let Some(explanation) = &q.explanation {
+            v.max_chars(&format!("{field}.explanation"), explanation, 2_000);
+        }
+    }
+}
+
+/// Asks the local model for a multiple-choice quiz at the student's (or the
+/// given) grade level, checks it against the question schema, and stores it.
+pub async fn generate_quiz(
+    State(state): State<AppState>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<GenerateQuizRequest>,
+) -> Result<Json<Quiz>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let profile = match payload.student_id {
+        Some(student_id) => {
+            students::ensure_exists(&state.pool, student_id).await?;
+            Some(students::profile(&state.pool, student_id).await?)
+        }
+        None => None,
+    };
+    let grade_level = profile
+        .as_ref()
+        .and_then(|p| p.grade_level.clone())
+        .or(payload.grade_level.clone());
+    let count = payload.question_count.unwrap_or(DEFAULT_QUESTIONS);
+    let topic = payload.topic.trim().to_string();
+
+    let mut request = vec![
+        format!("Topic: {topic}"),
+        format!("Number of questions: {count}"),
+        format!(
+            "Grade level: {}",
+            grade_level.as_deref().unwrap_or("unspecified")
+        ),
+    ];
+    if let Some(profile) = &profile {
+        if let Some(reading_level) = &profile.reading_level {
+            request.push(format!("Reading level: {reading_level}"));
+        }
+        if let Some(language) = &profile.preferred_language {
+            request.push(format!("Write the quiz in language: {language}"));
+        }
+    }
+
+    let (generated, completion) = assist::complete_json::<GeneratedQuiz>(
+        &state,
+        Prompt {
+            system: "You write multiple-choice quizzes for school students. Match vocabulary \
+                     and difficulty to the grade level, make exactly one choice correct, and \
+                     keep distractors plausible. Reply with only a JSON object: \
+                     {\"questions\": [{\"question\": string, \"choices\": [string, ...], \
+                     \"answer\": index of the correct choice starting at 0, \
+                     \"explanation\": string}]}. Use 4 choices per question."
+                .to_string(),
+            user: request.join("\n"),
+            model: payload.model,
+            temperature: Some(0.5),
+            max_tokens: Some(4_000),
+            user_id: payload.user_id,
+            student_id: payload.student_id,
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let mut v = Validator::default();
+    check_questions(&mut v, &generated.questions);
+    if let Err(AppError::Validation(errors)) = v.finish() {
+        let problems = errors
+            .iter()
+            .map(|(field, messages)| format!("{field} {}", messages.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ");
+        return Err(AppError::Upstream(format!(
+            "generated quiz failed validation: {problems}; interaction {}",
+            completion.interaction_id
+        )));
+    }
+
+    let created = sqlx::query_as::<_, Quiz>(&format!(
+        r#"
+        INSERT INTO quizzes(
+            title, topic, grade_level, student_id, questions, generation_id, school_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?)
+        RETURNING {QUIZ_COLUMNS}
+        "#
+    ))
+    .bind(payload.title.as_deref().map(str::trim).unwrap_or(&topic))
+    .bind(&topic)
+    .bind(&grade_level)
+    .bind(payload.student_id)
+    .bind(SqlJson(&generated.questions))
+    .bind(completion.interaction_id)
+    .bind(auth::school_id())
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+/// Newest first. Quizzes made for a student are left out unless the caller
+/// may see that student.
+pub async fn list_quizzes(
+    State(state): State<AppState>,
+    Query(query): Query<QuizListQuery>,
+) -> Result<Json<Vec<Quiz>>, AppError> {
+    let mut v = Validator::default();
+    if let Some(grade) = &query.grade_level {
+        v.one_of("grade_level", grade, GRADE_LEVELS);
+    }
+    v.finish()?;
+
+    let viewer = students::viewer();
+    let rows = sqlx::query_as::<_, Quiz>(&format!(
+        r#"
+        SELECT {QUIZ_COLUMNS}
+        FROM quizzes
+        WHERE school_id = ?
+          AND (student_id IS NULL
+               OR student_id IN (SELECT id FROM students WHERE {access_filter}))
+          AND (? IS NULL OR grade_level = ?)
+          AND (? IS NULL OR student_id = ?)
+          AND (? IS NULL OR instr(lower(topic), lower(?)) > 0)
+        ORDER BY created_at DESC, id DESC
+        "#,
+        access_filter = students::ACCESS_FILTER,
+    ))
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .bind(query.student_id)
+    .bind(query.student_id)
+    .bind(&query.topic)
+    .bind(&query.topic)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_quiz(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Quiz>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Replaces the title and questions, e.g. after a teacher edits a draft.
+pub async fn update_quiz(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UpdateQuizRequest>,
+) -> Result<Json<Quiz>, AppError> {
+    load(&state.pool, id).await?;
+
+    let updated = sqlx::query_as::<_, Quiz>(&format!(
+        r#"
+        UPDATE quizzes
+        SET title = ?, questions = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING {QUIZ_COLUMNS}
+        "#
+    ))
+    .bind(payload.title.trim())
+    .bind(SqlJson(&payload.questions))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("quiz {id}")))
+}
+
+pub async fn delete_quiz(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM quizzes WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("quiz {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 404 unless the quiz is in the caller's school and, if it was made for a
+/// student, the caller may see that student.
+pub async fn load(pool: &SqlitePool, id: i64) -> Result<Quiz, AppError> {
+    let quiz = sqlx::query_as::<_, Quiz>(&format!(
+        "SELECT {QUIZ_COLUMNS} FROM quizzes WHERE id = ? AND school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("quiz {id}")))?;
+
+    if let Some(student_id) = quiz.student_id {
+        students::ensure_record_visible(pool, student_id, || format!("quiz {id}")).await?;
+    }
+    Ok(quiz)
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    readability::{self, Readability},
+    routes::{students, users},
+    trace::TraceHeaders,
+    validation::{FieldErrors, ValidJson, Validate, Validator},
+};
+
+const MAX_SAMPLE_CHARS: usize = 20_000;
+/// Samples shorter than this give the formulas too little to go on.
+const MIN_SAMPLE_WORDS: usize = 30;
+const MAX_GRADE: f64 = 18.0;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReadingAssessment {
+    pub id: i64,
+    pub student_id: i64,
+    pub sample: String,
+    pub metrics: SqlJson<Readability>,
+    /// `metrics.average_grade`, kept as a column for trends.
+    pub formula_grade: f64,
+    pub model_grade: f64,
+    pub model_rationale: String,
+    /// The `ai_interactions` row for the model's judgment.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssessRequest {
+    pub student_id: i64,
+    /// Something the student wrote, or read aloud fluently.
+    pub text: String,
+    pub model: Option<String>,
+    /// Teacher the judgment is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for AssessRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.range("student_id", self.student_id, 1, i64::MAX);
+        v.required("text", &self.text);
+        v.max_chars("text", &self.text, MAX_SAMPLE_CHARS);
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrendPoint {
+    pub assessment_id: i64,
+    pub formula_grade: f64,
+    pub model_grade: f64,
+    pub created_at: String,
+    #[serde(skip)]
+    days: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadingTrend {
+    pub student_id: i64,
+    /// Oldest first.
+    pub points: Vec<TrendPoint>,
+    /// Least-squares slope of `formula_grade`, in grade levels per 30 days;
+    /// `null` with fewer than two assessments on different days.
+    pub formula_grade_per_month: Option<f64>,
+    pub model_grade_per_month: Option<f64>,
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct ModelJudgment {
+    grade_level: f64,
+    rationale: String,
+}
+
+const ASSESSMENT_COLUMNS: &str = "id, student_id, sample, metrics, formula_grade, model_grade, \
+                                  model_rationale, generation_id, created_at";
+
+/// Scores the sample with the readability formulas and asks the local model
+/// for its own grade-level judgment, then stores both against the student.
+pub async fn assess_reading_level(
+    State(state): State<AppState>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<AssessRequest>,
+) -> Result<Json<ReadingAssessment>, AppError> {
+    students::ensure_exists(&state.pool, payload.student_id).await?;
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+
+    let sample = payload.text.trim();
+    let metrics = readability::score(sample).filter(|m| m.words >= MIN_SAMPLE_WORDS);
+    let Some(metrics) = metrics else {
+        let mut errors = FieldErrors::new();
+        errors.insert(
+            "text".to_string(),
+            vec![format!("must have at least {MIN_SAMPLE_WORDS} words")],
+        );
+        return Err(AppError::Validation(errors));
+    };
+
+    let grade_level = students::grade_level(&state.pool, payload.student_id).await?;
+    let (judgment, completion) = assist::complete_json::<ModelJudgment>(
+        &state,
+        Prompt {
+            system: "You are a reading specialist. Judge the US school grade level of the \
+                     text: vocabulary, sentence structure, and the ideas it handles. Reply \
+                     with only a JSON object: {\"grade_level\": number from 0 to 18, \
+                     \"rationale\": string of one or two sentences}."
+                .to_string(),
+            user: format!(
+                "Student's enrolled grade: {}\n\nText:\n---\n{sample}\n---",
+                grade_level.as_deref().unwrap_or("unknown")
+            ),
+            model: payload.model,
+            temperature: Some(0.2),
+            max_tokens: Some(300),
+            user_id: payload.user_id,
+            student_id: Some(payload.student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    if !(0.0..=MAX_GRADE).contains(&judgment.grade_level) {
+        return Err(AppError::Upstream(format!(
+            "model gave grade level {} outside 0-{MAX_GRADE}; interaction {}",
+            judgment.grade_level, completion.interaction_id
+        )));
+    }
+
+    let saved = sqlx::query_as::<_, ReadingAssessment>(&format!(
+        r#"
+        INSERT INTO reading_assessments(
+            student_id, sample, metrics, formula_grade, model_grade, model_rationale,
+            generation_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?)
+        RETURNING {ASSESSMENT_COLUMNS}
+        "#
+    ))
+    .bind(payload.student_id)
+    .bind(sample)
+    .bind(SqlJson(&metrics))
+    .bind(metrics.average_grade)
+    .bind((judgment.grade_level * 10.0).round() / 10.0)
+    .bind(judgment.rationale.trim())
+    .bind(completion.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+/// Newest first.
+pub async fn list_reading_assessments(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<ReadingAssessment>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, ReadingAssessment>(&format!(
+        r#"
+        SELECT {ASSESSMENT_COLUMNS}
+        FROM reading_assessments
+        WHERE student_id = ?
+        ORDER BY created_at DESC, id DESC
+        "#
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Every assessment's grades over time, with how fast each is changing.
+pub async fn reading_trend(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<ReadingTrend>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let points = sqlx::query_as::<_, TrendPoint>(
+        r#"
+        SELECT id AS assessment_id, formula_grade, model_grade, created_at,
+               julianday(created_at) AS days
+        FROM reading_assessments
+        WHERE student_id = ?
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let formula_grade_per_month = slope(points.iter().map(|p| (p.days, p.formula_grade)));
+    let model_grade_per_month = slope(points.iter().map(|p| (p.days, p.model_grade)));
+    Ok(Json(ReadingTrend {
+        student_id,
+        points,
+        formula_grade_per_month,
+        model_grade_per_month,
+    }))
+}
+
+/// Least-squares slope per 30 days, rounded to a hundredth.
+fn slope(points: impl Iterator<Item = (f64, f64)> + Clone) -> Option<f64> {
+    let n = points.clone().count() as f64;
+    if n < 2.0 {
+        return None;
+    }
+    let mean_x = points.clone().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.clone().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x) * (x - mean_x);
+    }
+    // All on the same day.
+    if variance < 1e-9 {
+        return None;
+    }
+    Some((covariance / variance * 30.0 * 100.0).round() / 100.0)
+}
@@ -0,0 +1,276 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    auth, db,
+    error::AppError,
+    routes::{progress_reports::ReportContent, students},
+    time::unix_now,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const DEFAULT_EXPIRES_IN_DAYS: i64 = 7;
+const TYP: &str = "report_link";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    /// The student.
+    sub: String,
+    /// So no other token signed with the same key can stand in for one.
+    typ: String,
+    /// The `report_links` row.
+    jti: String,
+    exp: u64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReportLink {
+    pub id: i64,
+    pub student_id: i64,
+    /// `None` shows whichever report is newest when the link is opened.
+    pub report_id: Option<i64>,
+    pub created_by: Option<i64>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub view_count: i64,
+    pub last_viewed_at: Option<String>,
+}
+
+/// The only response that contains the link's token.
+#[derive(Debug, Serialize)]
+pub struct CreatedReportLink {
+    #[serde(flatten)]
+    pub link: ReportLink,
+    pub share_token: String,
+    /// `REPORT_LINK_URL` with the token, when configured.
+    pub share_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReportLinkRequest {
+    /// Defaults to 7.
+    pub expires_in_days: Option<i64>,
+    /// Share this report rather than the newest.
+    pub report_id: Option<i64>,
+}
+
+impl Validate for CreateReportLinkRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(days) = self.expires_in_days {
+            v.range("expires_in_days", days, 1, 90);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SharedReportQuery {
+    pub token: String,
+}
+
+/// What a family sees: the report, without any of the ids or accounting
+/// the dashboard shows teachers.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SharedReport {
+    pub student_name: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub report: SqlJson<ReportContent>,
+    pub created_at: String,
+    #[sqlx(skip)]
+    pub link_expires_at: String,
+}
+
+const LINK_COLUMNS: &str = "id, student_id, report_id, created_by, created_at, expires_at, \
+                            revoked_at, view_count, last_viewed_at";
+
+fn secret(state: &AppState) -> Result<&str, AppError> {
+    state.config.report_link_secret.as_deref().ok_or_else(|| {
+        AppError::NotFound("report links are not enabled (set REPORT_LINK_SECRET)".to_string())
+    })
+}
+
+/// Links for the student, newest first, including expired and revoked ones.
+pub async fn list_report_links(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<ReportLink>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, ReportLink>(&format!(
+        "SELECT {LINK_COLUMNS} FROM report_links WHERE student_id = ? \
+         ORDER BY created_at DESC, id DESC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_report_link(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<CreateReportLinkRequest>,
+) -> Result<Json<CreatedReportLink>, AppError> {
+    let secret = secret(&state)?;
+    students::ensure_exists(&state.pool, student_id).await?;
+    if let Some(report_id) = payload.report_id {
+        check_report(&state.pool, student_id, report_id).await?;
+    }
+
+    let days = payload.expires_in_days.unwrap_or(DEFAULT_EXPIRES_IN_DAYS);
+    let exp = unix_now() + days as u64 * 86400;
+    let link = sqlx::query_as::<_, ReportLink>(&format!(
+        r#"
+        INSERT INTO report_links(student_id, report_id, created_by, expires_at)
+        VALUES(?, ?, ?, datetime(?, 'unixepoch'))
+        RETURNING {LINK_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(payload.report_id)
+    .bind(auth::caller().map(|caller| caller.id))
+    .bind(exp as i64)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let claims = Claims {
+        sub: student_id.to_string(),
+        typ: TYP.to_string(),
+        jti: link.id.to_string(),
+        exp,
+    };
+    let share_token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .map_err(|err| AppError::Internal(format!("failed to sign report link: {err}")))?;
+    let share_url = state.config.report_link_url.as_deref().map(|url| {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}token={share_token}")
+    });
+
+    Ok(Json(CreatedReportLink {
+        link,
+        share_token,
+        share_url,
+    }))
+}
+
+/// Stops the link working at once; it stays listed as revoked.
+pub async fn revoke_report_link(
+    State(state): State<AppState>,
+    Path((student_id, link_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let result = sqlx::query(
+        "UPDATE report_links SET revoked_at = CURRENT_TIMESTAMP \
+         WHERE id = ? AND student_id = ? AND revoked_at IS NULL",
+    )
+    .bind(link_id)
+    .bind(student_id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "report link {link_id} for student {student_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The report behind a share link. Needs no login; the signed token is the
+/// only credential, and it shows nothing but this one report.
+pub async fn shared_progress_report(
+    State(state): State<AppState>,
+    Query(query): Query<SharedReportQuery>,
+) -> Result<Json<SharedReport>, AppError> {
+    let invalid = || AppError::NotFound("this link is invalid, expired, or revoked".to_string());
+    let secret = secret(&state)?;
+
+    let claims = decode::<Claims>(
+        query.token.trim(),
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map(|data| data.claims)
+    .ok()
+    .filter(|claims| claims.typ == TYP)
+    .ok_or_else(invalid)?;
+    let (Ok(student_id), Ok(link_id)) = (claims.sub.parse::<i64>(), claims.jti.parse::<i64>())
+    else {
+        return Err(invalid());
+    };
+
+    // Counting the view in the same statement that checks the link.
+    let link = db::retry_busy(|| {
+        sqlx::query_as::<_, (Option<i64>, String)>(
+            r#"
+            UPDATE report_links
+            SET view_count = view_count + 1, last_viewed_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND student_id = ? AND revoked_at IS NULL
+              AND expires_at > CURRENT_TIMESTAMP
+              AND student_id IN (SELECT id FROM students WHERE deleted_at IS NULL)
+            RETURNING report_id, expires_at
+            "#,
+        )
+        .bind(link_id)
+        .bind(student_id)
+        .fetch_optional(&state.pool)
+    })
+    .await?;
+    let Some((report_id, link_expires_at)) = link else {
+        return Err(invalid());
+    };
+
+    let mut report = sqlx::query_as::<_, SharedReport>(
+        r#"
+        SELECT s.name AS student_name, r.period_start, r.period_end, r.report, r.created_at
+        FROM progress_reports r
+        JOIN students s ON s.id = r.student_id
+        WHERE r.student_id = ? AND (? IS NULL OR r.id = ?)
+        ORDER BY r.created_at DESC, r.id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(student_id)
+    .bind(report_id)
+    .bind(report_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("there is no progress report to show yet".to_string()))?;
+    report.link_expires_at = link_expires_at;
+
+    Ok(Json(report))
+}
+
+async fn check_report(pool: &SqlitePool, student_id: i64, report_id: i64) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM progress_reports WHERE id = ? AND student_id = ?)",
+    )
+    .bind(report_id)
+    .bind(student_id)
+    .fetch_one(pool)
+    .await?;
+
+    let mut v = Validator::default();
+    if !exists {
+        v.error(
+            "report_id",
+            format!("progress report {report_id} does not belong to student {student_id}"),
+        );
+    }
+    v.finish()
+}
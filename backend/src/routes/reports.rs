@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_state::AppState, auth, error::AppError};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CostSummary {
+    pub model: String,
+    pub requests: i64,
+    pub avg_latency_ms: Option<f64>,
+    pub max_latency_ms: Option<i64>,
+    pub avg_ttft_ms: Option<f64>,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub estimated_cost: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CostReport {
+    pub totals: CostSummary,
+    pub models: Vec<CostSummary>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct VariantSummary {
+    pub experiment: String,
+    pub variant: String,
+    pub model: Option<String>,
+    pub students: i64,
+    pub requests: i64,
+    pub avg_latency_ms: Option<f64>,
+    pub max_latency_ms: Option<i64>,
+    pub avg_ttft_ms: Option<f64>,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub estimated_cost: f64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct FeedbackSummary {
+    pub model: String,
+    /// `None` for the per-model rollup and for chats without a persona.
+    pub persona_id: Option<i64>,
+    pub persona: Option<String>,
+    /// Interactions with at least one rating.
+    pub rated: i64,
+    pub thumbs_up: i64,
+    pub thumbs_down: i64,
+    /// `thumbs_up / (thumbs_up + thumbs_down)`.
+    pub approval: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackReport {
+    pub models: Vec<FeedbackSummary>,
+    /// One row per model and persona pair.
+    pub personas: Vec<FeedbackSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// Inclusive lower bound on `created_at`, e.g. `2026-09-01`.
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`.
+    pub until: Option<String>,
+    /// Only interactions by students enrolled in this class.
+    pub class_id: Option<i64>,
+}
+
+const SUMMARY_COLUMNS: &str = r#"
+    COUNT(*) AS requests,
+    AVG(latency_ms) AS avg_latency_ms,
+    MAX(latency_ms) AS max_latency_ms,
+    AVG(ttft_ms) AS avg_ttft_ms,
+    COALESCE(SUM(prompt_tokens), 0) AS prompt_tokens,
+    COALESCE(SUM(completion_tokens), 0) AS completion_tokens,
+    COALESCE(SUM(estimated_cost), 0.0) AS estimated_cost
+"#;
+
+const CLASS_FILTER: &str =
+    "(? IS NULL OR student_id IN (SELECT student_id FROM enrollments WHERE class_id = ?))";
+
+pub async fn cost_report(
+    State(state): State<AppState>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<CostReport>, AppError> {
+    let since = query.since.as_deref().unwrap_or("");
+    let until = query.until.as_deref().unwrap_or("9999");
+    let school_id = auth::school_id();
+
+    let totals = sqlx::query_as::<_, CostSummary>(&format!(
+        r#"
+        SELECT 'all' AS model, {SUMMARY_COLUMNS}
+        FROM ai_interactions
+        WHERE school_id = ? AND created_at >= ? AND created_at < ? AND {CLASS_FILTER}
+        "#
+    ))
+    .bind(school_id)
+    .bind(since)
+    .bind(until)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let models = sqlx::query_as::<_, CostSummary>(&format!(
+        r#"
+        SELECT COALESCE(model, 'unknown') AS model, {SUMMARY_COLUMNS}
+        FROM ai_interactions
+        WHERE school_id = ? AND created_at >= ? AND created_at < ? AND {CLASS_FILTER}
+        GROUP BY COALESCE(model, 'unknown')
+        ORDER BY requests DESC
+        "#
+    ))
+    .bind(school_id)
+    .bind(since)
+    .bind(until)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(CostReport { totals, models }))
+}
+
+/// Side-by-side numbers for each A/B variant, so response quality reviews
+/// can be weighed against speed and cost.
+pub async fn experiment_report(
+    State(state): State<AppState>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Vec<VariantSummary>>, AppError> {
+    let since = query.since.as_deref().unwrap_or("");
+    let until = query.until.as_deref().unwrap_or("9999");
+    let school_id = auth::school_id();
+
+    let rows = sqlx::query_as::<_, VariantSummary>(&format!(
+        r#"
+        SELECT experiment, variant, MAX(model) AS model,
+               COUNT(DISTINCT student_id) AS students, {SUMMARY_COLUMNS}
+        FROM ai_interactions
+        WHERE school_id = ? AND experiment IS NOT NULL AND created_at >= ? AND created_at < ? AND {CLASS_FILTER}
+        GROUP BY experiment, variant
+        ORDER BY experiment ASC, variant ASC
+        "#
+    ))
+    .bind(school_id)
+    .bind(since)
+    .bind(until)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Thumbs up/down counts per model and per model/persona pair, for
+/// comparing how well each local model serves the class. Dates filter on
+/// when the interaction happened, not when it was rated.
+pub async fn feedback_report(
+    State(state): State<AppState>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<FeedbackReport>, AppError> {
+    let since = query.since.as_deref().unwrap_or("");
+    let until = query.until.as_deref().unwrap_or("9999");
+    let school_id = auth::school_id();
+
+    let summary = |persona_columns: &str, group_by: &str| {
+        format!(
+            r#"
+            SELECT COALESCE(i.model, 'unknown') AS model, {persona_columns},
+                   COUNT(DISTINCT f.interaction_id) AS rated,
+                   SUM(f.rating = 'up') AS thumbs_up,
+                   SUM(f.rating = 'down') AS thumbs_down,
+                   AVG(f.rating = 'up') AS approval
+            FROM interaction_feedback f
+            JOIN ai_interactions i ON i.id = f.interaction_id
+            LEFT JOIN personas p ON p.id = i.persona_id
+            WHERE i.school_id = ? AND i.created_at >= ? AND i.created_at < ?
+              AND (? IS NULL OR i.student_id IN (SELECT student_id FROM enrollments WHERE class_id = ?))
+            GROUP BY {group_by}
+            ORDER BY approval DESC, rated DESC
+            "#
+        )
+    };
+
+    let models = sqlx::query_as::<_, FeedbackSummary>(&summary(
+        "NULL AS persona_id, NULL AS persona",
+        "COALESCE(i.model, 'unknown')",
+    ))
+    .bind(school_id)
+    .bind(since)
+    .bind(until)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let personas = sqlx::query_as::<_, FeedbackSummary>(&summary(
+        "i.persona_id AS persona_id, MAX(p.name) AS persona",
+        "COALESCE(i.model, 'unknown'), i.persona_id",
+    ))
+    .bind(school_id)
+    .bind(since)
+    .bind(until)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(FeedbackReport { models, personas }))
+}
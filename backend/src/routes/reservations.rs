@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Reservation {
+    pub id: i64,
+    pub class_name: String,
+    pub teacher_id: Option<i64>,
+    pub starts_at: String,
+    pub ends_at: String,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReservationRequest {
+    pub class_name: String,
+    pub teacher_id: Option<i64>,
+    pub starts_at: String,
+    pub ends_at: String,
+    pub note: Option<String>,
+}
+
+impl Validate for CreateReservationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("class_name", &self.class_name);
+        v.max_chars("class_name", &self.class_name, 100);
+        v.timestamp("starts_at", &self.starts_at);
+        v.timestamp("ends_at", &self.ends_at);
+        if self.ends_at <= self.starts_at {
+            v.error("ends_at", "must be after starts_at");
+        }
+        if let Some(note) = &self.note {
+            v.max_chars("note", note, 500);
+        }
+    }
+}
+
+const RESERVATION_COLUMNS: &str =
+    "id, class_name, teacher_id, starts_at, ends_at, note, created_at";
+
+/// Lists reservations that have not ended yet, soonest first.
+pub async fn list_reservations(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Reservation>>, AppError> {
+    let rows = sqlx::query_as::<_, Reservation>(&format!(
+        r#"
+        SELECT {RESERVATION_COLUMNS}
+        FROM reservations
+        WHERE ends_at > CURRENT_TIMESTAMP
+        ORDER BY starts_at ASC
+        "#
+    ))
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_reservation(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<CreateReservationRequest>,
+) -> Result<Json<Reservation>, AppError> {
+    user.require_admin()?;
+    let class_name = payload.class_name.trim();
+
+    // The whole GPU is reserved, so windows may not overlap.
+    let clash: Option<String> = sqlx::query_scalar(
+        "SELECT class_name FROM reservations WHERE starts_at < ? AND ends_at > ? LIMIT 1",
+    )
+    .bind(&payload.ends_at)
+    .bind(&payload.starts_at)
+    .fetch_optional(&state.pool)
+    .await?;
+    if let Some(other) = clash {
+        return Err(AppError::Conflict(format!(
+            "overlaps an existing reservation for {other}"
+        )));
+    }
+
+    let created = sqlx::query_as::<_, Reservation>(&format!(
+        r#"
+        INSERT INTO reservations(class_name, teacher_id, starts_at, ends_at, note)
+        VALUES(?, ?, ?, ?, ?)
+        RETURNING {RESERVATION_COLUMNS}
+        "#
+    ))
+    .bind(class_name)
+    .bind(payload.teacher_id)
+    .bind(&payload.starts_at)
+    .bind(&payload.ends_at)
+    .bind(&payload.note)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn delete_reservation(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let result = sqlx::query("DELETE FROM reservations WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("reservation {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,197 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    interactions::{self, ParsedInteraction},
+    pagination::{self, Page},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const STATUSES: &[&str] = &["pending", "approved", "dismissed"];
+const DECISIONS: &[&str] = &["approve", "dismiss"];
+
+/// A flagged interaction waiting for, or carrying, a reviewer's decision.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReviewItem {
+    pub id: i64,
+    pub interaction_id: i64,
+    /// `response_filter` or `content_filter`.
+    pub reason: String,
+    /// `pending`, `approved` (the flag was right), or `dismissed` (a false
+    /// alarm).
+    pub status: String,
+    pub reviewer: Option<String>,
+    pub note: Option<String>,
+    pub student_id: Option<i64>,
+    pub model: Option<String>,
+    pub created_at: String,
+    pub decided_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewItemDetail {
+    #[serde(flatten)]
+    pub item: ReviewItem,
+    pub interaction: ParsedInteraction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQueueQuery {
+    /// Defaults to `pending`.
+    pub status: Option<String>,
+    pub student_id: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecisionRequest {
+    /// `approve` or `dismiss`.
+    pub decision: String,
+    pub reviewer: String,
+    pub note: Option<String>,
+}
+
+impl Validate for DecisionRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("decision", &self.decision, DECISIONS);
+        v.required("reviewer", &self.reviewer);
+        v.max_chars("reviewer", &self.reviewer, 200);
+        if let Some(note) = &self.note {
+            v.max_chars("note", note, 2000);
+        }
+    }
+}
+
+const REVIEW_SELECT: &str = r#"
+    SELECT r.id, r.interaction_id, r.reason, r.status, r.reviewer, r.note,
+           i.student_id, i.model, r.created_at, r.decided_at
+    FROM review_items r
+    JOIN ai_interactions i ON i.id = r.interaction_id
+"#;
+
+/// Flagged interactions, oldest first so nothing waits forever.
+pub async fn list_review_items(
+    State(state): State<AppState>,
+    Query(query): Query<ReviewQueueQuery>,
+) -> Result<Json<Page<ReviewItem>>, AppError> {
+    let status = query.status.as_deref().unwrap_or("pending");
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    v.one_of("status", status, STATUSES);
+    v.finish()?;
+
+    let school_id = auth::school_id();
+    let filter = "i.school_id = ? AND r.status = ? AND (? IS NULL OR i.student_id = ?)";
+    let items = sqlx::query_as::<_, ReviewItem>(&format!(
+        "{REVIEW_SELECT} WHERE {filter} ORDER BY r.created_at ASC, r.id ASC LIMIT ? OFFSET ?"
+    ))
+    .bind(school_id)
+    .bind(status)
+    .bind(query.student_id)
+    .bind(query.student_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        r#"
+        SELECT COUNT(*)
+        FROM review_items r
+        JOIN ai_interactions i ON i.id = r.interaction_id
+        WHERE {filter}
+        "#
+    ))
+    .bind(school_id)
+    .bind(status)
+    .bind(query.student_id)
+    .bind(query.student_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(Page {
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+pub async fn get_review_item(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ReviewItemDetail>, AppError> {
+    let item = load(&state.pool, id).await?;
+    let interaction = interactions::get(&state.pool, item.interaction_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("interaction {}", item.interaction_id)))?;
+
+    Ok(Json(ReviewItemDetail {
+        item,
+        interaction: interaction.into(),
+    }))
+}
+
+/// Records the reviewer's call. Decisions are final; a decided item returns
+/// `409`.
+pub async fn decide_review_item(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<DecisionRequest>,
+) -> Result<Json<ReviewItem>, AppError> {
+    let status = if payload.decision == "approve" {
+        "approved"
+    } else {
+        "dismissed"
+    };
+    load(&state.pool, id).await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE review_items
+        SET status = ?, reviewer = ?, note = ?, decided_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND status = 'pending'
+        "#,
+    )
+    .bind(status)
+    .bind(payload.reviewer.trim())
+    .bind(
+        payload
+            .note
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty()),
+    )
+    .bind(id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        let item = load(&state.pool, id).await?;
+        return Err(AppError::Conflict(format!(
+            "review item {id} was already {}",
+            item.status
+        )));
+    }
+
+    load(&state.pool, id).await.map(Json)
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<ReviewItem, AppError> {
+    sqlx::query_as::<_, ReviewItem>(&format!(
+        "{REVIEW_SELECT} WHERE r.id = ? AND i.school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("review item {id}")))
+}
@@ -0,0 +1,417 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    auth,
+    error::AppError,
+    routes::{assignments, users},
+    trace::TraceHeaders,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_CRITERIA: usize = 20;
+const MAX_SUBMISSION_CHARS: usize = 20_000;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Criterion {
+    pub name: String,
+    pub description: String,
+    pub max_points: f64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Rubric {
+    pub id: i64,
+    pub title: String,
+    pub subject: Option<String>,
+    pub criteria: SqlJson<Vec<Criterion>>,
+    /// Sum of the criteria's `max_points`.
+    #[sqlx(skip)]
+    pub max_points: f64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CriterionScore {
+    pub name: String,
+    pub score: f64,
+    pub max_points: f64,
+    pub comment: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RubricResult {
+    pub criteria: Vec<CriterionScore>,
+    pub overall_comment: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RubricGrading {
+    pub id: i64,
+    pub assignment_id: i64,
+    pub student_id: i64,
+    pub rubric_id: Option<i64>,
+    pub submission: String,
+    pub result: SqlJson<RubricResult>,
+    pub total_points: f64,
+    pub max_points: f64,
+    /// The `ai_interactions` row for the grading request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RubricRequest {
+    pub title: String,
+    pub subject: Option<String>,
+    pub criteria: Vec<Criterion>,
+}
+
+impl Validate for RubricRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("title", &self.title);
+        v.max_chars("title", &self.title, 200);
+        if let Some(subject) = &self.subject {
+            v.required("subject", subject);
+            v.max_chars("subject", subject, 100);
+        }
+        if self.criteria.is_empty() {
+            v.error("criteria", "must not be empty");
+        }
+        if self.criteria.len() > MAX_CRITERIA {
+            v.error(
+                "criteria",
+                format!("must have at most {MAX_CRITERIA} criteria"),
+            );
+        }
+        for (i, criterion) in self.criteria.iter().enumerate() {
+            let field = format!("criteria[{i}]");
+            v.required(&format!("{field}.name"), &criterion.name);
+            v.max_chars(&format!("{field}.name"), &criterion.name, 100);
+            v.max_chars(
+                &format!("{field}.description"),
+                &criterion.description,
+                2_000,
+            );
+            v.range(
+                &format!("{field}.max_points"),
+                criterion.max_points,
+                0.5,
+                1_000.0,
+            );
+            let name = criterion.name.trim().to_lowercase();
+            if self.criteria[..i]
+                .iter()
+                .any(|other| other.name.trim().to_lowercase() == name)
+            {
+                v.error(format!("{field}.name"), "duplicates an earlier criterion");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GradeWithRubricRequest {
+    pub rubric_id: i64,
+    pub assignment_id: i64,
+    /// The student's work as text.
+    pub submission: String,
+    pub model: Option<String>,
+    /// Teacher the grading is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for GradeWithRubricRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("submission", &self.submission);
+        v.max_chars("submission", &self.submission, MAX_SUBMISSION_CHARS);
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+/// The JSON shape the model is asked for.
+#[derive(Debug, Deserialize)]
+struct ModelGrading {
+    criteria: Vec<ModelScore>,
+    #[serde(default)]
+    overall_comment: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelScore {
+    name: String,
+    score: f64,
+    #[serde(default)]
+    comment: String,
+}
+
+const RUBRIC_COLUMNS: &str = "id, title, subject, criteria, created_at, updated_at";
+const GRADING_COLUMNS: &str = "id, assignment_id, student_id, rubric_id, submission, result, \
+                               total_points, max_points, generation_id, created_at";
+
+pub async fn list_rubrics(State(state): State<AppState>) -> Result<Json<Vec<Rubric>>, AppError> {
+    let rows = sqlx::query_as::<_, Rubric>(&format!(
+        "SELECT {RUBRIC_COLUMNS} FROM rubrics WHERE school_id = ? ORDER BY title ASC, id ASC"
+    ))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows.into_iter().map(with_total).collect()))
+}
+
+pub async fn create_rubric(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<RubricRequest>,
+) -> Result<Json<Rubric>, AppError> {
+    let created = sqlx::query_as::<_, Rubric>(&format!(
+        r#"
+        INSERT INTO rubrics(title, subject, criteria, school_id)
+        VALUES(?, ?, ?, ?)
+        RETURNING {RUBRIC_COLUMNS}
+        "#
+    ))
+    .bind(payload.title.trim())
+    .bind(&payload.subject)
+    .bind(SqlJson(trimmed(payload.criteria)))
+    .bind(auth::school_id())
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(with_total(created)))
+}
+
+pub async fn get_rubric(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Rubric>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Past gradings keep the criteria they were scored against.
+pub async fn update_rubric(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<RubricRequest>,
+) -> Result<Json<Rubric>, AppError> {
+    let updated = sqlx::query_as::<_, Rubric>(&format!(
+        r#"
+        UPDATE rubrics
+        SET title = ?, subject = ?, criteria = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND school_id = ?
+        RETURNING {RUBRIC_COLUMNS}
+        "#
+    ))
+    .bind(payload.title.trim())
+    .bind(&payload.subject)
+    .bind(SqlJson(trimmed(payload.criteria)))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?;
+
+    updated
+        .map(|rubric| Json(with_total(rubric)))
+        .ok_or_else(|| AppError::NotFound(format!("rubric {id}")))
+}
+
+pub async fn delete_rubric(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM rubrics WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("rubric {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Scores a submission against each rubric criterion with the local model
+/// and stores the result. The assignment's grade is left alone; a teacher
+/// copies `total_points` into it once they agree with the scores.
+pub async fn grade_with_rubric(
+    State(state): State<AppState>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<GradeWithRubricRequest>,
+) -> Result<Json<RubricGrading>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let rubric = load(&state.pool, payload.rubric_id).await?;
+    let assignment = assignments::load(&state.pool, payload.assignment_id).await?;
+
+    let criteria = rubric
+        .criteria
+        .iter()
+        .map(|c| {
+            format!(
+                "- {} (0 to {} points): {}",
+                c.name, c.max_points, c.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut request = vec![format!("Assignment: {}", assignment.title)];
+    if let Some(description) = &assignment.description {
+        request.push(format!("Instructions: {description}"));
+    }
+    request.push(format!("Rubric criteria:\n{criteria}"));
+    request.push(format!(
+        "Student submission:\n---\n{}\n---",
+        payload.submission.trim()
+    ));
+
+    let (grading, completion) = assist::complete_json::<ModelGrading>(
+        &state,
+        Prompt {
+            system: "You grade student work against a rubric. Score every criterion, using \
+                     its exact name, between 0 and its maximum points, and justify each \
+                     score in one or two sentences that quote or point to the submission. \
+                     Reply with only a JSON object: {\"criteria\": [{\"name\": string, \
+                     \"score\": number, \"comment\": string}], \"overall_comment\": string}."
+                .to_string(),
+            user: request.join("\n\n"),
+            model: payload.model,
+            temperature: Some(0.2),
+            max_tokens: Some(2_000),
+            user_id: payload.user_id,
+            student_id: Some(assignment.student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let result = match_scores(&rubric.criteria, grading).map_err(|problem| {
+        AppError::Upstream(format!(
+            "model grading did not fit the rubric ({problem}); interaction {}",
+            completion.interaction_id
+        ))
+    })?;
+    let total_points = result.criteria.iter().map(|c| c.score).sum::<f64>();
+
+    let saved = sqlx::query_as::<_, RubricGrading>(&format!(
+        r#"
+        INSERT INTO rubric_gradings(
+            assignment_id, student_id, rubric_id, submission, result, total_points,
+            max_points, generation_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING {GRADING_COLUMNS}
+        "#
+    ))
+    .bind(assignment.id)
+    .bind(assignment.student_id)
+    .bind(rubric.id)
+    .bind(&payload.submission)
+    .bind(SqlJson(&result))
+    .bind(total_points)
+    .bind(rubric.max_points)
+    .bind(completion.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(saved))
+}
+
+/// Rubric gradings for an assignment, newest first.
+pub async fn list_rubric_gradings(
+    State(state): State<AppState>,
+    Path(assignment_id): Path<i64>,
+) -> Result<Json<Vec<RubricGrading>>, AppError> {
+    assignments::load(&state.pool, assignment_id).await?;
+
+    let rows = sqlx::query_as::<_, RubricGrading>(&format!(
+        r#"
+        SELECT {GRADING_COLUMNS}
+        FROM rubric_gradings
+        WHERE assignment_id = ?
+        ORDER BY created_at DESC, id DESC
+        "#
+    ))
+    .bind(assignment_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+/// Lines the model's scores up with the rubric, in rubric order. Every
+/// criterion must be scored once and within its range.
+fn match_scores(criteria: &[Criterion], grading: ModelGrading) -> Result<RubricResult, String> {
+    let mut scores = grading.criteria;
+    let mut matched = Vec::with_capacity(criteria.len());
+    for criterion in criteria {
+        let name = criterion.name.to_lowercase();
+        let index = scores
+            .iter()
+            .position(|s| s.name.trim().to_lowercase() == name)
+            .ok_or_else(|| format!("no score for '{}'", criterion.name))?;
+        let score = scores.swap_remove(index);
+        if !(0.0..=criterion.max_points).contains(&score.score) {
+            return Err(format!(
+                "'{}' scored {} of {}",
+                criterion.name, score.score, criterion.max_points
+            ));
+        }
+        matched.push(CriterionScore {
+            name: criterion.name.clone(),
+            score: score.score,
+            max_points: criterion.max_points,
+            comment: score.comment.trim().to_string(),
+        });
+    }
+    if let Some(extra) = scores.first() {
+        return Err(format!("unknown criterion '{}'", extra.name));
+    }
+
+    Ok(RubricResult {
+        criteria: matched,
+        overall_comment: grading.overall_comment.trim().to_string(),
+    })
+}
+
+fn trimmed(criteria: Vec<Criterion>) -> Vec<Criterion> {
+    criteria
+        .into_iter()
+        .map(|c| Criterion {
+            name: c.name.trim().to_string(),
+            description: c.description.trim().to_string(),
+            max_points: c.max_points,
+        })
+        .collect()
+}
+
+fn with_total(mut rubric: Rubric) -> Rubric {
+    rubric.max_points = rubric.criteria.iter().map(|c| c.max_points).sum();
+    rubric
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<Rubric, AppError> {
+    sqlx::query_as::<_, Rubric>(&format!(
+        "SELECT {RUBRIC_COLUMNS} FROM rubrics WHERE id = ? AND school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .map(with_total)
+    .ok_or_else(|| AppError::NotFound(format!("rubric {id}")))
+}
@@ -0,0 +1,302 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    validation::{ValidJson, Validate, Validator},
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SchoolYear {
+    pub id: i64,
+    pub name: String,
+    pub starts_on: String,
+    pub ends_on: String,
+    pub archived_at: Option<String>,
+    /// Students currently in this year's cohort, not counting soft-deleted ones.
+    pub student_count: i64,
+    pub class_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchoolYearRequest {
+    /// e.g. `2026-27`.
+    pub name: String,
+    pub starts_on: String,
+    pub ends_on: String,
+}
+
+impl Validate for SchoolYearRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 100);
+        v.date("starts_on", &self.starts_on);
+        v.date("ends_on", &self.ends_on);
+        // ISO dates compare correctly as strings.
+        if self.ends_on <= self.starts_on {
+            v.error("ends_on", "must be after starts_on");
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveRequest {
+    /// Moves the year's students into this (unarchived) year.
+    pub next_year_id: Option<i64>,
+    /// Students who stay with the archived year, e.g. graduates.
+    #[serde(default)]
+    pub exclude_student_ids: Vec<i64>,
+}
+
+impl Validate for ArchiveRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(next_year_id) = self.next_year_id {
+            v.range("next_year_id", next_year_id, 1, i64::MAX);
+        } else if !self.exclude_student_ids.is_empty() {
+            v.error("exclude_student_ids", "requires next_year_id");
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveResponse {
+    pub school_year: SchoolYear,
+    /// Students moved into `next_year_id`.
+    pub carried_over: u64,
+}
+
+const SCHOOL_YEAR_SELECT: &str = r#"
+    SELECT y.id, y.name, y.starts_on, y.ends_on, y.archived_at,
+           (SELECT COUNT(*) FROM students s
+            WHERE s.school_year_id = y.id AND s.deleted_at IS NULL) AS student_count,
+           (SELECT COUNT(*) FROM classes c WHERE c.school_year_id = y.id) AS class_count,
+           y.created_at
+    FROM school_years y
+"#;
+
+/// The caller's school's years, newest first.
+pub async fn list_school_years(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SchoolYear>>, AppError> {
+    let rows = sqlx::query_as::<_, SchoolYear>(&format!(
+        "{SCHOOL_YEAR_SELECT} WHERE y.school_id = ? ORDER BY y.starts_on DESC, y.id DESC"
+    ))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_school_year(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<SchoolYear>, AppError> {
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn create_school_year(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<SchoolYearRequest>,
+) -> Result<Json<SchoolYear>, AppError> {
+    let name = payload.name.trim().to_string();
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO school_years(school_id, name, starts_on, ends_on) VALUES(?, ?, ?, ?) RETURNING id",
+    )
+    .bind(auth::school_id())
+    .bind(&name)
+    .bind(&payload.starts_on)
+    .bind(&payload.ends_on)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || format!("school year '{name}' already exists"))
+    })?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn update_school_year(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<SchoolYearRequest>,
+) -> Result<Json<SchoolYear>, AppError> {
+    ensure_open(&state.pool, id).await?;
+    let name = payload.name.trim().to_string();
+
+    sqlx::query("UPDATE school_years SET name = ?, starts_on = ?, ends_on = ? WHERE id = ?")
+        .bind(&name)
+        .bind(&payload.starts_on)
+        .bind(&payload.ends_on)
+        .bind(id)
+        .execute(&state.pool)
+        .await
+        .map_err(|err| {
+            AppError::unique_conflict(err, || format!("school year '{name}' already exists"))
+        })?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+/// Closes the year. Its classes, and the students left in its cohort, become
+/// read-only but stay queryable with `?school_year_id=`. With `next_year_id`,
+/// the cohort (minus `exclude_student_ids`) moves on to the new year first.
+pub async fn archive_school_year(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<ArchiveRequest>,
+) -> Result<Json<ArchiveResponse>, AppError> {
+    ensure_open(&state.pool, id).await?;
+    if let Some(next_year_id) = payload.next_year_id {
+        let mut v = Validator::default();
+        if next_year_id == id {
+            v.error("next_year_id", "must be a different school year");
+        } else if let Some(archived) = archived_at(&state.pool, next_year_id).await? {
+            if archived.is_some() {
+                v.error(
+                    "next_year_id",
+                    format!("school year {next_year_id} is archived"),
+                );
+            }
+        } else {
+            v.error(
+                "next_year_id",
+                format!("school year {next_year_id} does not exist"),
+            );
+        }
+        v.finish()?;
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let mut carried_over = 0;
+    if let Some(next_year_id) = payload.next_year_id {
+        let excluded = serde_json::to_string(&payload.exclude_student_ids)
+            .map_err(|err| AppError::Internal(err.to_string()))?;
+        carried_over = sqlx::query(
+            r#"
+            UPDATE students
+            SET school_year_id = ?
+            WHERE school_year_id = ?
+              AND school_id = ?
+              AND id NOT IN (SELECT value FROM json_each(?))
+            "#,
+        )
+        .bind(next_year_id)
+        .bind(id)
+        .bind(auth::school_id())
+        .bind(excluded)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    sqlx::query("UPDATE school_years SET archived_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Json(ArchiveResponse {
+        school_year: load(&state.pool, id).await?,
+        carried_over,
+    }))
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<SchoolYear, AppError> {
+    sqlx::query_as::<_, SchoolYear>(&format!(
+        "{SCHOOL_YEAR_SELECT} WHERE y.id = ? AND y.school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("school year {id}")))
+}
+
+/// `None` if the year doesn't exist in the caller's school, otherwise its
+/// `archived_at`.
+async fn archived_at(pool: &SqlitePool, id: i64) -> Result<Option<Option<String>>, AppError> {
+    let archived =
+        sqlx::query_scalar("SELECT archived_at FROM school_years WHERE id = ? AND school_id = ?")
+            .bind(id)
+            .bind(auth::school_id())
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(archived)
+}
+
+/// Fails with 404 for an unknown year and 409 for an archived one.
+async fn ensure_open(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    match archived_at(pool, id).await? {
+        None => Err(AppError::NotFound(format!("school year {id}"))),
+        Some(Some(_)) => Err(AppError::Conflict(format!("school year {id} is archived"))),
+        Some(None) => Ok(()),
+    }
+}
+
+/// Checks a `school_year_id` field: the year must exist and be unarchived.
+pub async fn check_reference(pool: &SqlitePool, field: &str, id: i64) -> Result<(), AppError> {
+    let mut v = Validator::default();
+    match archived_at(pool, id).await? {
+        None => v.error(field, format!("school year {id} does not exist")),
+        Some(Some(_)) => v.error(field, format!("school year {id} is archived")),
+        Some(None) => {}
+    }
+    v.finish()
+}
+
+/// Fails with 409 if the student belongs to an archived year.
+pub async fn ensure_student_writable(pool: &SqlitePool, student_id: i64) -> Result<(), AppError> {
+    ensure_writable(pool, "students", "student", student_id).await
+}
+
+/// Fails with 409 if the class belongs to an archived year.
+pub async fn ensure_class_writable(pool: &SqlitePool, class_id: i64) -> Result<(), AppError> {
+    ensure_writable(pool, "classes", "class", class_id).await
+}
+
+async fn ensure_writable(
+    pool: &SqlitePool,
+    table: &str,
+    noun: &str,
+    id: i64,
+) -> Result<(), AppError> {
+    let year = sqlx::query_scalar::<_, String>(&format!(
+        r#"
+        SELECT y.name
+        FROM {table} t
+        JOIN school_years y ON y.id = t.school_year_id
+        WHERE t.id = ? AND y.archived_at IS NOT NULL
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    match year {
+        Some(year) => Err(AppError::Conflict(format!(
+            "{noun} {id} belongs to archived school year '{year}' and is read-only"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The `[since, until)` range covered by a year, for date-filtered queries.
+pub async fn date_range(pool: &SqlitePool, id: i64) -> Result<(String, String), AppError> {
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT starts_on, date(ends_on, '+1 day') FROM school_years WHERE id = ? AND school_id = ?",
+    )
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("school year {id}")))
+}
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    validation::{ValidJson, Validate, Validator},
+};
+
+/// Holds everything created before schools existed, anonymous requests, and
+/// the district admins who manage the other schools.
+pub const DEFAULT_SCHOOL_ID: i64 = 1;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct School {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub user_count: i64,
+    /// Not counting soft-deleted students.
+    pub student_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchoolRequest {
+    pub name: String,
+    /// Short unique handle, e.g. `lincoln-elementary`.
+    pub slug: String,
+}
+
+impl Validate for SchoolRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        v.required("slug", &self.slug);
+        v.max_chars("slug", &self.slug, 64);
+        if !self
+            .slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            v.error(
+                "slug",
+                "must contain only lowercase letters, digits, and hyphens",
+            );
+        }
+    }
+}
+
+const SCHOOL_SELECT: &str = r#"
+    SELECT sc.id, sc.name, sc.slug,
+           (SELECT COUNT(*) FROM users u WHERE u.school_id = sc.id) AS user_count,
+           (SELECT COUNT(*) FROM students s
+            WHERE s.school_id = sc.id AND s.deleted_at IS NULL) AS student_count,
+           sc.created_at
+    FROM schools sc
+"#;
+
+/// Admins of the default school manage the district: they create schools
+/// and can place users in any of them. Everyone else only sees their own.
+pub fn is_district_admin(user: &CurrentUser) -> bool {
+    user.is_admin() && user.school_id == DEFAULT_SCHOOL_ID
+}
+
+fn ensure_district_admin(user: &CurrentUser) -> Result<(), AppError> {
+    if !is_district_admin(user) {
+        return Err(AppError::Forbidden(
+            "only an admin of the default school can manage schools".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn list_schools(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<School>>, AppError> {
+    let all = is_district_admin(&user);
+    let rows = sqlx::query_as::<_, School>(&format!(
+        "{SCHOOL_SELECT} WHERE ? OR sc.id = ? ORDER BY sc.name ASC, sc.id ASC"
+    ))
+    .bind(all)
+    .bind(user.school_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_school(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Json<School>, AppError> {
+    if id != user.school_id {
+        ensure_district_admin(&user)?;
+    }
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn create_school(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<SchoolRequest>,
+) -> Result<Json<School>, AppError> {
+    ensure_district_admin(&user)?;
+    let slug = payload.slug.trim().to_string();
+
+    let id =
+        sqlx::query_scalar::<_, i64>("INSERT INTO schools(name, slug) VALUES(?, ?) RETURNING id")
+            .bind(payload.name.trim())
+            .bind(&slug)
+            .fetch_one(&state.pool)
+            .await
+            .map_err(|err| {
+                AppError::unique_conflict(err, || format!("school '{slug}' already exists"))
+            })?;
+
+    load(&state.pool, id).await.map(Json)
+}
+
+pub async fn update_school(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<SchoolRequest>,
+) -> Result<Json<School>, AppError> {
+    ensure_district_admin(&user)?;
+    let slug = payload.slug.trim().to_string();
+
+    let result = sqlx::query("UPDATE schools SET name = ?, slug = ? WHERE id = ?")
+        .bind(payload.name.trim())
+        .bind(&slug)
+        .bind(id)
+        .execute(&state.pool)
+        .await
+        .map_err(|err| {
+            AppError::unique_conflict(err, || format!("school '{slug}' already exists"))
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("school {id}")));
+    }
+    load(&state.pool, id).await.map(Json)
+}
+
+async fn load(pool: &SqlitePool, id: i64) -> Result<School, AppError> {
+    sqlx::query_as::<_, School>(&format!("{SCHOOL_SELECT} WHERE sc.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("school {id}")))
+}
+
+/// The school a user goes in: `requested` when a district admin names
+/// one, otherwise the caller's own.
+pub async fn for_user(pool: &SqlitePool, requested: Option<i64>) -> Result<i64, AppError> {
+    let Some(school_id) = requested.filter(|id| *id != auth::school_id()) else {
+        return Ok(auth::school_id());
+    };
+    if !auth::caller().is_some_and(|caller| is_district_admin(&caller)) {
+        return Err(AppError::Forbidden(
+            "only an admin of the default school can add users to another school".to_string(),
+        ));
+    }
+    check_reference(pool, "school_id", school_id).await?;
+    Ok(school_id)
+}
+
+/// Rejects a request body whose `field` names a school that doesn't exist.
+pub async fn check_reference(pool: &SqlitePool, field: &str, id: i64) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM schools WHERE id = ?)")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    let mut v = Validator::default();
+    if !exists {
+        v.error(field, format!("school {id} does not exist"));
+    }
+    v.finish()
+}
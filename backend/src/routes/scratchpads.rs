@@ -0,0 +1,310 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::students,
+    validation::{ValidJson, Validate, Validator},
+};
+
+const TOOL_NAME: &str = "update_scratchpad";
+const MAX_CONTENT_CHARS: usize = 100_000;
+pub const MAX_SESSION_ID_CHARS: usize = 128;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Scratchpad {
+    pub session_id: String,
+    pub student_id: Option<i64>,
+    pub version: i64,
+    pub content: String,
+    pub author: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ScratchpadVersion {
+    pub version: i64,
+    pub content: String,
+    pub author: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateScratchpadRequest {
+    pub content: String,
+    pub student_id: Option<i64>,
+    /// Version the edit was based on; rejected with 409 if it is stale.
+    pub base_version: Option<i64>,
+}
+
+impl Validate for UpdateScratchpadRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.max_chars("content", &self.content, MAX_CONTENT_CHARS);
+        if let Some(base) = self.base_version {
+            v.range("base_version", base, 0, i64::MAX);
+        }
+    }
+}
+
+pub async fn get_scratchpad(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Scratchpad>, AppError> {
+    ensure_visible(&state.pool, &session_id).await?;
+    latest(&state.pool, &session_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(&session_id))
+}
+
+pub async fn update_scratchpad(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    ValidJson(payload): ValidJson<UpdateScratchpadRequest>,
+) -> Result<Json<Scratchpad>, AppError> {
+    let mut v = Validator::default();
+    v.required("session_id", &session_id);
+    v.max_chars("session_id", &session_id, MAX_SESSION_ID_CHARS);
+    v.finish()?;
+    ensure_visible(&state.pool, &session_id).await?;
+    if let Some(student_id) = payload.student_id {
+        students::ensure_exists(&state.pool, student_id).await?;
+    }
+
+    save_version(
+        &state.pool,
+        &session_id,
+        payload.student_id,
+        &payload.content,
+        "student",
+        payload.base_version,
+    )
+    .await?;
+
+    latest(&state.pool, &session_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(&session_id))
+}
+
+pub async fn list_scratchpad_versions(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<ScratchpadVersion>>, AppError> {
+    ensure_visible(&state.pool, &session_id).await?;
+
+    let rows = sqlx::query_as::<_, ScratchpadVersion>(
+        r#"
+        SELECT v.version, v.content, v.author, v.created_at
+        FROM scratchpad_versions v
+        JOIN scratchpads s ON s.id = v.scratchpad_id
+        WHERE s.session_id = ? AND s.school_id = ?
+        ORDER BY v.version ASC
+        "#,
+    )
+    .bind(&session_id)
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn latest(pool: &SqlitePool, session_id: &str) -> Result<Option<Scratchpad>, AppError> {
+    let row = sqlx::query_as::<_, Scratchpad>(
+        r#"
+        SELECT s.session_id, s.student_id, v.version, v.content, v.author,
+               v.created_at AS updated_at
+        FROM scratchpads s
+        JOIN scratchpad_versions v ON v.scratchpad_id = s.id
+        WHERE s.session_id = ? AND s.school_id = ?
+        ORDER BY v.version DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(session_id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+async fn save_version(
+    pool: &SqlitePool,
+    session_id: &str,
+    student_id: Option<i64>,
+    content: &str,
+    author: &str,
+    base_version: Option<i64>,
+) -> Result<ScratchpadVersion, AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Insert first so the transaction takes the write lock before reading
+    // the current version.
+    sqlx::query(
+        r#"
+        INSERT INTO scratchpads(session_id, student_id, school_id)
+        VALUES(?, ?, ?)
+        ON CONFLICT(session_id) DO NOTHING
+        "#,
+    )
+    .bind(session_id)
+    .bind(student_id)
+    .bind(auth::school_id())
+    .execute(&mut *tx)
+    .await?;
+
+    let (scratchpad_id, current): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT s.id, COALESCE(MAX(v.version), 0)
+        FROM scratchpads s
+        LEFT JOIN scratchpad_versions v ON v.scratchpad_id = s.id
+        WHERE s.session_id = ? AND s.school_id = ?
+        GROUP BY s.id
+        "#,
+    )
+    .bind(session_id)
+    .bind(auth::school_id())
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| not_found(session_id))?;
+
+    if let Some(base) = base_version {
+        if base != current {
+            return Err(AppError::Conflict(format!(
+                "scratchpad is at version {current}, edit was based on {base}"
+            )));
+        }
+    }
+
+    let saved = sqlx::query_as::<_, ScratchpadVersion>(
+        r#"
+        INSERT INTO scratchpad_versions(scratchpad_id, version, content, author)
+        VALUES(?, ?, ?, ?)
+        RETURNING version, content, author, created_at
+        "#,
+    )
+    .bind(scratchpad_id)
+    .bind(current + 1)
+    .bind(content)
+    .bind(author)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(saved)
+}
+
+/// 404 if the session's scratchpad is another school's or belongs to a
+/// student the caller can't see. A session without one yet passes.
+async fn ensure_visible(pool: &SqlitePool, session_id: &str) -> Result<(), AppError> {
+    let row: Option<(i64, Option<i64>)> =
+        sqlx::query_as("SELECT school_id, student_id FROM scratchpads WHERE session_id = ?")
+            .bind(session_id)
+            .fetch_optional(pool)
+            .await?;
+
+    match row {
+        Some((school_id, _)) if school_id != auth::school_id() => Err(not_found(session_id)),
+        Some((_, Some(student_id))) => {
+            students::ensure_record_visible(pool, student_id, || {
+                format!("no scratchpad for session {session_id}")
+            })
+            .await
+        }
+        _ => Ok(()),
+    }
+}
+
+fn not_found(session_id: &str) -> AppError {
+    AppError::NotFound(format!("no scratchpad for session {session_id}"))
+}
+
+/// Prepends the current scratchpad to the chat messages and, when
+/// `with_tool` is set, offers the model the `update_scratchpad` tool.
+pub fn attach(payload: &mut Value, scratchpad: Option<&Scratchpad>, with_tool: bool) {
+    let context = match scratchpad {
+        Some(pad) => format!(
+            "Shared scratchpad (version {}):\n{}",
+            pad.version, pad.content
+        ),
+        None => "The shared scratchpad is empty.".to_string(),
+    };
+
+    if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+        messages.insert(0, json!({ "role": "system", "content": context }));
+    }
+
+    if !with_tool {
+        return;
+    }
+
+    let tool = json!({
+        "type": "function",
+        "function": {
+            "name": TOOL_NAME,
+            "description": "Replace the shared scratchpad with new content.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string", "description": "Full new scratchpad text." }
+                },
+                "required": ["content"]
+            }
+        }
+    });
+
+    match payload.get_mut("tools").and_then(Value::as_array_mut) {
+        Some(tools) => tools.push(tool),
+        None => payload["tools"] = json!([tool]),
+    }
+}
+
+/// Saves a new model-authored version for every `update_scratchpad` call in
+/// a chat completion.
+pub async fn apply_tool_calls(
+    pool: &SqlitePool,
+    session_id: &str,
+    student_id: Option<i64>,
+    completion: &Value,
+) -> Result<(), AppError> {
+    let Some(calls) = completion
+        .pointer("/choices/0/message/tool_calls")
+        .and_then(Value::as_array)
+    else {
+        return Ok(());
+    };
+
+    for call in calls {
+        if call.pointer("/function/name").and_then(Value::as_str) != Some(TOOL_NAME) {
+            continue;
+        }
+
+        let arguments = call
+            .pointer("/function/arguments")
+            .and_then(Value::as_str)
+            .and_then(|args| serde_json::from_str::<Value>(args).ok());
+        let Some(content) = arguments
+            .as_ref()
+            .and_then(|args| args.get("content"))
+            .and_then(Value::as_str)
+        else {
+            warn!(session_id, "ignoring malformed update_scratchpad call");
+            continue;
+        };
+
+        save_version(pool, session_id, student_id, content, "model", None).await?;
+    }
+
+    Ok(())
+}
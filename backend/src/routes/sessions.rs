@@ -0,0 +1,216 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, Credential, CurrentUser},
+    error::AppError,
+};
+
+/// A device the user is signed in on: a login session (cookie or bearer
+/// token) or a JWT login, which lasts as long as its refresh tokens.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ActiveSession {
+    /// The session id, or the JWT login's family id.
+    pub id: String,
+    /// `session` or `token`.
+    pub kind: String,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+    /// For a JWT login, when it was last refreshed.
+    pub last_seen_at: String,
+    pub expires_at: String,
+    /// Whether this request came in on it.
+    #[sqlx(skip)]
+    pub current: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeAllQuery {
+    /// Sign out everywhere else but stay signed in here.
+    #[serde(default)]
+    pub keep_current: bool,
+}
+
+const ACTIVE_SESSIONS: &str = r#"
+    SELECT CAST(id AS TEXT) AS id, 'session' AS kind, user_agent, created_at, last_seen_at,
+           expires_at
+    FROM sessions
+    WHERE user_id = ? AND expires_at > CURRENT_TIMESTAMP
+    UNION ALL
+    SELECT family_id, 'token', NULL, MIN(created_at), MAX(created_at), MAX(expires_at)
+    FROM refresh_tokens
+    WHERE user_id = ?
+    GROUP BY family_id
+    HAVING COUNT(revoked_at) = 0 AND MAX(expires_at) > CURRENT_TIMESTAMP
+    ORDER BY last_seen_at DESC, created_at DESC
+"#;
+
+/// The caller's sessions, most recently used first.
+pub async fn list_my_sessions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<Vec<ActiveSession>>, AppError> {
+    let mut rows = active_sessions(&state.pool, user.id).await?;
+    let current = current_id(&user.credential);
+    for row in &mut rows {
+        row.current = current.as_deref() == Some(row.id.as_str());
+    }
+
+    Ok(Json(rows))
+}
+
+/// Signs one of the caller's devices out. Takes effect on its next request.
+pub async fn revoke_my_session(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let revoked = match id.parse::<i64>() {
+        Ok(session_id) => sqlx::query("DELETE FROM sessions WHERE id = ? AND user_id = ?")
+            .bind(session_id)
+            .bind(user.id)
+            .execute(&state.pool)
+            .await?
+            .rows_affected(),
+        Err(_) => sqlx::query(
+            r#"
+            UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE family_id = ? AND user_id = ? AND revoked_at IS NULL
+            "#,
+        )
+        .bind(&id)
+        .bind(user.id)
+        .execute(&state.pool)
+        .await?
+        .rows_affected(),
+    };
+
+    if revoked == 0 {
+        return Err(AppError::NotFound(format!("session {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs the caller out everywhere, or everywhere else with
+/// `?keep_current=true`.
+pub async fn revoke_my_sessions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<RevokeAllQuery>,
+) -> Result<Response, AppError> {
+    let keep = query
+        .keep_current
+        .then(|| current_id(&user.credential))
+        .flatten();
+    revoke_all(&state.pool, user.id, keep.as_deref()).await?;
+
+    if keep.is_some() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, auth::session_cookie(&state, None))],
+    )
+        .into_response())
+}
+
+/// Another user's sessions in the caller's school; admins only.
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ActiveSession>>, AppError> {
+    ensure_manageable(&state.pool, &user, id).await?;
+    active_sessions(&state.pool, id).await.map(Json)
+}
+
+/// Signs a user out everywhere, e.g. after a lost device; admins only.
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    ensure_manageable(&state.pool, &user, id).await?;
+    revoke_all(&state.pool, id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn active_sessions(pool: &SqlitePool, user_id: i64) -> Result<Vec<ActiveSession>, AppError> {
+    let rows = sqlx::query_as::<_, ActiveSession>(ACTIVE_SESSIONS)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
+
+/// The id [`ActiveSession`] lists the credential under; API keys aren't
+/// sessions.
+fn current_id(credential: &Credential) -> Option<String> {
+    match credential {
+        Credential::Session(id) => Some(id.to_string()),
+        Credential::AccessToken { family_id, .. } => Some(family_id.clone()),
+        Credential::ApiKey { .. } => None,
+    }
+}
+
+/// Ends every session and JWT login of the user except `keep`. JWT access
+/// tokens check their login on every request, so they stop working too.
+pub async fn revoke_all(
+    pool: &SqlitePool,
+    user_id: i64,
+    keep: Option<&str>,
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM sessions WHERE user_id = ? AND (? IS NULL OR CAST(id AS TEXT) != ?)")
+        .bind(user_id)
+        .bind(keep)
+        .bind(keep)
+        .execute(pool)
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = ? AND revoked_at IS NULL AND (? IS NULL OR family_id != ?)
+        "#,
+    )
+    .bind(user_id)
+    .bind(keep)
+    .bind(keep)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+async fn ensure_manageable(
+    pool: &SqlitePool,
+    caller: &CurrentUser,
+    user_id: i64,
+) -> Result<(), AppError> {
+    if !caller.is_admin() {
+        return Err(AppError::Forbidden(
+            "only admins can manage other users' sessions".to_string(),
+        ));
+    }
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND school_id = ?)")
+            .bind(user_id)
+            .bind(auth::school_id())
+            .fetch_one(pool)
+            .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("user {user_id}")));
+    }
+    Ok(())
+}
@@ -0,0 +1,561 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::{classes, quizzes},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_CODE_CHARS: usize = 100;
+
+/// A curriculum standard such as a Common Core code. Codes are matched
+/// case-insensitively.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Standard {
+    pub id: i64,
+    pub code: String,
+    pub description: String,
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StandardRequest {
+    pub code: String,
+    pub description: String,
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+}
+
+impl Validate for StandardRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("code", &self.code);
+        v.max_chars("code", &self.code, MAX_CODE_CHARS);
+        if self.code.contains('/') {
+            v.error("code", "must not contain '/'");
+        }
+        v.required("description", &self.description);
+        v.max_chars("description", &self.description, 2_000);
+        if let Some(subject) = &self.subject {
+            v.max_chars("subject", subject, 100);
+        }
+        if let Some(grade) = &self.grade_level {
+            v.max_chars("grade_level", grade, 20);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StandardImportResponse {
+    pub imported: usize,
+    pub standards: Vec<Standard>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StandardListQuery {
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+    /// Substring match on the code or description.
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StandardCoverage {
+    pub code: String,
+    pub description: String,
+    pub subject: Option<String>,
+    pub grade_level: Option<String>,
+    /// Enrolled students with a tagged interaction or quiz.
+    pub students_reached: i64,
+    pub interaction_count: i64,
+    pub quiz_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClassCoverage {
+    pub class_id: i64,
+    pub student_count: i64,
+    /// Standards reached by at least one student.
+    pub covered: usize,
+    pub total: usize,
+    pub standards: Vec<StandardCoverage>,
+}
+
+/// What a standard can be attached to.
+#[derive(Clone, Copy)]
+enum Target {
+    Quiz,
+    LessonPlan,
+    Interaction,
+}
+
+impl Target {
+    fn link_table(self) -> &'static str {
+        match self {
+            Target::Quiz => "quiz_standards",
+            Target::LessonPlan => "lesson_plan_standards",
+            Target::Interaction => "interaction_standards",
+        }
+    }
+
+    fn link_column(self) -> &'static str {
+        match self {
+            Target::Quiz => "quiz_id",
+            Target::LessonPlan => "plan_id",
+            Target::Interaction => "interaction_id",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            Target::Quiz => "quizzes",
+            Target::LessonPlan => "lesson_plans",
+            Target::Interaction => "ai_interactions",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Target::Quiz => "quiz",
+            Target::LessonPlan => "lesson plan",
+            Target::Interaction => "interaction",
+        }
+    }
+}
+
+const STANDARD_COLUMNS: &str = "id, code, description, subject, grade_level, created_at";
+
+pub async fn list_standards(
+    State(state): State<AppState>,
+    Query(query): Query<StandardListQuery>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    let rows = sqlx::query_as::<_, Standard>(&format!(
+        r#"
+        SELECT {STANDARD_COLUMNS}
+        FROM standards
+        WHERE (? IS NULL OR lower(subject) = lower(?))
+          AND (? IS NULL OR grade_level = ?)
+          AND (? IS NULL OR instr(lower(code), lower(?)) > 0
+                         OR instr(lower(description), lower(?)) > 0)
+        ORDER BY code ASC
+        "#
+    ))
+    .bind(&query.subject)
+    .bind(&query.subject)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .bind(&query.q)
+    .bind(&query.q)
+    .bind(&query.q)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_standard(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<StandardRequest>,
+) -> Result<Json<Standard>, AppError> {
+    let code = payload.code.trim().to_string();
+
+    let created = sqlx::query_as::<_, Standard>(&format!(
+        r#"
+        INSERT INTO standards(code, description, subject, grade_level)
+        VALUES(?, ?, ?, ?)
+        RETURNING {STANDARD_COLUMNS}
+        "#
+    ))
+    .bind(&code)
+    .bind(payload.description.trim())
+    .bind(&payload.subject)
+    .bind(&payload.grade_level)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || format!("standard '{code}' already exists"))
+    })?;
+
+    Ok(Json(created))
+}
+
+/// Removes the standard and every tag that uses it.
+pub async fn delete_standard(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM standards WHERE code = ?")
+        .bind(&code)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("standard '{code}'")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Loads standards from a CSV body with `code` and `description` columns
+/// and optional `subject` and `grade_level`. Existing codes are updated, so
+/// re-importing a revised file is safe. All or nothing.
+pub async fn import_standards(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<Json<StandardImportResponse>, AppError> {
+    let rows = parse_standards(&body)?;
+
+    let mut tx = state.pool.begin().await?;
+    let mut standards = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let standard = sqlx::query_as::<_, Standard>(&format!(
+            r#"
+            INSERT INTO standards(code, description, subject, grade_level)
+            VALUES(?, ?, ?, ?)
+            ON CONFLICT(code) DO UPDATE SET
+                description = excluded.description,
+                subject = excluded.subject,
+                grade_level = excluded.grade_level
+            RETURNING {STANDARD_COLUMNS}
+            "#
+        ))
+        .bind(&row.code)
+        .bind(&row.description)
+        .bind(&row.subject)
+        .bind(&row.grade_level)
+        .fetch_one(&mut *tx)
+        .await?;
+        standards.push(standard);
+    }
+    tx.commit().await?;
+
+    Ok(Json(StandardImportResponse {
+        imported: standards.len(),
+        standards,
+    }))
+}
+
+fn parse_standards(body: &str) -> Result<Vec<StandardRequest>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|err| AppError::BadRequest(format!("invalid CSV header: {err}")))?
+        .clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim_start_matches('\u{feff}').eq_ignore_ascii_case(name))
+    };
+    let (Some(code_col), Some(description_col)) = (column("code"), column("description")) else {
+        return Err(AppError::BadRequest(
+            "CSV header must include 'code' and 'description' columns".to_string(),
+        ));
+    };
+    let subject_col = column("subject");
+    let grade_col = column("grade_level");
+
+    let mut v = Validator::default();
+    let mut rows = Vec::new();
+    let mut seen_codes = HashSet::new();
+    for (i, record) in reader.records().enumerate() {
+        // Line 1 is the header.
+        let line = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                v.error(format!("line {line}"), err.to_string());
+                continue;
+            }
+        };
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let row = StandardRequest {
+            code: field(Some(code_col)).unwrap_or_default(),
+            description: field(Some(description_col)).unwrap_or_default(),
+            subject: field(subject_col),
+            grade_level: field(grade_col),
+        };
+
+        let mut row_errors = Validator::default();
+        row.validate(&mut row_errors);
+        if let Err(AppError::Validation(errors)) = row_errors.finish() {
+            for (field, messages) in errors {
+                for message in messages {
+                    v.error(format!("line {line}.{field}"), message);
+                }
+            }
+        }
+        if !row.code.is_empty() && !seen_codes.insert(row.code.to_lowercase()) {
+            v.error(
+                format!("line {line}.code"),
+                "appears more than once in the file",
+            );
+        }
+        rows.push(row);
+    }
+
+    if rows.is_empty() {
+        v.error("body", "has no standard rows");
+    }
+    v.finish()?;
+    Ok(rows)
+}
+
+pub async fn list_quiz_standards(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    list_for(&state.pool, Target::Quiz, id).await.map(Json)
+}
+
+pub async fn tag_quiz(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    tag(&state.pool, Target::Quiz, id, &code).await.map(Json)
+}
+
+pub async fn untag_quiz(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    untag(&state.pool, Target::Quiz, id, &code).await
+}
+
+pub async fn list_lesson_plan_standards(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    list_for(&state.pool, Target::LessonPlan, id)
+        .await
+        .map(Json)
+}
+
+pub async fn tag_lesson_plan(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    tag(&state.pool, Target::LessonPlan, id, &code)
+        .await
+        .map(Json)
+}
+
+pub async fn untag_lesson_plan(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    untag(&state.pool, Target::LessonPlan, id, &code).await
+}
+
+pub async fn list_interaction_standards(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    list_for(&state.pool, Target::Interaction, id)
+        .await
+        .map(Json)
+}
+
+pub async fn tag_interaction(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<Json<Vec<Standard>>, AppError> {
+    tag(&state.pool, Target::Interaction, id, &code)
+        .await
+        .map(Json)
+}
+
+pub async fn untag_interaction(
+    State(state): State<AppState>,
+    Path((id, code)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    untag(&state.pool, Target::Interaction, id, &code).await
+}
+
+/// Every standard (filtered by `subject`/`grade_level`) with how often the
+/// class's students met it through tagged interactions and quizzes.
+/// Lesson plans aren't tied to a class, so they don't count here.
+pub async fn class_coverage(
+    State(state): State<AppState>,
+    Path(class_id): Path<i64>,
+    Query(query): Query<StandardListQuery>,
+) -> Result<Json<ClassCoverage>, AppError> {
+    classes::ensure_exists(&state.pool, class_id).await?;
+
+    let student_count = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM enrollments e
+        JOIN students s ON s.id = e.student_id
+        WHERE e.class_id = ? AND s.deleted_at IS NULL
+        "#,
+    )
+    .bind(class_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    let standards = sqlx::query_as::<_, StandardCoverage>(
+        r#"
+        SELECT st.code, st.description, st.subject, st.grade_level,
+               (SELECT COUNT(*)
+                FROM enrollments e
+                WHERE e.class_id = ?
+                  AND (EXISTS(SELECT 1 FROM interaction_standards x
+                              JOIN ai_interactions i ON i.id = x.interaction_id
+                              WHERE x.standard_id = st.id AND i.student_id = e.student_id)
+                       OR EXISTS(SELECT 1 FROM quiz_standards x
+                                 JOIN quizzes q ON q.id = x.quiz_id
+                                 WHERE x.standard_id = st.id AND q.student_id = e.student_id)))
+                   AS students_reached,
+               (SELECT COUNT(*)
+                FROM interaction_standards x
+                JOIN ai_interactions i ON i.id = x.interaction_id
+                JOIN enrollments e ON e.student_id = i.student_id AND e.class_id = ?
+                WHERE x.standard_id = st.id) AS interaction_count,
+               (SELECT COUNT(*)
+                FROM quiz_standards x
+                JOIN quizzes q ON q.id = x.quiz_id
+                JOIN enrollments e ON e.student_id = q.student_id AND e.class_id = ?
+                WHERE x.standard_id = st.id) AS quiz_count
+        FROM standards st
+        WHERE (? IS NULL OR lower(st.subject) = lower(?))
+          AND (? IS NULL OR st.grade_level = ?)
+          AND (? IS NULL OR instr(lower(st.code), lower(?)) > 0
+                         OR instr(lower(st.description), lower(?)) > 0)
+        ORDER BY st.code ASC
+        "#,
+    )
+    .bind(class_id)
+    .bind(class_id)
+    .bind(class_id)
+    .bind(&query.subject)
+    .bind(&query.subject)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .bind(&query.q)
+    .bind(&query.q)
+    .bind(&query.q)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(ClassCoverage {
+        class_id,
+        student_count,
+        covered: standards.iter().filter(|s| s.students_reached > 0).count(),
+        total: standards.len(),
+        standards,
+    }))
+}
+
+async fn list_for(pool: &SqlitePool, target: Target, id: i64) -> Result<Vec<Standard>, AppError> {
+    ensure_target(pool, target, id).await?;
+
+    let rows = sqlx::query_as::<_, Standard>(&format!(
+        r#"
+        SELECT st.id, st.code, st.description, st.subject, st.grade_level, st.created_at
+        FROM {link} x
+        JOIN standards st ON st.id = x.standard_id
+        WHERE x.{column} = ?
+        ORDER BY st.code ASC
+        "#,
+        link = target.link_table(),
+        column = target.link_column(),
+    ))
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Tagging twice is harmless. Returns the target's standards.
+async fn tag(
+    pool: &SqlitePool,
+    target: Target,
+    id: i64,
+    code: &str,
+) -> Result<Vec<Standard>, AppError> {
+    ensure_target(pool, target, id).await?;
+    let standard_id = sqlx::query_scalar::<_, i64>("SELECT id FROM standards WHERE code = ?")
+        .bind(code)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("standard '{code}'")))?;
+
+    sqlx::query(&format!(
+        "INSERT INTO {link}({column}, standard_id) VALUES(?, ?) ON CONFLICT DO NOTHING",
+        link = target.link_table(),
+        column = target.link_column(),
+    ))
+    .bind(id)
+    .bind(standard_id)
+    .execute(pool)
+    .await?;
+
+    list_for(pool, target, id).await
+}
+
+async fn untag(
+    pool: &SqlitePool,
+    target: Target,
+    id: i64,
+    code: &str,
+) -> Result<StatusCode, AppError> {
+    ensure_target(pool, target, id).await?;
+    let result = sqlx::query(&format!(
+        "DELETE FROM {link} \
+         WHERE {column} = ? AND standard_id = (SELECT id FROM standards WHERE code = ?)",
+        link = target.link_table(),
+        column = target.link_column(),
+    ))
+    .bind(id)
+    .bind(code)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "standard '{code}' on {} {id}",
+            target.label()
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 404 unless the target is in the caller's school; a quiz made for a
+/// student also needs the caller to see that student.
+async fn ensure_target(pool: &SqlitePool, target: Target, id: i64) -> Result<(), AppError> {
+    if let Target::Quiz = target {
+        return quizzes::load(pool, id).await.map(|_| ());
+    }
+    let exists: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE id = ? AND school_id = ?)",
+        target.table()
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_one(pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("{} {id}", target.label())));
+    }
+    Ok(())
+}
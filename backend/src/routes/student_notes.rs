@@ -0,0 +1,192 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    routes::{school_years, students},
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_BODY_CHARS: usize = 10_000;
+
+/// A teacher's observation about a student, optionally pointing at the AI
+/// interaction it refers to.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StudentNote {
+    pub id: i64,
+    pub student_id: i64,
+    pub author: String,
+    pub body: String,
+    pub interaction_id: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NoteRequest {
+    pub author: String,
+    pub body: String,
+    pub interaction_id: Option<i64>,
+}
+
+impl Validate for NoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("author", &self.author);
+        v.max_chars("author", &self.author, 200);
+        v.required("body", &self.body);
+        v.max_chars("body", &self.body, MAX_BODY_CHARS);
+        if let Some(id) = self.interaction_id {
+            v.range("interaction_id", id, 1, i64::MAX);
+        }
+    }
+}
+
+const NOTE_COLUMNS: &str = "id, student_id, author, body, interaction_id, created_at, updated_at";
+
+/// Newest first.
+pub async fn list_notes(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<StudentNote>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, StudentNote>(&format!(
+        r#"
+        SELECT {NOTE_COLUMNS}
+        FROM student_notes
+        WHERE student_id = ?
+        ORDER BY created_at DESC, id DESC
+        "#
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_note(
+    State(state): State<AppState>,
+    Path((student_id, note_id)): Path<(i64, i64)>,
+) -> Result<Json<StudentNote>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    sqlx::query_as::<_, StudentNote>(&format!(
+        "SELECT {NOTE_COLUMNS} FROM student_notes WHERE id = ? AND student_id = ?"
+    ))
+    .bind(note_id)
+    .bind(student_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or_else(|| AppError::NotFound(format!("note {note_id} for student {student_id}")))
+}
+
+pub async fn create_note(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    ValidJson(payload): ValidJson<NoteRequest>,
+) -> Result<Json<StudentNote>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    school_years::ensure_student_writable(&state.pool, student_id).await?;
+    check_interaction(&state.pool, student_id, payload.interaction_id).await?;
+
+    let created = sqlx::query_as::<_, StudentNote>(&format!(
+        r#"
+        INSERT INTO student_notes(student_id, author, body, interaction_id)
+        VALUES(?, ?, ?, ?)
+        RETURNING {NOTE_COLUMNS}
+        "#
+    ))
+    .bind(student_id)
+    .bind(payload.author.trim())
+    .bind(&payload.body)
+    .bind(payload.interaction_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(created))
+}
+
+pub async fn update_note(
+    State(state): State<AppState>,
+    Path((student_id, note_id)): Path<(i64, i64)>,
+    ValidJson(payload): ValidJson<NoteRequest>,
+) -> Result<Json<StudentNote>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    school_years::ensure_student_writable(&state.pool, student_id).await?;
+    check_interaction(&state.pool, student_id, payload.interaction_id).await?;
+
+    let updated = sqlx::query_as::<_, StudentNote>(&format!(
+        r#"
+        UPDATE student_notes
+        SET author = ?, body = ?, interaction_id = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ? AND student_id = ?
+        RETURNING {NOTE_COLUMNS}
+        "#
+    ))
+    .bind(payload.author.trim())
+    .bind(&payload.body)
+    .bind(payload.interaction_id)
+    .bind(note_id)
+    .bind(student_id)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("note {note_id} for student {student_id}")))
+}
+
+pub async fn delete_note(
+    State(state): State<AppState>,
+    Path((student_id, note_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    school_years::ensure_student_writable(&state.pool, student_id).await?;
+    let result = sqlx::query("DELETE FROM student_notes WHERE id = ? AND student_id = ?")
+        .bind(note_id)
+        .bind(student_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "note {note_id} for student {student_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A note may only link to one of the same student's interactions.
+async fn check_interaction(
+    pool: &SqlitePool,
+    student_id: i64,
+    interaction_id: Option<i64>,
+) -> Result<(), AppError> {
+    let Some(interaction_id) = interaction_id else {
+        return Ok(());
+    };
+    let matches: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM ai_interactions WHERE id = ? AND student_id = ?)",
+    )
+    .bind(interaction_id)
+    .bind(student_id)
+    .fetch_one(pool)
+    .await?;
+
+    let mut v = Validator::default();
+    if !matches {
+        v.error(
+            "interaction_id",
+            format!("interaction {interaction_id} does not belong to student {student_id}"),
+        );
+    }
+    v.finish()
+}
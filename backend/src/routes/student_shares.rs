@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    routes::{students, users},
+};
+
+/// A co-teacher who can see and edit a student they don't own.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StudentShare {
+    pub user_id: i64,
+    pub name: String,
+    pub email: String,
+    pub shared_by: Option<i64>,
+    pub created_at: String,
+}
+
+pub async fn list_student_shares(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<StudentShare>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    shares_for_student(&state.pool, student_id).await.map(Json)
+}
+
+/// Shares the student with a user in the same school; sharing twice is
+/// harmless. Only the owner or an admin may share. Returns the student's
+/// shares.
+pub async fn share_student(
+    State(state): State<AppState>,
+    Path((student_id, user_id)): Path<(i64, i64)>,
+) -> Result<Json<Vec<StudentShare>>, AppError> {
+    students::ensure_owner(&state.pool, student_id).await?;
+    users::check_reference(&state.pool, "user_id", user_id).await?;
+
+    sqlx::query(
+        "INSERT INTO student_shares(student_id, user_id, shared_by) VALUES(?, ?, ?) \
+         ON CONFLICT(student_id, user_id) DO NOTHING",
+    )
+    .bind(student_id)
+    .bind(user_id)
+    .bind(auth::caller().map(|caller| caller.id))
+    .execute(&state.pool)
+    .await?;
+
+    shares_for_student(&state.pool, student_id).await.map(Json)
+}
+
+pub async fn unshare_student(
+    State(state): State<AppState>,
+    Path((student_id, user_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    students::ensure_owner(&state.pool, student_id).await?;
+    let result = sqlx::query("DELETE FROM student_shares WHERE student_id = ? AND user_id = ?")
+        .bind(student_id)
+        .bind(user_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "share of student {student_id} with user {user_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn shares_for_student(
+    pool: &SqlitePool,
+    student_id: i64,
+) -> Result<Vec<StudentShare>, AppError> {
+    let rows = sqlx::query_as::<_, StudentShare>(
+        r#"
+        SELECT sh.user_id, u.name, u.email, sh.shared_by, sh.created_at
+        FROM student_shares sh
+        JOIN users u ON u.id = sh.user_id
+        WHERE sh.student_id = ?
+        ORDER BY u.name ASC, u.id ASC
+        "#,
+    )
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
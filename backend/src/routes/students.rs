@@ -1,51 +1,1066 @@
-use axum::{extract::State, Json};
-use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::Infallible};
 
-use crate::{app_state::AppState, error::AppError};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    auth,
+    error::AppError,
+    pagination::{self, Page},
+    routes::{school_years, users},
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const GRADE_LEVELS: &[&str] = &[
+    "PK", "K", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12",
+];
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct Student {
     pub id: i64,
     pub name: String,
     pub grade_level: Option<String>,
+    /// Id from the school information system, used to match imports.
+    pub external_id: Option<String>,
+    /// The parent or teacher account the student belongs to.
+    pub owner_user_id: Option<i64>,
+    /// Free-form, e.g. `650L` or `Level M`.
+    pub reading_level: Option<String>,
+    /// BCP 47 tag such as `es`; tutor replies use this language.
+    pub preferred_language: Option<String>,
+    pub accommodations: Option<String>,
+    pub has_iep: bool,
+    /// Current cohort; students in an archived year are read-only.
+    pub school_year_id: Option<i64>,
     pub created_at: String,
 }
 
+/// The parts of a student record that shape tutor prompts.
+#[derive(Debug, Default, sqlx::FromRow)]
+pub struct StudentProfile {
+    pub grade_level: Option<String>,
+    pub reading_level: Option<String>,
+    pub preferred_language: Option<String>,
+    pub accommodations: Option<String>,
+    pub has_iep: bool,
+}
+
+impl StudentProfile {
+    /// Adds a system message describing the learner, when any profile field
+    /// beyond grade is set. Goes first, so a persona's prompt still leads.
+    pub fn apply(&self, payload: &mut Value) {
+        let mut lines = Vec::new();
+        if let Some(reading_level) = &self.reading_level {
+            lines.push(format!(
+                "The student reads at level {reading_level}; match vocabulary and sentence \
+                 length to it."
+            ));
+        }
+        if let Some(language) = &self.preferred_language {
+            lines.push(format!(
+                "Reply in the language with tag '{language}' unless the student writes in \
+                 another language."
+            ));
+        }
+        if self.has_iep {
+            lines.push("The student has an IEP (individualized education program).".to_string());
+        }
+        if let Some(accommodations) = &self.accommodations {
+            lines.push(format!("Follow these accommodations: {accommodations}"));
+        }
+        if lines.is_empty() {
+            return;
+        }
+
+        if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+            let content = format!("Learner profile:\n{}", lines.join("\n"));
+            messages.insert(0, json!({ "role": "system", "content": content }));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct StudentDetail {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub student: Student,
+    pub interaction_count: i64,
+    /// Time of the student's most recent AI interaction.
+    pub last_activity_at: Option<String>,
+}
+
+pub const STUDENT_COLUMNS: &str = "id, name, grade_level, external_id, owner_user_id, \
+                                   reading_level, preferred_language, accommodations, has_iep, \
+                                   school_year_id, created_at";
+const MAX_EXTERNAL_ID_CHARS: usize = 100;
+const MAX_ACCOMMODATIONS_CHARS: usize = 2_000;
+
+#[derive(Debug, Deserialize)]
+pub struct StudentListQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// `id` (default), `name`, or `created_at`.
+    pub sort: Option<String>,
+    /// `asc` (default) or `desc`.
+    pub order: Option<String>,
+    pub grade_level: Option<String>,
+    /// Only students enrolled in this class.
+    pub class_id: Option<i64>,
+    /// Only students owned by this user.
+    pub owner_user_id: Option<i64>,
+    /// Only students with this tag.
+    pub tag: Option<String>,
+    /// Only students in this school year's cohort, archived or not.
+    pub school_year_id: Option<i64>,
+    /// Also list students in archived school years.
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `csv` (default) or `json`.
+    pub format: Option<String>,
+    /// Adds `interaction_count` and `last_activity_at` to each row.
+    #[serde(default)]
+    pub include_stats: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateStudentRequest {
     pub name: String,
     pub grade_level: Option<String>,
+    pub external_id: Option<String>,
+    pub owner_user_id: Option<i64>,
+    pub reading_level: Option<String>,
+    pub preferred_language: Option<String>,
+    pub accommodations: Option<String>,
+    #[serde(default)]
+    pub has_iep: bool,
+    pub school_year_id: Option<i64>,
+}
+
+impl Validate for CreateStudentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        if let Some(grade) = &self.grade_level {
+            v.one_of("grade_level", grade, GRADE_LEVELS);
+        }
+        if let Some(external_id) = &self.external_id {
+            v.required("external_id", external_id);
+            v.max_chars("external_id", external_id, MAX_EXTERNAL_ID_CHARS);
+        }
+        if let Some(owner) = self.owner_user_id {
+            v.range("owner_user_id", owner, 1, i64::MAX);
+        }
+        check_profile(
+            v,
+            self.reading_level.as_deref(),
+            self.preferred_language.as_deref(),
+            self.accommodations.as_deref(),
+        );
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub imported: usize,
+    pub students: Vec<Student>,
+}
+
+/// Omitted fields are left unchanged; `null` clears any optional field.
+#[derive(Debug, Deserialize)]
+pub struct UpdateStudentRequest {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub grade_level: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub owner_user_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub reading_level: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub preferred_language: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub accommodations: Option<Option<String>>,
+    pub has_iep: Option<bool>,
+    #[serde(default, deserialize_with = "present")]
+    pub school_year_id: Option<Option<i64>>,
+}
+
+impl Validate for UpdateStudentRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(name) = &self.name {
+            v.required("name", name);
+            v.max_chars("name", name, 200);
+        }
+        if let Some(Some(grade)) = &self.grade_level {
+            v.one_of("grade_level", grade, GRADE_LEVELS);
+        }
+        if let Some(Some(owner)) = self.owner_user_id {
+            v.range("owner_user_id", owner, 1, i64::MAX);
+        }
+        check_profile(
+            v,
+            self.reading_level.clone().flatten().as_deref(),
+            self.preferred_language.clone().flatten().as_deref(),
+            self.accommodations.clone().flatten().as_deref(),
+        );
+    }
+}
+
+fn check_profile(
+    v: &mut Validator,
+    reading_level: Option<&str>,
+    preferred_language: Option<&str>,
+    accommodations: Option<&str>,
+) {
+    if let Some(reading_level) = reading_level {
+        v.required("reading_level", reading_level);
+        v.max_chars("reading_level", reading_level, 20);
+    }
+    if let Some(language) = preferred_language {
+        let well_formed = (2..=35).contains(&language.len())
+            && language
+                .split('-')
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+        if !well_formed {
+            v.error(
+                "preferred_language",
+                "must be a language tag such as 'es' or 'pt-BR'",
+            );
+        }
+    }
+    if let Some(accommodations) = accommodations {
+        v.max_chars("accommodations", accommodations, MAX_ACCOMMODATIONS_CHARS);
+    }
+}
+
+/// Distinguishes an explicit `null` (`Some(None)`) from a missing field.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// The [`viewer`] of anonymous requests. No user has this id, so they see
+/// only students nobody owns.
+const ANONYMOUS_VIEWER: i64 = 0;
+
+/// The user whose access limits student queries: the caller, unless they
+/// are an admin, or [`ANONYMOUS_VIEWER`] for requests without a login.
+/// `None` (every student in the school) for admins and background jobs.
+pub fn viewer() -> Option<i64> {
+    match auth::caller() {
+        Some(caller) if caller.is_admin() => None,
+        Some(caller) => Some(caller.id),
+        None if auth::is_anonymous() => Some(ANONYMOUS_VIEWER),
+        None => None,
+    }
+}
+
+/// Students the viewer owns or has been shared, or unowned ones for
+/// anonymous requests; binds [`viewer`] three times.
+pub const ACCESS_FILTER: &str = "(? IS NULL OR owner_user_id IS NULLIF(?, 0) \
+                             OR id IN (SELECT student_id FROM student_shares WHERE user_id = ?))";
+
+/// Fails with 404 unless the student exists in the caller's school, the
+/// caller may see it, and it is not soft-deleted.
+pub async fn ensure_exists(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    ensure_visible(pool, id, false).await
+}
+
+/// Like [`ensure_exists`], optionally counting soft-deleted students.
+async fn ensure_visible(pool: &SqlitePool, id: i64, include_deleted: bool) -> Result<(), AppError> {
+    let viewer = viewer();
+    let exists: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS(SELECT 1 FROM students \
+         WHERE id = ? AND school_id = ? AND (? OR deleted_at IS NULL) AND {ACCESS_FILTER})"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(include_deleted)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_one(pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!("student {id}")));
+    }
+    Ok(())
+}
+
+/// For records that belong to a student (guardians, goals, essays, ...),
+/// loaded by their own id: 404 as `what` unless [`ensure_exists`] would
+/// pass for the student.
+pub async fn ensure_record_visible(
+    pool: &SqlitePool,
+    student_id: i64,
+    what: impl FnOnce() -> String,
+) -> Result<(), AppError> {
+    match ensure_exists(pool, student_id).await {
+        Err(AppError::NotFound(_)) => Err(AppError::NotFound(what())),
+        result => result,
+    }
+}
+
+/// Fails with 403 unless the caller owns the student; admins own them all.
+pub async fn ensure_owner(pool: &SqlitePool, id: i64) -> Result<(), AppError> {
+    ensure_exists(pool, id).await?;
+    let Some(viewer) = viewer() else {
+        return Ok(());
+    };
+    let owner: Option<i64> = sqlx::query_scalar("SELECT owner_user_id FROM students WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    if owner != Some(viewer) {
+        return Err(AppError::Forbidden(format!(
+            "only the owner of student {id} or an admin can do that"
+        )));
+    }
+    Ok(())
+}
+
+/// Profile fields used to tailor tutor prompts; defaults for unknown ids.
+pub async fn profile(pool: &SqlitePool, student_id: i64) -> Result<StudentProfile, AppError> {
+    let profile = sqlx::query_as::<_, StudentProfile>(
+        r#"
+        SELECT grade_level, reading_level, preferred_language, accommodations, has_iep
+        FROM students
+        WHERE id = ? AND school_id = ?
+        "#,
+    )
+    .bind(student_id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or_default();
+
+    Ok(profile)
 }
 
-pub async fn list_students(State(state): State<AppState>) -> Result<Json<Vec<Student>>, AppError> {
-    let rows = sqlx::query_as::<_, Student>(
-        "SELECT id, name, grade_level, created_at FROM students ORDER BY id ASC",
+pub async fn grade_level(pool: &SqlitePool, student_id: i64) -> Result<Option<String>, AppError> {
+    let grade = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT grade_level FROM students WHERE id = ? AND school_id = ?",
     )
+    .bind(student_id)
+    .bind(auth::school_id())
+    .fetch_optional(pool)
+    .await?
+    .flatten();
+
+    Ok(grade)
+}
+
+/// Rejects a request body whose `field` names a student in another school
+/// or one the caller hasn't been given. Unknown ids are let through, as
+/// chats have always allowed them.
+pub async fn check_reference(pool: &SqlitePool, field: &str, id: i64) -> Result<(), AppError> {
+    let viewer = viewer();
+    let foreign: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS(SELECT 1 FROM students \
+         WHERE id = ? AND NOT (school_id = ? AND {ACCESS_FILTER}))"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .fetch_one(pool)
+    .await?;
+
+    let mut v = Validator::default();
+    if foreign {
+        v.error(field, format!("student {id} does not exist"));
+    }
+    v.finish()
+}
+
+pub async fn list_students(
+    State(state): State<AppState>,
+    Query(query): Query<StudentListQuery>,
+) -> Result<Json<Page<Student>>, AppError> {
+    let mut v = Validator::default();
+    let (limit, offset) = pagination::resolve(&mut v, query.limit, query.offset);
+    let sort = query.sort.as_deref().unwrap_or("id");
+    v.one_of("sort", sort, &["id", "name", "created_at"]);
+    let order = query.order.as_deref().unwrap_or("asc");
+    v.one_of("order", order, &["asc", "desc"]);
+    if let Some(grade) = &query.grade_level {
+        v.one_of("grade_level", grade, GRADE_LEVELS);
+    }
+    if let Some(class_id) = query.class_id {
+        v.range("class_id", class_id, 1, i64::MAX);
+    }
+    if let Some(owner) = query.owner_user_id {
+        v.range("owner_user_id", owner, 1, i64::MAX);
+    }
+    v.finish()?;
+
+    // `sort` and `order` were checked against fixed lists above, so they
+    // are safe to interpolate.
+    let school_id = auth::school_id();
+    let viewer = viewer();
+    let filter = format!(
+        "school_id = ? AND {ACCESS_FILTER} AND deleted_at IS NULL \
+                  AND (? IS NULL OR grade_level = ?) \
+                  AND (? IS NULL OR id IN (SELECT student_id FROM enrollments WHERE class_id = ?)) \
+                  AND (? IS NULL OR owner_user_id = ?) \
+                  AND (? IS NULL OR id IN (SELECT st.student_id FROM student_tags st \
+                       JOIN tags t ON t.id = st.tag_id WHERE t.name = ?)) \
+                  AND (? IS NULL OR school_year_id = ?) \
+                  AND (? IS NOT NULL OR ? OR school_year_id IS NULL \
+                       OR school_year_id NOT IN \
+                       (SELECT id FROM school_years WHERE archived_at IS NOT NULL))"
+    );
+    let items = sqlx::query_as::<_, Student>(&format!(
+        r#"
+        SELECT {STUDENT_COLUMNS}
+        FROM students
+        WHERE {filter}
+        ORDER BY {sort} {order}, id {order}
+        LIMIT ? OFFSET ?
+        "#
+    ))
+    .bind(school_id)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(viewer)
+    .bind(&query.grade_level)
+    .bind(&query.grade_level)
+    .bind(query.class_id)
+    .bind(query.class_id)
+    .bind(query.owner_user_id)
+    .bind(query.owner_user_id)
+    .bind(&query.tag)
+    .bind(&query.tag)
+    .bind(query.school_year_id)
+    .bind(query.school_year_id)
+    .bind(query.school_year_id)
+    .bind(query.include_archived)
+    .bind(limit)
+    .bind(offset)
     .fetch_all(&state.pool)
     .await?;
 
-    Ok(Json(rows))
+    let total =
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM students WHERE {filter}"))
+            .bind(school_id)
+            .bind(viewer)
+            .bind(viewer)
+            .bind(viewer)
+            .bind(&query.grade_level)
+            .bind(&query.grade_level)
+            .bind(query.class_id)
+            .bind(query.class_id)
+            .bind(query.owner_user_id)
+            .bind(query.owner_user_id)
+            .bind(&query.tag)
+            .bind(&query.tag)
+            .bind(query.school_year_id)
+            .bind(query.school_year_id)
+            .bind(query.school_year_id)
+            .bind(query.include_archived)
+            .fetch_one(&state.pool)
+            .await?;
+
+    Ok(Json(Page {
+        items,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+pub async fn get_student(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<StudentDetail>, AppError> {
+    ensure_exists(&state.pool, id).await?;
+    let student = sqlx::query_as::<_, StudentDetail>(
+        r#"
+        SELECT s.id, s.name, s.grade_level, s.external_id, s.owner_user_id,
+               s.reading_level, s.preferred_language, s.accommodations, s.has_iep,
+               s.school_year_id, s.created_at,
+               COUNT(i.id) AS interaction_count,
+               MAX(i.created_at) AS last_activity_at
+        FROM students s
+        LEFT JOIN ai_interactions i ON i.student_id = s.id
+        WHERE s.id = ? AND s.school_id = ? AND s.deleted_at IS NULL
+        GROUP BY s.id
+        "#,
+    )
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("student {id}")))?;
+
+    Ok(Json(student))
 }
 
 pub async fn create_student(
     State(state): State<AppState>,
-    Json(payload): Json<CreateStudentRequest>,
+    ValidJson(payload): ValidJson<CreateStudentRequest>,
 ) -> Result<Json<Student>, AppError> {
-    if payload.name.trim().is_empty() {
-        return Err(AppError::BadRequest("name is required".to_string()));
+    if let Some(owner) = payload.owner_user_id {
+        users::check_reference(&state.pool, "owner_user_id", owner).await?;
     }
+    if let Some(school_year_id) = payload.school_year_id {
+        school_years::check_reference(&state.pool, "school_year_id", school_year_id).await?;
+    }
+    // Otherwise a teacher would lose sight of the student they just added.
+    let owner_user_id = payload
+        .owner_user_id
+        .or_else(|| viewer().filter(|&id| id != ANONYMOUS_VIEWER));
 
-    let created = sqlx::query_as::<_, Student>(
+    let created = sqlx::query_as::<_, Student>(&format!(
         r#"
-        INSERT INTO students(name, grade_level)
-        VALUES(?, ?)
-        RETURNING id, name, grade_level, created_at
-        "#,
-    )
+        INSERT INTO students(
+            name, grade_level, external_id, owner_user_id, reading_level, preferred_language,
+            accommodations, has_iep, school_year_id, school_id
+        )
+        VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING {STUDENT_COLUMNS}
+        "#
+    ))
     .bind(payload.name.trim())
     .bind(payload.grade_level)
+    .bind(payload.external_id.as_deref().map(str::trim))
+    .bind(owner_user_id)
+    .bind(&payload.reading_level)
+    .bind(&payload.preferred_language)
+    .bind(&payload.accommodations)
+    .bind(payload.has_iep)
+    .bind(payload.school_year_id)
+    .bind(auth::school_id())
     .fetch_one(&state.pool)
-    .await?;
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || {
+            format!(
+                "a student with external_id '{}' already exists",
+                payload.external_id.as_deref().unwrap_or_default()
+            )
+        })
+    })?;
 
     Ok(Json(created))
 }
+
+pub async fn update_student(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UpdateStudentRequest>,
+) -> Result<Json<Student>, AppError> {
+    if let Some(Some(owner)) = payload.owner_user_id {
+        users::check_reference(&state.pool, "owner_user_id", owner).await?;
+    }
+    if let Some(Some(school_year_id)) = payload.school_year_id {
+        school_years::check_reference(&state.pool, "school_year_id", school_year_id).await?;
+    }
+    ensure_exists(&state.pool, id).await?;
+    school_years::ensure_student_writable(&state.pool, id).await?;
+
+    let updated = sqlx::query_as::<_, Student>(&format!(
+        r#"
+        UPDATE students
+        SET name = COALESCE(?, name),
+            grade_level = CASE WHEN ? THEN ? ELSE grade_level END,
+            owner_user_id = CASE WHEN ? THEN ? ELSE owner_user_id END,
+            reading_level = CASE WHEN ? THEN ? ELSE reading_level END,
+            preferred_language = CASE WHEN ? THEN ? ELSE preferred_language END,
+            accommodations = CASE WHEN ? THEN ? ELSE accommodations END,
+            has_iep = COALESCE(?, has_iep),
+            school_year_id = CASE WHEN ? THEN ? ELSE school_year_id END
+        WHERE id = ? AND school_id = ? AND deleted_at IS NULL
+        RETURNING {STUDENT_COLUMNS}
+        "#
+    ))
+    .bind(payload.name.as_deref().map(str::trim))
+    .bind(payload.grade_level.is_some())
+    .bind(payload.grade_level.flatten())
+    .bind(payload.owner_user_id.is_some())
+    .bind(payload.owner_user_id.flatten())
+    .bind(payload.reading_level.is_some())
+    .bind(payload.reading_level.flatten())
+    .bind(payload.preferred_language.is_some())
+    .bind(payload.preferred_language.flatten())
+    .bind(payload.accommodations.is_some())
+    .bind(payload.accommodations.flatten())
+    .bind(payload.has_iep)
+    .bind(payload.school_year_id.is_some())
+    .bind(payload.school_year_id.flatten())
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("student {id}")))?;
+
+    Ok(Json(updated))
+}
+
+/// Soft delete: the row stays so `ai_interactions` keep their student, and
+/// `POST /students/:id/restore` brings it back.
+pub async fn delete_student(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    ensure_exists(&state.pool, id).await?;
+    school_years::ensure_student_writable(&state.pool, id).await?;
+    let deleted = sqlx::query(
+        "UPDATE students SET deleted_at = CURRENT_TIMESTAMP \
+         WHERE id = ? AND school_id = ? AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(auth::school_id())
+    .execute(&state.pool)
+    .await?
+    .rows_affected();
+
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("student {id}")));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn restore_student(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Student>, AppError> {
+    ensure_visible(&state.pool, id, true).await?;
+    school_years::ensure_student_writable(&state.pool, id).await?;
+    let restored = sqlx::query_as::<_, Student>(&format!(
+        r#"
+        UPDATE students SET deleted_at = NULL
+        WHERE id = ? AND school_id = ? AND deleted_at IS NOT NULL
+        RETURNING {STUDENT_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("deleted student {id}")))?;
+
+    Ok(Json(restored))
+}
+
+/// Imports a CSV roster with a `name` column and optional `grade_level` and
+/// `external_id` columns. Every row is validated first; if any fail, the
+/// response lists them by line and nothing is inserted.
+pub async fn import_students(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<Json<ImportResponse>, AppError> {
+    let rows = parse_roster(&body)?;
+
+    let school_id = auth::school_id();
+    let mut tx = state.pool.begin().await?;
+    let mut students = Vec::with_capacity(rows.len());
+    for (line, row) in &rows {
+        let student = sqlx::query_as::<_, Student>(&format!(
+            r#"
+            INSERT INTO students(name, grade_level, external_id, school_id)
+            VALUES(?, ?, ?, ?)
+            RETURNING {STUDENT_COLUMNS}
+            "#
+        ))
+        .bind(&row.name)
+        .bind(&row.grade_level)
+        .bind(&row.external_id)
+        .bind(school_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| {
+            AppError::unique_conflict(err, || {
+                format!(
+                    "line {line}: a student with external_id '{}' already exists",
+                    row.external_id.as_deref().unwrap_or_default()
+                )
+            })
+        })?;
+        students.push(student);
+    }
+    tx.commit().await?;
+
+    Ok(Json(ImportResponse {
+        imported: students.len(),
+        students,
+    }))
+}
+
+/// Parses and validates every row, keyed by its line in the file.
+fn parse_roster(body: &str) -> Result<Vec<(usize, CreateStudentRequest)>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|err| AppError::BadRequest(format!("invalid CSV header: {err}")))?
+        .clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim_start_matches('\u{feff}').eq_ignore_ascii_case(name))
+    };
+    let Some(name_col) = column("name") else {
+        return Err(AppError::BadRequest(
+            "CSV header must include a 'name' column".to_string(),
+        ));
+    };
+    let grade_col = column("grade_level");
+    let external_col = column("external_id");
+
+    let mut v = Validator::default();
+    let mut rows = Vec::new();
+    let mut seen_external_ids = std::collections::HashSet::new();
+    for (i, record) in reader.records().enumerate() {
+        // Line 1 is the header.
+        let line = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                v.error(format!("line {line}"), err.to_string());
+                continue;
+            }
+        };
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let row = CreateStudentRequest {
+            name: field(Some(name_col)).unwrap_or_default(),
+            grade_level: field(grade_col).map(|g| g.to_ascii_uppercase()),
+            external_id: field(external_col),
+            owner_user_id: None,
+            reading_level: None,
+            preferred_language: None,
+            accommodations: None,
+            has_iep: false,
+            school_year_id: None,
+        };
+
+        let mut row_errors = Validator::default();
+        row.validate(&mut row_errors);
+        if let Err(AppError::Validation(errors)) = row_errors.finish() {
+            for (field, messages) in errors {
+                for message in messages {
+                    v.error(format!("line {line}.{field}"), message);
+                }
+            }
+        }
+        if let Some(external_id) = &row.external_id {
+            if !seen_external_ids.insert(external_id.clone()) {
+                v.error(
+                    format!("line {line}.external_id"),
+                    "appears more than once in the file",
+                );
+            }
+        }
+        rows.push((line, row));
+    }
+
+    if rows.is_empty() {
+        v.error("body", "has no student rows");
+    }
+    v.finish()?;
+    Ok(rows)
+}
+
+/// Tables whose rows move from the duplicate to the kept student as-is.
+const MERGE_TABLES: &[&str] = &[
+    "ai_interactions",
+    "assignments",
+    "student_notes",
+    "progress_reports",
+    "quizzes",
+    "rubric_gradings",
+    "essays",
+    "goals",
+    "guardians",
+    "help_sessions",
+    "reading_assessments",
+    "vocabulary_lists",
+    "scratchpads",
+    "presence_intervals",
+];
+
+/// Tables with one row per student and key; when both students have a row,
+/// the kept student's wins and the duplicate's is dropped.
+const MERGE_KEYED_TABLES: &[&str] = &[
+    "parent_student",
+    "enrollments",
+    "student_tags",
+    "attendance",
+    "flashcard_reviews",
+    "family_digests",
+    "student_shares",
+    "student_consents",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct MergeStudentsRequest {
+    /// The duplicate; removed once its records are moved.
+    pub source_id: i64,
+    /// The student that is kept.
+    pub target_id: i64,
+}
+
+impl Validate for MergeStudentsRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.range("source_id", self.source_id, 1, i64::MAX);
+        v.range("target_id", self.target_id, 1, i64::MAX);
+        if self.source_id == self.target_id {
+            v.error("target_id", "must differ from source_id");
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeResponse {
+    pub student: Student,
+    /// Rows re-pointed to the kept student, by table.
+    pub moved: BTreeMap<&'static str, u64>,
+}
+
+/// Folds a duplicate student (typically from a roster import) into another.
+/// Everything the duplicate owns is re-pointed, profile fields the kept
+/// student lacks are filled in from the duplicate, and the duplicate is
+/// deleted, all in one transaction.
+pub async fn merge_students(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<MergeStudentsRequest>,
+) -> Result<Json<MergeResponse>, AppError> {
+    let (source_id, target_id) = (payload.source_id, payload.target_id);
+    // The source is deleted, so sharing isn't enough for either side.
+    ensure_owner(&state.pool, source_id).await?;
+    ensure_owner(&state.pool, target_id).await?;
+    school_years::ensure_student_writable(&state.pool, source_id).await?;
+    school_years::ensure_student_writable(&state.pool, target_id).await?;
+    let mut tx = state.pool.begin().await?;
+
+    let school_id = auth::school_id();
+    let load = format!(
+        "SELECT {STUDENT_COLUMNS} FROM students \
+         WHERE id = ? AND school_id = ? AND deleted_at IS NULL"
+    );
+    let source = sqlx::query_as::<_, Student>(&load)
+        .bind(source_id)
+        .bind(school_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("student {source_id}")))?;
+    sqlx::query_as::<_, Student>(&load)
+        .bind(target_id)
+        .bind(school_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("student {target_id}")))?;
+
+    let mut moved = BTreeMap::new();
+    for table in MERGE_TABLES {
+        let result = sqlx::query(&format!(
+            "UPDATE {table} SET student_id = ? WHERE student_id = ?"
+        ))
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+        moved.insert(*table, result.rows_affected());
+    }
+    for table in MERGE_KEYED_TABLES {
+        // Rows left behind by `OR IGNORE` collided with the kept student's
+        // and go when the duplicate is deleted below.
+        let result = sqlx::query(&format!(
+            "UPDATE OR IGNORE {table} SET student_id = ? WHERE student_id = ?"
+        ))
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+        moved.insert(*table, result.rows_affected());
+    }
+
+    // Deleted before the update so the target can take over `external_id`.
+    sqlx::query("DELETE FROM students WHERE id = ?")
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+    let student = sqlx::query_as::<_, Student>(&format!(
+        r#"
+        UPDATE students
+        SET grade_level = COALESCE(grade_level, ?),
+            external_id = COALESCE(external_id, ?),
+            owner_user_id = COALESCE(owner_user_id, ?),
+            reading_level = COALESCE(reading_level, ?),
+            preferred_language = COALESCE(preferred_language, ?),
+            accommodations = COALESCE(accommodations, ?),
+            has_iep = has_iep OR ?,
+            school_year_id = COALESCE(school_year_id, ?)
+        WHERE id = ?
+        RETURNING {STUDENT_COLUMNS}
+        "#
+    ))
+    .bind(source.grade_level)
+    .bind(source.external_id)
+    .bind(source.owner_user_id)
+    .bind(source.reading_level)
+    .bind(source.preferred_language)
+    .bind(source.accommodations)
+    .bind(source.has_iep)
+    .bind(source.school_year_id)
+    .bind(target_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(MergeResponse { student, moved }))
+}
+
+/// Streams the whole roster (soft-deleted students excluded) as a CSV or
+/// JSON download, one row at a time so large rosters aren't buffered.
+pub async fn export_students(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let format = query.format.as_deref().unwrap_or("csv");
+    let mut v = Validator::default();
+    v.one_of("format", format, &["csv", "json"]);
+    v.finish()?;
+    let csv = format == "csv";
+    let include_stats = query.include_stats;
+
+    let (tx, rx) = mpsc::channel::<Result<String, Infallible>>(32);
+    let pool = state.pool.clone();
+    let school_id = auth::school_id();
+    let viewer = viewer();
+    tokio::spawn(async move {
+        // Bound to a local: the row stream borrows the query text.
+        let sql = format!(
+            r#"
+            SELECT s.id, s.name, s.grade_level, s.external_id, s.owner_user_id,
+                   s.reading_level, s.preferred_language, s.accommodations, s.has_iep,
+                   s.school_year_id, s.created_at,
+                   COUNT(i.id) AS interaction_count,
+                   MAX(i.created_at) AS last_activity_at
+            FROM students s
+            LEFT JOIN ai_interactions i ON i.student_id = s.id AND ?
+            WHERE s.school_id = ? AND s.deleted_at IS NULL
+              AND s.id IN (SELECT id FROM students WHERE {ACCESS_FILTER})
+            GROUP BY s.id
+            ORDER BY s.id ASC
+            "#
+        );
+        let mut rows = sqlx::query_as::<_, StudentDetail>(&sql)
+            .bind(include_stats)
+            .bind(school_id)
+            .bind(viewer)
+            .bind(viewer)
+            .bind(viewer)
+            .fetch(&pool);
+
+        let header = if !csv {
+            "[".to_string()
+        } else if include_stats {
+            "id,name,grade_level,external_id,created_at,interaction_count,last_activity_at\n"
+                .to_string()
+        } else {
+            "id,name,grade_level,external_id,created_at\n".to_string()
+        };
+        if tx.send(Ok(header)).await.is_err() {
+            return;
+        }
+
+        let mut first = true;
+        while let Some(row) = rows.next().await {
+            let row = match row {
+                Ok(row) => row,
+                Err(err) => {
+                    // Headers are already sent, so the download just ends
+                    // early; the log says why.
+                    warn!(error = %err, "student export failed");
+                    return;
+                }
+            };
+            let chunk = if csv {
+                csv_row(&row, include_stats)
+            } else {
+                let separator = if first { "" } else { "," };
+                format!("{separator}{}", json_row(row, include_stats))
+            };
+            first = false;
+            if tx.send(Ok(chunk)).await.is_err() {
+                return;
+            }
+        }
+
+        if !csv {
+            let _ = tx.send(Ok("]".to_string())).await;
+        }
+    });
+
+    let (content_type, extension) = if csv {
+        ("text/csv; charset=utf-8", "csv")
+    } else {
+        ("application/json", "json")
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"students.{extension}\""),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    ))
+}
+
+fn csv_row(row: &StudentDetail, include_stats: bool) -> String {
+    let student = &row.student;
+    let mut fields = vec![
+        student.id.to_string(),
+        student.name.clone(),
+        student.grade_level.clone().unwrap_or_default(),
+        student.external_id.clone().unwrap_or_default(),
+        student.created_at.clone(),
+    ];
+    if include_stats {
+        fields.push(row.interaction_count.to_string());
+        fields.push(row.last_activity_at.clone().unwrap_or_default());
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    // Writing to a Vec can't fail.
+    let _ = writer.write_record(&fields);
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+fn json_row(row: StudentDetail, include_stats: bool) -> Value {
+    if include_stats {
+        serde_json::to_value(row)
+    } else {
+        serde_json::to_value(row.student)
+    }
+    .unwrap_or_default()
+}
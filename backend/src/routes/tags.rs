@@ -0,0 +1,297 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, SqlitePool};
+
+use crate::{
+    app_state::AppState,
+    auth::CurrentUser,
+    error::AppError,
+    routes::{
+        grade_policies::{PolicyLimits, UpsertPolicyRequest},
+        students,
+    },
+    validation::{ValidJson, Validate, Validator},
+};
+
+const MAX_TAG_CHARS: usize = 50;
+
+/// A label such as `ELL` or `needs-reading-support`. Names are matched
+/// case-insensitively.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// Tagged students, not counting soft-deleted ones.
+    pub student_count: i64,
+    pub created_at: String,
+}
+
+/// Generation limits for every student with the tag, on top of their
+/// grade's policy.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TagPolicy {
+    pub tag: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub limits: PolicyLimits,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+impl Validate for TagRequest {
+    fn validate(&self, v: &mut Validator) {
+        check_name(v, "name", &self.name);
+        if let Some(description) = &self.description {
+            v.max_chars("description", description, 500);
+        }
+    }
+}
+
+/// Tag names go in URLs, so they are limited to letters, digits, `-`, and
+/// `_`.
+pub fn check_name(v: &mut Validator, field: &str, name: &str) {
+    v.required(field, name);
+    v.max_chars(field, name, MAX_TAG_CHARS);
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        v.error(field, "may only contain letters, digits, '-', and '_'");
+    }
+}
+
+const TAG_SELECT: &str = r#"
+    SELECT t.id, t.name, t.description,
+           (SELECT COUNT(*) FROM student_tags st JOIN students s ON s.id = st.student_id
+            WHERE st.tag_id = t.id AND s.deleted_at IS NULL) AS student_count,
+           t.created_at
+    FROM tags t
+"#;
+
+pub async fn list_tags(State(state): State<AppState>) -> Result<Json<Vec<Tag>>, AppError> {
+    let rows = sqlx::query_as::<_, Tag>(&format!("{TAG_SELECT} ORDER BY t.name ASC"))
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn create_tag(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<TagRequest>,
+) -> Result<Json<Tag>, AppError> {
+    let name = payload.name.trim().to_string();
+
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO tags(name, description) VALUES(?, ?) RETURNING id",
+    )
+    .bind(&name)
+    .bind(&payload.description)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| AppError::unique_conflict(err, || format!("tag '{name}' already exists")))?;
+
+    let tag = sqlx::query_as::<_, Tag>(&format!("{TAG_SELECT} WHERE t.id = ?"))
+        .bind(id)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(Json(tag))
+}
+
+/// Removes the tag from every student along with its policy.
+pub async fn delete_tag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query("DELETE FROM tags WHERE name = ?")
+        .bind(&name)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("tag '{name}'")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_student_tags(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<String>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    names_for_student(&state.pool, student_id).await.map(Json)
+}
+
+/// Tags the student; tagging twice is harmless. Returns the student's tags.
+pub async fn tag_student(
+    State(state): State<AppState>,
+    Path((student_id, name)): Path<(i64, String)>,
+) -> Result<Json<Vec<String>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    let tag_id = tag_id(&state.pool, &name).await?;
+
+    sqlx::query(
+        "INSERT INTO student_tags(student_id, tag_id) VALUES(?, ?) \
+         ON CONFLICT(student_id, tag_id) DO NOTHING",
+    )
+    .bind(student_id)
+    .bind(tag_id)
+    .execute(&state.pool)
+    .await?;
+
+    names_for_student(&state.pool, student_id).await.map(Json)
+}
+
+pub async fn untag_student(
+    State(state): State<AppState>,
+    Path((student_id, name)): Path<(i64, String)>,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query(
+        "DELETE FROM student_tags \
+         WHERE student_id = ? AND tag_id = (SELECT id FROM tags WHERE name = ?)",
+    )
+    .bind(student_id)
+    .bind(&name)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "tag '{name}' on student {student_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+const TAG_POLICY_SELECT: &str = r#"
+    SELECT t.name AS tag, p.max_temperature, p.max_tokens, p.allowed_models, p.mode,
+           p.updated_at
+    FROM tag_policies p
+    JOIN tags t ON t.id = p.tag_id
+"#;
+
+pub async fn list_tag_policies(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<TagPolicy>>, AppError> {
+    let rows = sqlx::query_as::<_, TagPolicy>(&format!("{TAG_POLICY_SELECT} ORDER BY t.name ASC"))
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn upsert_tag_policy(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+    ValidJson(payload): ValidJson<UpsertPolicyRequest>,
+) -> Result<Json<TagPolicy>, AppError> {
+    user.require_admin()?;
+    let tag_id = tag_id(&state.pool, &name).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO tag_policies(tag_id, max_temperature, max_tokens, allowed_models, mode)
+        VALUES(?, ?, ?, ?, ?)
+        ON CONFLICT(tag_id) DO UPDATE SET
+            max_temperature = excluded.max_temperature,
+            max_tokens = excluded.max_tokens,
+            allowed_models = excluded.allowed_models,
+            mode = excluded.mode,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(tag_id)
+    .bind(payload.max_temperature)
+    .bind(payload.max_tokens)
+    .bind(payload.allowed_models.map(SqlJson))
+    .bind(payload.mode.as_deref().unwrap_or("clamp"))
+    .execute(&state.pool)
+    .await?;
+
+    let saved = sqlx::query_as::<_, TagPolicy>(&format!("{TAG_POLICY_SELECT} WHERE p.tag_id = ?"))
+        .bind(tag_id)
+        .fetch_one(&state.pool)
+        .await?;
+    Ok(Json(saved))
+}
+
+pub async fn delete_tag_policy(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    let result =
+        sqlx::query("DELETE FROM tag_policies WHERE tag_id = (SELECT id FROM tags WHERE name = ?)")
+            .bind(&name)
+            .execute(&state.pool)
+            .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("tag policy for '{name}'")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The student's tag names, alphabetically.
+pub async fn names_for_student(
+    pool: &SqlitePool,
+    student_id: i64,
+) -> Result<Vec<String>, AppError> {
+    let names = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT t.name
+        FROM student_tags st
+        JOIN tags t ON t.id = st.tag_id
+        WHERE st.student_id = ?
+        ORDER BY t.name ASC
+        "#,
+    )
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(names)
+}
+
+/// Policies for the student's tags, by tag name.
+pub async fn policies_for_student(
+    pool: &SqlitePool,
+    student_id: i64,
+) -> Result<Vec<TagPolicy>, AppError> {
+    let rows = sqlx::query_as::<_, TagPolicy>(&format!(
+        r#"
+        {TAG_POLICY_SELECT}
+        JOIN student_tags st ON st.tag_id = p.tag_id
+        WHERE st.student_id = ?
+        ORDER BY t.name ASC
+        "#
+    ))
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+async fn tag_id(pool: &SqlitePool, name: &str) -> Result<i64, AppError> {
+    sqlx::query_scalar::<_, i64>("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("tag '{name}'")))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    auth::{self, CurrentUser},
+    error::AppError,
+    routes::schools,
+    validation::{ValidJson, Validate, Validator},
+};
+
+pub const USER_ROLES: &[&str] = &["parent", "student", "admin"];
+
+/// A person who uses the backend: a parent or teacher, a student with their
+/// own login, or an admin.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct User {
+    pub id: i64,
+    pub role: String,
+    pub name: String,
+    pub email: String,
+    pub school_id: i64,
+    pub created_at: String,
+    /// `None` until the user first logs in with a password.
+    pub last_login_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserRequest {
+    pub role: String,
+    pub name: String,
+    pub email: String,
+    /// Only admins of the default school may set this; otherwise new users
+    /// join the caller's school and existing users stay where they are.
+    pub school_id: Option<i64>,
+}
+
+impl Validate for UserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("role", &self.role, USER_ROLES);
+        v.required("name", &self.name);
+        v.max_chars("name", &self.name, 200);
+        v.required("email", &self.email);
+        v.max_chars("email", &self.email, 320);
+        if !self.email.trim().is_empty() && !self.email.contains('@') {
+            v.error("email", "must be an email address");
+        }
+    }
+}
+
+pub const USER_COLUMNS: &str = "id, role, name, email, school_id, created_at, last_login_at";
+
+/// Users in the caller's school.
+pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<User>>, AppError> {
+    let rows = sqlx::query_as::<_, User>(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE school_id = ? ORDER BY name ASC, id ASC"
+    ))
+    .bind(auth::school_id())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<User>, AppError> {
+    sqlx::query_as::<_, User>(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE id = ? AND school_id = ?"
+    ))
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await?
+    .map(Json)
+    .ok_or_else(|| AppError::NotFound(format!("user {id}")))
+}
+
+/// Admins only.
+pub async fn create_user(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    ValidJson(payload): ValidJson<UserRequest>,
+) -> Result<Json<User>, AppError> {
+    if !user.is_admin() {
+        return Err(AppError::Forbidden(
+            "only admins can create users".to_string(),
+        ));
+    }
+    let email = payload.email.trim().to_lowercase();
+    let school_id = schools::for_user(&state.pool, payload.school_id).await?;
+
+    let created = sqlx::query_as::<_, User>(&format!(
+        "INSERT INTO users(role, name, email, school_id) VALUES(?, ?, ?, ?) \
+         RETURNING {USER_COLUMNS}"
+    ))
+    .bind(&payload.role)
+    .bind(payload.name.trim())
+    .bind(&email)
+    .bind(school_id)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || format!("email '{email}' is already in use"))
+    })?;
+
+    Ok(Json(created))
+}
+
+/// Admins may update anyone in their school. Other users may only change
+/// their own name and email.
+pub async fn update_user(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UserRequest>,
+) -> Result<Json<User>, AppError> {
+    if !user.is_admin() {
+        if id != user.id {
+            return Err(AppError::Forbidden(
+                "only admins can update other users".to_string(),
+            ));
+        }
+        if payload.role != user.role || payload.school_id.is_some() {
+            return Err(AppError::Forbidden(
+                "only admins can change a user's role or school".to_string(),
+            ));
+        }
+    }
+    let email = payload.email.trim().to_lowercase();
+    let school_id = match payload.school_id {
+        Some(requested) => Some(schools::for_user(&state.pool, Some(requested)).await?),
+        None => None,
+    };
+
+    let updated = sqlx::query_as::<_, User>(&format!(
+        r#"
+        UPDATE users SET role = ?, name = ?, email = ?, school_id = COALESCE(?, school_id)
+        WHERE id = ? AND school_id = ?
+        RETURNING {USER_COLUMNS}
+        "#
+    ))
+    .bind(&payload.role)
+    .bind(payload.name.trim())
+    .bind(&email)
+    .bind(school_id)
+    .bind(id)
+    .bind(auth::school_id())
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|err| {
+        AppError::unique_conflict(err, || format!("email '{email}' is already in use"))
+    })?;
+
+    updated
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("user {id}")))
+}
+
+/// Students and interactions owned by the user are kept, with their owner
+/// cleared. Admins only.
+pub async fn delete_user(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    if !user.is_admin() {
+        return Err(AppError::Forbidden(
+            "only admins can delete users".to_string(),
+        ));
+    }
+    let result = sqlx::query("DELETE FROM users WHERE id = ? AND school_id = ?")
+        .bind(id)
+        .bind(auth::school_id())
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("user {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rejects a request body whose `field` names a user that doesn't exist in
+/// the caller's school.
+pub async fn check_reference(pool: &SqlitePool, field: &str, id: i64) -> Result<(), AppError> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND school_id = ?)")
+            .bind(id)
+            .bind(auth::school_id())
+            .fetch_one(pool)
+            .await?;
+
+    let mut v = Validator::default();
+    if !exists {
+        v.error(field, format!("user {id} does not exist"));
+    }
+    v.finish()
+}
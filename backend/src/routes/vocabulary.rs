@@ -0,0 +1,530 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    app_state::AppState,
+    assist::{self, Prompt},
+    error::AppError,
+    routes::{
+        progress_reports::{excerpt, question},
+        students, users,
+    },
+    trace::TraceHeaders,
+    validation::{FieldErrors, ValidJson, Validate, Validator},
+};
+
+const MAX_WORDS: usize = 40;
+const DEFAULT_WORDS: usize = 15;
+const MAX_TEXT_CHARS: usize = 20_000;
+const DEFAULT_HISTORY_DAYS: i64 = 30;
+/// Interactions drawn on when building from chat history, newest first.
+const MAX_INTERACTIONS: i64 = 40;
+const MAX_SENTENCES_PER_WORD: usize = 5;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct VocabularyList {
+    pub id: i64,
+    pub student_id: i64,
+    pub title: String,
+    /// `history` (the student's tutor chats) or `text` (an uploaded text).
+    pub source: String,
+    pub word_count: i64,
+    /// The `ai_interactions` row for the extraction request.
+    pub generation_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct VocabularyWord {
+    pub id: i64,
+    pub list_id: i64,
+    pub word: String,
+    pub definition: String,
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VocabularyListDetail {
+    #[serde(flatten)]
+    pub list: VocabularyList,
+    /// Alphabetical.
+    pub words: Vec<VocabularyWord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BuildListRequest {
+    /// Text to draw words from; without it, the student's recent tutor
+    /// chats are used.
+    pub text: Option<String>,
+    /// How far back to look in chat history; default 30, max 365.
+    pub days: Option<i64>,
+    pub word_count: Option<usize>,
+    pub title: Option<String>,
+    pub model: Option<String>,
+    /// Teacher the extraction is recorded against.
+    pub user_id: Option<i64>,
+}
+
+impl Validate for BuildListRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(text) = &self.text {
+            v.required("text", text);
+            v.max_chars("text", text, MAX_TEXT_CHARS);
+            if self.days.is_some() {
+                v.error("days", "only applies when building from chat history");
+            }
+        }
+        if let Some(days) = self.days {
+            v.range("days", days, 1, 365);
+        }
+        if let Some(count) = self.word_count {
+            v.range("word_count", count, 1, MAX_WORDS);
+        }
+        if let Some(title) = &self.title {
+            v.required("title", title);
+            v.max_chars("title", title, 200);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PracticeRequest {
+    /// Words to practice; defaults to the whole list.
+    #[serde(default)]
+    pub word_ids: Vec<i64>,
+    /// Default 2.
+    pub sentences_per_word: Option<usize>,
+    pub model: Option<String>,
+    pub user_id: Option<i64>,
+}
+
+impl Validate for PracticeRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(count) = self.sentences_per_word {
+            v.range("sentences_per_word", count, 1, MAX_SENTENCES_PER_WORD);
+        }
+        if let Some(model) = &self.model {
+            v.required("model", model);
+            v.max_chars("model", model, 200);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PracticeSentence {
+    pub word_id: i64,
+    pub word: String,
+    pub sentence: String,
+}
+
+/// Practice sentences aren't stored; ask again for a fresh set.
+#[derive(Debug, Serialize)]
+pub struct PracticeResponse {
+    pub list_id: i64,
+    pub sentences: Vec<PracticeSentence>,
+    pub generation_id: i64,
+}
+
+/// The JSON shapes the model is asked for.
+#[derive(Debug, Deserialize)]
+struct ExtractedWords {
+    words: Vec<ExtractedWord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedWord {
+    word: String,
+    definition: String,
+    #[serde(default)]
+    context: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedSentences {
+    sentences: Vec<GeneratedSentence>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedSentence {
+    word: String,
+    sentence: String,
+}
+
+const LIST_SELECT: &str = r#"
+    SELECT l.id, l.student_id, l.title, l.source,
+           (SELECT COUNT(*) FROM vocabulary_words w WHERE w.list_id = l.id) AS word_count,
+           l.generation_id, l.created_at
+    FROM vocabulary_lists l
+"#;
+
+const WORD_COLUMNS: &str = "id, list_id, word, definition, context";
+
+/// Has the local model pick words the student is unlikely to know from an
+/// uploaded text or their recent tutor chats, and stores them as a list.
+/// Words already on one of the student's lists are skipped.
+pub async fn build_list(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<BuildListRequest>,
+) -> Result<Json<VocabularyListDetail>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+
+    let (source, material) = match &payload.text {
+        Some(text) => ("text", text.trim().to_string()),
+        None => {
+            let days = payload.days.unwrap_or(DEFAULT_HISTORY_DAYS);
+            let rows = sqlx::query_as::<_, (String, String)>(
+                r#"
+                SELECT prompt, response
+                FROM ai_interactions
+                WHERE student_id = ? AND created_at >= datetime('now', ?)
+                ORDER BY created_at DESC
+                LIMIT ?
+                "#,
+            )
+            .bind(student_id)
+            .bind(format!("-{days} days"))
+            .bind(MAX_INTERACTIONS)
+            .fetch_all(&state.pool)
+            .await?;
+            if rows.is_empty() {
+                let mut errors = FieldErrors::new();
+                errors.insert(
+                    "days".to_string(),
+                    vec![format!(
+                        "the student has no tutor chats in the last {days} days"
+                    )],
+                );
+                return Err(AppError::Validation(errors));
+            }
+            let turns = rows.iter().rev().map(|(prompt, response)| {
+                format!(
+                    "Student: {}\nTutor: {}",
+                    excerpt(&question(prompt)),
+                    excerpt(response)
+                )
+            });
+            ("history", turns.collect::<Vec<_>>().join("\n\n"))
+        }
+    };
+
+    let known = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT DISTINCT w.word
+        FROM vocabulary_words w
+        JOIN vocabulary_lists l ON l.id = w.list_id
+        WHERE l.student_id = ?
+        "#,
+    )
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+    let is_known = |word: &str| known.iter().any(|k| k.eq_ignore_ascii_case(word));
+
+    let count = payload.word_count.unwrap_or(DEFAULT_WORDS);
+    let profile = students::profile(&state.pool, student_id).await?;
+    let mut request = vec![
+        format!("Number of words: {count}"),
+        format!(
+            "Grade level: {}",
+            profile.grade_level.as_deref().unwrap_or("unknown")
+        ),
+    ];
+    if let Some(reading_level) = &profile.reading_level {
+        request.push(format!("Reading level: {reading_level}"));
+    }
+    if !known.is_empty() {
+        request.push(format!("Already studied, skip these: {}", known.join(", ")));
+    }
+    let label = if source == "text" {
+        "Text"
+    } else {
+        "Conversations"
+    };
+    request.push(format!("{label}:\n---\n{material}\n---"));
+
+    let (extracted, completion) = assist::complete_json::<ExtractedWords>(
+        &state,
+        Prompt {
+            system: "You help build vocabulary lists for a school student. From the material, \
+                     pick words or short phrases the student is likely not to know yet but \
+                     should learn at their level. Skip names and everyday words. Give each a \
+                     short, student-friendly definition and quote the phrase where it \
+                     appears. Reply with only a JSON object: {\"words\": [{\"word\": string, \
+                     \"definition\": string, \"context\": string}]}."
+                .to_string(),
+            user: request.join("\n"),
+            model: payload.model,
+            temperature: Some(0.3),
+            max_tokens: Some(3_000),
+            user_id: payload.user_id,
+            student_id: Some(student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    let mut words: Vec<ExtractedWord> = Vec::new();
+    for word in extracted.words {
+        let term = word.word.trim();
+        if term.is_empty()
+            || word.definition.trim().is_empty()
+            || is_known(term)
+            || words.iter().any(|w| w.word.eq_ignore_ascii_case(term))
+        {
+            continue;
+        }
+        words.push(ExtractedWord {
+            word: term.to_string(),
+            definition: word.definition.trim().to_string(),
+            context: word
+                .context
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty()),
+        });
+        if words.len() == count {
+            break;
+        }
+    }
+    if words.is_empty() {
+        return Err(AppError::Upstream(format!(
+            "model returned no new words; interaction {}",
+            completion.interaction_id
+        )));
+    }
+
+    let title = match &payload.title {
+        Some(title) => title.trim().to_string(),
+        None if source == "text" => "Words from a reading".to_string(),
+        None => "Words from tutor chats".to_string(),
+    };
+    let mut tx = state.pool.begin().await?;
+    let list_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO vocabulary_lists(student_id, title, source, generation_id)
+        VALUES(?, ?, ?, ?)
+        RETURNING id
+        "#,
+    )
+    .bind(student_id)
+    .bind(&title)
+    .bind(source)
+    .bind(completion.interaction_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    for word in &words {
+        sqlx::query(
+            "INSERT INTO vocabulary_words(list_id, word, definition, context) VALUES(?, ?, ?, ?)",
+        )
+        .bind(list_id)
+        .bind(&word.word)
+        .bind(&word.definition)
+        .bind(&word.context)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    load_detail(&state.pool, list_id).await.map(Json)
+}
+
+/// Newest first.
+pub async fn list_lists(
+    State(state): State<AppState>,
+    Path(student_id): Path<i64>,
+) -> Result<Json<Vec<VocabularyList>>, AppError> {
+    students::ensure_exists(&state.pool, student_id).await?;
+
+    let rows = sqlx::query_as::<_, VocabularyList>(&format!(
+        "{LIST_SELECT} WHERE l.student_id = ? ORDER BY l.created_at DESC, l.id DESC"
+    ))
+    .bind(student_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<VocabularyListDetail>, AppError> {
+    load_detail(&state.pool, id).await.map(Json)
+}
+
+pub async fn delete_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    load_list(&state.pool, id).await?;
+
+    let result = sqlx::query("DELETE FROM vocabulary_lists WHERE id = ?")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("vocabulary list {id}")));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Drops one word, e.g. one the student already knows.
+pub async fn delete_word(
+    State(state): State<AppState>,
+    Path((list_id, word_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    load_list(&state.pool, list_id).await?;
+
+    let result = sqlx::query("DELETE FROM vocabulary_words WHERE id = ? AND list_id = ?")
+        .bind(word_id)
+        .bind(list_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!(
+            "word {word_id} in vocabulary list {list_id}"
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Writes fresh example sentences using the list's words, at the student's
+/// level, for reading practice or fill-in-the-blank exercises.
+pub async fn practice_sentences(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    trace: TraceHeaders,
+    ValidJson(payload): ValidJson<PracticeRequest>,
+) -> Result<Json<PracticeResponse>, AppError> {
+    if let Some(user_id) = payload.user_id {
+        users::check_reference(&state.pool, "user_id", user_id).await?;
+    }
+    let detail = load_detail(&state.pool, id).await?;
+    let words: Vec<&VocabularyWord> = if payload.word_ids.is_empty() {
+        detail.words.iter().collect()
+    } else {
+        let mut v = Validator::default();
+        for word_id in &payload.word_ids {
+            if !detail.words.iter().any(|w| w.id == *word_id) {
+                v.error("word_ids", format!("word {word_id} is not on this list"));
+            }
+        }
+        v.finish()?;
+        detail
+            .words
+            .iter()
+            .filter(|w| payload.word_ids.contains(&w.id))
+            .collect()
+    };
+    if words.is_empty() {
+        let mut errors = FieldErrors::new();
+        errors.insert(
+            "word_ids".to_string(),
+            vec!["the list has no words".to_string()],
+        );
+        return Err(AppError::Validation(errors));
+    }
+
+    let per_word = payload.sentences_per_word.unwrap_or(2);
+    let grade_level = students::grade_level(&state.pool, detail.list.student_id).await?;
+    let lines = words
+        .iter()
+        .map(|w| format!("- {}: {}", w.word, w.definition));
+    let request = format!(
+        "Grade level: {}\nSentences per word: {per_word}\nWords:\n{}",
+        grade_level.as_deref().unwrap_or("unknown"),
+        lines.collect::<Vec<_>>().join("\n")
+    );
+
+    let (generated, completion) = assist::complete_json::<GeneratedSentences>(
+        &state,
+        Prompt {
+            system: "You write vocabulary practice sentences for a school student. Each \
+                     sentence uses the word correctly, in a context that makes its meaning \
+                     clear, with vocabulary otherwise suited to the grade. Reply with only \
+                     a JSON object: {\"sentences\": [{\"word\": string, \"sentence\": \
+                     string}]}."
+                .to_string(),
+            user: request,
+            model: payload.model,
+            temperature: Some(0.7),
+            max_tokens: Some(2_000),
+            user_id: payload.user_id,
+            student_id: Some(detail.list.student_id),
+            json: true,
+        },
+        trace,
+    )
+    .await?;
+
+    // Sentences for words that aren't on the list are dropped.
+    let sentences: Vec<PracticeSentence> = generated
+        .sentences
+        .into_iter()
+        .filter_map(|s| {
+            let word = words
+                .iter()
+                .find(|w| w.word.eq_ignore_ascii_case(s.word.trim()))?;
+            let sentence = s.sentence.trim().to_string();
+            (!sentence.is_empty()).then(|| PracticeSentence {
+                word_id: word.id,
+                word: word.word.clone(),
+                sentence,
+            })
+        })
+        .collect();
+    if sentences.is_empty() {
+        return Err(AppError::Upstream(format!(
+            "model returned no usable sentences; interaction {}",
+            completion.interaction_id
+        )));
+    }
+
+    Ok(Json(PracticeResponse {
+        list_id: id,
+        sentences,
+        generation_id: completion.interaction_id,
+    }))
+}
+
+async fn load_list(pool: &SqlitePool, id: i64) -> Result<VocabularyList, AppError> {
+    let list = sqlx::query_as::<_, VocabularyList>(&format!("{LIST_SELECT} WHERE l.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("vocabulary list {id}")))?;
+
+    students::ensure_record_visible(pool, list.student_id, || format!("vocabulary list {id}"))
+        .await?;
+    Ok(list)
+}
+
+async fn load_detail(pool: &SqlitePool, id: i64) -> Result<VocabularyListDetail, AppError> {
+    let list = load_list(pool, id).await?;
+    let words = sqlx::query_as::<_, VocabularyWord>(&format!(
+        "SELECT {WORD_COLUMNS} FROM vocabulary_words WHERE list_id = ? ORDER BY word ASC"
+    ))
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(VocabularyListDetail { list, words })
+}
@@ -0,0 +1,131 @@
+//! Concurrency gate in front of the inference server.
+//!
+//! Every upstream call holds a slot from `total`. While a reservation is
+//! active, requests from other classes must also hold one of the few
+//! `unreserved` slots, which leaves the rest of `total` to the reserved class.
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+use sqlx::SqlitePool;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{error::AppError, time::unix_now};
+
+pub struct Scheduler {
+    total: Arc<Semaphore>,
+    unreserved: Arc<Semaphore>,
+    max_concurrency: u32,
+    /// Set while the model is being swapped; new requests are turned away.
+    paused: AtomicBool,
+    /// Unix time a request last started or finished.
+    last_active: Arc<AtomicU64>,
+}
+
+/// Held for the lifetime of one upstream request, including streams.
+pub struct InferencePermit {
+    _total: OwnedSemaphorePermit,
+    _unreserved: Option<OwnedSemaphorePermit>,
+    last_active: Arc<AtomicU64>,
+}
+
+impl Drop for InferencePermit {
+    fn drop(&mut self) {
+        self.last_active.store(unix_now(), Ordering::Relaxed);
+    }
+}
+
+impl Scheduler {
+    pub fn new(max_concurrency: usize, unreserved_slots: usize) -> Self {
+        Self {
+            total: Arc::new(Semaphore::new(max_concurrency)),
+            unreserved: Arc::new(Semaphore::new(unreserved_slots)),
+            max_concurrency: max_concurrency as u32,
+            paused: AtomicBool::new(false),
+            last_active: Arc::new(AtomicU64::new(unix_now())),
+        }
+    }
+
+    /// Seconds since the last request finished, or `None` while any are
+    /// in flight or the scheduler is draining.
+    pub fn idle_secs(&self) -> Option<u64> {
+        let busy = self.paused.load(Ordering::Relaxed)
+            || self.total.available_permits() < self.max_concurrency as usize;
+        if busy {
+            return None;
+        }
+        Some(unix_now().saturating_sub(self.last_active.load(Ordering::Relaxed)))
+    }
+
+    /// Turns away new requests, then waits for in-flight ones to finish.
+    /// Requests are admitted again once the returned permit is dropped and
+    /// [`Scheduler::resume`] is called.
+    pub async fn drain(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        self.paused.store(true, Ordering::Relaxed);
+        self.total
+            .clone()
+            .acquire_many_owned(self.max_concurrency)
+            .await
+            .map_err(|_| AppError::Internal("inference scheduler closed".to_string()))
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub async fn acquire(
+        &self,
+        pool: &SqlitePool,
+        class_name: Option<&str>,
+    ) -> Result<InferencePermit, AppError> {
+        if self.paused.load(Ordering::Relaxed) {
+            return Err(AppError::Overloaded {
+                retry_after: Some(30),
+                message: "the model is being switched; try again shortly".to_string(),
+            });
+        }
+
+        let reserved_class = active_reservation(pool).await?;
+        let throttled = match reserved_class.as_deref() {
+            Some(reserved) => class_name != Some(reserved),
+            None => false,
+        };
+
+        let unreserved = if throttled {
+            Some(acquire(&self.unreserved).await?)
+        } else {
+            None
+        };
+        let total = acquire(&self.total).await?;
+        self.last_active.store(unix_now(), Ordering::Relaxed);
+
+        Ok(InferencePermit {
+            _total: total,
+            _unreserved: unreserved,
+            last_active: self.last_active.clone(),
+        })
+    }
+}
+
+async fn acquire(semaphore: &Arc<Semaphore>) -> Result<OwnedSemaphorePermit, AppError> {
+    semaphore
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|_| AppError::Internal("inference scheduler closed".to_string()))
+}
+
+pub async fn active_reservation(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT class_name FROM reservations
+        WHERE starts_at <= CURRENT_TIMESTAMP AND ends_at > CURRENT_TIMESTAMP
+        ORDER BY starts_at ASC
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+}
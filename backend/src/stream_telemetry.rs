@@ -0,0 +1,108 @@
+//! Per-chunk timing for streamed chat responses, so throughput over the
+//! course of a long reply can be read back, e.g. to spot a GPU that slows
+//! down as it heats up. Only recorded with `LLM_STREAM_TELEMETRY=true`; a
+//! long reply is a few hundred small rows.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::db;
+
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct ChunkSample {
+    /// Milliseconds since the upstream request went out.
+    pub offset_ms: i64,
+    /// Size of the chunk's line on the wire.
+    pub bytes: i64,
+    /// Characters of reply text it carried; `0` for role or usage chunks.
+    pub content_chars: i64,
+}
+
+/// Chunks and reply text that arrived during one second of the stream.
+#[derive(Debug, Serialize)]
+pub struct SecondBucket {
+    pub second: i64,
+    pub chunks: i64,
+    pub content_chars: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreamTelemetry {
+    pub interaction_id: i64,
+    pub chunks: Vec<ChunkSample>,
+    /// Every second from the first chunk to the last, gaps included.
+    pub per_second: Vec<SecondBucket>,
+    /// Reply characters per second between the first and last chunk.
+    pub chars_per_sec: Option<f64>,
+}
+
+/// Stores the samples in one statement.
+pub async fn record(
+    pool: &SqlitePool,
+    interaction_id: i64,
+    samples: &[ChunkSample],
+) -> Result<(), sqlx::Error> {
+    let samples = serde_json::to_string(samples).unwrap_or_else(|_| "[]".to_string());
+    db::retry_busy(|| {
+        sqlx::query(
+            r#"
+            INSERT INTO stream_chunks(interaction_id, seq, offset_ms, bytes, content_chars)
+            SELECT ?, key, json_extract(value, '$.offset_ms'), json_extract(value, '$.bytes'),
+                   json_extract(value, '$.content_chars')
+            FROM json_each(?)
+            "#,
+        )
+        .bind(interaction_id)
+        .bind(&samples)
+        .execute(pool)
+    })
+    .await?;
+
+    Ok(())
+}
+
+pub async fn load(pool: &SqlitePool, interaction_id: i64) -> Result<StreamTelemetry, sqlx::Error> {
+    let chunks = sqlx::query_as::<_, ChunkSample>(
+        r#"
+        SELECT offset_ms, bytes, content_chars
+        FROM stream_chunks
+        WHERE interaction_id = ?
+        ORDER BY seq ASC
+        "#,
+    )
+    .bind(interaction_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut per_second: Vec<SecondBucket> = Vec::new();
+    if let (Some(first), Some(last)) = (chunks.first(), chunks.last()) {
+        let start = first.offset_ms / 1000;
+        per_second = (start..=last.offset_ms / 1000)
+            .map(|second| SecondBucket {
+                second,
+                chunks: 0,
+                content_chars: 0,
+            })
+            .collect();
+        for chunk in &chunks {
+            let bucket = &mut per_second[(chunk.offset_ms / 1000 - start) as usize];
+            bucket.chunks += 1;
+            bucket.content_chars += chunk.content_chars;
+        }
+    }
+
+    let chars_per_sec = match (chunks.first(), chunks.last()) {
+        (Some(first), Some(last)) if last.offset_ms > first.offset_ms => {
+            let chars: i64 = chunks.iter().map(|c| c.content_chars).sum();
+            Some(chars as f64 * 1000.0 / (last.offset_ms - first.offset_ms) as f64)
+        }
+        _ => None,
+    };
+
+    Ok(StreamTelemetry {
+        interaction_id,
+        chunks,
+        per_second,
+        chars_per_sec,
+    })
+}
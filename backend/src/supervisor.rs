@@ -0,0 +1,164 @@
+//! Keeps the inference container running when `DOCKER_LLM_SUPERVISE` is on:
+//! creates it from `DOCKER_LLM_IMAGE` if it's missing and restarts it with
+//! exponential backoff after it exits. Each action is recorded in
+//! `inference_events`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+
+use crate::{
+    app_state::AppState,
+    docker::{ContainerSpec, InferenceContainer},
+    error::AppError,
+};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Running this long after a restart resets the backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+
+/// Starts the supervisor if enabled. An admin stop through
+/// `/admin/inference/stop` is respected until the next start or restart.
+pub fn spawn(state: AppState) {
+    if !state.config.docker_llm_supervise {
+        return;
+    }
+    let Some(container) = state.inference.clone() else {
+        return;
+    };
+
+    let cfg = &state.config;
+    let spec = cfg.docker_llm_image.clone().map(|image| ContainerSpec {
+        image,
+        args: cfg.docker_llm_args.clone(),
+        ports: cfg.docker_llm_ports.clone(),
+        volumes: cfg.docker_llm_volumes.clone(),
+        gpus: cfg.docker_llm_gpus.clone(),
+        restart_unless_stopped: false,
+    });
+    let interval = Duration::from_secs(cfg.docker_llm_supervise_interval_secs.max(1));
+    let pool = state.pool.clone();
+
+    tokio::spawn(async move {
+        let mut backoff = Backoff::default();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            check(&pool, &container, spec.as_ref(), &mut backoff).await;
+        }
+    });
+}
+
+#[derive(Default)]
+struct Backoff {
+    attempts: u32,
+    next_attempt: Option<Instant>,
+    running_since: Option<Instant>,
+}
+
+impl Backoff {
+    fn ready(&self) -> bool {
+        match self.next_attempt {
+            Some(at) => Instant::now() >= at,
+            None => true,
+        }
+    }
+
+    fn attempted(&mut self) {
+        let delay = INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.attempts))
+            .min(MAX_BACKOFF);
+        self.attempts += 1;
+        self.next_attempt = Some(Instant::now() + delay);
+        self.running_since = None;
+    }
+
+    fn running(&mut self) {
+        let since = *self.running_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= STABLE_AFTER {
+            self.attempts = 0;
+            self.next_attempt = None;
+        }
+    }
+}
+
+async fn check(
+    pool: &SqlitePool,
+    container: &Arc<InferenceContainer>,
+    spec: Option<&ContainerSpec>,
+    backoff: &mut Backoff,
+) {
+    if container.held_stopped() {
+        return;
+    }
+
+    let detail = match container.status().await {
+        Ok(status) if status.running => {
+            backoff.running();
+            return;
+        }
+        Ok(status) => {
+            let state = status.state.unwrap_or_else(|| "stopped".to_string());
+            match status.exit_code {
+                Some(code) => format!("{state} with exit code {code}"),
+                None => state,
+            }
+        }
+        Err(AppError::NotFound(_)) => {
+            let Some(spec) = spec else {
+                warn!(
+                    container = %container.name,
+                    "inference container is missing and DOCKER_LLM_IMAGE is unset"
+                );
+                return;
+            };
+            if !backoff.ready() {
+                return;
+            }
+            if let Err(err) = container.create(spec).await {
+                backoff.attempted();
+                record(pool, &container.name, "restart_failed", &err.to_string()).await;
+                return;
+            }
+            record(pool, &container.name, "created", &spec.image).await;
+            "created".to_string()
+        }
+        Err(err) => {
+            warn!(container = %container.name, error = %err, "failed to inspect inference container");
+            return;
+        }
+    };
+
+    if !backoff.ready() {
+        return;
+    }
+    backoff.attempted();
+    match container.start().await {
+        Ok(_) => {
+            info!(container = %container.name, was = %detail, "started inference container");
+            record(pool, &container.name, "restarted", &detail).await;
+        }
+        Err(err) => {
+            warn!(container = %container.name, error = %err, "failed to start inference container");
+            record(pool, &container.name, "restart_failed", &err.to_string()).await;
+        }
+    }
+}
+
+async fn record(pool: &SqlitePool, container: &str, event: &str, detail: &str) {
+    if let Err(err) =
+        sqlx::query("INSERT INTO inference_events(container, event, detail) VALUES(?, ?, ?)")
+            .bind(container)
+            .bind(event)
+            .bind(detail)
+            .execute(pool)
+            .await
+    {
+        warn!(error = %err, event, "failed to record inference event");
+    }
+}
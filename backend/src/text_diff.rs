@@ -0,0 +1,157 @@
+//! Word-level diff between two texts, via longest common subsequence.
+//! Whitespace is not compared, so re-wrapped text diffs as equal.
+
+use serde::Serialize;
+
+/// Longer texts are compared only up to this many words each, which keeps
+/// the LCS table to a few megabytes.
+const MAX_WORDS: usize = 1500;
+
+#[derive(Debug, Serialize)]
+pub struct Diff {
+    pub words_added: usize,
+    pub words_removed: usize,
+    pub words_unchanged: usize,
+    /// Share of words the two texts have in common, 0-1.
+    pub similarity: f64,
+    /// Runs of words in order; `equal`, `insert` (only in the new text), or
+    /// `delete` (only in the old one).
+    pub changes: Vec<Change>,
+    /// One of the texts was cut to the first `MAX_WORDS` words.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub op: &'static str,
+    pub text: String,
+}
+
+pub fn diff(old: &str, new: &str) -> Diff {
+    let old_words: Vec<&str> = old.split_whitespace().collect();
+    let new_words: Vec<&str> = new.split_whitespace().collect();
+    let truncated = old_words.len() > MAX_WORDS || new_words.len() > MAX_WORDS;
+    let a = &old_words[..old_words.len().min(MAX_WORDS)];
+    let b = &new_words[..new_words.len().min(MAX_WORDS)];
+
+    // lcs[i][j]: common subsequence length of a[i..] and b[j..].
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+    let mut push = |op: &'static str, word: &str| match changes.last_mut() {
+        Some(last) if last.op == op => {
+            last.text.push(' ');
+            last.text.push_str(word);
+        }
+        _ => changes.push(Change {
+            op,
+            text: word.to_string(),
+        }),
+    };
+    let (mut i, mut j) = (0, 0);
+    let (mut added, mut removed, mut unchanged) = (0, 0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            push("equal", a[i]);
+            unchanged += 1;
+            i += 1;
+            j += 1;
+        } else if j < b.len()
+            && (i == a.len() || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j])
+        {
+            push("insert", b[j]);
+            added += 1;
+            j += 1;
+        } else {
+            push("delete", a[i]);
+            removed += 1;
+            i += 1;
+        }
+    }
+
+    let total = a.len() + b.len();
+    let similarity = if total == 0 {
+        1.0
+    } else {
+        (2 * unchanged) as f64 / total as f64
+    };
+
+    Diff {
+        words_added: added,
+        words_removed: removed,
+        words_unchanged: unchanged,
+        similarity: (similarity * 1000.0).round() / 1000.0,
+        changes,
+        truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(diff: &Diff) -> Vec<(&str, &str)> {
+        diff.changes
+            .iter()
+            .map(|c| (c.op, c.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn identical_texts_ignore_whitespace() {
+        let diff = diff("the  cat\nsat", "the cat sat");
+        assert_eq!(ops(&diff), [("equal", "the cat sat")]);
+        assert_eq!(diff.words_unchanged, 3);
+        assert_eq!(diff.similarity, 1.0);
+        assert!(!diff.truncated);
+    }
+
+    #[test]
+    fn replaced_word_is_an_insert_and_a_delete() {
+        let diff = diff("a b c", "a x c");
+        assert_eq!(
+            ops(&diff),
+            [
+                ("equal", "a"),
+                ("insert", "x"),
+                ("delete", "b"),
+                ("equal", "c")
+            ]
+        );
+        assert_eq!((diff.words_added, diff.words_removed), (1, 1));
+        assert_eq!(diff.similarity, 0.667);
+    }
+
+    #[test]
+    fn adjacent_changes_are_merged_into_runs() {
+        let diff = diff("", "one two three");
+        assert_eq!(ops(&diff), [("insert", "one two three")]);
+        assert_eq!(diff.similarity, 0.0);
+    }
+
+    #[test]
+    fn empty_texts_are_equal() {
+        let diff = diff("", " ");
+        assert!(diff.changes.is_empty());
+        assert_eq!(diff.similarity, 1.0);
+    }
+
+    #[test]
+    fn long_texts_are_truncated() {
+        let long = "word ".repeat(MAX_WORDS + 10);
+        let diff = diff(&long, "word");
+        assert!(diff.truncated);
+        assert_eq!(diff.words_unchanged, 1);
+        assert_eq!(diff.words_removed, MAX_WORDS - 1);
+    }
+}
@@ -0,0 +1,55 @@
+//! HTTPS for the listener, from `TLS_CERT_PATH`/`TLS_KEY_PATH` or a
+//! self-signed pair generated for LAN use (`TLS_SELF_SIGNED`).
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::info;
+
+use crate::config::Config;
+
+/// `None` when TLS isn't configured and the listener serves plain HTTP.
+pub async fn rustls_config(cfg: &Config) -> io::Result<Option<RustlsConfig>> {
+    let (Some(cert), Some(key)) = (&cfg.tls_cert_path, &cfg.tls_key_path) else {
+        return Ok(None);
+    };
+    // reqwest and sqlx already pull in ring; pick it explicitly so rustls
+    // never has to guess between providers.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    if cfg.tls_self_signed && !(Path::new(cert).is_file() && Path::new(key).is_file()) {
+        generate_self_signed(cert, key, &cfg.tls_self_signed_hosts)?;
+    }
+    RustlsConfig::from_pem_file(cert, key).await.map(Some)
+}
+
+/// Writes a new certificate and key, kept across restarts so clients only
+/// have to trust it once. The key is readable by the owner only.
+fn generate_self_signed(cert_path: &str, key_path: &str, hosts: &[String]) -> io::Result<()> {
+    let generated = rcgen::generate_simple_self_signed(hosts.to_vec()).map_err(io::Error::other)?;
+
+    for path in [cert_path, key_path] {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    fs::write(cert_path, generated.cert.pem())?;
+    let mut key_file = OpenOptions::new();
+    key_file.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut key_file, 0o600);
+    key_file
+        .open(key_path)?
+        .write_all(generated.key_pair.serialize_pem().as_bytes())?;
+
+    info!(
+        cert = cert_path,
+        hosts = %hosts.join(", "),
+        "generated self-signed TLS certificate"
+    );
+    Ok(())
+}
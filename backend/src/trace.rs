@@ -0,0 +1,62 @@
+//! Trace context forwarded to the inference server so backend spans can be
+//! matched with its own request logs.
+
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderMap},
+};
+use reqwest::RequestBuilder;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+const REQUEST_ID: &str = "x-request-id";
+
+#[derive(Clone, Debug, Default)]
+pub struct TraceHeaders {
+    pub traceparent: Option<String>,
+    pub tracestate: Option<String>,
+    /// Set for every request by `SetRequestIdLayer` unless the client sent one.
+    pub request_id: Option<String>,
+}
+
+impl TraceHeaders {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            traceparent: get(TRACEPARENT),
+            tracestate: get(TRACESTATE),
+            request_id: get(REQUEST_ID),
+        }
+    }
+
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        for (name, value) in [
+            (TRACEPARENT, &self.traceparent),
+            (TRACESTATE, &self.tracestate),
+            (REQUEST_ID, &self.request_id),
+        ] {
+            if let Some(value) = value {
+                request = request.header(name, value);
+            }
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for TraceHeaders {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::config::{Config, LoadBalancing};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendRole {
+    Primary,
+    Canary,
+    Experiment,
+    Deployment,
+}
+
+#[derive(Debug, Default)]
+pub struct BackendMetrics {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    total_latency_ms: AtomicU64,
+}
+
+impl BackendMetrics {
+    pub fn record(&self, latency: Duration, ok: bool) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_latency_ms
+            .fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> BackendMetricsSnapshot {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let total_latency_ms = self.total_latency_ms.load(Ordering::Relaxed);
+
+        BackendMetricsSnapshot {
+            requests,
+            errors: self.errors.load(Ordering::Relaxed),
+            avg_latency_ms: total_latency_ms.checked_div(requests).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendMetricsSnapshot {
+    pub requests: u64,
+    pub errors: u64,
+    pub avg_latency_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    #[default]
+    Unknown,
+    Up,
+    Down,
+}
+
+/// Latest result of the background health probe for one backend.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BackendHealth {
+    pub state: Availability,
+    pub last_checked_at: Option<u64>,
+    pub last_latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub models: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Backend {
+    pub role: BackendRole,
+    pub base_url: String,
+    pub metrics: BackendMetrics,
+    pub health: Mutex<BackendHealth>,
+    in_flight: AtomicU64,
+}
+
+impl Backend {
+    fn new(role: BackendRole, base_url: String) -> Self {
+        Self {
+            role,
+            base_url,
+            metrics: BackendMetrics::default(),
+            health: Mutex::new(BackendHealth::default()),
+            in_flight: AtomicU64::new(0),
+        }
+    }
+
+    fn is_down(&self) -> bool {
+        self.health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .state
+            == Availability::Down
+    }
+
+    pub fn health(&self) -> BackendHealth {
+        self.health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set_health(&self, health: BackendHealth) {
+        *self
+            .health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = health;
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn status(&self) -> BackendStatus {
+        BackendStatus {
+            role: self.role,
+            base_url: self.base_url.clone(),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            metrics: self.metrics.snapshot(),
+        }
+    }
+}
+
+/// A backend picked for one request. It counts toward the backend's
+/// in-flight requests, used by least-connections balancing, until dropped.
+#[derive(Debug)]
+pub struct BackendLease(Arc<Backend>);
+
+impl BackendLease {
+    fn new(backend: Arc<Backend>) -> Self {
+        backend.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(backend)
+    }
+}
+
+impl Deref for BackendLease {
+    type Target = Backend;
+
+    fn deref(&self) -> &Backend {
+        &self.0
+    }
+}
+
+impl Drop for BackendLease {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendStatus {
+    pub role: BackendRole,
+    pub base_url: String,
+    pub in_flight: u64,
+    pub metrics: BackendMetricsSnapshot,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackendHealthStatus {
+    pub role: BackendRole,
+    pub base_url: String,
+    #[serde(flatten)]
+    pub health: BackendHealth,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamStatus {
+    pub canary_percent: u8,
+    pub load_balancing: &'static str,
+    pub backends: Vec<BackendStatus>,
+}
+
+/// Chat backends plus the canary split between them.
+#[derive(Debug)]
+pub struct Upstreams {
+    /// Swapped as a whole on reload; requests already holding a lease keep
+    /// their backend.
+    configured: RwLock<Arc<Configured>>,
+    /// Managed model deployments, by name. Requests whose `model` names a
+    /// deployment go to it.
+    deployments: RwLock<HashMap<String, Arc<Backend>>>,
+    canary_percent: AtomicU8,
+    counter: AtomicU64,
+    replica_counter: AtomicU64,
+}
+
+/// The backends that come from the config.
+#[derive(Debug)]
+struct Configured {
+    /// Replicas of the primary model server; never empty.
+    primaries: Vec<Arc<Backend>>,
+    canary: Option<Arc<Backend>>,
+    experiment: Option<Arc<Backend>>,
+    load_balancing: LoadBalancing,
+}
+
+impl Configured {
+    /// Reuses `previous` backends whose URL is unchanged, so their health,
+    /// metrics, and in-flight counts carry over.
+    fn from_config(cfg: &Config, previous: Option<&Configured>) -> Self {
+        let backend = |role: BackendRole, url: &str| {
+            previous
+                .into_iter()
+                .flat_map(Configured::backends)
+                .find(|existing| existing.role == role && existing.base_url == url)
+                .unwrap_or_else(|| Arc::new(Backend::new(role, url.to_string())))
+        };
+
+        Self {
+            primaries: cfg
+                .llm_base_urls
+                .iter()
+                .map(|url| backend(BackendRole::Primary, url))
+                .collect(),
+            canary: cfg
+                .llm_canary_base_url
+                .as_deref()
+                .map(|url| backend(BackendRole::Canary, url)),
+            experiment: cfg
+                .llm_experiment_base_url
+                .as_deref()
+                .map(|url| backend(BackendRole::Experiment, url)),
+            load_balancing: cfg.llm_load_balancing,
+        }
+    }
+
+    fn backends(&self) -> Vec<Arc<Backend>> {
+        self.primaries
+            .iter()
+            .chain(self.canary.iter())
+            .chain(self.experiment.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Upstreams {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            configured: RwLock::new(Arc::new(Configured::from_config(cfg, None))),
+            deployments: RwLock::default(),
+            canary_percent: AtomicU8::new(cfg.llm_canary_percent),
+            counter: AtomicU64::new(0),
+            replica_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Switches to the backends, canary split, and load balancing in `cfg`.
+    /// Deployments are left alone.
+    pub fn reload(&self, cfg: &Config) {
+        let mut configured = self
+            .configured
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *configured = Arc::new(Configured::from_config(cfg, Some(&**configured)));
+        self.canary_percent
+            .store(cfg.llm_canary_percent, Ordering::Relaxed);
+    }
+
+    fn configured(&self) -> Arc<Configured> {
+        self.configured
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Picks the backend for the next request. The split is deterministic:
+    /// out of every 100 requests exactly `canary_percent` go to the canary,
+    /// spread evenly rather than in one burst.
+    pub fn pick(&self) -> BackendLease {
+        let configured = self.configured();
+        let Some(canary) = &configured.canary else {
+            return BackendLease::new(self.pick_primary(&configured));
+        };
+
+        let percent = u64::from(self.canary_percent.load(Ordering::Relaxed));
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) % 100;
+        if (n + 1) * percent / 100 > n * percent / 100 {
+            BackendLease::new(canary.clone())
+        } else {
+            BackendLease::new(self.pick_primary(&configured))
+        }
+    }
+
+    /// Balances across primary replicas, skipping ones the health monitor
+    /// reports down unless every replica is down.
+    fn pick_primary(&self, configured: &Configured) -> Arc<Backend> {
+        let primaries = &configured.primaries;
+        if primaries.len() == 1 {
+            return primaries[0].clone();
+        }
+
+        let mut candidates: Vec<&Arc<Backend>> =
+            primaries.iter().filter(|b| !b.is_down()).collect();
+        if candidates.is_empty() {
+            candidates = primaries.iter().collect();
+        }
+
+        let start =
+            self.replica_counter.fetch_add(1, Ordering::Relaxed) as usize % candidates.len();
+        let picked = match configured.load_balancing {
+            LoadBalancing::RoundRobin => candidates[start],
+            // Starting the scan at a rotating offset spreads ties evenly.
+            LoadBalancing::LeastConnections => candidates
+                .iter()
+                .cycle()
+                .skip(start)
+                .take(candidates.len())
+                .min_by_key(|b| b.in_flight.load(Ordering::Relaxed))
+                .copied()
+                .unwrap_or(candidates[start]),
+        };
+
+        picked.clone()
+    }
+
+    pub fn backends(&self) -> Vec<Arc<Backend>> {
+        self.configured().backends()
+    }
+
+    /// Every managed deployment's backend.
+    pub fn deployments(&self) -> Vec<Arc<Backend>> {
+        self.read_deployments().values().cloned().collect()
+    }
+
+    pub fn deployment(&self, name: &str) -> Option<BackendLease> {
+        self.read_deployments()
+            .get(name)
+            .cloned()
+            .map(BackendLease::new)
+    }
+
+    pub fn set_deployment(&self, name: &str, base_url: String) {
+        self.write_deployments().insert(
+            name.to_string(),
+            Arc::new(Backend::new(BackendRole::Deployment, base_url)),
+        );
+    }
+
+    pub fn remove_deployment(&self, name: &str) {
+        self.write_deployments().remove(name);
+    }
+
+    fn read_deployments(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, Arc<Backend>>> {
+        self.deployments
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_deployments(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, Arc<Backend>>> {
+        self.deployments
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Backend for A/B treatment requests, if the experiment uses a
+    /// separate server rather than just a different model name.
+    pub fn experiment(&self) -> Option<BackendLease> {
+        self.configured().experiment.clone().map(BackendLease::new)
+    }
+
+    pub fn has_canary(&self) -> bool {
+        self.configured().canary.is_some()
+    }
+
+    pub fn set_canary_percent(&self, percent: u8) {
+        self.canary_percent.store(percent, Ordering::Relaxed);
+    }
+
+    pub fn health_status(&self) -> Vec<BackendHealthStatus> {
+        self.backends()
+            .iter()
+            .chain(self.deployments().iter())
+            .map(|backend| BackendHealthStatus {
+                role: backend.role,
+                base_url: backend.base_url.clone(),
+                health: backend.health(),
+            })
+            .collect()
+    }
+
+    pub fn status(&self) -> UpstreamStatus {
+        UpstreamStatus {
+            canary_percent: self.canary_percent.load(Ordering::Relaxed),
+            load_balancing: match self.configured().load_balancing {
+                LoadBalancing::RoundRobin => "round_robin",
+                LoadBalancing::LeastConnections => "least_connections",
+            },
+            backends: self
+                .backends()
+                .iter()
+                .chain(self.deployments().iter())
+                .map(|backend| backend.status())
+                .collect(),
+        }
+    }
+}
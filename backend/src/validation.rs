@@ -0,0 +1,129 @@
+//! Request DTO validation. Handlers take `ValidJson<T>` instead of `Json<T>`
+//! and get a 422 with per-field messages when `T::validate` reports errors.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::AppError;
+
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: FieldErrors,
+}
+
+impl Validator {
+    pub fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors
+            .entry(field.into())
+            .or_default()
+            .push(message.into());
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.error(field, "is required");
+        }
+    }
+
+    pub fn max_chars(&mut self, field: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.error(field, format!("must be at most {max} characters"));
+        }
+    }
+
+    pub fn range<T: PartialOrd + Display>(&mut self, field: &str, value: T, min: T, max: T) {
+        if value < min || value > max {
+            self.error(field, format!("must be between {min} and {max}"));
+        }
+    }
+
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) {
+        if !allowed.contains(&value) {
+            self.error(field, format!("must be one of: {}", allowed.join(", ")));
+        }
+    }
+
+    /// Accepts SQLite's `YYYY-MM-DD HH:MM:SS` (UTC), which is what
+    /// `CURRENT_TIMESTAMP` produces and what range queries compare against.
+    pub fn timestamp(&mut self, field: &str, value: &str) {
+        let bytes = value.as_bytes();
+        let well_formed = bytes.len() == 19
+            && bytes.iter().enumerate().all(|(i, b)| match i {
+                4 | 7 => *b == b'-',
+                10 => *b == b' ',
+                13 | 16 => *b == b':',
+                _ => b.is_ascii_digit(),
+            });
+        if !well_formed {
+            self.error(field, "must be formatted as YYYY-MM-DD HH:MM:SS (UTC)");
+        }
+    }
+
+    /// Accepts a calendar date as `YYYY-MM-DD`.
+    pub fn date(&mut self, field: &str, value: &str) {
+        let bytes = value.as_bytes();
+        let well_formed = bytes.len() == 10
+            && bytes.iter().enumerate().all(|(i, b)| match i {
+                4 | 7 => *b == b'-',
+                _ => b.is_ascii_digit(),
+            })
+            && matches!(value[5..7].parse::<u8>(), Ok(1..=12))
+            && matches!(value[8..10].parse::<u8>(), Ok(1..=31));
+        if !well_formed {
+            self.error(field, "must be a date formatted as YYYY-MM-DD");
+        }
+    }
+
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+}
+
+pub fn validate<T: Validate>(value: &T) -> Result<(), AppError> {
+    let mut v = Validator::default();
+    value.validate(&mut v);
+    v.finish()
+}
+
+pub struct ValidJson<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| match rejection {
+                JsonRejection::JsonDataError(err) => {
+                    let mut errors = FieldErrors::new();
+                    errors.insert("body".to_string(), vec![err.body_text()]);
+                    AppError::Validation(errors)
+                }
+                other => AppError::BadRequest(other.body_text()),
+            })?;
+
+        validate(&value)?;
+
+        Ok(Self(value))
+    }
+}
@@ -0,0 +1,54 @@
+//! Optional startup request that forces the upstream to load its model.
+
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, config::LlmBackendKind, ollama};
+
+/// Sends a one-token completion to every backend. Failures are logged, not
+/// fatal: the API still starts and the first real request pays the cold start.
+pub async fn run(state: &AppState) {
+    let Some(model) = state.config.llm_warmup_model.as_deref() else {
+        return;
+    };
+
+    let payload = json!({
+        "model": model,
+        "messages": [{ "role": "user", "content": "Hi" }],
+        "max_tokens": 1,
+    });
+    let payload = match state.config.llm_backend_kind {
+        LlmBackendKind::OpenAi => payload,
+        LlmBackendKind::Ollama => ollama::chat_request(&payload),
+    };
+    let timeout = Duration::from_secs(state.config.llm_warmup_timeout_secs);
+
+    for backend in state.upstreams.backends() {
+        let started = Instant::now();
+        info!(base_url = %backend.base_url, model, "warming up llm backend");
+
+        let result = state
+            .llm_client
+            .post(backend.url(&state.config.llm_chat_path))
+            .json(&payload)
+            .timeout(timeout)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match result {
+            Ok(_) => info!(
+                base_url = %backend.base_url,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "llm backend warm"
+            ),
+            Err(err) => warn!(
+                base_url = %backend.base_url,
+                error = %err,
+                "llm warmup failed; continuing startup"
+            ),
+        }
+    }
+}